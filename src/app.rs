@@ -0,0 +1,507 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use web_time::Instant;
+
+use crate::backend::Renderer;
+use crate::bookmarks::{Bookmarks, BOOKMARKS_PATH, BOOKMARK_SLOTS};
+use crate::config::{save_settings, Config};
+use crate::expr::ExpressionShaders;
+use crate::framebuffer::Framebuffer;
+use crate::heightmap;
+use crate::help::Help;
+use crate::hud::HudIcons;
+use crate::i18n::Strings;
+use crate::input::{Action, Bindings, InputFrame, InputSource};
+use crate::menu::Menu;
+use crate::multiplayer::{Multiplayer, Session, World};
+use crate::obj::Obj;
+use crate::objectives::{Objectives, OBJECTIVES_PATH};
+use crate::options::Options;
+use crate::replay::{Recorder, Replay};
+use crate::scene::{default_system, kuiper_belt};
+use crate::screenshot;
+use crate::script::Script;
+use crate::settings::Settings;
+use crate::shot::Shot;
+use crate::simulation::{Simulation, Snapshot};
+use crate::skybox::SkyboxLibrary;
+use crate::spectator::Spectator;
+use crate::state;
+use crate::still::Still;
+use crate::telemetry::Telemetry;
+use crate::vertex::Vertex;
+
+pub const WINDOW_TITLE: &str = "Sistema Solar Procedural";
+// Window size in logical pixels; the frame has `scale` times as many
+pub const WINDOW_WIDTH: usize = 1200;
+pub const WINDOW_HEIGHT: usize = 800;
+const FIXED_TIMESTEP: f32 = 1.0 / 120.0;
+
+// Everything but the window: each windowing backend owns its window, calls
+// `frame` once per redraw and shows `screen`
+pub struct App {
+    renderer: Box<dyn Renderer>,
+    // UI text in the chosen language, shared with the renderer's HUD
+    strings: Rc<Strings>,
+    simulation: Simulation,
+    bindings: Bindings,
+    input: InputFrame,
+    // Live keyboard state for the menu, separate from the (maybe replayed)
+    // input that drives the simulation
+    ui_input: InputFrame,
+    menu: Menu,
+    help: Help,
+    settings: Settings,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    telemetry: Option<Telemetry>,
+    spectator: Option<Spectator>,
+    multiplayer: Option<Multiplayer>,
+    // Off in multiplayer, where every copy of a player starts without
+    // bookmarks, so the file is left alone
+    save_bookmarks: bool,
+    script: Option<Script>,
+    skyboxes: SkyboxLibrary,
+    // Kept to build the offscreen renderer of high-quality stills
+    ship_vertices: Vec<Vertex>,
+    config_path: PathBuf,
+    last_frame: Instant,
+    accumulator: f32,
+}
+
+// Frame size in physical pixels on a screen with `scale` pixels per logical one
+pub fn frame_size(scale: f32) -> (usize, usize) {
+    (
+        (WINDOW_WIDTH as f32 * scale).round() as usize,
+        (WINDOW_HEIGHT as f32 * scale).round() as usize,
+    )
+}
+
+impl App {
+    pub fn new(options: &Options, scale: f32) -> Self {
+        let ship_vertices = Obj::load("assets/models/Nave.obj")
+            .expect("No se pudo cargar el modelo de la nave")
+            .get_vertex_array();
+
+        let replay = options
+            .replay
+            .as_ref()
+            .map(|path| Replay::load(path).expect("No se pudo cargar la repetición"));
+        let shot = options
+            .restore_shot
+            .as_ref()
+            .map(|path| Shot::load(path).expect("No se pudo cargar la toma"));
+        let config = load_config(&options.config);
+        let local = World {
+            seed: options.seed,
+            scale: config.scale,
+            star_mass: config.star_mass,
+            kepler: config.kepler,
+        };
+        let session = match (options.host, &options.join) {
+            (Some(port), _) => Some(Session::host(port, options.players, local)),
+            (None, Some(address)) => Some(Session::join(address)),
+            (None, None) => None,
+        }
+        .map(|session| session.expect("No se pudo abrir la partida multijugador"));
+        // Every player builds the host's system, whatever their own
+        // config.toml says
+        let world = match &session {
+            Some(session) => {
+                let host = session.world;
+                let ours = (config.scale, config.star_mass, config.kepler);
+                if session.player != 0 && (host.scale, host.star_mass, host.kepler) != ours {
+                    eprintln!(
+                        "Se usan la escala, la masa de la estrella y el modo Kepler del anfitrión"
+                    );
+                }
+                session.world
+            }
+            None => World {
+                seed: replay
+                    .as_ref()
+                    .map(|replay| replay.seed)
+                    .or(shot.as_ref().map(|shot| shot.seed))
+                    .unwrap_or(options.seed),
+                ..local
+            },
+        };
+        let mut simulation = shared_simulation(&world);
+        for warning in simulation.orbit_warnings() {
+            eprintln!("Escena: {}", warning);
+        }
+        if let Some(time) = options.time {
+            simulation.set_time(time);
+        }
+        // In multiplayer the time scale is the host's, which goes out with its
+        // first input, and the field of view only changes with the keys every
+        // player replays
+        if session.is_none() {
+            simulation.time_scale = config.settings.time_scale;
+            simulation.camera.fov = config.settings.fov;
+        }
+        if let Some(shot) = &shot {
+            if shot.gravity {
+                eprintln!("Toma sacada con gravedad: los cuerpos quedan en órbitas de relojería");
+            }
+            shot.restore(&mut simulation);
+        }
+        let save_bookmarks = session.is_none();
+        if save_bookmarks {
+            simulation.bookmarks = Bookmarks::load(BOOKMARKS_PATH).unwrap_or_else(|error| {
+                eprintln!("Marcadores inválidos ({}): {}", BOOKMARKS_PATH, error);
+                Bookmarks::default()
+            });
+        }
+        simulation.objectives = Objectives::load(OBJECTIVES_PATH).unwrap_or_else(|error| {
+            eprintln!("Misiones inválidas ({}): {}", OBJECTIVES_PATH, error);
+            Objectives::default()
+        });
+        let mut skyboxes = SkyboxLibrary::new(skybox_sources(options, &config), world.seed);
+        let skybox = skyboxes.current();
+        let recorder = options.record.as_ref().map(|path| {
+            Recorder::create(path, simulation.seed).expect("No se pudo crear la grabación")
+        });
+
+        let script = options
+            .script
+            .as_ref()
+            .map(|path| Script::load(path).expect("No se pudo cargar el guion"));
+
+        let telemetry = options.stats.as_ref().map(|path| {
+            Telemetry::create(path).expect("No se pudo crear el archivo de estadísticas")
+        });
+
+        let spectator = options.spectate.map(|port| {
+            let spectator = Spectator::serve(port, options.spectate_public)
+                .expect("No se pudo abrir el puerto para espectadores");
+            eprintln!("Espectadores en http://localhost:{}/", port);
+            spectator
+        });
+
+        let multiplayer = session.map(|session| {
+            Multiplayer::new(
+                session,
+                || shared_simulation(&world),
+                config.settings.time_scale,
+            )
+        });
+
+        let icons = HudIcons::load().expect("No se pudieron cargar los iconos del HUD");
+        let strings =
+            Rc::new(Strings::load(&options.language).expect("No se pudieron cargar los textos"));
+        let (width, height) = frame_size(scale);
+        let mut renderer = options
+            .backend
+            .create(
+                width,
+                height,
+                skybox,
+                ship_vertices.clone(),
+                icons,
+                Rc::clone(&strings),
+            )
+            .expect("No se pudo crear el renderer");
+        renderer.screen_mut().ui_scale = scale;
+        renderer.set_expression_shaders(ExpressionShaders::new(&config.shaders));
+        renderer.set_fill_light(config.fill_light);
+        renderer.set_atlas_baking(options.bake, &simulation.planets);
+        renderer.set_noise_baking(options.bake && config.bake_noise, &simulation.planets);
+        for &pass in &config.disabled_passes {
+            renderer.graph_mut().set_enabled(pass, false);
+        }
+
+        App {
+            renderer,
+            strings,
+            simulation,
+            bindings: Bindings::default(),
+            input: InputFrame::default(),
+            ui_input: InputFrame::default(),
+            menu: Menu::default(),
+            help: Help::default(),
+            settings: config.settings,
+            recorder,
+            replay,
+            telemetry,
+            spectator,
+            multiplayer,
+            save_bookmarks,
+            script,
+            skyboxes,
+            ship_vertices,
+            config_path: options.config.clone(),
+            last_frame: Instant::now(),
+            accumulator: 0.0,
+        }
+    }
+
+    // One iteration of the main loop; false once the user asks to quit
+    pub fn frame(&mut self, window: &dyn InputSource) -> bool {
+        let live = self.bindings.poll(window, &self.ui_input);
+        if live.is_held(Action::Quit) {
+            return false;
+        }
+        self.help.handle(&live);
+        if self.menu.handle(&live, &mut self.settings) {
+            match self.multiplayer.as_mut() {
+                Some(multiplayer) => multiplayer.set_time_scale(self.settings.time_scale),
+                None => {
+                    self.simulation.time_scale = self.settings.time_scale;
+                    self.simulation.camera.fov = self.settings.fov;
+                }
+            }
+        }
+        self.ui_input = live;
+
+        let now = Instant::now();
+        let frame_time = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        self.accumulator += frame_time.min(0.05);
+
+        while self.accumulator >= FIXED_TIMESTEP {
+            if !self.step(window) {
+                // Waiting on another player: show the last step as it is
+                self.accumulator = FIXED_TIMESTEP;
+                break;
+            }
+            self.accumulator -= FIXED_TIMESTEP;
+        }
+
+        if let Some(skybox) = self.skyboxes.reload_if_changed() {
+            self.renderer.set_skybox(skybox);
+        }
+        self.renderer.reload_expression_shaders();
+
+        let snapshot = self
+            .simulation
+            .interpolated(self.accumulator / FIXED_TIMESTEP);
+        self.renderer
+            .render_frame(&self.simulation, &snapshot, &self.settings);
+        // Before the menu is drawn so it never ends up in the picture
+        if self.ui_input.was_pressed(Action::Screenshot) {
+            match screenshot::save(self.renderer.screen()) {
+                Ok(path) => {
+                    eprintln!("Captura guardada en {}", path.display());
+                    if let Err(error) = Shot::capture(&self.simulation).save_beside(&path) {
+                        eprintln!("No se pudieron guardar los datos de la captura: {}", error);
+                    }
+                }
+                Err(error) => eprintln!("No se pudo guardar la captura: {}", error),
+            }
+        }
+        if self.ui_input.was_pressed(Action::ExportHeightmap) {
+            self.export_heightmap();
+        }
+        if self.ui_input.was_pressed(Action::DumpState) {
+            self.dump_state();
+        }
+        if self.ui_input.was_pressed(Action::RenderStill) {
+            self.render_still(&snapshot);
+        }
+        let screen = self.renderer.screen_mut();
+        self.menu.draw(screen, &self.settings, &self.strings);
+        let theme = self.settings.theme();
+        self.help
+            .draw(screen, &self.bindings, &self.strings, &theme);
+        screen.composite_overlay();
+        if let Some(spectator) = self.spectator.as_mut() {
+            spectator.publish(self.renderer.screen());
+        }
+
+        if let Some(telemetry) = self.telemetry.as_mut() {
+            if let Err(error) =
+                telemetry.record(frame_time, self.renderer.stats(), self.renderer.graph())
+            {
+                eprintln!("Estadísticas detenidas: {}", error);
+                self.telemetry = None;
+            }
+        }
+
+        true
+    }
+
+    pub fn screen(&self) -> &Framebuffer {
+        self.renderer.screen()
+    }
+
+    // Called once when the window closes
+    pub fn shutdown(&self) {
+        if let Err(error) = save_settings(&self.config_path, &self.settings) {
+            eprintln!("No se pudieron guardar los ajustes: {}", error);
+        }
+    }
+
+    // One fixed step; false when it has to wait for the other players
+    fn step(&mut self, window: &dyn InputSource) -> bool {
+        let (mut delta, mut input) = match self.next_replay_frame() {
+            Some(frame) => frame,
+            // The menu takes over the arrow keys while it is open, and the
+            // help overlay covers the view
+            None if self.menu.open || self.help.open => (FIXED_TIMESTEP, InputFrame::default()),
+            None => (FIXED_TIMESTEP, self.bindings.poll(window, &self.input)),
+        };
+        // In multiplayer the step runs for every player at once
+        let mut stepped = false;
+        if let Some(multiplayer) = self.multiplayer.as_mut() {
+            match multiplayer.advance(&mut self.simulation, &input, &self.input, FIXED_TIMESTEP) {
+                Ok(Some(own)) => {
+                    input = own;
+                    delta = FIXED_TIMESTEP;
+                    stepped = true;
+                }
+                Ok(None) => return false,
+                Err(error) => {
+                    eprintln!("Partida multijugador terminada: {}", error);
+                    self.multiplayer = None;
+                    self.simulation.fleet.clear();
+                }
+            }
+        }
+        self.input = input;
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(error) = recorder.record(delta, &self.input) {
+                eprintln!("Grabación detenida: {}", error);
+                self.recorder = None;
+            }
+        }
+
+        if let Some(script) = self.script.as_mut() {
+            if !script.advance(&mut self.simulation, delta) {
+                eprintln!("Guion terminado");
+                self.script = None;
+            }
+        }
+        if !stepped {
+            self.simulation.update(&self.input, delta);
+        }
+        self.settings.fov = self.simulation.camera.fov;
+        // A player who doesn't host sees the host's time scale in the menu
+        if self
+            .multiplayer
+            .as_ref()
+            .is_some_and(|multiplayer| !multiplayer.is_host())
+        {
+            self.settings.time_scale = self.simulation.time_scale;
+        }
+
+        let stored =
+            (0..BOOKMARK_SLOTS).any(|slot| self.input.was_pressed(Action::StoreBookmark(slot)));
+        if stored && self.save_bookmarks {
+            if let Err(error) = self.simulation.bookmarks.save(BOOKMARKS_PATH) {
+                eprintln!("No se pudieron guardar los marcadores: {}", error);
+            }
+        }
+        if self.simulation.objectives.take_changed() {
+            if let Err(error) = self.simulation.objectives.save(OBJECTIVES_PATH) {
+                eprintln!("No se pudieron guardar las misiones: {}", error);
+            }
+        }
+
+        if self.input.was_pressed(Action::CycleSkybox) {
+            let sources = load_config(&self.config_path).skyboxes;
+            if let Some(skybox) = self.skyboxes.next(sources) {
+                self.renderer.set_skybox(skybox);
+            }
+        }
+        true
+    }
+
+    // Bakes the selected planet's relief into a 16-bit heightmap, with the
+    // seed its terrain mesh uses so both match
+    fn export_heightmap(&self) {
+        let Some(index) = self.simulation.selected else {
+            eprintln!("Selecciona un planeta con Tab para exportar su relieve");
+            return;
+        };
+        let planet = &self.simulation.planets[index];
+        let Some(height) = planet.height else {
+            eprintln!("{} no tiene relieve que exportar", planet.name);
+            return;
+        };
+        match heightmap::export(&planet.name, height, index as u32) {
+            Ok((path, heightmap)) => eprintln!(
+                "Relieve de {} guardado en {} (negro {:.4} y blanco {:.4} del radio)",
+                planet.name,
+                path.display(),
+                heightmap.low,
+                heightmap.high,
+            ),
+            Err(error) => eprintln!("No se pudo guardar el relieve: {}", error),
+        }
+    }
+
+    // Prints the scene state as one line of JSON and keeps a copy; every
+    // status message goes to stderr, so stdout gets nothing but the JSON
+    fn dump_state(&self) {
+        let json = state::to_json(&self.simulation);
+        println!("{}", json);
+        match state::save(&json) {
+            Ok(path) => eprintln!("Estado guardado en {}", path.display()),
+            Err(error) => eprintln!("No se pudo guardar el estado: {}", error),
+        }
+    }
+
+    // Renders the view again offscreen at high quality; the window waits
+    fn render_still(&mut self, snapshot: &Snapshot) {
+        eprintln!("Renderizando foto de alta calidad...");
+        let config = load_config(&self.config_path);
+        let still = Still::new(
+            self.renderer.screen(),
+            self.skyboxes.current(),
+            self.ship_vertices.clone(),
+            Rc::clone(&self.strings),
+            &config,
+        );
+        match still.and_then(|still| still.render(&self.simulation, snapshot, &self.settings)) {
+            Ok(path) => {
+                eprintln!("Foto guardada en {}", path.display());
+                if let Err(error) = Shot::capture(&self.simulation).save_beside(&path) {
+                    eprintln!("No se pudieron guardar los datos de la foto: {}", error);
+                }
+            }
+            Err(error) => eprintln!("No se pudo guardar la foto: {}", error),
+        }
+    }
+
+    fn next_replay_frame(&mut self) -> Option<(f32, InputFrame)> {
+        let frame = self.replay.as_mut()?.next_frame(&self.input);
+        if frame.is_none() {
+            eprintln!("Repetición terminada, control devuelto al teclado");
+            self.replay = None;
+        }
+        frame
+    }
+}
+
+// The system every run starts from, the same for every player of a
+// multiplayer game, who all get the host's world
+fn shared_simulation(world: &World) -> Simulation {
+    let mut simulation = Simulation::new(default_system(), world.seed);
+    simulation.belt = kuiper_belt(world.seed);
+    simulation.set_scene_scale(world.scale);
+    if let Some(mass) = world.star_mass {
+        simulation.set_star_mass(mass);
+    }
+    if world.kepler {
+        simulation.apply_kepler();
+    }
+    simulation
+}
+
+fn load_config(path: &Path) -> Config {
+    Config::load(path).unwrap_or_else(|error| {
+        eprintln!("Configuración inválida ({}): {}", path.display(), error);
+        Config::default()
+    })
+}
+
+// `--skybox` goes first so it is what the app starts with
+fn skybox_sources(options: &Options, config: &Config) -> Vec<String> {
+    let mut sources = config.skyboxes.clone();
+    if let Some(path) = &options.skybox {
+        sources.insert(0, path.display().to_string());
+    }
+    sources
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use crate::fragment::Fragment;
+use crate::line::LineSample;
+use crate::vertex::Vertex;
+
+// Buffers every draw needs, owned by the renderer and reused from draw to
+// draw and frame to frame, so once they have grown to fit the scene
+// rasterizing allocates nothing
+#[derive(Default)]
+pub struct FrameArena {
+    // The mesh being drawn, after the vertex shader
+    pub vertices: Vec<Vertex>,
+    // Fragments of the draw, one list per batch of triangles the workers
+    // split it into, in mesh order
+    pub batches: Vec<Vec<Fragment>>,
+    pub pool: FragmentPool,
+    // Terrain vertices displaced for the body being landed on
+    pub terrain: Vec<Vertex>,
+    pub lines: LineBuffers,
+}
+
+impl FrameArena {
+    // Hands the draw's fragment lists back to the pool, emptied
+    pub fn recycle(&mut self) {
+        for mut batch in self.batches.drain(..) {
+            batch.clear();
+            self.pool.give(batch);
+        }
+    }
+
+    // Once per frame: whatever the last frame left behind goes back
+    pub fn reset(&mut self) {
+        self.recycle();
+        self.vertices.clear();
+    }
+}
+
+// What drawing a polyline needs: the samples of the segment at hand and,
+// per pixel the line touches, its strongest coverage with the depth and
+// progress along the line there
+#[derive(Default)]
+pub struct LineBuffers {
+    pub samples: Vec<LineSample>,
+    pub coverage: HashMap<(i32, i32), (f32, f32, f32)>,
+}
+
+// Empty fragment lists that keep their capacity, handed out to the workers
+// rasterizing a draw in parallel. A worker that panicked holding the lock
+// leaves nothing but empty lists behind, so the pool carries on
+#[derive(Default)]
+pub struct FragmentPool {
+    lists: Mutex<Vec<Vec<Fragment>>>,
+}
+
+impl FragmentPool {
+    pub fn take(&self) -> Vec<Fragment> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_default()
+    }
+
+    pub fn give(&mut self, list: Vec<Fragment>) {
+        self.lists
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(list);
+    }
+}
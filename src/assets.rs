@@ -0,0 +1,40 @@
+use std::io;
+use std::path::Path;
+
+// Files the app needs to start. The browser has no file system, so the web
+// build compiles them in; everywhere else they are read from the working
+// directory as usual
+#[cfg(target_arch = "wasm32")]
+const EMBEDDED: &[(&str, &[u8])] = &[
+    (
+        "assets/models/Nave.obj",
+        include_bytes!("../assets/models/Nave.obj"),
+    ),
+    (
+        "assets/icons/mira.png",
+        include_bytes!("../assets/icons/mira.png"),
+    ),
+    ("lang/es.toml", include_bytes!("../lang/es.toml")),
+    ("lang/en.toml", include_bytes!("../lang/en.toml")),
+    ("config.toml", include_bytes!("../config.toml")),
+];
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    EMBEDDED
+        .iter()
+        .find(|(name, _)| Path::new(name) == path)
+        .map(|(_, bytes)| bytes.to_vec())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.display().to_string()))
+}
+
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    String::from_utf8(read(path)?)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
@@ -0,0 +1,117 @@
+use std::f32::consts::{PI, TAU};
+
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+
+use crate::color::Color;
+use crate::fragment_shaders::{Surface, SurfaceFunction};
+use crate::renderer::Uniforms;
+
+// Equirectangular: twice as wide as tall, one texel per degree and a bit
+const ATLAS_WIDTH: usize = 1024;
+const ATLAS_HEIGHT: usize = ATLAS_WIDTH / 2;
+// Screen radius in pixels up to which the atlas still has a texel per pixel
+// across the visible half of the planet; closer than that the procedural
+// shader takes over
+pub const ATLAS_MAX_RADIUS: f32 = ATLAS_WIDTH as f32 / 4.0;
+
+// A planet's surface shader evaluated once over its whole sphere, so distant
+// planets cost a texture lookup per fragment instead of several fbm calls
+pub struct PlanetAtlas {
+    albedo: Vec<Color>,
+    emission: Vec<Color>,
+}
+
+impl PlanetAtlas {
+    pub fn bake(surface: SurfaceFunction, seed: u32) -> Self {
+        let uniforms = Uniforms {
+            seed,
+            ..Uniforms::default()
+        };
+        let texels: Vec<Surface> = (0..ATLAS_WIDTH * ATLAS_HEIGHT)
+            .into_par_iter()
+            .map(|texel| {
+                let direction = texel_direction(texel, ATLAS_WIDTH, ATLAS_HEIGHT);
+                surface(direction, &uniforms)
+            })
+            .collect();
+
+        let color = |value: Vec3| Color::from_float(value.x, value.y, value.z);
+        PlanetAtlas {
+            albedo: texels.iter().map(|texel| color(texel.albedo)).collect(),
+            emission: texels.iter().map(|texel| color(texel.emission)).collect(),
+        }
+    }
+
+    // Bilinear lookup in the direction of an object-space position
+    pub fn sample(&self, position: Vec3) -> Surface {
+        let direction = position.normalize();
+        let lon = direction.z.atan2(direction.x);
+        let lat = direction.y.clamp(-1.0, 1.0).asin();
+        let u = (lon + PI) / TAU * ATLAS_WIDTH as f32 - 0.5;
+        let v = ((PI / 2.0 - lat) / PI * ATLAS_HEIGHT as f32 - 0.5)
+            .clamp(0.0, ATLAS_HEIGHT as f32 - 1.0);
+
+        let (x0, y0) = (u.floor(), v.floor());
+        let (tx, ty) = (u - x0, v - y0);
+        // Longitude wraps around, latitude stops at the poles
+        let column = |x: f32| (x as i32).rem_euclid(ATLAS_WIDTH as i32) as usize;
+        let (left, right) = (column(x0), column(x0 + 1.0));
+        let top = y0 as usize;
+        let bottom = (top + 1).min(ATLAS_HEIGHT - 1);
+
+        let filter = |texels: &[Color]| {
+            let at = |x: usize, y: usize| texels[y * ATLAS_WIDTH + x].to_vec3();
+            let upper = at(left, top) + (at(right, top) - at(left, top)) * tx;
+            let lower = at(left, bottom) + (at(right, bottom) - at(left, bottom)) * tx;
+            upper + (lower - upper) * ty
+        };
+        // No water: atlas planets are drawn without the glint
+        Surface {
+            emission: filter(&self.emission),
+            ..Surface::matte(filter(&self.albedo))
+        }
+    }
+}
+
+// Direction through the center of texel number `texel` of an
+// equirectangular map: longitude -180° at the left edge, north at the top
+pub fn texel_direction(texel: usize, width: usize, height: usize) -> Vec3 {
+    let u = (texel % width) as f32 + 0.5;
+    let v = (texel / width) as f32 + 0.5;
+    let lon = u / width as f32 * TAU - PI;
+    let lat = PI / 2.0 - v / height as f32 * PI;
+    Vec3::new(lat.cos() * lon.cos(), lat.sin(), lat.cos() * lon.sin())
+}
+
+// Mean albedo and emission over the whole sphere, from evenly spread
+// directions; what a planet looks like once it shrinks to a few pixels
+pub fn average_surface(surface: SurfaceFunction, seed: u32) -> Surface {
+    const SAMPLES: usize = 1024;
+    let uniforms = Uniforms {
+        seed,
+        ..Uniforms::default()
+    };
+    let golden_angle = PI * (3.0 - 5f32.sqrt());
+    let (albedo, emission) = (0..SAMPLES)
+        .map(|sample| {
+            // Fibonacci sphere: equal-area bands, each point turned by the
+            // golden angle from the last
+            let y = 1.0 - (sample as f32 + 0.5) / SAMPLES as f32 * 2.0;
+            let ring = (1.0 - y * y).sqrt();
+            let angle = sample as f32 * golden_angle;
+            let texel = surface(
+                Vec3::new(ring * angle.cos(), y, ring * angle.sin()),
+                &uniforms,
+            );
+            (texel.albedo, texel.emission)
+        })
+        .fold(
+            (Vec3::zeros(), Vec3::zeros()),
+            |(albedo, emission), texel| (albedo + texel.0, emission + texel.1),
+        );
+    Surface {
+        emission: emission / SAMPLES as f32,
+        ..Surface::matte(albedo / SAMPLES as f32)
+    }
+}
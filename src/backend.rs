@@ -0,0 +1,92 @@
+use std::io;
+use std::rc::Rc;
+
+use crate::expr::ExpressionShaders;
+use crate::framebuffer::Framebuffer;
+#[cfg(feature = "gpu")]
+use crate::gpu::GpuRenderer;
+use crate::hud::HudIcons;
+use crate::i18n::Strings;
+use crate::passes::RenderGraph;
+use crate::renderer::{FrameStats, SoftwareRenderer};
+use crate::settings::Settings;
+use crate::simulation::{Planet, Simulation, Snapshot};
+use crate::skybox::Skybox;
+use crate::vertex::Vertex;
+
+// Draws the simulation into a window-sized image. The software renderer is
+// the reference; other backends are there to be compared against it
+pub trait Renderer {
+    fn render_frame(&mut self, simulation: &Simulation, snapshot: &Snapshot, settings: &Settings);
+
+    // The last frame with its HUD; menus are drawn on top before it is shown
+    fn screen(&self) -> &Framebuffer;
+    fn screen_mut(&mut self) -> &mut Framebuffer;
+
+    fn stats(&self) -> &FrameStats;
+    // Passes a frame is drawn in, which of them run and how long they took
+    fn graph(&self) -> &RenderGraph;
+    fn graph_mut(&mut self) -> &mut RenderGraph;
+
+    fn set_skybox(&mut self, skybox: Skybox);
+    fn set_fill_light(&mut self, intensity: f32);
+
+    // Only the software renderer has these; other backends shade every
+    // planet with its built-in shader
+    fn set_expression_shaders(&mut self, _expressions: ExpressionShaders) {}
+    fn reload_expression_shaders(&mut self) {}
+    fn set_noise_baking(&mut self, _enabled: bool, _planets: &[Planet]) {}
+    fn set_atlas_baking(&mut self, _enabled: bool, _planets: &[Planet]) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Software,
+    // Experimental wgpu port, compiled in with `--features gpu`
+    Gpu,
+}
+
+impl Backend {
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "software" => Some(Backend::Software),
+            "gpu" => Some(Backend::Gpu),
+            _ => None,
+        }
+    }
+
+    pub fn create(
+        self,
+        width: usize,
+        height: usize,
+        skybox: Skybox,
+        ship_vertices: Vec<Vertex>,
+        icons: HudIcons,
+        strings: Rc<Strings>,
+    ) -> io::Result<Box<dyn Renderer>> {
+        match self {
+            Backend::Software => Ok(Box::new(SoftwareRenderer::new(
+                width,
+                height,
+                skybox,
+                ship_vertices,
+                icons,
+                strings,
+            ))),
+            #[cfg(feature = "gpu")]
+            Backend::Gpu => Ok(Box::new(GpuRenderer::new(
+                width,
+                height,
+                skybox,
+                ship_vertices,
+                icons,
+                strings,
+            )?)),
+            #[cfg(not(feature = "gpu"))]
+            Backend::Gpu => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "compilado sin la característica gpu (cargo run --release --features gpu)",
+            )),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::f32::consts::{FRAC_PI_3, TAU};
+
+use nalgebra_glm::Vec3;
+
+use crate::rng::Rng;
+
+// One of the many small bodies past the planets. They stay on clockwork
+// orbits even in gravity mode: there are too many to integrate and far too
+// little mass to matter
+pub struct BeltBody {
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub phase: f32,
+    // Height above or below the orbital plane, so the belt has thickness
+    pub height: f32,
+    pub scale: f32,
+    pub color: Vec3,
+}
+
+impl BeltBody {
+    pub fn position(&self, time: f32) -> Vec3 {
+        let angle = time * self.orbit_speed + self.phase;
+        Vec3::new(
+            self.orbit_radius * angle.cos(),
+            self.height,
+            self.orbit_radius * angle.sin(),
+        )
+    }
+}
+
+// Shape of a belt: how many bodies, how many of them are dwarf planets and
+// where they orbit
+pub struct BeltDef {
+    pub count: usize,
+    pub dwarf_planets: usize,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub thickness: f32,
+    // Angular speed at the inner edge; farther bodies slow down like Kepler's
+    // third law says
+    pub inner_speed: f32,
+    pub scale: (f32, f32),
+    pub dwarf_scale: (f32, f32),
+    // Colors are mixed between these two
+    pub colors: (Vec3, Vec3),
+}
+
+pub fn generate_belt(def: &BeltDef, seed: u64) -> Vec<BeltBody> {
+    let mut rng = Rng::stream(seed, "belt");
+    (0..def.count)
+        .map(|index| {
+            let radius = rng.range(def.inner_radius, def.outer_radius);
+            // The first few are the dwarf planets
+            let (small, large) = if index < def.dwarf_planets {
+                def.dwarf_scale
+            } else {
+                def.scale
+            };
+            // Small bodies far outnumber large ones
+            let size = rng.unit().powi(3);
+            let (from, to) = def.colors;
+            BeltBody {
+                orbit_radius: radius,
+                orbit_speed: def.inner_speed * (def.inner_radius / radius).powf(1.5),
+                phase: rng.unit() * TAU,
+                height: (rng.unit() - 0.5) * def.thickness,
+                scale: small + (large - small) * size,
+                color: from + (to - from) * rng.unit(),
+            }
+        })
+        .collect()
+}
+
+// Shape of the Trojan clusters around a planet's L4 and L5 points
+pub struct TrojanDef {
+    // Bodies around each point
+    pub count: usize,
+    // How far they spread along the orbit either way, in radians, and
+    // across it as a fraction of the orbit radius; clusters are long and thin
+    pub spread: f32,
+    pub width: f32,
+    pub scale: (f32, f32),
+    pub colors: (Vec3, Vec3),
+}
+
+// Trojans of a planet on a circular orbit: they share its radius and speed,
+// a sixth of a turn ahead of it and behind it, so in clockwork they keep
+// their place
+pub fn generate_trojans(
+    def: &TrojanDef,
+    orbit_radius: f32,
+    orbit_speed: f32,
+    phase: f32,
+    rng: &mut Rng,
+) -> Vec<BeltBody> {
+    let lead = FRAC_PI_3 * orbit_speed.signum();
+    let mut bodies = Vec::with_capacity(def.count * 2);
+    for point in [phase + lead, phase - lead] {
+        for _ in 0..def.count {
+            // Two uniform draws summed bunch the bodies towards the point
+            let along = (rng.unit() + rng.unit() - 1.0) * def.spread;
+            let across = (rng.unit() + rng.unit() - 1.0) * def.width;
+            let (from, to) = def.colors;
+            bodies.push(BeltBody {
+                orbit_radius: orbit_radius * (1.0 + across),
+                orbit_speed,
+                phase: point + along,
+                height: (rng.unit() - 0.5) * def.width * orbit_radius,
+                scale: def.scale.0 + (def.scale.1 - def.scale.0) * rng.unit().powi(3),
+                color: from + (to - from) * rng.unit(),
+            });
+        }
+    }
+    bodies
+}
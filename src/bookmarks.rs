@@ -0,0 +1,111 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use nalgebra_glm::Vec3;
+
+use crate::toml;
+
+pub const BOOKMARKS_PATH: &str = "bookmarks.toml";
+// One per digit key, 1 to 9
+pub const BOOKMARK_SLOTS: usize = 9;
+
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub name: String,
+    pub position: Vec3,
+    pub zoom: f32,
+    pub tilt: f32,
+}
+
+#[derive(Default)]
+pub struct Bookmarks {
+    slots: [Option<Bookmark>; BOOKMARK_SLOTS],
+    // While on, the digit keys recall bookmarks instead of warping to planets
+    pub browsing: bool,
+}
+
+impl Bookmarks {
+    // A missing file just means nothing has been saved yet
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Bookmarks::default())
+            }
+            Err(error) => return Err(error),
+        };
+        let table = toml::parse(&text)?;
+        let mut bookmarks = Bookmarks::default();
+
+        for (slot, entry) in bookmarks.slots.iter_mut().enumerate() {
+            let key = |field: &str| format!("{}.{}", slot + 1, field);
+            let number = |field: &str| table.get(&key(field)).and_then(|value| value.as_f32());
+            let position = table
+                .get(&key("position"))
+                .and_then(|value| value.as_array())
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_f32())
+                        .collect::<Vec<_>>()
+                });
+            if let (Some([x, y, z]), Some(zoom), Some(tilt)) =
+                (position.as_deref(), number("zoom"), number("tilt"))
+            {
+                let name = table
+                    .get(&key("name"))
+                    .and_then(|value| value.as_str())
+                    .unwrap_or("SIN NOMBRE");
+                *entry = Some(Bookmark {
+                    name: name.to_string(),
+                    position: Vec3::new(*x, *y, *z),
+                    zoom,
+                    tilt,
+                });
+            }
+        }
+
+        Ok(bookmarks)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut output = String::from(
+            "# Marcadores de cámara: Ctrl+1..9 guarda la vista, M y luego 1..9 vuelve a ella.\n\
+             # Los nombres se pueden editar a mano.\n",
+        );
+        for (slot, bookmark) in self.slots.iter().enumerate() {
+            if let Some(bookmark) = bookmark {
+                output.push_str(&format!(
+                    "\n[{}]\nname = \"{}\"\nposition = [{}, {}, {}]\nzoom = {}\ntilt = {}\n",
+                    slot + 1,
+                    bookmark.name.replace('\\', "\\\\").replace('"', "\\\""),
+                    bookmark.position.x,
+                    bookmark.position.y,
+                    bookmark.position.z,
+                    bookmark.zoom,
+                    bookmark.tilt,
+                ));
+            }
+        }
+        fs::write(path, output)
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Bookmark> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    pub fn store(&mut self, slot: usize, bookmark: Bookmark) {
+        if let Some(entry) = self.slots.get_mut(slot) {
+            *entry = Some(bookmark);
+        }
+    }
+
+    // (slot, bookmark) for every slot in use
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Bookmark)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, bookmark)| bookmark.as_ref().map(|bookmark| (slot, bookmark)))
+    }
+}
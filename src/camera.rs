@@ -0,0 +1,405 @@
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::input::{Action, InputFrame};
+use crate::settings::FOV_RANGE;
+
+// Field of view the pseudo-projection was tuned for
+pub const REFERENCE_FOV: f32 = 60.0;
+// Degrees per second while [ or ] is held
+const FOV_SPEED: f32 = 30.0;
+
+// Photo mode moves, zooms and turns at this fraction of the normal rate
+const PHOTO_SLOWDOWN: f32 = 0.15;
+// Radians per second
+const ROLL_SPEED: f32 = 0.6;
+// Free camera: how quickly velocity settles on the keys held with flight
+// assist on (per second), and thrust as a fraction of top speed per second
+// with it off
+const MOVE_DAMPING: f32 = 4.0;
+const DRIFT_THRUST: f32 = 1.5;
+const BOOST: f32 = 2.2;
+// The free camera crosses the scene's radius in about this many seconds,
+// and may wander this far past it
+const CROSSING_TIME: f32 = 9.0;
+const BOUNDS_MARGIN: f32 = 1.25;
+
+// Warps leave this much room around bodies they have to go around, as a
+// multiple of the collision radius, and take extra time per detour
+const WARP_CLEARANCE: f32 = 1.3;
+const WARP_DURATION: f32 = 0.9;
+const DETOUR_DURATION: f32 = 0.3;
+
+struct WarpState {
+    // Origin, detour waypoints and target, followed with a spline
+    path: Vec<Vec3>,
+    elapsed: f32,
+    duration: f32,
+}
+
+// The subset of camera state the renderer needs to project the scene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub position: Vec3,
+    pub zoom: f32,
+    // How much depth slides up the screen. A stand-in for pitch kept for the
+    // pseudo-projection; new camera controls should go through roll and fov
+    pub tilt: f32,
+    // Rotation of the image about the screen center, in radians
+    pub roll: f32,
+    // Horizontal field of view in degrees
+    pub fov: f32,
+    // Sub-pixel shift of the whole image, used by temporal accumulation
+    pub jitter: Vec2,
+}
+
+impl View {
+    pub fn lerp(&self, next: &View, alpha: f32) -> View {
+        View {
+            position: self.position + (next.position - self.position) * alpha,
+            zoom: self.zoom + (next.zoom - self.zoom) * alpha,
+            tilt: self.tilt + (next.tilt - self.tilt) * alpha,
+            roll: self.roll + (next.roll - self.roll) * alpha,
+            fov: self.fov + (next.fov - self.fov) * alpha,
+            jitter: next.jitter,
+        }
+    }
+
+    // Same view with everything on screen `factor` times larger
+    pub fn magnified(&self, factor: f32) -> View {
+        View {
+            zoom: self.zoom * factor,
+            tilt: self.tilt * factor,
+            ..*self
+        }
+    }
+
+    // How much larger the scene looks than at the reference field of view
+    pub fn fov_magnification(&self) -> f32 {
+        let half = |degrees: f32| (degrees.to_radians() * 0.5).tan();
+        half(REFERENCE_FOV) / half(self.fov)
+    }
+
+    // Offset from the screen center (y grows downwards) in x/y, and the
+    // distance used for depth testing in z
+    pub fn project(&self, world: Vec3) -> Vec3 {
+        let relative = world - self.position;
+        let (x, y) = self.rolled(
+            relative.x * self.zoom,
+            -(relative.y * self.zoom + relative.z * self.tilt),
+        );
+        Vec3::new(
+            x + self.jitter.x,
+            y + self.jitter.y,
+            relative.magnitude().max(0.0001),
+        )
+    }
+
+    // Turns a world-space offset into the frame meshes are shaded in:
+    // screen pixels on x/y like `project`, and z pointing away from the camera
+    pub fn orient(&self, offset: Vec3) -> Vec3 {
+        let (x, y) = self.rolled(
+            offset.x * self.zoom,
+            -(offset.y * self.zoom + offset.z * self.tilt),
+        );
+        Vec3::new(x, y, offset.z * self.zoom - offset.y * self.tilt)
+    }
+
+    fn rolled(&self, x: f32, y: f32) -> (f32, f32) {
+        let (sin, cos) = self.roll.sin_cos();
+        (x * cos - y * sin, x * sin + y * cos)
+    }
+}
+
+pub struct Camera {
+    pub position: Vec3,
+    pub zoom: f32,
+    pub tilt: f32,
+    pub roll: f32,
+    pub fov: f32,
+    pub speed: f32,
+    // Half the side of the box the camera is kept inside
+    pub bounds: f32,
+    pub velocity: Vec3,
+    // On, the camera eases to a stop when the keys are released; off, it
+    // keeps drifting until thrust the other way cancels it
+    pub flight_assist: bool,
+    warp: Option<WarpState>,
+    pub last_direction: Vec3,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            position: Vec3::new(0.0, 0.0, -250.0),
+            zoom: 1.0,
+            tilt: 0.45,
+            roll: 0.0,
+            fov: REFERENCE_FOV,
+            speed: 200.0,
+            bounds: 1600.0,
+            velocity: Vec3::zeros(),
+            flight_assist: true,
+            warp: None,
+            last_direction: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // Speed and bounds for a scene reaching `extent` from its center
+    pub fn fit_extent(&mut self, extent: f32) {
+        self.speed = extent / CROSSING_TIME;
+        self.bounds = extent * BOUNDS_MARGIN;
+    }
+
+    pub fn view(&self) -> View {
+        View {
+            position: self.position,
+            zoom: self.zoom,
+            tilt: self.tilt,
+            roll: self.roll,
+            fov: self.fov,
+            jitter: Vec2::zeros(),
+        }
+    }
+
+    pub fn handle_input(&mut self, input: &InputFrame, delta: f32, anchor: Option<Vec2>) {
+        if self.warp.is_some() {
+            return;
+        }
+
+        let direction = self.movement(input);
+        let thrust = if direction.magnitude() > 0.0 {
+            let boost = if input.is_held(Action::Boost) { BOOST } else { 1.0 };
+            direction.normalize() * self.speed * boost
+        } else {
+            Vec3::zeros()
+        };
+        if self.flight_assist {
+            self.velocity += (thrust - self.velocity) * (1.0 - (-MOVE_DAMPING * delta).exp());
+        } else {
+            self.velocity += thrust * DRIFT_THRUST * delta;
+        }
+        self.position += self.velocity * delta;
+        self.track_direction(direction);
+        self.handle_zoom(input, delta, anchor);
+        self.handle_lens(input, delta);
+
+        if self.position.y.abs() > 140.0 {
+            self.position.y = self.position.y.clamp(-140.0, 140.0);
+            self.velocity.y = 0.0;
+        }
+    }
+
+    // Photo mode: slow, unconstrained movement
+    pub fn handle_photo_input(&mut self, input: &InputFrame, delta: f32, anchor: Option<Vec2>) {
+        let direction = self.movement(input);
+        if direction.magnitude() > 0.0 {
+            self.position += direction.normalize() * self.speed * PHOTO_SLOWDOWN * delta;
+        }
+        self.handle_zoom(input, delta * PHOTO_SLOWDOWN, anchor);
+        self.handle_lens(input, delta * PHOTO_SLOWDOWN);
+    }
+
+    // Roll and field of view, shared by every way of driving the camera
+    pub fn handle_lens(&mut self, input: &InputFrame, delta: f32) {
+        if input.is_held(Action::RollLeft) {
+            self.roll -= ROLL_SPEED * delta;
+        }
+        if input.is_held(Action::RollRight) {
+            self.roll += ROLL_SPEED * delta;
+        }
+        if input.is_held(Action::WidenFov) {
+            self.fov = (self.fov + FOV_SPEED * delta).min(FOV_RANGE.1);
+        }
+        if input.is_held(Action::NarrowFov) {
+            self.fov = (self.fov - FOV_SPEED * delta).max(FOV_RANGE.0);
+        }
+    }
+
+    // Direction of the movement keys held, turned with the roll so left and
+    // right follow the screen; not normalized
+    fn movement(&self, input: &InputFrame) -> Vec3 {
+        let mut direction = Vec3::new(0.0, 0.0, 0.0);
+        if input.is_held(Action::Forward) {
+            direction.z -= 1.0;
+        }
+        if input.is_held(Action::Backward) {
+            direction.z += 1.0;
+        }
+        if input.is_held(Action::Left) {
+            direction.x -= 1.0;
+        }
+        if input.is_held(Action::Right) {
+            direction.x += 1.0;
+        }
+        if input.is_held(Action::Ascend) {
+            direction.y += 1.0;
+        }
+        if input.is_held(Action::Descend) {
+            direction.y -= 1.0;
+        }
+        let (sin, cos) = self.roll.sin_cos();
+        Vec3::new(
+            direction.x * cos - direction.y * sin,
+            direction.x * sin + direction.y * cos,
+            direction.z,
+        )
+    }
+
+    // `anchor` is the world x/y offset from the camera of a point that should
+    // keep its place on screen; without one, zoom scales about the center
+    pub fn handle_zoom(&mut self, input: &InputFrame, delta: f32, anchor: Option<Vec2>) {
+        let previous = self.zoom;
+        if input.is_held(Action::ZoomIn) {
+            self.zoom = (self.zoom + delta * 0.6).min(1.8);
+        }
+        if input.is_held(Action::ZoomOut) {
+            self.zoom = (self.zoom - delta * 0.6).max(0.35);
+        }
+        if let Some(anchor) = anchor {
+            let shift = anchor * (1.0 - previous / self.zoom);
+            self.position.x += shift.x;
+            self.position.y += shift.y;
+        }
+    }
+
+    // World x/y offset of whatever is drawn at `cursor` (pixels from the screen
+    // center, y down), assuming it lies level with the camera
+    pub fn offset_under(&self, cursor: Vec2) -> Vec2 {
+        let view = self.view();
+        let (sin, cos) = view.roll.sin_cos();
+        let scale = view.fov_magnification() * view.zoom;
+        Vec2::new(
+            (cursor.x * cos + cursor.y * sin) / scale,
+            (cursor.x * sin - cursor.y * cos) / scale,
+        )
+    }
+
+    // Remembers the last movement direction so the ship model can bank into turns
+    pub fn track_direction(&mut self, direction: Vec3) {
+        if direction.magnitude() > 0.0 {
+            self.last_direction = direction.normalize();
+        } else {
+            self.last_direction *= 0.9;
+        }
+    }
+
+    pub fn is_warping(&self) -> bool {
+        self.warp.is_some()
+    }
+
+    pub fn advance_warp(&mut self, delta: f32) {
+        if let Some(state) = self.warp.as_mut() {
+            state.elapsed += delta;
+            let progress = (state.elapsed / state.duration).clamp(0.0, 1.0);
+            self.position = along_path(&state.path, ease_in_out_cubic(progress));
+            if progress >= 1.0 {
+                self.warp = None;
+            }
+        }
+    }
+
+    // Goes around any of the `blockers` (center, collision radius) on the way
+    pub fn start_warp(&mut self, target: Vec3, blockers: &[(Vec3, f32)]) {
+        let path = plan_warp(self.position, target, blockers);
+        self.velocity = Vec3::zeros();
+        self.warp = Some(WarpState {
+            duration: WARP_DURATION + DETOUR_DURATION * (path.len() - 2) as f32,
+            path,
+            elapsed: 0.0,
+        });
+    }
+
+    pub fn warp_progress(&self) -> Option<f32> {
+        self.warp
+            .as_ref()
+            .map(|state| (state.elapsed / state.duration).clamp(0.0, 1.0))
+    }
+
+    // Returns true when the camera had to be pushed out of a body
+    pub fn resolve_collisions(&mut self, blockers: &[(Vec3, f32)]) -> bool {
+        let mut collided = false;
+        for (center, radius) in blockers {
+            let planar = Vec3::new(self.position.x - center.x, 0.0, self.position.z - center.z);
+            let distance = (planar.x * planar.x + planar.z * planar.z).sqrt();
+            if distance < *radius && distance > 0.001 {
+                let push = planar.normalize() * (*radius - distance + 4.0);
+                self.position.x += push.x;
+                self.position.z += push.z;
+                collided = true;
+            }
+        }
+
+        let bounds = self.bounds;
+        self.position.x = self.position.x.clamp(-bounds, bounds);
+        self.position.y = self.position.y.clamp(-bounds, bounds);
+        self.position.z = self.position.z.clamp(-bounds, bounds);
+        collided
+    }
+}
+
+// Straight line unless it cuts through a body, in which case a waypoint beside
+// each one in the way, in order along the line. Collisions only look at the
+// orbital plane, so detours stay in it too. Bodies around the start or the
+// target (usually the planet warped to) don't count.
+fn plan_warp(origin: Vec3, target: Vec3, blockers: &[(Vec3, f32)]) -> Vec<Vec3> {
+    let flat = |v: Vec3| Vec3::new(v.x, 0.0, v.z);
+    let line = flat(target - origin);
+    let length_squared = line.magnitude_squared();
+    let mut detours: Vec<(f32, Vec3)> = Vec::new();
+
+    for &(center, radius) in blockers {
+        let inside = |point: Vec3| flat(point - center).magnitude() < radius;
+        if length_squared < 0.0001 || inside(origin) || inside(target) {
+            continue;
+        }
+        let t = (flat(center - origin).dot(&line) / length_squared).clamp(0.0, 1.0);
+        let closest = origin + (target - origin) * t;
+        let away = flat(closest - center);
+        if away.magnitude() >= radius {
+            continue;
+        }
+        // Dead center: either side will do
+        let side = if away.magnitude() > 0.001 {
+            away.normalize()
+        } else {
+            Vec3::new(-line.z, 0.0, line.x).normalize()
+        };
+        let beside = Vec3::new(center.x, closest.y, center.z) + side * radius * WARP_CLEARANCE;
+        detours.push((t, beside));
+    }
+    detours.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut path = vec![origin];
+    path.extend(detours.into_iter().map(|(_, point)| point));
+    path.push(target);
+    path
+}
+
+// Catmull-Rom spline through every point of `path`, `t` from 0 to 1
+fn along_path(path: &[Vec3], t: f32) -> Vec3 {
+    let segments = path.len() - 1;
+    let scaled = t.clamp(0.0, 1.0) * segments as f32;
+    let index = (scaled as usize).min(segments - 1);
+    let local = scaled - index as f32;
+    let (p0, p1, p2, p3) = (
+        path[index.saturating_sub(1)],
+        path[index],
+        path[index + 1],
+        path[(index + 2).min(segments)],
+    );
+    let (t2, t3) = (local * local, local * local * local);
+    (p1 * 2.0
+        + (p2 - p0) * local
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+fn ease_in_out_cubic(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
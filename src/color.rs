@@ -1,5 +1,7 @@
 use std::fmt;
 
+use nalgebra_glm::Vec3;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
     r: u8,
@@ -9,7 +11,7 @@ pub struct Color {
 
 impl Color {
     // Constructor to initialize the color using r, g, b values as u8
-    pub fn new(r: u8, g: u8, b: u8) -> Self {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
         Color { r, g, b }
     }
 
@@ -39,6 +41,90 @@ impl Color {
     pub fn to_hex(&self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
+
+    // Channels as 0.0 to 1.0 floats, the form shaders do their math in
+    pub fn to_vec3(self) -> Vec3 {
+        Vec3::new(self.r as f32, self.g as f32, self.b as f32) / 255.0
+    }
+
+    // Hue in degrees (0 to 360), saturation and value from 0.0 to 1.0
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, second, 0.0),
+            1 => (second, chroma, 0.0),
+            2 => (0.0, chroma, second),
+            3 => (0.0, second, chroma),
+            4 => (second, 0.0, chroma),
+            _ => (chroma, 0.0, second),
+        };
+        let offset = value - chroma;
+        Color::from_float(r + offset, g + offset, b + offset)
+    }
+
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let rgb = self.to_vec3();
+        let max = rgb.x.max(rgb.y).max(rgb.z);
+        let min = rgb.x.min(rgb.y).min(rgb.z);
+        let chroma = max - min;
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == rgb.x {
+            60.0 * ((rgb.y - rgb.z) / chroma).rem_euclid(6.0)
+        } else if max == rgb.y {
+            60.0 * ((rgb.z - rgb.x) / chroma + 2.0)
+        } else {
+            60.0 * ((rgb.x - rgb.y) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max)
+    }
+
+    // `self` at t = 0, `other` at t = 1
+    pub fn lerp(&self, other: &Color, t: f32) -> Color {
+        let mixed = self.to_vec3() + (other.to_vec3() - self.to_vec3()) * t.clamp(0.0, 1.0);
+        Color::from_float(mixed.x, mixed.y, mixed.z)
+    }
+
+    // Perceived brightness (Rec. 601 weights), 0.0 to 1.0
+    pub fn luminance(&self) -> f32 {
+        let rgb = self.to_vec3();
+        rgb.x * 0.299 + rgb.y * 0.587 + rgb.z * 0.114
+    }
+}
+
+// Color ramp: stops sorted by position, linearly blended in between and held
+// flat past either end. Borrows its stops so ramps can be `const`.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette<'a> {
+    // (position, color) pairs in increasing position
+    pub stops: &'a [(f32, Color)],
+}
+
+impl<'a> Palette<'a> {
+    pub const fn new(stops: &'a [(f32, Color)]) -> Self {
+        Palette { stops }
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let Some(&(first_position, first)) = self.stops.first() else {
+            return Color::black();
+        };
+        if t <= first_position {
+            return first;
+        }
+        for pair in self.stops.windows(2) {
+            let ((start, from), (end, to)) = (pair[0], pair[1]);
+            if t <= end {
+                let span = (end - start).max(f32::EPSILON);
+                return from.lerp(&to, (t - start) / span);
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
 }
 
 // Implement addition for Color
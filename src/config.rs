@@ -0,0 +1,241 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::assets;
+use crate::passes::Pass;
+use crate::pixel_art::PixelPalette;
+use crate::settings::Settings;
+use crate::theme::PalettePreset;
+use crate::toml;
+use crate::units::SceneScale;
+
+pub const CONFIG_PATH: &str = "config.toml";
+const SETTINGS_HEADER: &str = "[settings]";
+
+pub struct Config {
+    // Backgrounds cycled with B: image paths or `procedural:<preset>`
+    pub skyboxes: Vec<String>,
+    // (planet name, expression file) pairs from the `[shaders]` section
+    pub shaders: Vec<(String, String)>,
+    // Brightness of the white light from the camera; 0 leaves night sides dark
+    pub fill_light: f32,
+    // Bake the planets' static noise onto cubemaps at startup instead of
+    // evaluating it for every fragment
+    pub bake_noise: bool,
+    // Proportions the scene is drawn in
+    pub scale: SceneScale,
+    // Orbit speeds from Kepler's third law instead of the scene's
+    pub kepler: bool,
+    // Mass of the star the orbits go round; None keeps the built-in one
+    pub star_mass: Option<f32>,
+    // Render passes left out of every frame, for debugging and profiling
+    pub disabled_passes: Vec<Pass>,
+    // Menu options from the `[settings]` section, rewritten on exit
+    pub settings: Settings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            skyboxes: vec!["procedural:estrellas".to_string()],
+            shaders: Vec::new(),
+            fill_light: 0.15,
+            bake_noise: true,
+            scale: SceneScale::Stylized,
+            kepler: false,
+            star_mass: None,
+            disabled_passes: Vec::new(),
+            settings: Settings::default(),
+        }
+    }
+}
+
+impl Config {
+    // A missing file is not an error: every setting falls back to its default
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = match assets::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(error),
+        };
+        let table = toml::parse(&text)?;
+        let mut config = Config::default();
+
+        if let Some(list) = table.get("skyboxes").and_then(|value| value.as_array()) {
+            let skyboxes: Vec<String> = list
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect();
+            if !skyboxes.is_empty() {
+                config.skyboxes = skyboxes;
+            }
+        }
+
+        if let Some(fill_light) = table.get("fill_light").and_then(|value| value.as_f32()) {
+            config.fill_light = fill_light.max(0.0);
+        }
+
+        if let Some(bake_noise) = table.get("bake_noise").and_then(|value| value.as_bool()) {
+            config.bake_noise = bake_noise;
+        }
+
+        if let Some(name) = table.get("scale").and_then(|value| value.as_str()) {
+            config.scale = SceneScale::named(name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("escala desconocida: {} (estilizada o semirrealista)", name),
+                )
+            })?;
+        }
+
+        if let Some(kepler) = table.get("kepler").and_then(|value| value.as_bool()) {
+            config.kepler = kepler;
+        }
+
+        if let Some(mass) = table.get("star_mass").and_then(|value| value.as_f32()) {
+            config.star_mass = Some(mass.max(1.0));
+        }
+
+        if let Some(list) = table
+            .get("disabled_passes")
+            .and_then(|value| value.as_array())
+        {
+            for name in list.iter().filter_map(|item| item.as_str()) {
+                let pass = Pass::named(name).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("pasada desconocida: {}", name),
+                    )
+                })?;
+                config.disabled_passes.push(pass);
+            }
+        }
+
+        for (key, value) in &table {
+            if let (Some(planet), Some(path)) = (key.strip_prefix("shaders."), value.as_str()) {
+                config.shaders.push((planet.to_string(), path.to_string()));
+            }
+        }
+
+        let flag = |key: &str| table.get(key).and_then(|value| value.as_bool());
+        let number = |key: &str| table.get(key).and_then(|value| value.as_f32());
+        let settings = &mut config.settings;
+        settings.show_orbits = flag("settings.orbits").unwrap_or(settings.show_orbits);
+        settings.show_labels = flag("settings.labels").unwrap_or(settings.show_labels);
+        settings.show_grid = flag("settings.grid").unwrap_or(settings.show_grid);
+        settings.show_lagrange = flag("settings.lagrange").unwrap_or(settings.show_lagrange);
+        settings.trojans = flag("settings.trojans").unwrap_or(settings.trojans);
+        settings.bloom = flag("settings.bloom").unwrap_or(settings.bloom);
+        settings.bloom_intensity =
+            number("settings.bloom_intensity").unwrap_or(settings.bloom_intensity);
+        settings.antialiasing = flag("settings.antialiasing").unwrap_or(settings.antialiasing);
+        settings.visibility_assist =
+            flag("settings.visibility_assist").unwrap_or(settings.visibility_assist);
+        settings.accumulation = flag("settings.accumulation").unwrap_or(settings.accumulation);
+        settings.time_scale = number("settings.time_scale").unwrap_or(settings.time_scale);
+        settings.fov = number("settings.fov").unwrap_or(settings.fov);
+        settings.resolution_scale =
+            number("settings.resolution_scale").unwrap_or(settings.resolution_scale);
+        settings.depth_of_field =
+            number("settings.depth_of_field").unwrap_or(settings.depth_of_field);
+        settings.vignette = number("settings.vignette").unwrap_or(settings.vignette);
+        settings.film_grain = number("settings.film_grain").unwrap_or(settings.film_grain);
+        settings.crt = flag("settings.crt").unwrap_or(settings.crt);
+        if let Some(name) = table
+            .get("settings.pixel_art")
+            .and_then(|value| value.as_str())
+        {
+            settings.pixel_art = PixelPalette::named(name);
+        }
+        if let Some(name) = table
+            .get("settings.palette")
+            .and_then(|value| value.as_str())
+        {
+            // An unknown name keeps the default rather than refusing to start,
+            // since this section is rewritten on every exit anyway
+            settings.palette = PalettePreset::named(name).unwrap_or(settings.palette);
+        }
+        settings.high_contrast = flag("settings.high_contrast").unwrap_or(settings.high_contrast);
+        config.settings = config.settings.clamped();
+
+        Ok(config)
+    }
+}
+
+// Replaces the `[settings]` section of the file (it is always written last)
+// and leaves the rest, comments included, as the user wrote it
+pub fn save_settings<P: AsRef<Path>>(path: P, settings: &Settings) -> io::Result<()> {
+    let path = path.as_ref();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+
+    let mut kept = Vec::new();
+    let mut in_settings = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') && !trimmed.contains('"') {
+            in_settings = trimmed == SETTINGS_HEADER;
+        }
+        if !in_settings {
+            kept.push(line);
+        }
+    }
+    while kept.last().is_some_and(|line| line.trim().is_empty()) {
+        kept.pop();
+    }
+
+    let mut output = kept.join("\n");
+    if !output.is_empty() {
+        output.push_str("\n\n");
+    }
+    output.push_str(&format!(
+        "{}\n\
+         # Ajustes del menú (O); se reescriben al salir\n\
+         orbits = {}\n\
+         labels = {}\n\
+         grid = {}\n\
+         lagrange = {}\n\
+         trojans = {}\n\
+         bloom = {}\n\
+         bloom_intensity = {}\n\
+         antialiasing = {}\n\
+         visibility_assist = {}\n\
+         accumulation = {}\n\
+         time_scale = {}\n\
+         fov = {}\n\
+         resolution_scale = {}\n\
+         depth_of_field = {}\n\
+         vignette = {}\n\
+         film_grain = {}\n\
+         crt = {}\n\
+         pixel_art = \"{}\"\n\
+         palette = \"{}\"\n\
+         high_contrast = {}\n",
+        SETTINGS_HEADER,
+        settings.show_orbits,
+        settings.show_labels,
+        settings.show_grid,
+        settings.show_lagrange,
+        settings.trojans,
+        settings.bloom,
+        settings.bloom_intensity,
+        settings.antialiasing,
+        settings.visibility_assist,
+        settings.accumulation,
+        settings.time_scale,
+        settings.fov,
+        settings.resolution_scale,
+        settings.depth_of_field,
+        settings.vignette,
+        settings.film_grain,
+        settings.crt,
+        settings.pixel_art.map_or("no", PixelPalette::name),
+        settings.palette.name(),
+        settings.high_contrast,
+    ));
+    fs::write(path, output)
+}
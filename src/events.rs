@@ -0,0 +1,190 @@
+use std::f32::consts::{PI, TAU};
+
+use nalgebra_glm::Vec3;
+
+use crate::camera::View;
+use crate::simulation::{BodyState, Planet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignmentKind {
+    // A planet's moon crossing in front of the planet
+    MoonTransit,
+    // A planet crossing in front of the star
+    StarEclipse,
+}
+
+// One body passing in front of another as seen through the current view
+#[derive(Debug, Clone, Copy)]
+pub struct Alignment {
+    pub kind: AlignmentKind,
+    pub planet: usize,
+    pub front: Vec3,
+    pub back: Vec3,
+    pub front_radius: f32,
+    // 0 when the front body is at the rim of the back one, 1 when centered
+    pub coverage: f32,
+}
+
+impl Alignment {
+    // A spot just outside the front body, on the side away from the back one,
+    // so the pair stays lined up and near the center of the screen
+    pub fn viewpoint(&self) -> Vec3 {
+        let away = self.front - self.back;
+        let direction = if away.magnitude() > 0.001 {
+            away.normalize()
+        } else {
+            Vec3::new(0.0, 0.0, -1.0)
+        };
+        self.front + direction * self.front_radius * 3.0
+    }
+}
+
+// An alignment the clockwork orbits will reach, seen from the star rather
+// than through the view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Predicted {
+    pub kind: PredictedKind,
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredictedKind {
+    // Two planets in line with the star on the same side; the inner one
+    // crosses the star as seen from the outer
+    Conjunction(usize, usize),
+    // A moon passing between its planet and the star
+    MoonTransit(usize),
+}
+
+// Orbits are sampled this often looking for crossings, in simulated seconds;
+// short enough that the fastest moon turns well under half a circle
+const PREDICTION_STEP: f32 = 0.1;
+const BISECTIONS: usize = 20;
+
+// Alignments between `from` and `from + horizon`, soonest first, worked out
+// from the orbits in closed form. A negative horizon looks back in time
+pub fn predict_events(planets: &[Planet], from: f32, horizon: f32) -> Vec<Predicted> {
+    let longitude = |index: usize, time: f32| {
+        let position = planets[index].position(time);
+        position.z.atan2(position.x)
+    };
+    let orbiting: Vec<usize> = (1..planets.len())
+        .filter(|&index| planets[index].orbit_radius > 0.0)
+        .collect();
+
+    let mut events = Vec::new();
+    for (order, &planet) in orbiting.iter().enumerate() {
+        for &other in &orbiting[order + 1..] {
+            let separation = |time| wrap_angle(longitude(planet, time) - longitude(other, time));
+            for time in crossings(separation, from, horizon) {
+                let kind = PredictedKind::Conjunction(planet, other);
+                events.push(Predicted { kind, time });
+            }
+        }
+        for moon in &planets[planet].moons {
+            // Zero when the moon sits on the star's side of the planet
+            let separation = |time| {
+                let offset = moon.offset(time);
+                wrap_angle(offset.z.atan2(offset.x) - longitude(planet, time) - PI)
+            };
+            for time in crossings(separation, from, horizon) {
+                let kind = PredictedKind::MoonTransit(planet);
+                events.push(Predicted { kind, time });
+            }
+        }
+    }
+    events.sort_by(|a, b| (a.time - from).abs().total_cmp(&(b.time - from).abs()));
+    events
+}
+
+// Times where the angle `angle(t)` passes through zero, leaving out one that
+// is happening right at `from`. A jump between -π and π is the angle wrapping
+// around, not a crossing
+fn crossings(angle: impl Fn(f32) -> f32, from: f32, horizon: f32) -> Vec<f32> {
+    let steps = (horizon.abs() / PREDICTION_STEP).ceil() as usize;
+    let stride = PREDICTION_STEP.copysign(horizon);
+    let mut found = Vec::new();
+    let (mut start, mut previous) = (from, angle(from));
+    for step in 1..=steps {
+        let time = from + step as f32 * stride;
+        let value = angle(time);
+        if previous.signum() != value.signum() && (value - previous).abs() < PI {
+            // Halve the step until the crossing is pinned down
+            let (mut low, mut high) = (start, time);
+            for _ in 0..BISECTIONS {
+                let middle = (low + high) * 0.5;
+                if angle(middle).signum() == previous.signum() {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            let crossing = (low + high) * 0.5;
+            if (crossing - from).abs() > PREDICTION_STEP * 0.5 {
+                found.push(crossing);
+            }
+        }
+        (start, previous) = (time, value);
+    }
+    found
+}
+
+// The same angle in (-π, π]
+fn wrap_angle(angle: f32) -> f32 {
+    PI - (PI - angle).rem_euclid(TAU)
+}
+
+pub fn find_alignments(planets: &[Planet], bodies: &[BodyState], view: &View) -> Vec<Alignment> {
+    let mut alignments = Vec::new();
+    let star = planets.first().zip(bodies.first());
+
+    for (index, (planet, body)) in planets.iter().zip(bodies).enumerate() {
+        for (moon, &moon_position) in planet.moons.iter().zip(&body.moon_positions) {
+            let overlap = disk_overlap(view, moon_position, body.position, planet.scale);
+            if let Some(coverage) = overlap {
+                alignments.push(Alignment {
+                    kind: AlignmentKind::MoonTransit,
+                    planet: index,
+                    front: moon_position,
+                    back: body.position,
+                    front_radius: moon.scale,
+                    coverage,
+                });
+            }
+        }
+
+        if let Some((star, star_body)) = star.filter(|_| index > 0) {
+            let overlap = disk_overlap(view, body.position, star_body.position, star.scale);
+            if let Some(coverage) = overlap {
+                alignments.push(Alignment {
+                    kind: AlignmentKind::StarEclipse,
+                    planet: index,
+                    front: body.position,
+                    back: star_body.position,
+                    front_radius: planet.scale,
+                    coverage,
+                });
+            }
+        }
+    }
+
+    alignments.sort_by(|a, b| b.coverage.total_cmp(&a.coverage));
+    alignments
+}
+
+// How centered the projected `front` body is over the disk of `back`, or
+// `None` when its center is off that disk or `front` is actually behind
+fn disk_overlap(view: &View, front: Vec3, back: Vec3, back_radius: f32) -> Option<f32> {
+    let near = view.project(front);
+    let far = view.project(back);
+    if near.z >= far.z {
+        return None;
+    }
+
+    let reach = back_radius * view.zoom;
+    let separation = ((near.x - far.x).powi(2) + (near.y - far.y).powi(2)).sqrt();
+    if separation >= reach {
+        return None;
+    }
+    Some(1.0 - separation / reach)
+}
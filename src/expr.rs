@@ -0,0 +1,535 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use nalgebra_glm::Vec3;
+use web_time::Instant;
+
+use crate::noise::{fbm, noise, ridged, simplex, warp};
+
+const RELOAD_CHECK: Duration = Duration::from_millis(500);
+
+// Inputs every expression can read; their slots come first in `Program::slots`
+const INPUTS: [&str; 6] = ["pos", "normal", "lat", "lon", "time", "light"];
+// No function takes more arguments than this, so calls evaluate them on the
+// stack
+const MAX_ARGS: usize = 3;
+
+thread_local! {
+    // Slot values for `Program::run`, reused so fragments don't allocate
+    static SLOTS: RefCell<Vec<Value>> = const { RefCell::new(Vec::new()) };
+}
+
+// A tiny shading language for tweaking planet looks without recompiling.
+// A file is a list of `name = expression` lines; the last assignment to
+// `color` is the fragment color. Values are numbers or vec3s, with
+// component access (`pos.y`), + - * / and a handful of GLSL-like functions.
+pub struct Program {
+    statements: Vec<(usize, Expr)>,
+    slots: usize,
+    color: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Scalar(f32),
+    Vector(Vec3),
+}
+
+impl Value {
+    fn scalar(self) -> f32 {
+        match self {
+            Value::Scalar(value) => value,
+            Value::Vector(vector) => vector.x,
+        }
+    }
+
+    fn vector(self) -> Vec3 {
+        match self {
+            Value::Scalar(value) => Vec3::new(value, value, value),
+            Value::Vector(vector) => vector,
+        }
+    }
+
+    // Applies `op` per component, broadcasting scalars against vectors
+    fn zip(self, other: Value, op: impl Fn(f32, f32) -> f32) -> Value {
+        match (self, other) {
+            (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(op(a, b)),
+            (a, b) => {
+                let (a, b) = (a.vector(), b.vector());
+                Value::Vector(Vec3::new(op(a.x, b.x), op(a.y, b.y), op(a.z, b.z)))
+            }
+        }
+    }
+
+    fn map(self, op: impl Fn(f32) -> f32) -> Value {
+        match self {
+            Value::Scalar(value) => Value::Scalar(op(value)),
+            Value::Vector(v) => Value::Vector(Vec3::new(op(v.x), op(v.y), op(v.z))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Sin,
+    Cos,
+    Abs,
+    Floor,
+    Fract,
+    Sqrt,
+    Pow,
+    Min,
+    Max,
+    Clamp,
+    Mix,
+    Smoothstep,
+    Length,
+    Normalize,
+    Dot,
+    Noise,
+    Fbm,
+    Simplex,
+    Ridged,
+    Warp,
+    Vec3,
+}
+
+impl Function {
+    fn named(name: &str) -> Option<(Function, usize)> {
+        let function = match name {
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "abs" => (Function::Abs, 1),
+            "floor" => (Function::Floor, 1),
+            "fract" => (Function::Fract, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            "pow" => (Function::Pow, 2),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            "clamp" => (Function::Clamp, 3),
+            "mix" => (Function::Mix, 3),
+            "smoothstep" => (Function::Smoothstep, 3),
+            "length" => (Function::Length, 1),
+            "normalize" => (Function::Normalize, 1),
+            "dot" => (Function::Dot, 2),
+            "noise" => (Function::Noise, 1),
+            "fbm" => (Function::Fbm, 2),
+            "simplex" => (Function::Simplex, 1),
+            "ridged" => (Function::Ridged, 2),
+            "warp" => (Function::Warp, 2),
+            "vec3" => (Function::Vec3, 3),
+            _ => return None,
+        };
+        Some(function)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(f32),
+    Slot(usize),
+    Component(Box<Expr>, usize),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, slots: &[Value]) -> Value {
+        match self {
+            Expr::Number(value) => Value::Scalar(*value),
+            Expr::Slot(slot) => slots[*slot],
+            Expr::Component(inner, index) => Value::Scalar(inner.eval(slots).vector()[*index]),
+            Expr::Negate(inner) => inner.eval(slots).map(|value| -value),
+            Expr::Binary(op, left, right) => {
+                let (a, b) = (left.eval(slots), right.eval(slots));
+                match op {
+                    '+' => a.zip(b, |a, b| a + b),
+                    '-' => a.zip(b, |a, b| a - b),
+                    '*' => a.zip(b, |a, b| a * b),
+                    _ => a.zip(b, |a, b| if b == 0.0 { 0.0 } else { a / b }),
+                }
+            }
+            Expr::Call(function, args) => {
+                let mut values = [Value::Scalar(0.0); MAX_ARGS];
+                for (value, arg) in values.iter_mut().zip(args) {
+                    *value = arg.eval(slots);
+                }
+                call(*function, &values)
+            }
+        }
+    }
+}
+
+fn call(function: Function, args: &[Value]) -> Value {
+    match function {
+        Function::Sin => args[0].map(f32::sin),
+        Function::Cos => args[0].map(f32::cos),
+        Function::Abs => args[0].map(f32::abs),
+        Function::Floor => args[0].map(f32::floor),
+        Function::Fract => args[0].map(|value| value - value.floor()),
+        Function::Sqrt => args[0].map(|value| value.max(0.0).sqrt()),
+        Function::Pow => args[0].zip(args[1], |a, b| a.max(0.0).powf(b)),
+        Function::Min => args[0].zip(args[1], f32::min),
+        Function::Max => args[0].zip(args[1], f32::max),
+        Function::Clamp => args[0].zip(args[1], f32::max).zip(args[2], f32::min),
+        Function::Mix => {
+            let difference = args[1].zip(args[0], |b, a| b - a);
+            args[0].zip(difference.zip(args[2], |d, t| d * t), |a, d| a + d)
+        }
+        Function::Smoothstep => {
+            let (edge0, edge1) = (args[0].scalar(), args[1].scalar());
+            args[2].map(|x| {
+                let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            })
+        }
+        Function::Length => Value::Scalar(args[0].vector().magnitude()),
+        Function::Normalize => {
+            let vector = args[0].vector();
+            let length = vector.magnitude();
+            Value::Vector(if length > 0.0 {
+                vector / length
+            } else {
+                vector
+            })
+        }
+        Function::Dot => Value::Scalar(args[0].vector().dot(&args[1].vector())),
+        Function::Noise => Value::Scalar(noise(args[0].vector(), 0)),
+        Function::Fbm => Value::Scalar(fbm(
+            args[0].vector(),
+            args[1].scalar().clamp(1.0, 8.0) as u32,
+            0,
+        )),
+        Function::Simplex => Value::Scalar(simplex(args[0].vector(), 0)),
+        Function::Ridged => Value::Scalar(ridged(
+            args[0].vector(),
+            args[1].scalar().clamp(1.0, 8.0) as u32,
+            0,
+        )),
+        Function::Warp => Value::Vector(warp(args[0].vector(), args[1].scalar(), 0)),
+        Function::Vec3 => Value::Vector(Vec3::new(
+            args[0].scalar(),
+            args[1].scalar(),
+            args[2].scalar(),
+        )),
+    }
+}
+
+impl Program {
+    pub fn parse(source: &str) -> Result<Program, String> {
+        let mut names: Vec<String> = INPUTS.iter().map(|name| name.to_string()).collect();
+        let mut statements = Vec::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("línea {}: {}", number + 1, message);
+
+            let (name, expression) = line
+                .split_once('=')
+                .ok_or_else(|| error("se esperaba `nombre = expresión`".to_string()))?;
+            let name = name.trim();
+            if !is_identifier(name) || INPUTS.contains(&name) {
+                return Err(error(format!("no se puede asignar a `{}`", name)));
+            }
+
+            let tokens = tokenize(expression).map_err(error)?;
+            let mut parser = Parser {
+                tokens,
+                cursor: 0,
+                names: &names,
+            };
+            let expr = parser.expression().map_err(error)?;
+            if parser.cursor < parser.tokens.len() {
+                return Err(error("sobra texto al final".to_string()));
+            }
+
+            let slot = match names.iter().position(|existing| existing == name) {
+                Some(slot) => slot,
+                None => {
+                    names.push(name.to_string());
+                    names.len() - 1
+                }
+            };
+            statements.push((slot, expr));
+        }
+
+        let color = names
+            .iter()
+            .position(|name| name == "color")
+            .ok_or_else(|| "falta asignar `color`".to_string())?;
+        Ok(Program {
+            statements,
+            slots: names.len(),
+            color,
+        })
+    }
+
+    // Returns the color as unclamped RGB; `light` is the diffuse brightness
+    pub fn run(&self, position: Vec3, normal: Vec3, light: f32, time: f32) -> Vec3 {
+        let radius = position.magnitude().max(0.0001);
+        SLOTS.with_borrow_mut(|slots| {
+            slots.clear();
+            slots.resize(self.slots, Value::Scalar(0.0));
+            slots[0] = Value::Vector(position);
+            slots[1] = Value::Vector(normal);
+            slots[2] = Value::Scalar(position.y / radius);
+            slots[3] = Value::Scalar(position.z.atan2(position.x));
+            slots[4] = Value::Scalar(time);
+            slots[5] = Value::Scalar(light);
+
+            for (slot, expr) in &self.statements {
+                slots[*slot] = expr.eval(slots);
+            }
+            slots[self.color].vector()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let character = chars[index];
+        if character.is_whitespace() {
+            index += 1;
+        } else if character.is_ascii_digit()
+            || (character == '.' && chars.get(index + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+            let literal: String = chars[start..index].iter().collect();
+            let value = literal
+                .parse()
+                .map_err(|_| format!("número inválido `{}`", literal))?;
+            tokens.push(Token::Number(value));
+        } else if character.is_alphabetic() || character == '_' {
+            let start = index;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            tokens.push(Token::Name(chars[start..index].iter().collect()));
+        } else if "+-*/(),.".contains(character) {
+            tokens.push(Token::Symbol(character));
+            index += 1;
+        } else {
+            return Err(format!("carácter inesperado `{}`", character));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|rest| rest.is_alphanumeric() || rest == '_')
+}
+
+// Recursive descent: sum -> product -> unary -> postfix -> primary
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    cursor: usize,
+    names: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.cursor)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("se esperaba `{}`", symbol))
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                '*'
+            } else if self.eat('/') {
+                '/'
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        while self.eat('.') {
+            let component = match self.peek() {
+                Some(Token::Name(name)) if name == "x" || name == "r" => 0,
+                Some(Token::Name(name)) if name == "y" || name == "g" => 1,
+                Some(Token::Name(name)) if name == "z" || name == "b" => 2,
+                _ => return Err("se esperaba un componente x, y o z".to_string()),
+            };
+            self.cursor += 1;
+            expr = Expr::Component(Box::new(expr), component);
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| "expresión incompleta".to_string())?;
+        self.cursor += 1;
+
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Symbol('(') => {
+                let inner = self.expression()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Token::Name(name) if self.eat('(') => {
+                let (function, arity) = Function::named(&name)
+                    .ok_or_else(|| format!("función desconocida `{}`", name))?;
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                if args.len() != arity {
+                    return Err(format!("`{}` recibe {} argumentos", name, arity));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Token::Name(name) => self
+                .names
+                .iter()
+                .position(|existing| *existing == name)
+                .map(Expr::Slot)
+                .ok_or_else(|| format!("variable desconocida `{}`", name)),
+            Token::Symbol(symbol) => Err(format!("símbolo inesperado `{}`", symbol)),
+        }
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    program: Option<Arc<Program>>,
+}
+
+// Expression shaders assigned to planets by name in the config. Files are
+// polled for changes; a file that fails to parse keeps its last good version.
+pub struct ExpressionShaders {
+    entries: HashMap<String, Entry>,
+    last_check: Instant,
+}
+
+impl ExpressionShaders {
+    pub fn new(assignments: &[(String, String)]) -> Self {
+        let mut shaders = ExpressionShaders {
+            entries: HashMap::new(),
+            last_check: Instant::now(),
+        };
+        for (planet, path) in assignments {
+            let entry = Entry {
+                path: PathBuf::from(path),
+                modified: None,
+                program: None,
+            };
+            shaders.entries.insert(planet.clone(), entry);
+        }
+        shaders.reload(true);
+        shaders
+    }
+
+    pub fn get(&self, planet: &str) -> Option<Arc<Program>> {
+        self.entries.get(planet)?.program.clone()
+    }
+
+    pub fn reload_if_changed(&mut self) {
+        if self.last_check.elapsed() >= RELOAD_CHECK {
+            self.last_check = Instant::now();
+            self.reload(false);
+        }
+    }
+
+    fn reload(&mut self, force: bool) {
+        for (planet, entry) in self.entries.iter_mut() {
+            let modified = fs::metadata(&entry.path)
+                .and_then(|meta| meta.modified())
+                .ok();
+            if !force && modified == entry.modified {
+                continue;
+            }
+            entry.modified = modified;
+
+            let parsed = fs::read_to_string(&entry.path)
+                .map_err(|error| error.to_string())
+                .and_then(|source| Program::parse(&source));
+            match parsed {
+                Ok(program) => {
+                    eprintln!("Shader de {} cargado: {}", planet, entry.path.display());
+                    entry.program = Some(Arc::new(program));
+                }
+                Err(error) => eprintln!("Shader {} inválido: {}", entry.path.display(), error),
+            }
+        }
+    }
+}
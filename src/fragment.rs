@@ -5,14 +5,40 @@ pub struct Fragment {
     pub position: Vec2,
     pub color: Color,
     pub depth: f32,
+    // How much of what is behind it the fragment covers, 1 for solid
+    pub alpha: f32,
+    pub emissive: Color,
 }
 
 impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32) -> Self {
+    pub fn new(x: f32, y: f32, color: Color, depth: f32, alpha: f32) -> Self {
         Fragment {
             position: Vec2::new(x, y),
             color,
             depth,
+            alpha,
+            emissive: Color::black(),
+        }
+    }
+}
+
+// What a fragment shader returns for a fragment it keeps: the color and
+// its coverage, below 1 for translucent surfaces
+#[derive(Debug, Clone, Copy)]
+pub struct Shaded {
+    pub color: Color,
+    pub alpha: f32,
+    // Light the fragment gives off by itself (lava, a star's surface). It
+    // goes to the framebuffer's emissive buffer, which bloom spreads as glow
+    pub emissive: Color,
+}
+
+impl Shaded {
+    pub fn opaque(color: Color) -> Self {
+        Shaded {
+            color,
+            alpha: 1.0,
+            emissive: Color::black(),
         }
     }
 }
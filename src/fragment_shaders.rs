@@ -1,18 +1,76 @@
-use crate::color::Color;
+use crate::color::{Color, Palette};
+use crate::fragment::Shaded;
+use crate::light::TO_CAMERA;
+use crate::noise::{fbm, hash, NoiseLayer};
+use crate::renderer::{Uniforms, CORONA_SHELL};
 use crate::vertex::Vertex;
-use nalgebra_glm::{dot, Vec2, Vec3};
+use nalgebra_glm::{dot, reflect_vec, Vec2, Vec3};
+
+// Called with the triangle's vertices, then the fragment's object-space
+// position (unrotated, so patterns stay fixed to the body), its rotated normal
+// and its texture coordinates. `None` discards the fragment
+pub type FragmentShader =
+    fn(&Vertex, &Vertex, &Vertex, Vec3, Vec3, Vec2, &Uniforms) -> Option<Shaded>;
+
+// The lighting-independent part of a static surface shader, at an
+// object-space position. It is what gets baked into a planet's atlas
+pub type SurfaceFunction = fn(Vec3, &Uniforms) -> Surface;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Surface {
+    // Color the incoming light is multiplied by
+    pub albedo: Vec3,
+    // Light the surface gives off by itself, added after lighting
+    pub emission: Vec3,
+    // Share of the point that is open water, which mirrors the stars
+    pub water: f32,
+}
 
-pub type FragmentShader = fn(&Vertex, &Vertex, &Vertex, Vec3, Vec3, Vec2) -> Color;
+impl Surface {
+    pub fn matte(albedo: Vec3) -> Self {
+        Surface {
+            albedo,
+            emission: Vec3::zeros(),
+            water: 0.0,
+        }
+    }
 
-// Utility functions for noise and patterns
-fn hash(n: f32) -> f32 {
-    let x = (n * 12.9898).sin() * 43758.5453;
-    x - x.floor()
+    pub fn ocean(albedo: Vec3, water: f32) -> Self {
+        Surface {
+            water,
+            ..Surface::matte(albedo)
+        }
+    }
 }
 
-fn hash_vec3(p: Vec3) -> f32 {
-    let n = p.x * 12.9898 + p.y * 78.233 + p.z * 45.164;
-    hash(n)
+// The static fbm layers of each surface shader, in the order they are read.
+// The renderer can bake them per body (see `NoiseCube`) and the terrain
+// heights reuse them so the relief matches the colors
+pub const ROCKY_LAYERS: [NoiseLayer; 3] = [
+    NoiseLayer::new(2.0, 4),
+    NoiseLayer::new(3.0, 3),
+    NoiseLayer::new(4.0, 3),
+];
+pub const AZURE_LAYERS: [NoiseLayer; 3] = [
+    NoiseLayer::new(4.0, 4),
+    NoiseLayer::new(2.5, 3),
+    NoiseLayer::new(6.0, 5),
+];
+pub const CRIMSON_LAYERS: [NoiseLayer; 2] = [NoiseLayer::new(3.5, 4), NoiseLayer::new(8.0, 5)];
+pub const ICE_GIANT_LAYERS: [NoiseLayer; 2] = [NoiseLayer::new(1.5, 3), NoiseLayer::new(4.0, 3)];
+pub const MOLTEN_LAYERS: [NoiseLayer; 3] = [
+    NoiseLayer::new(2.5, 4),
+    NoiseLayer::new(6.0, 4),
+    NoiseLayer::new(3.0, 3),
+];
+
+// Layer `index` of `layers`, read from the body's baked cubemap when the
+// renderer made one and computed on the spot otherwise
+fn layer(layers: &[NoiseLayer], index: usize, position: Vec3, uniforms: &Uniforms) -> f32 {
+    match &uniforms.noise {
+        Some(cube) => cube.sample(index, position),
+        None => layers[index].sample(position, uniforms.seed),
+    }
 }
 
 fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
@@ -20,53 +78,82 @@ fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
     t * t * (3.0 - 2.0 * t)
 }
 
-fn noise(p: Vec3) -> f32 {
-    let i = Vec3::new(p.x.floor(), p.y.floor(), p.z.floor());
-    let f = Vec3::new(p.x - i.x, p.y - i.y, p.z - i.z);
+// Cooled basalt to glowing fissures; the crust ramp stops at the ember color
+const LAVA_CRUST: Palette = Palette::new(&[
+    (0.0, Color::new(51, 13, 13)),
+    (1.0, Color::new(189, 41, 20)),
+]);
+const LAVA: Palette = Palette::new(&[
+    (0.0, Color::new(51, 13, 13)),
+    (0.5, Color::new(189, 41, 20)),
+    (1.0, Color::new(255, 107, 46)),
+]);
+// Cooled crust of a molten world, from fresh dark glass to ashen rock
+const COOLED_CRUST: Palette =
+    Palette::new(&[(0.0, Color::new(28, 22, 24)), (1.0, Color::new(82, 66, 60))]);
+// Ice giant bands, from deep teal to pale cyan
+const ICE_BANDS: Palette = Palette::new(&[
+    (0.0, Color::new(24, 96, 130)),
+    (0.5, Color::new(70, 170, 196)),
+    (1.0, Color::new(168, 228, 236)),
+]);
+// Aurora curtains: green at the base, violet towards the top
+const AURORA: Palette = Palette::new(&[
+    (0.0, Color::new(51, 255, 115)),
+    (1.0, Color::new(179, 64, 230)),
+]);
+
+// Half-width of the twilight band of an atmosphere of density 1, as the
+// cosine of the star's elevation; denser air spreads it wider
+const TWILIGHT_WIDTH: f32 = 0.2;
+
+// Diffuse light reaching a surface point, summed over every light. Stars
+// get a twilight band as wide as the body's atmosphere makes it, and are
+// blocked by the occluders between them and the point
+fn lighting(position: Vec3, normal: Vec3, uniforms: &Uniforms) -> Vec3 {
+    let offset = uniforms.to_view(position);
+    let normal = normal.normalize();
+    let width = TWILIGHT_WIDTH * uniforms.atmosphere;
+    let (stars, others) = uniforms
+        .lights
+        .split_at(uniforms.stars.min(uniforms.lights.len()));
+    let starlight = stars.iter().fold(Vec3::zeros(), |sum, light| {
+        let shadow = light.visibility(offset, &uniforms.occluders);
+        sum + light.scattered(offset, &normal, width) * shadow
+    });
+    others
+        .iter()
+        .fold(starlight, |sum, light| sum + light.diffuse(offset, &normal))
+}
 
-    // Smooth interpolation
-    let u = Vec3::new(
-        smoothstep(0.0, 1.0, f.x),
-        smoothstep(0.0, 1.0, f.y),
-        smoothstep(0.0, 1.0, f.z),
-    );
+// How tight the sun glint on water is, and how bright at its center
+const GLINT_SHININESS: f32 = 90.0;
+const GLINT_STRENGTH: f32 = 0.9;
 
-    // Hash values at corners
-    let a = hash_vec3(i);
-    let b = hash_vec3(Vec3::new(i.x + 1.0, i.y, i.z));
-    let c = hash_vec3(Vec3::new(i.x, i.y + 1.0, i.z));
-    let d = hash_vec3(Vec3::new(i.x + 1.0, i.y + 1.0, i.z));
-    let e = hash_vec3(Vec3::new(i.x, i.y, i.z + 1.0));
-    let f_val = hash_vec3(Vec3::new(i.x + 1.0, i.y, i.z + 1.0));
-    let g = hash_vec3(Vec3::new(i.x, i.y + 1.0, i.z + 1.0));
-    let h = hash_vec3(Vec3::new(i.x + 1.0, i.y + 1.0, i.z + 1.0));
-
-    // Trilinear interpolation
-    let x1 = a + (b - a) * u.x;
-    let x2 = c + (d - c) * u.x;
-    let y1 = x1 + (x2 - x1) * u.y;
-
-    let x3 = e + (f_val - e) * u.x;
-    let x4 = g + (h - g) * u.x;
-    let y2 = x3 + (x4 - x3) * u.y;
-
-    y1 + (y2 - y1) * u.z
-}
-
-fn fbm(p: Vec3, octaves: u32) -> f32 {
-    let mut value = 0.0;
-    let mut amplitude = 0.5;
-    let mut frequency = 1.0;
-
-    for _ in 0..octaves {
-        value += amplitude * noise(Vec3::new(p.x * frequency, p.y * frequency, p.z * frequency));
-        amplitude *= 0.5;
-        frequency *= 2.0;
+// Reflection of every star on the open water of a surface point: the
+// bright highlight where the normal halves the way to the star and camera
+fn sun_glint(position: Vec3, normal: Vec3, water: f32, uniforms: &Uniforms) -> Vec3 {
+    if water <= 0.0 {
+        return Vec3::zeros();
     }
+    let offset = uniforms.to_view(position);
+    let normal = normal.normalize();
+    let stars = &uniforms.lights[..uniforms.stars.min(uniforms.lights.len())];
+    let glint = stars.iter().fold(Vec3::zeros(), |sum, light| {
+        sum + light.specular(offset, &normal, GLINT_SHININESS)
+    });
+    glint * (water * GLINT_STRENGTH)
+}
 
-    value
+// Per-channel factor a surface color is multiplied by: the diffuse light
+// plus an ambient floor tinted like the stars
+fn light_factor(light: Vec3, diffuse: f32, ambient: f32, uniforms: &Uniforms) -> Vec3 {
+    light * diffuse + uniforms.ambient * ambient
 }
 
+// Share of the photosphere's color it gives to bloom
+const STAR_GLOW: f32 = 0.12;
+
 // Star/Sun Shader
 pub fn star_shader(
     _v1: &Vertex,
@@ -75,17 +162,19 @@ pub fn star_shader(
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
     let light_dir = Vec3::new(0.0, 0.0, -1.0);
     let intensity = dot(&normal, &light_dir).max(0.0);
 
-    // Base yellow-orange color
-    let base_color = Vec3::new(1.0, 0.7, 0.3);
+    // Photosphere color derived from the star's temperature
+    let base_color = uniforms.star.surface;
 
     // Add noise for surface variation
     let noise_value = fbm(
         Vec3::new(position.x * 5.0, position.y * 5.0, position.z * 5.0),
         3,
+        uniforms.seed,
     );
     let variation = 0.1 * noise_value;
 
@@ -103,8 +192,14 @@ pub fn star_shader(
     // Apply lighting
     let light_factor = intensity * 0.7 + 0.3;
     let final_color = Vec3::new(r * light_factor, g * light_factor, b * light_factor);
-
-    Color::from_float(final_color.x, final_color.y, final_color.z)
+    let color = Color::from_float(final_color.x, final_color.y, final_color.z);
+
+    // The whole photosphere glows softly, not just what clears the bloom
+    // threshold; it is bright enough that a little goes a long way
+    Some(Shaded {
+        emissive: color * STAR_GLOW,
+        ..Shaded::opaque(color)
+    })
 }
 
 // Rocky Planet Shader (Earth-like)
@@ -115,38 +210,37 @@ pub fn rocky_planet_shader(
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
-    let intensity = dot(&normal, &light_dir).max(0.0);
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
+    let surface = rocky_planet_surface(position, uniforms);
+
+    // Apply lighting with ambient
+    let light_factor = light_factor(light, 0.8, 0.2, uniforms);
+    let final_color = surface.albedo.component_mul(&light_factor)
+        + sun_glint(position, normal, surface.water, uniforms);
+
+    Some(Shaded::opaque(Color::from_float(final_color.x, final_color.y, final_color.z)))
+}
 
+pub fn rocky_planet_surface(position: Vec3, uniforms: &Uniforms) -> Surface {
     // Use spherical coordinates for consistent mapping
     let lat = (position.y / position.magnitude()).acos();
 
     // Layer 1: Ocean/Continents base
-    let continent_noise = fbm(
-        Vec3::new(position.x * 2.0, position.y * 2.0, position.z * 2.0),
-        4,
-    );
+    let continent_noise = layer(&ROCKY_LAYERS, 0, position, uniforms);
     let is_land = continent_noise > 0.1;
 
     // Layer 2: Ocean depth variation
     let ocean_depth = if !is_land {
-        fbm(
-            Vec3::new(position.x * 3.0, position.y * 3.0, position.z * 3.0),
-            3,
-        ) * 0.3
-            + 0.7
+        layer(&ROCKY_LAYERS, 1, position, uniforms) * 0.3 + 0.7
     } else {
         0.0
     };
 
     // Layer 3: Land elevation
     let elevation = if is_land {
-        fbm(
-            Vec3::new(position.x * 4.0, position.y * 4.0, position.z * 4.0),
-            3,
-        ) * 0.5
-            + 0.5
+        layer(&ROCKY_LAYERS, 2, position, uniforms) * 0.5 + 0.5
     } else {
         0.0
     };
@@ -201,11 +295,7 @@ pub fn rocky_planet_shader(
         (ocean_color.x, ocean_color.y, ocean_color.z)
     };
 
-    // Apply lighting with ambient
-    let light_factor = intensity * 0.8 + 0.2;
-    let final_color = Vec3::new(r * light_factor, g * light_factor, b * light_factor);
-
-    Color::from_float(final_color.x, final_color.y, final_color.z)
+    Surface::ocean(Vec3::new(r, g, b), if is_land { 0.0 } else { 1.0 })
 }
 
 pub fn azure_planet_shader(
@@ -215,18 +305,28 @@ pub fn azure_planet_shader(
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(0.1, 0.2, -1.0).normalize();
-    let intensity = dot(&normal.normalize(), &light_dir).max(0.0);
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
+    let surface = azure_planet_surface(position, uniforms);
 
-    let polar_noise = fbm(
-        Vec3::new(position.x * 4.0, position.y * 4.0, position.z * 4.0),
-        4,
-    );
-    let ocean_noise = fbm(
-        Vec3::new(position.x * 2.5, position.y * 2.5, position.z * 2.5),
-        3,
-    );
+    let highlight = (normal.y * 0.5 + 0.5).powf(8.0) * 0.3;
+    let final_color = surface
+        .albedo
+        .component_mul(&light_factor(light, 0.75, 0.25, uniforms))
+        + Vec3::new(highlight, highlight, highlight * 0.8)
+        + sun_glint(position, normal, surface.water, uniforms);
+
+    Some(Shaded::opaque(Color::from_float(
+        final_color.x.clamp(0.0, 1.0),
+        final_color.y.clamp(0.0, 1.0),
+        final_color.z.clamp(0.0, 1.0),
+    )))
+}
+
+pub fn azure_planet_surface(position: Vec3, uniforms: &Uniforms) -> Surface {
+    let polar_noise = layer(&AZURE_LAYERS, 0, position, uniforms);
+    let ocean_noise = layer(&AZURE_LAYERS, 1, position, uniforms);
 
     let ice_caps = ((position.y.abs() / position.magnitude()).powi(4) + polar_noise * 0.3)
         .clamp(0.0, 1.0);
@@ -237,25 +337,15 @@ pub fn azure_planet_shader(
     let aurora = Vec3::new(0.5, 0.9, 1.0);
 
     let base_water = abyss * (1.0 - ocean_mix) + lagoon * ocean_mix;
-    let cloud_bands = fbm(
-        Vec3::new(position.x * 6.0, position.y * 6.0, position.z * 6.0),
-        5,
-    )
-        .powf(3.0);
+    let cloud_bands = layer(&AZURE_LAYERS, 2, position, uniforms).powf(3.0);
     let cloud_color = Vec3::new(0.85, 0.95, 1.0);
     let mixed = base_water * (1.0 - cloud_bands) + cloud_color * cloud_bands;
 
     let ice_color = aurora * (0.6 + ice_caps * 0.4);
     let final_base = mixed * (1.0 - ice_caps) + ice_color * ice_caps;
 
-    let highlight = (normal.y * 0.5 + 0.5).powf(8.0) * 0.3;
-    let final_color = final_base * (intensity * 0.75 + 0.25) + Vec3::new(highlight, highlight, highlight * 0.8);
-
-    Color::from_float(
-        final_color.x.clamp(0.0, 1.0),
-        final_color.y.clamp(0.0, 1.0),
-        final_color.z.clamp(0.0, 1.0),
-    )
+    // Clouds and ice cover the water
+    Surface::ocean(final_base, (1.0 - cloud_bands) * (1.0 - ice_caps))
 }
 
 pub fn crimson_planet_shader(
@@ -265,117 +355,409 @@ pub fn crimson_planet_shader(
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(-0.2, 0.4, -1.0).normalize();
-    let intensity = dot(&normal.normalize(), &light_dir).max(0.0);
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
+    let surface = crimson_planet_surface(position, uniforms);
 
-    let basalt_noise = fbm(
-        Vec3::new(position.x * 3.5, position.y * 3.5, position.z * 3.5),
-        4,
-    );
-    let fissure_noise = fbm(
-        Vec3::new(position.x * 8.0, position.y * 8.0, position.z * 8.0),
-        5,
+    let rim_specular = (normal.y * 0.5 + 0.5).powf(8.0) * 0.3;
+
+    let shaded = surface.albedo.component_mul(&light_factor(light, 0.8, 0.2, uniforms)) + surface.emission;
+    let final_color = Vec3::new(
+        (shaded.x + rim_specular).clamp(0.0, 1.0),
+        (shaded.y + rim_specular * 0.4).clamp(0.0, 1.0),
+        shaded.z.clamp(0.0, 1.0),
     );
 
+    let glow = surface.emission;
+    Some(Shaded {
+        emissive: Color::from_float(glow.x, glow.y, glow.z),
+        ..Shaded::opaque(Color::from_float(final_color.x, final_color.y, final_color.z))
+    })
+}
+
+pub fn crimson_planet_surface(position: Vec3, uniforms: &Uniforms) -> Surface {
+    let basalt_noise = layer(&CRIMSON_LAYERS, 0, position, uniforms);
+    let fissure_noise = layer(&CRIMSON_LAYERS, 1, position, uniforms);
+
     let crater_mask = (basalt_noise - 0.45).abs();
     let lava_threshold = (fissure_noise * 1.4 - 0.5).clamp(0.0, 1.0);
 
-    let basalt = Vec3::new(0.2, 0.05, 0.05);
-    let ember = Vec3::new(0.74, 0.16, 0.08);
-    let lava_core = Vec3::new(1.0, 0.42, 0.18);
-
     let lava_mix = lava_threshold.powf(1.6);
-    let surface_color = basalt * (1.0 - lava_mix) + ember * lava_mix;
-    let molten_core = surface_color * (1.0 - lava_mix) + lava_core * lava_mix;
+    let surface_color = LAVA_CRUST.sample(lava_mix).to_vec3();
+    let molten_core = LAVA.sample(lava_mix).to_vec3();
 
     let crater_color = surface_color * (0.5 + crater_mask * 0.4);
     let final_base = crater_color * (1.0 - lava_mix) + molten_core * lava_mix;
 
-    let rim_specular = (normal.y * 0.5 + 0.5).powf(8.0) * 0.3;
     let glow = lava_mix * 0.4;
+    Surface {
+        emission: Vec3::new(glow, glow * 0.6, glow * 0.4),
+        ..Surface::matte(final_base)
+    }
+}
 
-    let shaded = final_base * (intensity * 0.8 + 0.2) + Vec3::new(glow, glow * 0.6, glow * 0.4);
-    let final_color = Vec3::new(
-        (shaded.x + rim_specular).clamp(0.0, 1.0),
-        (shaded.y + rim_specular * 0.4).clamp(0.0, 1.0),
-        shaded.z.clamp(0.0, 1.0),
-    );
+// Ice giant (Neptune-like): smooth cyan and teal bands with faint methane
+// streaks running along them
+pub fn ice_giant_shader(
+    _v1: &Vertex,
+    _v2: &Vertex,
+    _v3: &Vertex,
+    position: Vec3,
+    normal: Vec3,
+    _tex_coords: Vec2,
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
+    let surface = ice_giant_surface(position, uniforms);
+    let color = surface
+        .albedo
+        .component_mul(&light_factor(light, 0.75, 0.25, uniforms));
+    Some(Shaded::opaque(Color::from_float(color.x, color.y, color.z)))
+}
+
+pub fn ice_giant_surface(position: Vec3, uniforms: &Uniforms) -> Surface {
+    let lat = position.y / position.magnitude();
+    let drift = layer(&ICE_GIANT_LAYERS, 0, position, uniforms);
+    let wisps = layer(&ICE_GIANT_LAYERS, 1, position, uniforms);
 
-    Color::from_float(final_color.x, final_color.y, final_color.z)
+    // Few, wide bands, barely bent
+    let band = ((lat + (drift - 0.5) * 0.12) * 7.0).sin() * 0.5 + 0.5;
+    let base = ICE_BANDS
+        .sample(band * 0.85 + (drift - 0.5) * 0.3)
+        .to_vec3();
+
+    // Streaks: thin lines of latitude, only where the wisps layer is high
+    let lines = ((lat + (drift - 0.5) * 0.05) * 60.0).sin().abs().powf(12.0);
+    let streak = lines * smoothstep(0.5, 0.7, wisps) * 0.35;
+    let methane = Vec3::new(0.85, 0.95, 1.0);
+
+    Surface::matte(base * (1.0 - streak) + methane * streak)
 }
 
-// Gas Giant Shader (Jupiter-like)
-pub fn gas_giant_shader(
+// Molten world: a crust cooling over a network of glowing cracks, emissive
+// everywhere so even its night side smoulders
+pub fn molten_shader(
     _v1: &Vertex,
     _v2: &Vertex,
     _v3: &Vertex,
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
-    let intensity = dot(&normal, &light_dir).max(0.0);
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
+    let surface = molten_surface(position, uniforms);
+    let color = surface
+        .albedo
+        .component_mul(&light_factor(light, 0.7, 0.1, uniforms))
+        + surface.emission;
+    let glow = surface.emission;
+    Some(Shaded {
+        emissive: Color::from_float(glow.x, glow.y, glow.z),
+        ..Shaded::opaque(Color::from_float(color.x, color.y, color.z))
+    })
+}
 
-    // Use latitude for banding
-    let lat = position.y / position.magnitude();
+// How hot a molten world's surface is, 0 (cooled crust) to 1 (open crack).
+// Cracks run where the wide and fine layers cross their middle value, and
+// the crust stays warm for a while on either side of the wide ones
+fn molten_heat(wide: f32, fine: f32) -> (f32, f32) {
+    let crack = (1.0 - smoothstep(0.0, 0.03, (wide - 0.5).abs()))
+        .max((1.0 - smoothstep(0.0, 0.015, (fine - 0.5).abs())) * 0.8);
+    let halo = 1.0 - smoothstep(0.0, 0.14, (wide - 0.5).abs());
+    (crack, crack.max(halo * 0.4))
+}
 
-    // Layer 1: Base band structure
-    let band_freq = 8.0;
-    let band = (lat * band_freq).sin() * 0.5 + 0.5;
+pub fn molten_surface(position: Vec3, uniforms: &Uniforms) -> Surface {
+    let wide = layer(&MOLTEN_LAYERS, 0, position, uniforms);
+    let fine = layer(&MOLTEN_LAYERS, 1, position, uniforms);
+    let crust = layer(&MOLTEN_LAYERS, 2, position, uniforms);
+    let (crack, heat) = molten_heat(wide, fine);
+
+    let rock = COOLED_CRUST.sample(crust * 1.4 - 0.2).to_vec3();
+    let albedo = rock * (1.0 - crack) + LAVA.sample(1.0).to_vec3() * crack;
+    Surface {
+        emission: LAVA.sample(heat).to_vec3() * (0.12 + heat * 0.88),
+        ..Surface::matte(albedo)
+    }
+}
 
-    // Layer 2: Turbulence for swirls
-    let turbulence = fbm(
-        Vec3::new(position.x * 3.0, position.y * 3.0, position.z * 3.0),
-        4,
+// Zonal wind speed (radians of longitude per second) at a latitude in [-1, 1]:
+// alternating jets like Jupiter's belts and zones, strongest near the equator.
+fn zonal_wind(lat: f32) -> f32 {
+    let jets = (lat * std::f32::consts::PI * 4.0).cos();
+    let equatorial = 1.0 - lat.abs();
+    0.05 + jets * 0.12 * (0.4 + equatorial * 0.6)
+}
+
+const STORM_SLOTS: u32 = 5;
+
+// Storms live in recycled slots: each slot spawns a storm at a hashed
+// latitude, lets it drift with the local wind, and dissipates it before
+// spawning the next one, so the whole field is a pure function of time.
+fn storm_field(lat: f32, lon: f32, time: f32) -> f32 {
+    let mut strength: f32 = 0.0;
+
+    for slot in 0..STORM_SLOTS {
+        let slot_seed = slot as f32 * 17.31;
+        let lifetime = 40.0 + hash(slot_seed) * 50.0;
+        let clock = time + hash(slot_seed + 3.7) * lifetime;
+        let generation = (clock / lifetime).floor();
+        let age = clock / lifetime - generation;
+
+        let storm_seed = slot_seed + generation * 101.7;
+        let storm_lat = (hash(storm_seed) * 2.0 - 1.0) * 0.6;
+        let spawn_lon = hash(storm_seed + 1.3) * std::f32::consts::TAU;
+        let storm_lon = spawn_lon + zonal_wind(storm_lat) * age * lifetime;
+        let radius = 0.12 + hash(storm_seed + 2.9) * 0.16;
+
+        let mut d_lon = (lon - storm_lon).rem_euclid(std::f32::consts::TAU);
+        if d_lon > std::f32::consts::PI {
+            d_lon -= std::f32::consts::TAU;
+        }
+        let d_lat = lat - storm_lat;
+        // Storms are stretched along the wind, like the Great Red Spot
+        let distance = ((d_lon * 0.55).powi(2) + (d_lat * 1.4).powi(2)).sqrt();
+
+        let growth = smoothstep(0.0, 0.15, age) * (1.0 - smoothstep(0.7, 1.0, age));
+        let core = (1.0 - distance / radius).clamp(0.0, 1.0).powf(1.5);
+        strength = strength.max(core * growth);
+    }
+
+    strength
+}
+
+// Gas Giant Shader (Jupiter-like)
+pub fn gas_giant_shader(
+    _v1: &Vertex,
+    _v2: &Vertex,
+    _v3: &Vertex,
+    position: Vec3,
+    normal: Vec3,
+    _tex_coords: Vec2,
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
+    let time = uniforms.time;
+
+    let radius = position.magnitude();
+    let lat = position.y / radius;
+    let lon = position.z.atan2(position.x);
+
+    // Advect the cloud deck: each latitude slides east at its own wind speed
+    let advected_lon = lon - zonal_wind(lat) * time;
+    let ring_radius = (1.0 - lat * lat).max(0.0).sqrt();
+    let advected = Vec3::new(
+        advected_lon.cos() * ring_radius,
+        lat,
+        advected_lon.sin() * ring_radius,
+    );
+
+    // Domain warping turns the bands into swirls and eddies
+    let warp = Vec3::new(
+        fbm(
+            advected * 2.0 + Vec3::new(0.0, time * 0.01, 0.0),
+            3,
+            uniforms.seed,
+        ),
+        fbm(advected * 2.0 + Vec3::new(5.2, 1.3, 2.8), 3, uniforms.seed),
+        fbm(
+            advected * 2.0 + Vec3::new(1.7, 9.2, time * 0.01),
+            3,
+            uniforms.seed,
+        ),
     );
+    let warped = advected * 3.0 + warp * 1.4;
+    let turbulence = fbm(warped, 4, uniforms.seed);
     let swirl = (turbulence * 2.0 - 1.0) * 0.3;
 
-    // Layer 3: Color variation within bands
-    let color_variation = fbm(
-        Vec3::new(position.x * 5.0, position.y * 5.0, position.z * 5.0),
-        3,
-    ) * 0.2;
+    // Base band structure, bent by the warped turbulence
+    let band_freq = 8.0;
+    let band = ((lat + swirl * 0.08) * band_freq).sin() * 0.5 + 0.5;
 
-    // Layer 4: Great Red Spot-like feature
-    let spot_pos = Vec3::new(0.0, 0.3, 0.8);
-    let spot_dist = (position.normalize() - spot_pos).magnitude();
-    let spot = if spot_dist < 0.3 {
-        (1.0 - spot_dist / 0.3).powf(2.0) * 0.4
-    } else {
-        0.0
-    };
+    let color_variation = fbm(warped * 1.6, 3, uniforms.seed) * 0.2;
+
+    let storm = storm_field(lat, lon, time);
+    let storm_swirl = fbm(
+        warped * 2.5 + Vec3::new(0.0, storm * 3.0, 0.0),
+        2,
+        uniforms.seed,
+    );
+    let spot = (storm * (0.7 + storm_swirl * 0.6)).clamp(0.0, 1.0) * 0.75;
 
     // Jupiter-like colors: browns, oranges, whites
     let dark_band = Vec3::new(0.5, 0.3, 0.2);
     let light_band = Vec3::new(0.8, 0.7, 0.6);
     let red_spot = Vec3::new(0.8, 0.3, 0.2);
 
-    // Mix bands
     let base_color = dark_band * (1.0 - band) + light_band * band;
-
-    // Add swirl
     let swirled_color = base_color + Vec3::new(swirl, swirl * 0.5, -swirl * 0.3);
-
-    // Add color variation
     let varied_color = swirled_color
         + Vec3::new(
             color_variation,
             color_variation * 0.5,
             -color_variation * 0.3,
         );
-
-    // Add red spot
     let final_base = varied_color * (1.0 - spot) + red_spot * spot;
 
     // Apply lighting
-    let final_color = final_base * (intensity * 0.7 + 0.3);
+    let final_color = final_base.component_mul(&light_factor(light, 0.7, 0.3, uniforms));
 
-    Color::from_float(
+    Some(Shaded::opaque(Color::from_float(
         final_color.x.clamp(0.0, 1.0),
         final_color.y.clamp(0.0, 1.0),
         final_color.z.clamp(0.0, 1.0),
-    )
+    )))
+}
+
+// Aurora shell: black (no contribution) away from the poles, drawn with
+// additive blending on a sphere slightly larger than the planet.
+pub fn aurora_shader(
+    _v1: &Vertex,
+    _v2: &Vertex,
+    _v3: &Vertex,
+    position: Vec3,
+    normal: Vec3,
+    _tex_coords: Vec2,
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let time = uniforms.time;
+    let radius = position.magnitude();
+    let lat = (position.y / radius).abs();
+    let lon = position.z.atan2(position.x);
+
+    // Auroral oval: a band around each pole, wobbling with longitude and time
+    let drift = fbm(
+        Vec3::new(lon.cos(), lon.sin(), time * 0.05) * 2.0,
+        2,
+        uniforms.seed,
+    );
+    let wobble = (lon * 3.0 + time * 0.3).sin() * 0.03 + drift * 0.06;
+    let band_center = 0.82 + wobble;
+    let band = 1.0 - ((lat - band_center) / 0.1).abs().min(1.0);
+    if band <= 0.0 {
+        return None;
+    }
+
+    // Curtains: thin vertical rays that ripple along the oval
+    let rays = fbm(
+        Vec3::new(lon * 9.0 + time * 0.4, time * 0.15, lat * 2.0),
+        3,
+        uniforms.seed,
+    );
+    let curtain = smoothstep(0.35, 0.75, rays);
+    let flicker = 0.75 + 0.25 * (time * 2.3 + lon * 5.0).sin();
+
+    // Edge-on shell is brighter, like looking through more of the glowing layer
+    let limb = 1.0 - normal.normalize().z.abs();
+    let strength = band * curtain * flicker * (0.35 + limb * 0.65);
+
+    // Green at the base of the curtain, violet towards its top
+    let height = ((lat - band_center) / 0.1 * 0.5 + 0.5).clamp(0.0, 1.0);
+    Some(Shaded::opaque(AURORA.sample(height) * (strength * 1.3)))
+}
+
+// Corona shell around a star, drawn additively on a sphere CORONA_SHELL times
+// the star's radius: faint over the disk, brightest just past its edge and
+// fading out with streamers that drift over time
+pub fn corona_shader(
+    _v1: &Vertex,
+    _v2: &Vertex,
+    _v3: &Vertex,
+    position: Vec3,
+    _normal: Vec3,
+    _tex_coords: Vec2,
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    // Shell point as seen on screen
+    let facing = uniforms.to_view(position).normalize();
+    // Distance from the disk center in shell radii; the star's limb sits at `edge`
+    let rho = (1.0 - facing.z * facing.z).max(0.0).sqrt();
+    let edge = 1.0 / CORONA_SHELL;
+    let falloff = if rho < edge {
+        (rho / edge).powi(8)
+    } else {
+        ((1.0 - rho) / (1.0 - edge)).powi(2)
+    };
+
+    let angle = facing.y.atan2(facing.x);
+    let time = uniforms.time;
+    let streamers = fbm(
+        Vec3::new(angle.cos() * 3.0, angle.sin() * 3.0, time * 0.08),
+        3,
+        uniforms.seed,
+    );
+    let flicker = 0.7 + 0.6 * smoothstep(0.3, 0.8, streamers);
+
+    let glow = uniforms.star.corona * (falloff * flicker * 0.6);
+    let color = Color::from_float(glow.x, glow.y, glow.z);
+    Some(Shaded {
+        emissive: color,
+        ..Shaded::opaque(color)
+    })
+}
+
+// Runs the planet's interpreted program from `uniforms.expression`
+pub fn expression_shader(
+    _v1: &Vertex,
+    _v2: &Vertex,
+    _v3: &Vertex,
+    position: Vec3,
+    normal: Vec3,
+    _tex_coords: Vec2,
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    match &uniforms.expression {
+        Some(program) => {
+            // Programs see the light as a brightness; its color tints the result
+            let light = lighting(position, normal, uniforms);
+            let brightness = light.max();
+            let tint = if brightness > 0.0 {
+                light / brightness
+            } else {
+                uniforms.ambient
+            };
+            let color = program
+                .run(position, normal, brightness.min(1.0), uniforms.time)
+                .component_mul(&tint);
+            Some(Shaded::opaque(Color::from_float(color.x, color.y, color.z)))
+        }
+        None => Some(Shaded::opaque(Color::new(255, 0, 255))),
+    }
+}
+
+// Planets far enough away to be drawn from their baked atlas: the stored
+// surface under live lighting, without the view-dependent highlights
+pub fn atlas_shader(
+    _v1: &Vertex,
+    _v2: &Vertex,
+    _v3: &Vertex,
+    position: Vec3,
+    normal: Vec3,
+    _tex_coords: Vec2,
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    match &uniforms.atlas {
+        Some(atlas) => {
+            let light = lighting(position, normal, uniforms);
+            let surface = atlas.sample(position);
+            let color = surface
+                .albedo
+                .component_mul(&light_factor(light, 0.8, 0.2, uniforms))
+                + surface.emission;
+            let glow = surface.emission;
+            Some(Shaded {
+                emissive: Color::from_float(glow.x, glow.y, glow.z),
+                ..Shaded::opaque(Color::from_float(
+                    color.x.clamp(0.0, 1.0),
+                    color.y.clamp(0.0, 1.0),
+                    color.z.clamp(0.0, 1.0),
+                ))
+            })
+        }
+        None => Some(Shaded::opaque(Color::new(255, 0, 255))),
+    }
 }
 
 // Moon Shader (simple gray with craters)
@@ -386,9 +768,9 @@ pub fn moon_shader(
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
-    let intensity = dot(&normal, &light_dir).max(0.0);
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
 
     // Base gray color
     let base_gray = 0.5;
@@ -397,6 +779,7 @@ pub fn moon_shader(
     let craters = fbm(
         Vec3::new(position.x * 8.0, position.y * 8.0, position.z * 8.0),
         4,
+        uniforms.seed,
     );
     let crater_depth = (craters - 0.5).abs() * 2.0;
     let crater = if crater_depth > 0.7 {
@@ -408,12 +791,12 @@ pub fn moon_shader(
     let gray = (base_gray - crater).clamp(0.2, 0.8);
 
     // Apply lighting
-    let final_gray = gray * (intensity * 0.9 + 0.1);
+    let final_color = light_factor(light, 0.9, 0.1, uniforms) * gray;
 
-    Color::from_float(final_gray, final_gray, final_gray)
+    Some(Shaded::opaque(Color::from_float(final_color.x, final_color.y, final_color.z)))
 }
 
-// Ring Shader (simple gradient)
+// Ring Shader (the ring's color profile across its width)
 pub fn ring_shader(
     v1: &Vertex,
     v2: &Vertex,
@@ -421,27 +804,23 @@ pub fn ring_shader(
     position: Vec3,
     normal: Vec3,
     tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
-    let intensity = dot(&normal, &light_dir).max(0.0);
-
-    // Use texture coordinates for radial gradient
-    let radial = tex_coords.y; // 0.0 = inner, 1.0 = outer
-
-    // Dusty brown-gray color
-    let inner_color = Vec3::new(0.4, 0.35, 0.3);
-    let outer_color = Vec3::new(0.5, 0.45, 0.4);
-
-    let color = Vec3::new(
-        inner_color.x * (1.0 - radial) + outer_color.x * radial,
-        inner_color.y * (1.0 - radial) + outer_color.y * radial,
-        inner_color.z * (1.0 - radial) + outer_color.z * radial,
-    );
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    // Thin enough to let light through, so both faces are lit
+    let light = lighting(position, normal, uniforms) + lighting(position, -normal, uniforms);
+
+    // Texture coordinates run from the inner edge (0) to the outer one (1)
+    let radial = tex_coords.y;
+    let color = match uniforms.ring {
+        Some(colors) => colors.sample(radial).to_vec3(),
+        None => Vec3::new(0.45, 0.4, 0.35),
+    };
 
     // Add some variation
     let variation = fbm(
         Vec3::new(position.x * 10.0, position.y * 10.0, position.z * 10.0),
         2,
+        uniforms.seed,
     ) * 0.1;
     let final_color = Vec3::new(
         color.x + variation,
@@ -450,18 +829,29 @@ pub fn ring_shader(
     );
 
     // Apply lighting with transparency effect
-    let light_factor = intensity * 0.6 + 0.4;
-    let ring_final = Vec3::new(
-        final_color.x * light_factor,
-        final_color.y * light_factor,
-        final_color.z * light_factor,
-    );
+    let ring_final = final_color.component_mul(&light_factor(light, 0.6, 0.4, uniforms));
 
-    Color::from_float(
+    Some(Shaded::opaque(Color::from_float(
         ring_final.x.clamp(0.0, 1.0),
         ring_final.y.clamp(0.0, 1.0),
         ring_final.z.clamp(0.0, 1.0),
-    )
+    )))
+}
+
+// Share of its surroundings the hull mirrors seen head-on; at grazing
+// angles it mirrors more, following Schlick's Fresnel approximation
+const HULL_REFLECTANCE: f32 = 0.2;
+
+// The environment the hull mirrors at a point: what the view ray meets once
+// it bounces off the normal
+fn hull_reflection(normal: Vec3, uniforms: &Uniforms) -> Vec3 {
+    let Some(environment) = &uniforms.environment else {
+        return Vec3::zeros();
+    };
+    let normal = normal.normalize();
+    let facing = dot(&normal, &TO_CAMERA).clamp(0.0, 1.0);
+    let fresnel = HULL_REFLECTANCE + (1.0 - HULL_REFLECTANCE) * (1.0 - facing).powi(5);
+    environment.sample(reflect_vec(&-TO_CAMERA, &normal)) * fresnel
 }
 
 pub fn ship_shader(
@@ -471,15 +861,16 @@ pub fn ship_shader(
     position: Vec3,
     normal: Vec3,
     _tex_coords: Vec2,
-) -> Color {
-    let light_dir = Vec3::new(0.3, -0.8, -0.5).normalize();
-    let intensity = dot(&normal.normalize(), &light_dir).max(0.0);
+    uniforms: &Uniforms,
+) -> Option<Shaded> {
+    let light = lighting(position, normal, uniforms);
 
     let base_gray = Vec3::new(0.58, 0.6, 0.63);
     let dark_plate = Vec3::new(0.25, 0.27, 0.3);
     let panel_variation = fbm(
         Vec3::new(position.x * 7.0, position.y * 7.0, position.z * 7.0),
         3,
+        uniforms.seed,
     )
         .clamp(0.0, 1.0);
     let panel_color = dark_plate * (1.0 - panel_variation) + base_gray * panel_variation;
@@ -488,12 +879,47 @@ pub fn ship_shader(
     let engine_glow = (position.y * 0.4).sin().abs() * 0.05;
 
     let specular = normal.normalize().z.max(0.0).powi(6) * 0.5;
-    let lit = panel_color * (intensity * 0.65 + 0.35) + Vec3::new(edge_highlight, edge_highlight, edge_highlight);
+    let lit = panel_color.component_mul(&light_factor(light, 0.65, 0.35, uniforms)) + Vec3::new(edge_highlight, edge_highlight, edge_highlight);
+    let lit = lit + hull_reflection(normal, uniforms);
     let final_color = Vec3::new(
         (lit.x + specular + engine_glow).clamp(0.0, 1.0),
         (lit.y + specular + engine_glow).clamp(0.0, 1.0),
         (lit.z + specular + engine_glow).clamp(0.0, 1.0),
     );
 
-    Color::from_float(final_color.x, final_color.y, final_color.z)
+    Some(Shaded::opaque(Color::from_float(final_color.x, final_color.y, final_color.z)))
+}
+
+pub type HeightFunction = fn(Vec3, u32) -> f32;
+
+// Terrain heights as a fraction of the planet radius, built from the same
+// noise layers their shaders use so the relief matches the colors.
+pub fn rocky_planet_height(position: Vec3, seed: u32) -> f32 {
+    let continent_noise = ROCKY_LAYERS[0].sample(position, seed);
+    if continent_noise <= 0.1 {
+        return 0.0;
+    }
+    let elevation = ROCKY_LAYERS[2].sample(position, seed);
+    (continent_noise - 0.1) * 0.12 + elevation * 0.02
+}
+
+pub fn azure_planet_height(position: Vec3, seed: u32) -> f32 {
+    let ocean_noise = AZURE_LAYERS[1].sample(position, seed);
+    (ocean_noise - 0.5).max(0.0) * 0.06
+}
+
+// Crust plates stand one to four hundredths of the radius above the cracks
+pub fn molten_height(position: Vec3, seed: u32) -> f32 {
+    let wide = MOLTEN_LAYERS[0].sample(position, seed);
+    let fine = MOLTEN_LAYERS[1].sample(position, seed);
+    let crust = MOLTEN_LAYERS[2].sample(position, seed);
+    let (crack, _) = molten_heat(wide, fine);
+    (0.01 + crust * 0.03) * (1.0 - crack)
+}
+
+pub fn crimson_planet_height(position: Vec3, seed: u32) -> f32 {
+    let basalt_noise = CRIMSON_LAYERS[0].sample(position, seed);
+    let fissure_noise = CRIMSON_LAYERS[1].sample(position, seed);
+    let lava = (fissure_noise * 1.4 - 0.5).clamp(0.0, 1.0).powf(1.6);
+    basalt_noise * 0.08 * (1.0 - lava)
 }
@@ -1,12 +1,145 @@
 // framebuffer.rs
 
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+
+use crate::color::Color;
+use crate::noise::noise;
+use crate::sprite::Sprite;
+
+const AO_SAMPLES: usize = 16;
+// Bloom works on a buffer this many times smaller per side
+const BLOOM_DOWNSAMPLE: usize = 4;
+const BLOOM_BLUR_RADIUS: usize = 3;
+// Depth of field: blur radius in pixels at full strength, and samples per pixel
+const DOF_MAX_RADIUS: f32 = 10.0;
+const DOF_SAMPLES: usize = 24;
+// Minimum luma contrast `smooth_edges` treats as an edge
+const EDGE_THRESHOLD: f32 = 0.08;
+// Shimmer: largest offset as a fraction of the width, how many noise cells
+// cross the image and how fast the noise drifts
+const SHIMMER_AMPLITUDE: f32 = 0.003;
+const SHIMMER_GRAIN: f32 = 90.0;
+const SHIMMER_SPEED: f32 = 1.5;
+// How far out from the center, with the corners at 1, `tint_edges` starts
+const EDGE_TINT_START: f32 = 0.45;
+// Vignette: where the darkening starts, same measure, and how dark the
+// corners get at full strength
+const VIGNETTE_START: f32 = 0.35;
+const VIGNETTE_DEPTH: f32 = 0.6;
+// Film grain: largest brightness change at full strength, and how many
+// times a second the grain changes, like film running through a projector
+const GRAIN_AMOUNT: f32 = 0.12;
+const GRAIN_RATE: f32 = 24.0;
+// CRT: how far the screen bulges toward its corners, how far apart red
+// and blue land at the edges (both in half-screens), and the height in
+// pixels and darkness of the scanlines
+const CRT_CURVATURE: f32 = 0.08;
+const CRT_ABERRATION: f32 = 0.006;
+const CRT_SCANLINE_PERIOD: f32 = 3.0;
+const CRT_SCANLINE_DEPTH: f32 = 0.35;
+// Distances the logarithmic depth range is spread over
+const DEPTH_NEAR: f32 = 1.0;
+const DEPTH_FAR: f32 = 50000.0;
+
+// Callers pass depth as a distance; the z-buffer keeps its logarithm so the
+// precision follows the scale of the scene, fine up close where rings sit
+// against their planets and coarse across the far side of the system. Below
+// `DEPTH_NEAR` it continues linearly so geometry poking towards the camera
+// still orders correctly.
+fn encode_depth(distance: f32) -> f32 {
+    let scale = (DEPTH_FAR / DEPTH_NEAR).ln();
+    if distance >= DEPTH_NEAR {
+        (distance / DEPTH_NEAR).ln() / scale
+    } else {
+        (distance / DEPTH_NEAR - 1.0) / scale
+    }
+}
+
+fn decode_depth(depth: f32) -> f32 {
+    let scale = (DEPTH_FAR / DEPTH_NEAR).ln();
+    if depth >= 0.0 {
+        DEPTH_NEAR * (depth * scale).exp()
+    } else {
+        DEPTH_NEAR * (depth * scale + 1.0)
+    }
+}
+
+// A rectangle of a buffer, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    pub fn full(width: usize, height: usize) -> Self {
+        Viewport {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (
+            self.x as f32 + self.width as f32 * 0.5,
+            self.y as f32 + self.height as f32 * 0.5,
+        )
+    }
+}
+
+// A spot of hot air for `Framebuffer::shimmer`, in pixels: `strength` at
+// the center, fading out to nothing at `radius`
+#[derive(Clone, Copy, Debug)]
+pub struct Shimmer {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl Shimmer {
+    fn at(&self, x: f32, y: f32) -> f32 {
+        let distance = ((x - self.x).powi(2) + (y - self.y).powi(2)).sqrt();
+        self.strength * (1.0 - distance / self.radius).max(0.0)
+    }
+}
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    pub idbuffer: Vec<u32>,
+    // The color buffer as the scene passes left it, kept by
+    // `keep_unprocessed` for post effects that sample around a pixel
+    pub unprocessed: Vec<u32>,
+    // Light given off by what is visible at each pixel, written alongside
+    // color. Bloom glows from it besides from whatever is bright enough
+    pub emissive: Vec<[f32; 3]>,
+    // Where 3D drawing goes: projection centers on it and the rasterizer and
+    // depth-tested writes stay inside it. The whole buffer unless a smaller
+    // view, like an inset, is being drawn
+    pub viewport: Viewport,
+    // Physical pixels per logical one on HiDPI screens; UI sizes are given
+    // in logical pixels and go through `ui`
+    pub ui_scale: f32,
+    // Lines and UI drawn over the image as premultiplied color plus coverage.
+    // It stays apart from `buffer` until `composite_overlay`, so post effects
+    // and 3D drawn later never touch it
+    overlay: Vec<[f32; 4]>,
     background_color: u32,
     current_color: u32,
+    current_id: u32,
+    current_emissive: [f32; 3],
 }
 
 impl Framebuffer {
@@ -16,26 +149,113 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            idbuffer: vec![0; width * height],
+            unprocessed: Vec::new(),
+            emissive: vec![[0.0; 3]; width * height],
+            viewport: Viewport::full(width, height),
+            ui_scale: 1.0,
+            overlay: vec![[0.0; 4]; width * height],
             background_color: 0x000000,
             current_color: 0xFFFFFF,
+            current_id: 0,
+            current_emissive: [0.0; 3],
         }
     }
 
-    pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
-        }
-        for depth in self.zbuffer.iter_mut() {
-            *depth = f32::INFINITY;
+    // Every row, for writing them in parallel without sharing the buffers
+    pub fn rows_mut(&mut self) -> impl IndexedParallelIterator<Item = Row<'_>> {
+        self.rows().par_rows()
+    }
+
+    // The rows above `row` and the rest, each free to go to its own worker
+    pub fn split_at_row(&mut self, row: usize) -> (Rows<'_>, Rows<'_>) {
+        self.rows().split_at_row(row)
+    }
+
+    fn rows(&mut self) -> Rows<'_> {
+        Rows {
+            width: self.width,
+            top: 0,
+            color: &mut self.buffer,
+            depth: &mut self.zbuffer,
+            ids: &mut self.idbuffer,
         }
     }
 
+    pub fn ui(&self, pixels: i32) -> i32 {
+        (pixels as f32 * self.ui_scale).round() as i32
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.fill(self.background_color);
+        self.clear_keeping_color();
+    }
+
+    // Clears everything but the colors, for when something is about to
+    // paint over every pixel anyway, like the skybox
+    pub fn clear_keeping_color(&mut self) {
+        self.rows_mut().for_each(|row| {
+            row.depth.fill(f32::INFINITY);
+            row.ids.fill(0);
+        });
+        self.emissive.fill([0.0; 3]);
+        self.overlay.fill([0.0; 4]);
+    }
+
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
+        if self.viewport.contains(x, y) {
             let index = y * self.width + x;
+            let depth = encode_depth(depth);
             if self.zbuffer[index] > depth {
                 self.buffer[index] = self.current_color;
                 self.zbuffer[index] = depth;
+                self.idbuffer[index] = self.current_id;
+                self.emissive[index] = self.current_emissive;
+            }
+        }
+    }
+
+    // Depth-tested but leaves depth and ids alone, so glowing shells add light
+    // on top of whatever they cover without hiding it
+    pub fn blend_add(&mut self, x: usize, y: usize, depth: f32, color: u32) {
+        if self.viewport.contains(x, y) {
+            let index = y * self.width + x;
+            if self.zbuffer[index] > encode_depth(depth) {
+                let sum = Color::from_hex(self.buffer[index]) + Color::from_hex(color);
+                self.buffer[index] = sum.to_hex();
+                for (glow, added) in self.emissive[index].iter_mut().zip(self.current_emissive) {
+                    *glow += added;
+                }
+            }
+        }
+    }
+
+    // Depth-tested mix towards `color` by `alpha`; like `blend_add` it leaves
+    // depth and ids untouched
+    pub fn blend(&mut self, x: usize, y: usize, depth: f32, color: u32, alpha: f32) {
+        if self.viewport.contains(x, y) {
+            let index = y * self.width + x;
+            if self.zbuffer[index] > encode_depth(depth) {
+                let alpha = alpha.clamp(0.0, 1.0);
+                let mixed = Color::from_hex(self.buffer[index]) * (1.0 - alpha)
+                    + Color::from_hex(color) * alpha;
+                self.buffer[index] = mixed.to_hex();
+                for (glow, own) in self.emissive[index].iter_mut().zip(self.current_emissive) {
+                    *glow = *glow * (1.0 - alpha) + own * alpha;
+                }
+            }
+        }
+    }
+
+    // Depth and id without color, so a blended surface still hides what is
+    // drawn behind it later
+    pub fn write_depth(&mut self, x: usize, y: usize, depth: f32) {
+        if self.viewport.contains(x, y) {
+            let index = y * self.width + x;
+            let depth = encode_depth(depth);
+            if self.zbuffer[index] > depth {
+                self.zbuffer[index] = depth;
+                self.idbuffer[index] = self.current_id;
             }
         }
     }
@@ -48,39 +268,832 @@ impl Framebuffer {
         self.current_color = color;
     }
 
+    // Object id written alongside color by `point`; 0 means "no object"
+    pub fn set_current_id(&mut self, id: u32) {
+        self.current_id = id;
+    }
+
+    pub fn current_id(&self) -> u32 {
+        self.current_id
+    }
+
+    // Emissive light written by `point`, `blend` and `blend_add`
+    pub fn set_current_emissive(&mut self, emissive: [f32; 3]) {
+        self.current_emissive = emissive;
+    }
+
+    // Paints `color` on every pixel within `thickness` of the footprint of `ids`
+    pub fn outline(&mut self, ids: &[u32], thickness: i32, color: u32) {
+        let bounds = self
+            .idbuffer
+            .par_chunks(self.width)
+            .enumerate()
+            .filter_map(|(y, row)| {
+                let first = row.iter().position(|id| ids.contains(id))?;
+                let last = row.iter().rposition(|id| ids.contains(id))?;
+                Some((first, y, last, y))
+            })
+            .reduce_with(|(ax0, ay0, ax1, ay1), (bx0, by0, bx1, by1)| {
+                (ax0.min(bx0), ay0.min(by0), ax1.max(bx1), ay1.max(by1))
+            });
+
+        let Some((min_x, min_y, max_x, max_y)) = bounds else {
+            return;
+        };
+
+        let reach = thickness.max(1);
+        let x_range =
+            (min_x as i32 - reach).max(0)..=(max_x as i32 + reach).min(self.width as i32 - 1);
+        let y_range =
+            (min_y as i32 - reach).max(0)..=(max_y as i32 + reach).min(self.height as i32 - 1);
+        let selected = |x: i32, y: i32| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < self.width
+                && (y as usize) < self.height
+                && ids.contains(&self.idbuffer[y as usize * self.width + x as usize])
+        };
+        let edge: Vec<(i32, i32)> = y_range
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                x_range.clone().filter_map(move |x| {
+                    let touches = !selected(x, y)
+                        && (-reach..=reach).any(|dy| {
+                            (-reach..=reach).any(|dx| {
+                                dx * dx + dy * dy <= reach * reach && selected(x + dx, y + dy)
+                            })
+                        });
+                    touches.then_some((x, y))
+                })
+            })
+            .collect();
+
+        for (x, y) in edge {
+            self.plot_overlay(x, y, color);
+        }
+    }
+
+    // Screen-space contact darkening: a pixel gets darker the more of its
+    // neighbors within `radius` belong to another object that sits in front of
+    // it by less than `range`. Pixels with id 0 (ship, background) neither
+    // receive nor cast occlusion.
+    pub fn ambient_occlusion(&mut self, radius: i32, range: f32, strength: f32) {
+        let kernel: Vec<(i32, i32, f32)> = (0..AO_SAMPLES)
+            .map(|sample| {
+                // Golden-angle spiral spreads the samples evenly over the disk
+                let angle = sample as f32 * 2.399_963;
+                let distance = radius as f32 * ((sample as f32 + 0.5) / AO_SAMPLES as f32).sqrt();
+                let (dx, dy) = (angle.cos() * distance, angle.sin() * distance);
+                (dx as i32, dy as i32, 1.0 - distance / (radius as f32 + 1.0))
+            })
+            .collect();
+        let total: f32 = kernel.iter().map(|(_, _, weight)| weight).sum();
+
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (zbuffer, idbuffer) = (&self.zbuffer, &self.idbuffer);
+        self.buffer
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let y = y as i32;
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let x = x as i32;
+                    let index = (y * width + x) as usize;
+                    let id = idbuffer[index];
+                    if id == 0 {
+                        continue;
+                    }
+                    let depth = decode_depth(zbuffer[index]);
+
+                    let mut occlusion = 0.0;
+                    for &(dx, dy, weight) in &kernel {
+                        let (sx, sy) = (x + dx, y + dy);
+                        if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                            continue;
+                        }
+                        let sample = (sy * width + sx) as usize;
+                        let other = idbuffer[sample];
+                        if other == 0 || other == id {
+                            continue;
+                        }
+                        let gap = depth - decode_depth(zbuffer[sample]);
+                        if gap > 0.0 && gap < range {
+                            occlusion += weight * (1.0 - gap / range);
+                        }
+                    }
+
+                    if occlusion > 0.0 {
+                        let shade = 1.0 - strength * (occlusion / total).min(1.0);
+                        *pixel = (Color::from_hex(*pixel) * shade).to_hex();
+                    }
+                }
+            });
+    }
+
+    // Bright and emissive parts of the frame bleed light into their
+    // surroundings: whatever exceeds `threshold`, plus the emissive buffer,
+    // is averaged into a quarter-size buffer, blurred and added back on top
+    pub fn bloom(&mut self, threshold: f32, strength: f32) {
+        let (width, height) = (
+            self.width / BLOOM_DOWNSAMPLE,
+            self.height / BLOOM_DOWNSAMPLE,
+        );
+        if width < 2 || height < 2 {
+            return;
+        }
+
+        let cell_area = (BLOOM_DOWNSAMPLE * BLOOM_DOWNSAMPLE) as f32;
+        let mut glow = vec![[0.0f32; 3]; width * height];
+        for (index, cell) in glow.iter_mut().enumerate() {
+            let (cell_x, cell_y) = (index % width, index / width);
+            for y in cell_y * BLOOM_DOWNSAMPLE..(cell_y + 1) * BLOOM_DOWNSAMPLE {
+                for x in cell_x * BLOOM_DOWNSAMPLE..(cell_x + 1) * BLOOM_DOWNSAMPLE {
+                    let index = y * self.width + x;
+                    let rgb = channels(self.buffer[index]);
+                    for ((sum, value), emitted) in
+                        cell.iter_mut().zip(rgb).zip(self.emissive[index])
+                    {
+                        *sum += ((value - threshold).max(0.0) + emitted) / cell_area;
+                    }
+                }
+            }
+        }
+
+        // Two box passes are close enough to a gaussian for a glow
+        for _ in 0..2 {
+            box_blur(&mut glow, width, height, 1, width);
+            box_blur(&mut glow, height, width, width, 1);
+        }
+
+        let glow = &glow;
+        self.buffer
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let gy = ((y as f32 + 0.5) / BLOOM_DOWNSAMPLE as f32 - 0.5)
+                    .clamp(0.0, (height - 1) as f32);
+                let (y0, ty) = (gy as usize, gy.fract());
+                let y1 = (y0 + 1).min(height - 1);
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let gx = ((x as f32 + 0.5) / BLOOM_DOWNSAMPLE as f32 - 0.5)
+                        .clamp(0.0, (width - 1) as f32);
+                    let (x0, tx) = (gx as usize, gx.fract());
+                    let x1 = (x0 + 1).min(width - 1);
+
+                    let mut rgb = channels(*pixel);
+                    for (channel, value) in rgb.iter_mut().enumerate() {
+                        let top = glow[y0 * width + x0][channel] * (1.0 - tx)
+                            + glow[y0 * width + x1][channel] * tx;
+                        let bottom = glow[y1 * width + x0][channel] * (1.0 - tx)
+                            + glow[y1 * width + x1][channel] * tx;
+                        *value += (top * (1.0 - ty) + bottom * ty) * strength;
+                    }
+                    *pixel = Color::from_float(rgb[0], rgb[1], rgb[2]).to_hex();
+                }
+            });
+    }
+
+    // Blurs each pixel by how far its depth is from `focus` (a distance), up
+    // to `strength` times the largest radius. A sample only spreads over a
+    // sharper pixel if it sits behind it or is itself blurred that far, so
+    // in-focus edges don't smear onto the background.
+    pub fn depth_of_field(&mut self, focus: f32, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let radius_at = |depth: f32| {
+            let distance = decode_depth(depth);
+            let (near, far) = (distance.min(focus), distance.max(focus));
+            let defocus = if far.is_finite() {
+                1.0 - near / far
+            } else if near.is_finite() {
+                1.0
+            } else {
+                0.0
+            };
+            defocus * strength * DOF_MAX_RADIUS
+        };
+        let radii: Vec<f32> = self.zbuffer.iter().map(|&depth| radius_at(depth)).collect();
+        let kernel: Vec<(f32, f32, f32)> = (0..DOF_SAMPLES)
+            .map(|sample| {
+                let angle = sample as f32 * 2.399_963;
+                let distance = ((sample as f32 + 0.5) / DOF_SAMPLES as f32).sqrt();
+                (angle.cos() * distance, angle.sin() * distance, distance)
+            })
+            .collect();
+
+        let (width, height) = (self.width as i32, self.height as i32);
+        let source = self.buffer.clone();
+        let (source, radii, zbuffer) = (&source, &radii, &self.zbuffer);
+        self.buffer
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let index = y * width as usize + x;
+                    let radius = radii[index];
+                    if radius < 0.5 {
+                        continue;
+                    }
+                    let mut sum = channels(source[index]);
+                    let mut count = 1.0;
+                    for &(dx, dy, distance) in &kernel {
+                        let sx = x as i32 + (dx * radius) as i32;
+                        let sy = y as i32 + (dy * radius) as i32;
+                        if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                            continue;
+                        }
+                        let sample = (sy * width + sx) as usize;
+                        let behind = zbuffer[sample] >= zbuffer[index];
+                        if !behind && radii[sample] < distance * radius {
+                            continue;
+                        }
+                        for (total, value) in sum.iter_mut().zip(channels(source[sample])) {
+                            *total += value;
+                        }
+                        count += 1.0;
+                    }
+                    *pixel =
+                        Color::from_float(sum[0] / count, sum[1] / count, sum[2] / count).to_hex();
+                }
+            });
+    }
+
+    // Distance to whatever is drawn at the center of the frame
+    pub fn center_depth(&self) -> f32 {
+        decode_depth(self.zbuffer[(self.height / 2) * self.width + self.width / 2])
+    }
+
+    // Copies the image into `unprocessed`, so post effects can read the
+    // scene as rasterized while they write over `buffer`
+    pub fn keep_unprocessed(&mut self) {
+        self.unprocessed.clone_from(&self.buffer);
+    }
+
+    // Hot air: pixels show `unprocessed` a little off their place, moved by
+    // noise that drifts with `time`. The offset grows with `everywhere` plus
+    // the spots covering the pixel, up to 1 in all. Needs `keep_unprocessed`
+    // first
+    pub fn shimmer(&mut self, time: f32, everywhere: f32, spots: &[Shimmer]) {
+        if (everywhere <= 0.0 && spots.is_empty()) || self.unprocessed.len() != self.buffer.len() {
+            return;
+        }
+        let amplitude = SHIMMER_AMPLITUDE * self.width as f32;
+        let frequency = SHIMMER_GRAIN / self.width as f32;
+        let (width, height) = (self.width, self.height);
+        let source = &self.unprocessed;
+        self.buffer
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let fy = y as f32;
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let fx = x as f32;
+                    let strength = spots
+                        .iter()
+                        .fold(everywhere, |sum, spot| sum + spot.at(fx, fy))
+                        .min(1.0);
+                    if strength <= 0.0 {
+                        continue;
+                    }
+                    // Rising, like the air it stands for
+                    let p = Vec3::new(
+                        fx * frequency,
+                        fy * frequency + time * SHIMMER_SPEED,
+                        time * SHIMMER_SPEED,
+                    );
+                    let offset = amplitude * strength;
+                    let dx = (noise(p, 1) * 2.0 - 1.0) * offset;
+                    let dy = (noise(p, 2) * 2.0 - 1.0) * offset;
+                    let sx = (fx + dx).round().clamp(0.0, (width - 1) as f32);
+                    let sy = (fy + dy).round().clamp(0.0, (height - 1) as f32);
+                    *pixel = source[sy as usize * width + sx as usize];
+                }
+            });
+    }
+
+    // Mixes the border of the image towards `color`, fading in from
+    // `EDGE_TINT_START` out to `strength` at the corners
+    pub fn tint_edges(&mut self, color: u32, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let tint = Color::from_hex(color);
+        let (half_width, half_height) = (self.width as f32 * 0.5, self.height as f32 * 0.5);
+        self.buffer
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let ny = (y as f32 + 0.5) / half_height - 1.0;
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let nx = (x as f32 + 0.5) / half_width - 1.0;
+                    let out = (nx * nx + ny * ny).sqrt() / std::f32::consts::SQRT_2;
+                    let edge = ((out - EDGE_TINT_START) / (1.0 - EDGE_TINT_START)).clamp(0.0, 1.0);
+                    if edge > 0.0 {
+                        *pixel = Color::from_hex(*pixel)
+                            .lerp(&tint, edge * edge * strength)
+                            .to_hex();
+                    }
+                }
+            });
+    }
+
+    // Darkens the frame toward its corners, gently, like a lens does
+    pub fn vignette(&mut self, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let (half_width, half_height) = (self.width as f32 * 0.5, self.height as f32 * 0.5);
+        self.buffer
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let ny = (y as f32 + 0.5) / half_height - 1.0;
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let nx = (x as f32 + 0.5) / half_width - 1.0;
+                    let out = (nx * nx + ny * ny).sqrt() / std::f32::consts::SQRT_2;
+                    let edge = ((out - VIGNETTE_START) / (1.0 - VIGNETTE_START)).clamp(0.0, 1.0);
+                    if edge > 0.0 {
+                        let shade = 1.0 - edge * edge * VIGNETTE_DEPTH * strength;
+                        *pixel = (Color::from_hex(*pixel) * shade).to_hex();
+                    }
+                }
+            });
+    }
+
+    // Brightens or darkens every pixel by a random amount that changes
+    // `GRAIN_RATE` times a second of `time`
+    pub fn film_grain(&mut self, time: f32, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let frame = (time * GRAIN_RATE).floor() as i32 as u32;
+        let amount = GRAIN_AMOUNT * strength;
+        self.buffer
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let offset = (grain(x as u32, y as u32, frame) - 0.5) * 2.0 * amount;
+                    let [r, g, b] = channels(*pixel);
+                    *pixel = Color::from_float(r + offset, g + offset, b + offset).to_hex();
+                }
+            });
+    }
+
+    // Shows the frame as an old television would, sampling what
+    // `keep_unprocessed` kept: bulged toward the corners, with red and blue
+    // pulled apart from green more the farther out they are, and dark
+    // scanlines across
+    pub fn crt(&mut self) {
+        if self.unprocessed.len() != self.buffer.len() {
+            return;
+        }
+        let (width, height) = (self.width, self.height);
+        let source = &self.unprocessed;
+        // Color of one channel (shifted by `shift` bits) at a point in
+        // half-screens from the center, black past the edge of the tube
+        let channel = |cx: f32, cy: f32, shift: u32| {
+            if cx.abs() > 1.0 || cy.abs() > 1.0 {
+                return 0;
+            }
+            let sx = ((cx + 1.0) * 0.5 * width as f32) as usize;
+            let sy = ((cy + 1.0) * 0.5 * height as f32) as usize;
+            let pixel = source[sy.min(height - 1) * width + sx.min(width - 1)];
+            pixel & (0xFF << shift)
+        };
+        self.buffer
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let ny = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+                let phase = (y as f32 + 0.5) / CRT_SCANLINE_PERIOD * std::f32::consts::TAU;
+                let scanline = 1.0 - CRT_SCANLINE_DEPTH * (0.5 - 0.5 * phase.cos());
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let nx = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                    let bulge = 1.0 + CRT_CURVATURE * (nx * nx + ny * ny);
+                    let (cx, cy) = (nx * bulge, ny * bulge);
+                    let red = 1.0 + CRT_ABERRATION;
+                    let blue = 1.0 - CRT_ABERRATION;
+                    let color = channel(cx * red, cy * red, 16)
+                        | channel(cx, cy, 8)
+                        | channel(cx * blue, cy * blue, 0);
+                    *pixel = (Color::from_hex(color) * scanline).to_hex();
+                }
+            });
+    }
+
+    // Cheap post-process antialiasing: pixels whose brightness differs sharply
+    // from their neighbors are blended towards them, softening stair steps
+    pub fn smooth_edges(&mut self) {
+        let (width, height) = (self.width, self.height);
+        if width < 3 || height < 3 {
+            return;
+        }
+
+        let source = self.buffer.clone();
+        let luma: Vec<f32> = source
+            .iter()
+            .map(|&pixel| Color::from_hex(pixel).luminance())
+            .collect();
+        let (source, luma) = (&source, &luma);
+        self.buffer
+            .par_chunks_mut(width)
+            .enumerate()
+            .skip(1)
+            .take(height - 2)
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate().skip(1).take(width - 2) {
+                    let index = y * width + x;
+                    let neighbors = [index - width, index + width, index - 1, index + 1];
+                    let (mut low, mut high) = (luma[index], luma[index]);
+                    for &neighbor in &neighbors {
+                        low = low.min(luma[neighbor]);
+                        high = high.max(luma[neighbor]);
+                    }
+                    let contrast = high - low;
+                    if contrast < EDGE_THRESHOLD.max(high * 0.125) {
+                        continue;
+                    }
+
+                    let mut average = [0.0; 3];
+                    for &neighbor in &neighbors {
+                        for (sum, value) in average.iter_mut().zip(channels(source[neighbor])) {
+                            *sum += value * 0.25;
+                        }
+                    }
+                    let blend = contrast.min(1.0) * 0.5;
+                    let center = channels(source[index]);
+                    let mixed: [f32; 3] = std::array::from_fn(|channel| {
+                        center[channel] + (average[channel] - center[channel]) * blend
+                    });
+                    *pixel = Color::from_float(mixed[0], mixed[1], mixed[2]).to_hex();
+                }
+            });
+    }
+
+    // Folds this frame into `history`, the running average of the last
+    // `samples` frames (pass 1 to restart it), and shows that average
+    pub fn accumulate(&mut self, history: &mut [[f32; 3]], samples: u32) {
+        let weight = 1.0 / samples.max(1) as f32;
+        self.buffer
+            .par_iter_mut()
+            .zip(history.par_iter_mut())
+            .for_each(|(pixel, average)| {
+                for (sum, value) in average.iter_mut().zip(channels(*pixel)) {
+                    *sum += (value - *sum) * weight;
+                }
+                *pixel = Color::from_float(average[0], average[1], average[2]).to_hex();
+            });
+    }
+
+    // Nearest-neighbor copy of `source` stretched over `viewport` of this
+    // buffer; only colors are copied
+    pub fn blit_scaled(&mut self, source: &Framebuffer, viewport: Viewport) {
+        let Viewport {
+            x: left,
+            y: top,
+            width,
+            height,
+        } = viewport;
+        let right = (left + width).min(self.width);
+        let (_, below) = self.split_at_row(top);
+        let (band, _) = below.split_at_row(top + height);
+        band.par_rows().for_each(|row| {
+            let sy = ((row.y - top) * source.height / height).min(source.height - 1);
+            for (x, pixel) in row.color.iter_mut().enumerate().take(right).skip(left) {
+                let sx = ((x - left) * source.width / width).min(source.width - 1);
+                *pixel = source.buffer[sy * source.width + sx];
+            }
+        });
+    }
+
+    // Overlay layer index of (x, y), if it is on screen
+    fn overlay_index(&self, x: i32, y: i32) -> Option<usize> {
+        let inside = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height;
+        inside.then(|| y as usize * self.width + x as usize)
+    }
+
     pub fn plot_overlay(&mut self, x: i32, y: i32, color: u32) {
-        if x < 0 || y < 0 {
+        self.blend_overlay(x, y, color, 1.0);
+    }
+
+    // Lays `color` over the overlay pixel by `alpha`, ignoring depth; the
+    // blended counterpart of `plot_overlay`
+    pub fn blend_overlay(&mut self, x: i32, y: i32, color: u32, alpha: f32) {
+        if let Some(index) = self.overlay_index(x, y) {
+            blend_layer(&mut self.overlay[index], color, alpha);
+        }
+    }
+
+    // Runs `shade` on the overlay pixels of the rectangle that are on
+    // screen, a row per worker, so fills as large as the screen stay cheap
+    fn shade_overlay(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        shade: impl Fn(i32, i32, &mut [f32; 4]) + Sync,
+    ) {
+        let left = x.max(0) as usize;
+        let right = (x + width).clamp(0, self.width as i32) as usize;
+        let top = y.max(0) as usize;
+        let bottom = (y + height).clamp(0, self.height as i32) as usize;
+        if left >= right || top >= bottom {
             return;
         }
+        self.overlay
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .skip(top)
+            .take(bottom - top)
+            .for_each(|(row, layers)| {
+                for (column, layer) in layers.iter_mut().enumerate().take(right).skip(left) {
+                    shade(column as i32, row as i32, layer);
+                }
+            });
+    }
 
-        let (xu, yu) = (x as usize, y as usize);
-        if xu < self.width && yu < self.height {
-            let index = yu * self.width + xu;
-            self.buffer[index] = color;
+    // `blend_overlay` where the scene is farther than `depth`, so lines in
+    // the world still pass behind the bodies
+    pub fn blend_overlay_at(&mut self, x: i32, y: i32, depth: f32, color: u32, alpha: f32) {
+        if let Some(index) = self.overlay_index(x, y) {
+            if self.zbuffer[index] > encode_depth(depth) {
+                self.blend_overlay(x, y, color, alpha);
+            }
         }
     }
 
-    pub fn draw_overlay_line(&mut self, mut x0: i32, mut y0: i32, x1: i32, y1: i32, color: u32) {
-        let dx = (x1 - x0).abs();
-        let sx = if x0 < x1 { 1 } else { -1 };
-        let dy = -(y1 - y0).abs();
-        let sy = if y0 < y1 { 1 } else { -1 };
-        let mut err = dx + dy;
+    // Lays the overlay over the image and clears it for the next frame; runs
+    // once the post effects are done
+    pub fn composite_overlay(&mut self) {
+        self.buffer
+            .par_iter_mut()
+            .zip(self.overlay.par_iter_mut())
+            .for_each(|(pixel, layer)| {
+                if layer[3] > 0.0 {
+                    let keep = 1.0 - layer[3];
+                    let [r, g, b] = channels(*pixel);
+                    *pixel = Color::from_float(
+                        r * keep + layer[0],
+                        g * keep + layer[1],
+                        b * keep + layer[2],
+                    )
+                    .to_hex();
+                }
+                *layer = [0.0; 4];
+            });
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: u32) {
+        self.shade_overlay(x, y, width, height, |_, _, layer| {
+            blend_layer(layer, color, 1.0)
+        });
+    }
 
-        loop {
-            self.plot_overlay(x0, y0, color);
-            if x0 == x1 && y0 == y1 {
-                break;
+    // Filled disk with a one-pixel soft edge
+    pub fn fill_circle(&mut self, center_x: i32, center_y: i32, radius: i32, color: u32) {
+        self.shade_circle(center_x, center_y, radius, color, |distance| {
+            (0.5 - distance).clamp(0.0, 1.0)
+        });
+    }
+
+    // Anti-aliased one-pixel ring
+    pub fn draw_circle(&mut self, center_x: i32, center_y: i32, radius: i32, color: u32) {
+        self.shade_circle(center_x, center_y, radius, color, |distance| {
+            1.0 - distance.abs()
+        });
+    }
+
+    // Rounded rectangle mixed over the frame by `alpha`, with an optional
+    // opaque one-pixel border; the backdrop for HUD boxes and menus
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_panel(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        radius: i32,
+        color: u32,
+        alpha: f32,
+        border: Option<u32>,
+    ) {
+        let half = (width as f32 * 0.5, height as f32 * 0.5);
+        let center = (x as f32 + half.0, y as f32 + half.1);
+        let radius = (radius as f32).min(half.0).min(half.1);
+
+        self.shade_overlay(x, y, width, height, |px, py, layer| {
+            // Signed distance to the rounded box, negative inside
+            let qx = (px as f32 + 0.5 - center.0).abs() - (half.0 - radius);
+            let qy = (py as f32 + 0.5 - center.1).abs() - (half.1 - radius);
+            let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+            let distance = outside + qx.max(qy).min(0.0) - radius;
+
+            blend_layer(layer, color, alpha * (0.5 - distance).clamp(0.0, 1.0));
+            if let Some(border) = border {
+                blend_layer(layer, border, 1.0 - (distance + 0.5).abs());
+            }
+        });
+    }
+
+    // Draws `sprite` with its top-left corner at (x, y), blended by its alpha
+    // and stretched by `scale` (1.0 for native size, nearest-neighbor)
+    pub fn blit_sprite(&mut self, x: i32, y: i32, sprite: &Sprite, scale: f32) {
+        if scale <= 0.0 {
+            return;
+        }
+        let width = (sprite.width as f32 * scale).round() as i32;
+        let height = (sprite.height as f32 * scale).round() as i32;
+
+        for dy in 0..height {
+            let sy = ((dy as f32 / scale) as usize).min(sprite.height - 1);
+            for dx in 0..width {
+                let sx = ((dx as f32 / scale) as usize).min(sprite.width - 1);
+                let pixel = sprite.pixels[sy * sprite.width + sx];
+                let alpha = (pixel >> 24) as f32 / 255.0;
+                self.blend_overlay(x + dx, y + dy, pixel & 0xFFFFFF, alpha);
             }
-            let e2 = err * 2;
-            if e2 >= dy {
-                err += dy;
-                x0 += sx;
+        }
+    }
+
+    // Walks the bounding box of a circle and blends `color` by `coverage`
+    // of each pixel's signed distance to the circle's edge
+    fn shade_circle(
+        &mut self,
+        center_x: i32,
+        center_y: i32,
+        radius: i32,
+        color: u32,
+        coverage: impl Fn(f32) -> f32,
+    ) {
+        let reach = radius + 1;
+        for y in center_y - reach..=center_y + reach {
+            for x in center_x - reach..=center_x + reach {
+                let (dx, dy) = ((x - center_x) as f32, (y - center_y) as f32);
+                let distance = (dx * dx + dy * dy).sqrt() - radius as f32;
+                self.blend_overlay(x, y, color, coverage(distance));
             }
-            if e2 <= dx {
-                err += dx;
-                y0 += sy;
+        }
+    }
+}
+
+// One row of a framebuffer's colors, depths (encoded as the z-buffer keeps
+// them) and ids, borrowed apart from every other row
+pub struct Row<'a> {
+    pub y: usize,
+    pub color: &'a mut [u32],
+    pub depth: &'a mut [f32],
+    pub ids: &'a mut [u32],
+}
+
+// A band of whole rows of a framebuffer, starting at row `top`
+pub struct Rows<'a> {
+    width: usize,
+    top: usize,
+    color: &'a mut [u32],
+    depth: &'a mut [f32],
+    ids: &'a mut [u32],
+}
+
+impl<'a> Rows<'a> {
+    // The rows above `row` and the rest; `row` counts from the top of the
+    // framebuffer and is clamped to the band
+    pub fn split_at_row(self, row: usize) -> (Rows<'a>, Rows<'a>) {
+        let height = self.color.len() / self.width;
+        let split = row.clamp(self.top, self.top + height);
+        let at = (split - self.top) * self.width;
+        let (color_above, color_below) = self.color.split_at_mut(at);
+        let (depth_above, depth_below) = self.depth.split_at_mut(at);
+        let (ids_above, ids_below) = self.ids.split_at_mut(at);
+        (
+            Rows {
+                width: self.width,
+                top: self.top,
+                color: color_above,
+                depth: depth_above,
+                ids: ids_above,
+            },
+            Rows {
+                width: self.width,
+                top: split,
+                color: color_below,
+                depth: depth_below,
+                ids: ids_below,
+            },
+        )
+    }
+
+    pub fn par_rows(self) -> impl IndexedParallelIterator<Item = Row<'a>> {
+        let (width, top) = (self.width, self.top);
+        self.color
+            .par_chunks_mut(width)
+            .zip(self.depth.par_chunks_mut(width))
+            .zip(self.ids.par_chunks_mut(width))
+            .enumerate()
+            .map(move |(y, ((color, depth), ids))| Row {
+                y: top + y,
+                color,
+                depth,
+                ids,
+            })
+    }
+}
+
+// An offscreen color and depth buffer of any size, shown over `viewport` of
+// another buffer. The scene is drawn into one at the resolution scale and
+// stretched over the window; insets, bakes or a minimap can have their own
+// and reuse the same pipeline without touching the window's buffer
+pub struct RenderTarget {
+    pub framebuffer: Framebuffer,
+    pub viewport: Viewport,
+}
+
+impl RenderTarget {
+    pub fn new(width: usize, height: usize) -> Self {
+        RenderTarget {
+            framebuffer: Framebuffer::new(width, height),
+            viewport: Viewport::full(width, height),
+        }
+    }
+
+    // Places it over `viewport` with `resolution_scale` of its pixels per
+    // pixel there; the buffer is only rebuilt when its size changes
+    pub fn fit(&mut self, viewport: Viewport, resolution_scale: f32) {
+        let width = ((viewport.width as f32 * resolution_scale) as usize).max(1);
+        let height = ((viewport.height as f32 * resolution_scale) as usize).max(1);
+        if (width, height) != (self.framebuffer.width, self.framebuffer.height) {
+            self.framebuffer = Framebuffer::new(width, height);
+        }
+        self.viewport = viewport;
+    }
+
+    // Lays its overlay over the image and copies it onto its viewport
+    pub fn present(&mut self, destination: &mut Framebuffer) {
+        self.framebuffer.composite_overlay();
+        destination.blit_scaled(&self.framebuffer, self.viewport);
+    }
+}
+
+// Lays `color` over a pixel of the overlay layer by `alpha`
+fn blend_layer(layer: &mut [f32; 4], color: u32, alpha: f32) {
+    if alpha <= 0.0 {
+        return;
+    }
+    let alpha = alpha.min(1.0);
+    let [r, g, b] = channels(color);
+    let keep = 1.0 - alpha;
+    *layer = [
+        r * alpha + layer[0] * keep,
+        g * alpha + layer[1] * keep,
+        b * alpha + layer[2] * keep,
+        alpha + layer[3] * keep,
+    ];
+}
+
+// Uniform in [0, 1) for each pixel and grain frame; an integer hash, since
+// the sine one in `noise` repeats over coordinates this large
+fn grain(x: u32, y: u32, frame: u32) -> f32 {
+    let mut hash = x
+        .wrapping_mul(0x8DA6_B343)
+        .wrapping_add(y.wrapping_mul(0xD816_3841))
+        .wrapping_add(frame.wrapping_mul(0xCB1A_B31F));
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2C1B_3C6D);
+    hash ^= hash >> 12;
+    hash = hash.wrapping_mul(0x297A_2D39);
+    hash ^= hash >> 15;
+    (hash >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn channels(hex: u32) -> [f32; 3] {
+    [
+        ((hex >> 16) & 0xFF) as f32 / 255.0,
+        ((hex >> 8) & 0xFF) as f32 / 255.0,
+        (hex & 0xFF) as f32 / 255.0,
+    ]
+}
+
+// Box blur along one axis of a `lines` x `length` grid; `line_stride` and
+// `step` give the index distance between lines and between samples in a line
+fn box_blur(values: &mut [[f32; 3]], length: usize, lines: usize, step: usize, line_stride: usize) {
+    let radius = BLOOM_BLUR_RADIUS as i32;
+    let mut line = vec![[0.0f32; 3]; length];
+    for start in (0..lines).map(|line| line * line_stride) {
+        for (position, slot) in line.iter_mut().enumerate() {
+            *slot = values[start + position * step];
+        }
+        for position in 0..length as i32 {
+            let mut sum = [0.0; 3];
+            for offset in -radius..=radius {
+                let sample = (position + offset).clamp(0, length as i32 - 1) as usize;
+                for (total, value) in sum.iter_mut().zip(line[sample]) {
+                    *total += value;
+                }
             }
+            values[start + position as usize * step] =
+                sum.map(|total| total / (radius * 2 + 1) as f32);
         }
     }
 }
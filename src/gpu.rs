@@ -0,0 +1,1031 @@
+use std::collections::HashMap;
+use std::io;
+use std::num::NonZeroU64;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+use rayon::prelude::*;
+use web_time::Instant;
+use wgpu::util::DeviceExt;
+
+use crate::arena::LineBuffers;
+use crate::backend::Renderer;
+use crate::camera::View;
+use crate::fragment_shaders::{
+    azure_planet_shader, crimson_planet_shader, gas_giant_shader, ice_giant_shader, molten_shader,
+    moon_shader, rocky_planet_shader, star_shader, FragmentShader,
+};
+use crate::framebuffer::{Framebuffer, RenderTarget, Viewport};
+use crate::hud::HudIcons;
+use crate::i18n::Strings;
+use crate::light::{Emitter, Light};
+use crate::map::MapCache;
+use crate::passes::{Pass, RenderGraph};
+use crate::renderer::{
+    create_model_matrix, draw_overlay, is_offscreen, lens_effects, moon_id, object_id,
+    post_process, ring_id, ship_transform, world_to_screen, Frame, FrameStats, Meshes, SceneLights,
+    Uniforms, CORONA_SHELL,
+};
+use crate::settings::Settings;
+use crate::simulation::{BodyState, MeshKind, Planet, Simulation, Snapshot};
+use crate::skybox::Skybox;
+use crate::sphere::generate_ring;
+use crate::star::StarLight;
+use crate::vertex::Vertex;
+
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+// Holds the bits of an f32, as float targets are not renderable everywhere
+const DEPTH_VALUE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+// Position, normal and texture coordinates as floats
+const VERTEX_STRIDE: u64 = 8 * 4;
+// Size of the `Draw` struct in gpu.wgsl, in 32-bit words
+const DRAW_WORDS: usize = 196;
+const MAX_LIGHTS: usize = 8;
+const MAX_RING_STOPS: usize = 8;
+// Meshes uploaded at startup, in this order; ring meshes follow them
+const STAR_MESH: usize = 0;
+const ROCKY_MESH: usize = 1;
+const GAS_MESH: usize = 2;
+const MOON_MESH: usize = 3;
+const SHIP_MESH: usize = 4;
+
+// Programs `fs_main` in gpu.wgsl switches on
+#[derive(Debug, Clone, Copy)]
+enum Shader {
+    Star,
+    Rocky,
+    Azure,
+    Crimson,
+    GasGiant,
+    Moon,
+    Ring,
+    Ship,
+    Corona,
+    IceGiant,
+    Molten,
+    // Anything without a port, drawn flat magenta
+    Unported,
+}
+
+impl Shader {
+    fn of(shader: FragmentShader) -> Self {
+        let ports: [(FragmentShader, Shader); 8] = [
+            (star_shader, Shader::Star),
+            (rocky_planet_shader, Shader::Rocky),
+            (azure_planet_shader, Shader::Azure),
+            (crimson_planet_shader, Shader::Crimson),
+            (gas_giant_shader, Shader::GasGiant),
+            (moon_shader, Shader::Moon),
+            (ice_giant_shader, Shader::IceGiant),
+            (molten_shader, Shader::Molten),
+        ];
+        ports
+            .into_iter()
+            .find(|(port, _)| std::ptr::fn_addr_eq(*port, shader))
+            .map_or(Shader::Unported, |(_, program)| program)
+    }
+}
+
+// How a draw's colors land on what is already in the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Blending {
+    // Replaces color, depth and id
+    Solid,
+    // Adds light without hiding what is behind, like `render_additive`
+    Additive,
+    // Mixes over what is behind by its opacity, like `render_blended`
+    Blended,
+}
+
+struct Mesh {
+    vertices: wgpu::Buffer,
+    count: u32,
+}
+
+struct Draw {
+    mesh: usize,
+    blending: Blending,
+    uniforms: [u32; DRAW_WORDS],
+}
+
+// Render targets at the scene's resolution and the buffer they are read
+// back through; rows are padded to what texture copies require
+struct Targets {
+    width: u32,
+    height: u32,
+    padded_row: u32,
+    color: wgpu::Texture,
+    depth_value: wgpu::Texture,
+    ids: wgpu::Texture,
+    depth: wgpu::Texture,
+    readback: wgpu::Buffer,
+}
+
+impl Targets {
+    fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let texture = |label, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let readable = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        Targets {
+            width,
+            height,
+            padded_row,
+            color: texture("color", COLOR_FORMAT, readable),
+            depth_value: texture("depth value", DEPTH_VALUE_FORMAT, readable),
+            ids: texture("ids", ID_FORMAT, readable),
+            depth: texture(
+                "depth",
+                DEPTH_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ),
+            // Room for the color, depth and id images one after the other
+            readback: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("readback"),
+                size: padded_row as u64 * height as u64 * 3,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    fn layer_size(&self) -> u64 {
+        self.padded_row as u64 * self.height as u64
+    }
+}
+
+// Experimental wgpu backend: the bodies, rings and ship go through WGSL
+// ports of their shaders and are read back into the same framebuffer, so
+// the skybox, post effects and overlay stay on the CPU and both backends
+// can be compared pass by pass. Not ported: the belt, auroras, landing
+// terrain, impostors, baked noise and atlases, the ship's reflections and
+// shadow, expression shaders and accumulation
+pub struct GpuRenderer {
+    scene: RenderTarget,
+    screen: Framebuffer,
+    stats: FrameStats,
+    strings: Rc<Strings>,
+    graph: RenderGraph,
+    skybox: Skybox,
+    icons: HudIcons,
+    // Atlas for the map view, baked on the CPU like the reference renderer's
+    map: MapCache,
+    // Scratch space for the orbit and grid lines, drawn on the CPU as well
+    lines: LineBuffers,
+    lights: SceneLights,
+    fill_light: Option<Light>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    solid: wgpu::RenderPipeline,
+    additive: wgpu::RenderPipeline,
+    blended: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    // Every draw's uniforms in one buffer, picked with a dynamic offset
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    uniform_stride: u64,
+    meshes: Vec<Mesh>,
+    // Index into `meshes` per (planet index, ring index), built on first sight
+    rings: HashMap<(usize, usize), usize>,
+    targets: Option<Targets>,
+    // The clock film grain runs on
+    started: Instant,
+}
+
+impl GpuRenderer {
+    pub fn new(
+        width: usize,
+        height: usize,
+        skybox: Skybox,
+        ship_vertices: Vec<Vertex>,
+        icons: HudIcons,
+        strings: Rc<Strings>,
+    ) -> io::Result<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no hay ningún adaptador gráfico disponible",
+            )
+        })?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits:
+                    wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .map_err(io::Error::other)?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gpu.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: NonZeroU64::new(DRAW_WORDS as u64 * 4),
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |blending| create_pipeline(&device, &pipeline_layout, &module, blending);
+        let (solid, additive, blended) = (
+            pipeline(Blending::Solid),
+            pipeline(Blending::Additive),
+            pipeline(Blending::Blended),
+        );
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let uniform_stride = (DRAW_WORDS as u64 * 4).next_multiple_of(alignment);
+        let (uniforms, bind_group) = uniform_buffer(&device, &layout, uniform_stride, 64);
+
+        let shapes = Meshes::new(ship_vertices);
+        let meshes = [
+            shapes.get(MeshKind::Star),
+            shapes.get(MeshKind::Rocky),
+            shapes.get(MeshKind::GasGiant),
+            shapes.get(MeshKind::Moon),
+            shapes.ship(),
+        ]
+        .into_iter()
+        .map(|vertices| upload(&device, vertices))
+        .collect();
+
+        let mut scene = RenderTarget::new(width, height);
+        scene.framebuffer.set_background_color(0x000000);
+        Ok(GpuRenderer {
+            scene,
+            screen: Framebuffer::new(width, height),
+            stats: FrameStats::default(),
+            strings,
+            graph: RenderGraph::default(),
+            skybox,
+            icons,
+            map: MapCache::default(),
+            lines: LineBuffers::default(),
+            lights: SceneLights::default(),
+            fill_light: None,
+            device,
+            queue,
+            solid,
+            additive,
+            blended,
+            layout,
+            uniforms,
+            bind_group,
+            uniform_stride,
+            meshes,
+            rings: HashMap::new(),
+            targets: None,
+            started: Instant::now(),
+        })
+    }
+
+    fn run_pass(&mut self, pass: Pass, frame: &Frame, opaque_drawn: bool) {
+        let time = frame.snapshot.time;
+        match pass {
+            Pass::Skybox => {
+                self.skybox
+                    .draw(&mut self.scene.framebuffer, time, frame.scene_view.roll);
+            }
+            Pass::Opaque => {
+                let draws = self.opaque_draws(frame);
+                self.draw(&draws, true);
+            }
+            // The belt is not ported
+            Pass::Particles => {}
+            Pass::Transparent => {
+                let draws = self.transparent_draws(frame);
+                // Translucent rings are tested against the bodies' depth,
+                // left in the depth target by the opaque pass
+                self.draw(&draws, !opaque_drawn);
+            }
+            Pass::Post => {
+                post_process(&mut self.scene.framebuffer, frame);
+                let seconds = self.started.elapsed().as_secs_f32();
+                lens_effects(&mut self.scene.framebuffer, frame, seconds);
+            }
+            Pass::Overlay => {
+                let map = self.map.atlas(frame.simulation);
+                draw_overlay(
+                    &mut self.scene.framebuffer,
+                    &mut self.screen,
+                    &mut self.lines,
+                    frame,
+                    &self.icons,
+                    &self.strings,
+                    map.as_deref(),
+                )
+            }
+        }
+    }
+
+    // Uniforms for an object centered at `origin`, lit by the frame's lights
+    fn uniforms(&self, model_matrix: Mat4, time: f32, origin: Vec3, view: &View) -> Uniforms {
+        Uniforms {
+            model_matrix,
+            time,
+            expression: None,
+            star: StarLight::default(),
+            lights: self.lights.relative_to(origin, view),
+            stars: self.lights.stars,
+            ambient: self.lights.ambient,
+            atmosphere: 0.0,
+            seed: 0,
+            noise: None,
+            atlas: None,
+            ring: None,
+            environment: None,
+            occluders: Vec::new(),
+        }
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.scene.framebuffer.width, self.scene.framebuffer.height)
+    }
+
+    // Bodies, solid rings, moons, the ship and the star coronas, in the
+    // software renderer's order
+    fn opaque_draws(&mut self, frame: &Frame) -> Vec<Draw> {
+        let (simulation, snapshot) = (frame.simulation, frame.snapshot);
+        let view = &frame.scene_view;
+        let time = snapshot.time;
+        let size = self.size();
+        let viewport = self.scene.framebuffer.viewport;
+        let mut draws = Vec::new();
+
+        let bodies = simulation.planets.iter().zip(&snapshot.bodies);
+        for (index, (planet, body)) in bodies.enumerate() {
+            let screen_position = world_to_screen(body.position, view, &viewport);
+            let scale = planet.scale * view.zoom;
+            let extent = planet
+                .rings
+                .iter()
+                .fold(scale, |extent, ring| extent.max(scale * ring.outer_radius));
+
+            if is_offscreen(screen_position, extent, &viewport) {
+                self.stats.culled_objects += 1;
+            } else {
+                let rotation = planet.rotation(time);
+                let model_matrix = create_model_matrix(screen_position, scale, rotation);
+                let uniforms = Uniforms {
+                    star: planet
+                        .temperature
+                        .map_or_else(StarLight::default, StarLight::from_temperature),
+                    ambient: self.lights.ambient_for(scale),
+                    atmosphere: planet.atmosphere_density,
+                    seed: index as u32,
+                    ..self.uniforms(model_matrix, time, body.position, view)
+                };
+                draws.push(Draw {
+                    mesh: mesh_index(planet.mesh),
+                    blending: Blending::Solid,
+                    uniforms: pack_uniforms(
+                        &uniforms,
+                        Shader::of(planet.shader),
+                        object_id(index),
+                        1.0,
+                        size,
+                    ),
+                });
+
+                if planet.temperature.is_some() {
+                    let corona_uniforms = Uniforms {
+                        model_matrix: create_model_matrix(
+                            screen_position,
+                            scale * CORONA_SHELL,
+                            rotation,
+                        ),
+                        ..uniforms
+                    };
+                    draws.push(Draw {
+                        mesh: mesh_index(planet.mesh),
+                        blending: Blending::Additive,
+                        uniforms: pack_uniforms(&corona_uniforms, Shader::Corona, 0, 0.0, size),
+                    });
+                }
+
+                self.ring_draws(&mut draws, index, planet, body, view, time, false);
+            }
+
+            for (moon_index, (moon, &moon_world)) in
+                planet.moons.iter().zip(&body.moon_positions).enumerate()
+            {
+                let moon_screen = world_to_screen(moon_world, view, &viewport);
+                let moon_scale = moon.scale * view.zoom;
+                if is_offscreen(moon_screen, moon_scale, &viewport) {
+                    self.stats.culled_objects += 1;
+                    continue;
+                }
+                let moon_matrix = create_model_matrix(moon_screen, moon_scale, moon.rotation(time));
+                let mut moon_uniforms = Uniforms {
+                    ambient: self.lights.ambient_for(moon_scale),
+                    seed: index as u32 + moon_index as u32 * 17,
+                    ..self.uniforms(moon_matrix, time, moon_world, view)
+                };
+                moon_uniforms.lights.truncate(self.lights.stars);
+                draws.push(Draw {
+                    mesh: mesh_index(moon.mesh),
+                    blending: Blending::Solid,
+                    uniforms: pack_uniforms(
+                        &moon_uniforms,
+                        Shader::of(moon.shader),
+                        moon_id(index, moon_index),
+                        1.0,
+                        size,
+                    ),
+                });
+            }
+        }
+
+        if !frame.photo {
+            let (ship_world, ship_matrix) =
+                ship_transform(view, snapshot.ship_bank, time, &viewport);
+            let ship_uniforms = self.uniforms(ship_matrix, time, ship_world, view);
+            draws.push(Draw {
+                mesh: SHIP_MESH,
+                blending: Blending::Solid,
+                uniforms: pack_uniforms(&ship_uniforms, Shader::Ship, 0, 1.0, size),
+            });
+        }
+        draws
+    }
+
+    // Translucent rings, farthest first
+    fn transparent_draws(&mut self, frame: &Frame) -> Vec<Draw> {
+        let (simulation, snapshot) = (frame.simulation, frame.snapshot);
+        let view = &frame.scene_view;
+        let mut translucent: Vec<(usize, f32)> = snapshot
+            .bodies
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                let rings = &simulation.planets[*index].rings;
+                rings.iter().any(|ring| ring.opacity < 1.0)
+            })
+            .map(|(index, body)| (index, (body.position - view.position).magnitude()))
+            .collect();
+        translucent.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut draws = Vec::new();
+        for (index, _) in translucent {
+            let (planet, body) = (&simulation.planets[index], &snapshot.bodies[index]);
+            self.ring_draws(&mut draws, index, planet, body, view, snapshot.time, true);
+        }
+        draws
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn ring_draws(
+        &mut self,
+        draws: &mut Vec<Draw>,
+        index: usize,
+        planet: &Planet,
+        body: &BodyState,
+        view: &View,
+        time: f32,
+        translucent: bool,
+    ) {
+        let size = self.size();
+        let viewport = self.scene.framebuffer.viewport;
+        let screen_position = world_to_screen(body.position, view, &viewport);
+        let scale = planet.scale * view.zoom;
+        let rings = planet
+            .rings
+            .iter()
+            .enumerate()
+            .filter(|(_, ring)| (ring.opacity < 1.0) == translucent);
+
+        for (slot, ring) in rings {
+            if is_offscreen(screen_position, scale * ring.outer_radius, &viewport) {
+                continue;
+            }
+            let ring_matrix = create_model_matrix(screen_position, scale, ring.rotation(time));
+            let ring_uniforms = Uniforms {
+                ambient: self.lights.ambient_for(scale),
+                seed: index as u32,
+                ring: Some(ring.colors),
+                ..self.uniforms(ring_matrix, time, body.position, view)
+            };
+            let mesh = match self.rings.get(&(index, slot)) {
+                Some(&mesh) => mesh,
+                None => {
+                    let vertices = generate_ring(ring.inner_radius, ring.outer_radius, 120);
+                    self.meshes.push(upload(&self.device, &vertices));
+                    self.rings.insert((index, slot), self.meshes.len() - 1);
+                    self.meshes.len() - 1
+                }
+            };
+            let (blending, id, opacity) = if translucent {
+                (Blending::Blended, 0, ring.opacity)
+            } else {
+                (Blending::Solid, ring_id(index), 1.0)
+            };
+            draws.push(Draw {
+                mesh,
+                blending,
+                uniforms: pack_uniforms(&ring_uniforms, Shader::Ring, id, opacity, size),
+            });
+        }
+    }
+
+    // Runs `draws` in one render pass, waits for it and lays the result
+    // over the framebuffer. `clear_depth` starts the depth test over; the
+    // opaque pass writes depth and ids back too
+    fn draw(&mut self, draws: &[Draw], clear_depth: bool) {
+        let (width, height) = (
+            self.scene.framebuffer.width as u32,
+            self.scene.framebuffer.height as u32,
+        );
+        if self
+            .targets
+            .as_ref()
+            .is_none_or(|targets| (targets.width, targets.height) != (width, height))
+        {
+            self.targets = Some(Targets::new(&self.device, width, height));
+        }
+        self.reserve_uniforms(draws.len());
+
+        let stride = self.uniform_stride as usize;
+        let mut data = vec![0; draws.len() * stride];
+        for (draw, chunk) in draws.iter().zip(data.chunks_mut(stride)) {
+            let bytes = draw.uniforms.iter().flat_map(|word| word.to_le_bytes());
+            for (byte, value) in chunk.iter_mut().zip(bytes) {
+                *byte = value;
+            }
+        }
+        self.queue.write_buffer(&self.uniforms, 0, &data);
+
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        let solid = draws.iter().any(|draw| draw.blending == Blending::Solid);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let view = |texture: &wgpu::Texture| texture.create_view(&Default::default());
+            let (color, depth_value, ids, depth) = (
+                view(&targets.color),
+                view(&targets.depth_value),
+                view(&targets.ids),
+                view(&targets.depth),
+            );
+            let attachment = |view, clear| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            };
+            let depth_load = if clear_depth {
+                wgpu::LoadOp::Clear(1.0)
+            } else {
+                wgpu::LoadOp::Load
+            };
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[
+                    attachment(&color, wgpu::Color::TRANSPARENT),
+                    attachment(&depth_value, wgpu::Color::TRANSPARENT),
+                    attachment(&ids, wgpu::Color::TRANSPARENT),
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: depth_load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            for (index, draw) in draws.iter().enumerate() {
+                let mesh = &self.meshes[draw.mesh];
+                if mesh.count == 0 {
+                    continue;
+                }
+                pass.set_pipeline(match draw.blending {
+                    Blending::Solid => &self.solid,
+                    Blending::Additive => &self.additive,
+                    Blending::Blended => &self.blended,
+                });
+                let offset = (index as u64 * self.uniform_stride) as u32;
+                pass.set_bind_group(0, &self.bind_group, &[offset]);
+                pass.set_vertex_buffer(0, mesh.vertices.slice(..));
+                pass.draw(0..mesh.count, 0..1);
+                self.stats.triangles += mesh.count as usize / 3;
+            }
+        }
+
+        let layers: &[&wgpu::Texture] = if solid {
+            &[&targets.color, &targets.depth_value, &targets.ids]
+        } else {
+            &[&targets.color]
+        };
+        for (layer, texture) in layers.iter().enumerate() {
+            encoder.copy_texture_to_buffer(
+                texture.as_image_copy(),
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &targets.readback,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset: layer as u64 * targets.layer_size(),
+                        bytes_per_row: Some(targets.padded_row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        self.queue.submit([encoder.finish()]);
+
+        let slice = targets.readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        match receiver.recv() {
+            Ok(Ok(())) => {
+                composite(
+                    &mut self.scene.framebuffer,
+                    targets,
+                    &slice.get_mapped_range(),
+                    solid,
+                );
+                targets.readback.unmap();
+            }
+            _ => eprintln!("No se pudo leer la imagen de la GPU"),
+        }
+    }
+
+    // Grows the uniform buffer to fit `draws` draws
+    fn reserve_uniforms(&mut self, draws: usize) {
+        let capacity = self.uniforms.size() / self.uniform_stride;
+        if draws as u64 > capacity {
+            let capacity = (draws as u64).next_power_of_two();
+            (self.uniforms, self.bind_group) =
+                uniform_buffer(&self.device, &self.layout, self.uniform_stride, capacity);
+        }
+    }
+}
+
+impl Renderer for GpuRenderer {
+    fn render_frame(&mut self, simulation: &Simulation, snapshot: &Snapshot, settings: &Settings) {
+        let full = Viewport::full(self.screen.width, self.screen.height);
+        self.scene.fit(full, settings.scene_scale(full.height));
+        let scale = self.scene.framebuffer.width as f32 / self.screen.width as f32;
+        let frame = Frame::new(
+            simulation,
+            snapshot,
+            settings,
+            self.screen.ui_scale,
+            scale,
+            Vec2::zeros(),
+            1,
+        );
+
+        self.lights = SceneLights::gather(&frame, self.fill_light);
+        self.stats = FrameStats::default();
+        // The skybox repaints the whole frame, so clearing its colors first
+        // would be wasted
+        if self.graph.is_enabled(Pass::Skybox) && self.skybox.covers_frame() {
+            self.scene.framebuffer.clear_keeping_color();
+        } else {
+            self.scene.framebuffer.clear();
+        }
+
+        let mut opaque_drawn = false;
+        for pass in Pass::ALL {
+            if !self.graph.is_enabled(pass) {
+                self.graph.record(pass, Duration::ZERO);
+                continue;
+            }
+            let start = Instant::now();
+            self.run_pass(pass, &frame, opaque_drawn);
+            self.graph.record(pass, start.elapsed());
+            opaque_drawn |= pass == Pass::Opaque;
+        }
+
+        self.scene.present(&mut self.screen);
+        self.screen.composite_overlay();
+    }
+
+    fn screen(&self) -> &Framebuffer {
+        &self.screen
+    }
+
+    fn screen_mut(&mut self) -> &mut Framebuffer {
+        &mut self.screen
+    }
+
+    fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    fn graph(&self) -> &RenderGraph {
+        &self.graph
+    }
+
+    fn graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.graph
+    }
+
+    fn set_skybox(&mut self, skybox: Skybox) {
+        self.skybox = skybox;
+    }
+
+    fn set_fill_light(&mut self, intensity: f32) {
+        self.fill_light = SceneLights::fill_light(intensity);
+    }
+}
+
+fn mesh_index(kind: MeshKind) -> usize {
+    match kind {
+        MeshKind::Star => STAR_MESH,
+        MeshKind::Rocky => ROCKY_MESH,
+        MeshKind::GasGiant => GAS_MESH,
+        MeshKind::Moon => MOON_MESH,
+    }
+}
+
+fn upload(device: &wgpu::Device, vertices: &[Vertex]) -> Mesh {
+    let contents: Vec<u8> = vertices
+        .iter()
+        .flat_map(|vertex| {
+            let (position, normal, uv) = (vertex.position, vertex.normal, vertex.tex_coords);
+            [
+                position.x, position.y, position.z, normal.x, normal.y, normal.z, uv.x, uv.y,
+            ]
+        })
+        .flat_map(f32::to_le_bytes)
+        .collect();
+    Mesh {
+        vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &contents,
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        count: vertices.len() as u32,
+    }
+}
+
+fn uniform_buffer(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    stride: u64,
+    capacity: u64,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("uniforms"),
+        size: stride * capacity,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: NonZeroU64::new(DRAW_WORDS as u64 * 4),
+            }),
+        }],
+    });
+    (buffer, bind_group)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    blending: Blending,
+) -> wgpu::RenderPipeline {
+    let solid = blending == Blending::Solid;
+    let blend = match blending {
+        Blending::Solid => None,
+        Blending::Additive => {
+            let add = wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            };
+            Some(wgpu::BlendState {
+                color: add,
+                alpha: add,
+            })
+        }
+        Blending::Blended => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+    };
+    // Only solid draws leave their depth and id behind
+    let writes = if solid {
+        wgpu::ColorWrites::ALL
+    } else {
+        wgpu::ColorWrites::empty()
+    };
+    let target = |format, blend| {
+        Some(wgpu::ColorTargetState {
+            format,
+            blend,
+            write_mask: writes,
+        })
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: VERTEX_STRIDE,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x3,
+                    1 => Float32x3,
+                    2 => Float32x2,
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[
+                Some(wgpu::ColorTargetState {
+                    format: COLOR_FORMAT,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                target(DEPTH_VALUE_FORMAT, None),
+                target(ID_FORMAT, None),
+            ],
+        }),
+        // Like the software rasterizer, both faces are drawn
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: solid,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+        multisample: Default::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+// Lays out `Draw` from gpu.wgsl. Opacity is 1 for solid draws, 0 for
+// additive ones and the mix amount for blended ones
+fn pack_uniforms(
+    uniforms: &Uniforms,
+    shader: Shader,
+    id: u32,
+    opacity: f32,
+    (width, height): (usize, usize),
+) -> [u32; DRAW_WORDS] {
+    let mut words = Vec::with_capacity(DRAW_WORDS);
+    let mut floats = |values: &[f32]| words.extend(values.iter().map(|value| value.to_bits()));
+    floats(uniforms.model_matrix.as_slice());
+    floats(&[
+        width as f32,
+        height as f32,
+        uniforms.time,
+        uniforms.seed as f32,
+    ]);
+    let stops = uniforms.ring.map_or(&[][..], |palette| palette.stops);
+    let stops = &stops[..stops.len().min(MAX_RING_STOPS)];
+    let lights = &uniforms.lights[..uniforms.lights.len().min(MAX_LIGHTS)];
+    words.extend([shader as u32, lights.len() as u32, stops.len() as u32, id]);
+
+    let mut floats = |values: &[f32]| words.extend(values.iter().map(|value| value.to_bits()));
+    let (ambient, star) = (uniforms.ambient, &uniforms.star);
+    floats(&[ambient.x, ambient.y, ambient.z, opacity]);
+    let stars = uniforms.stars.min(lights.len()) as f32;
+    floats(&[star.surface.x, star.surface.y, star.surface.z, stars]);
+    let atmosphere = uniforms.atmosphere;
+    floats(&[star.corona.x, star.corona.y, star.corona.z, atmosphere]);
+    for slot in 0..MAX_RING_STOPS {
+        match stops.get(slot) {
+            Some(&(position, color)) => {
+                let color = color.to_vec3();
+                floats(&[color.x, color.y, color.z, position]);
+            }
+            None => floats(&[0.0; 4]),
+        }
+    }
+    for slot in 0..MAX_LIGHTS {
+        match lights.get(slot) {
+            Some(light) => floats(&pack_light(light)),
+            None => floats(&[0.0; 16]),
+        }
+    }
+    words
+        .try_into()
+        .expect("El tamaño de los uniformes no coincide con gpu.wgsl")
+}
+
+// `Light` from gpu.wgsl: position and kind, color and range, spot axis and
+// cone
+fn pack_light(light: &Light) -> [f32; 16] {
+    let range = light.range.unwrap_or(0.0);
+    let (position, kind, axis, inner, outer) = match light.emitter {
+        Emitter::Point(position) => (position, 0.0, Vec3::zeros(), 0.0, 0.0),
+        Emitter::Directional(direction) => (direction, 1.0, Vec3::zeros(), 0.0, 0.0),
+        Emitter::Spot {
+            position,
+            direction,
+            inner,
+            outer,
+        } => (position, 2.0, direction, inner, outer),
+    };
+    let color = light.color;
+    [
+        position.x, position.y, position.z, kind, color.x, color.y, color.z, range, axis.x, axis.y,
+        axis.z, inner, outer, 0.0, 0.0, 0.0,
+    ]
+}
+
+// Lays a pass's premultiplied colors over the framebuffer; after a pass
+// with solid draws, the pixels they covered take their depth and ids too
+fn composite(framebuffer: &mut Framebuffer, targets: &Targets, data: &[u8], solid: bool) {
+    let layer = targets.layer_size() as usize;
+    let row = targets.padded_row as usize;
+    let word = |layer_index: usize, x: usize, y: usize| {
+        let start = layer_index * layer + y * row + x * 4;
+        [
+            data[start],
+            data[start + 1],
+            data[start + 2],
+            data[start + 3],
+        ]
+    };
+    framebuffer.rows_mut().for_each(|row| {
+        let y = row.y;
+        for (x, pixel) in row.color.iter_mut().enumerate() {
+            let [r, g, b, alpha] = word(0, x, y);
+            if (r, g, b, alpha) == (0, 0, 0, 0) {
+                continue;
+            }
+            let below = *pixel;
+            let keep = 255 - alpha as u32;
+            let channel = |over: u8, shift: u32| {
+                let under = (below >> shift) & 0xFF;
+                (over as u32 + under * keep / 255).min(255) << shift
+            };
+            *pixel = channel(r, 16) | channel(g, 8) | channel(b, 0);
+            if solid && alpha == 255 {
+                row.depth[x] = f32::from_le_bytes(word(1, x, y));
+                row.ids[x] = u32::from_le_bytes(word(2, x, y));
+            }
+        }
+    });
+}
@@ -0,0 +1,599 @@
+// WGSL ports of the shaders in fragment_shaders.rs and the light model in
+// light.rs, for the experimental GPU backend. Keep them in step with the
+// Rust versions: the software renderer is the reference they are compared to
+
+const PI: f32 = 3.14159265;
+const TAU: f32 = 6.28318531;
+// Same range as the framebuffer's depth encoding
+const DEPTH_NEAR: f32 = 1.0;
+const DEPTH_FAR: f32 = 50000.0;
+const CORONA_SHELL: f32 = 1.35;
+const STORM_SLOTS: u32 = 5u;
+
+const SHADER_STAR: u32 = 0u;
+const SHADER_ROCKY: u32 = 1u;
+const SHADER_AZURE: u32 = 2u;
+const SHADER_CRIMSON: u32 = 3u;
+const SHADER_GAS_GIANT: u32 = 4u;
+const SHADER_MOON: u32 = 5u;
+const SHADER_RING: u32 = 6u;
+const SHADER_SHIP: u32 = 7u;
+const SHADER_CORONA: u32 = 8u;
+const SHADER_ICE_GIANT: u32 = 9u;
+const SHADER_MOLTEN: u32 = 10u;
+
+struct Light {
+    // Position relative to the object, or direction for directional lights;
+    // w is the kind: 0 point, 1 directional, 2 spot
+    position: vec4<f32>,
+    // w is the range, 0 when it never dims
+    color: vec4<f32>,
+    // Spot axis; w is the cosine of the inner half-angle
+    axis: vec4<f32>,
+    // x is the cosine of the outer half-angle
+    cone: vec4<f32>,
+}
+
+// One draw's uniforms, laid out by `pack_uniforms` in gpu.rs
+struct Draw {
+    model: mat4x4<f32>,
+    // Scene width and height in pixels, time and seed
+    frame: vec4<f32>,
+    // Shader, light count, ring color stops and object id
+    info: vec4<u32>,
+    // w is the opacity: 1 solid, 0 additive, anything between blended
+    ambient: vec4<f32>,
+    // w is how many of the lights are stars; they come first
+    star_surface: vec4<f32>,
+    // w is the body's atmosphere density
+    star_corona: vec4<f32>,
+    // Color and position of each stop
+    ring: array<vec4<f32>, 8>,
+    lights: array<Light, 8>,
+}
+
+@group(0) @binding(0) var<uniform> draw: Draw;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    // Object space, so patterns stay fixed to the body
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+    // Distance from the camera, the software rasterizer's depth
+    @location(3) distance: f32,
+}
+
+struct FragmentOutput {
+    @builtin(frag_depth) frag_depth: f32,
+    @location(0) color: vec4<f32>,
+    // Bits of the depth encoded like the framebuffer's zbuffer, copied into
+    // it after readback; float targets are not renderable everywhere
+    @location(1) depth: u32,
+    @location(2) id: u32,
+}
+
+// The model matrix already maps to pixels with depth in z, so the clip
+// position is just the pixel rescaled
+@vertex
+fn vs_main(vertex: VertexInput) -> VertexOutput {
+    let transformed = draw.model * vec4<f32>(vertex.position, 1.0);
+    let screen = transformed.xyz / transformed.w;
+    let size = draw.frame.xy;
+
+    var out: VertexOutput;
+    out.clip = vec4<f32>(screen.x / size.x * 2.0 - 1.0, 1.0 - screen.y / size.y * 2.0, 0.5, 1.0);
+    out.position = vertex.position;
+    // Scale is uniform, so the rotation alone turns normals
+    let rotation = mat3x3<f32>(draw.model[0].xyz, draw.model[1].xyz, draw.model[2].xyz);
+    out.normal = rotation * vertex.normal;
+    out.tex_coords = vertex.tex_coords;
+    out.distance = screen.z;
+    return out;
+}
+
+fn encode_depth(distance: f32) -> f32 {
+    let scale = log(DEPTH_FAR / DEPTH_NEAR);
+    if distance >= DEPTH_NEAR {
+        return log(distance / DEPTH_NEAR) / scale;
+    }
+    return (distance / DEPTH_NEAR - 1.0) / scale;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> FragmentOutput {
+    let normal = normalize(input.normal);
+    var color: vec3<f32>;
+    switch draw.info.x {
+        case SHADER_STAR: { color = star_shader(input.position, normal); }
+        case SHADER_ROCKY: { color = rocky_planet_shader(input.position, normal); }
+        case SHADER_AZURE: { color = azure_planet_shader(input.position, normal); }
+        case SHADER_CRIMSON: { color = crimson_planet_shader(input.position, normal); }
+        case SHADER_GAS_GIANT: { color = gas_giant_shader(input.position, normal); }
+        case SHADER_MOON: { color = moon_shader(input.position, normal); }
+        case SHADER_RING: { color = ring_shader(input.position, normal, input.tex_coords); }
+        case SHADER_SHIP: { color = ship_shader(input.position, normal); }
+        case SHADER_CORONA: { color = corona_shader(input.position); }
+        case SHADER_ICE_GIANT: { color = ice_giant_shader(input.position, normal); }
+        case SHADER_MOLTEN: { color = molten_shader(input.position, normal); }
+        // Shaders without a port show up flat magenta
+        default: { color = vec3<f32>(1.0, 0.0, 1.0); }
+    }
+    color = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    let opacity = draw.ambient.w;
+    let encoded = encode_depth(input.distance);
+    var out: FragmentOutput;
+    out.frag_depth = clamp(encoded * 0.5 + 0.5, 0.0, 1.0);
+    // Premultiplied, so additive shells (opacity 0) leave alpha alone
+    out.color = vec4<f32>(color * select(1.0, opacity, opacity > 0.0), opacity);
+    out.depth = bitcast<u32>(encoded);
+    out.id = draw.info.w;
+    return out;
+}
+
+// Noise, as in noise.rs
+
+fn hash(n: f32) -> f32 {
+    return fract(sin(n * 12.9898) * 43758.5453);
+}
+
+fn hash_vec3(p: vec3<f32>) -> f32 {
+    return hash(p.x * 12.9898 + p.y * 78.233 + p.z * 45.164);
+}
+
+fn seed_offset(seed: f32) -> vec3<f32> {
+    if seed == 0.0 {
+        return vec3<f32>(0.0);
+    }
+    return vec3<f32>(
+        hash(seed * 0.731 + 0.17),
+        hash(seed * 1.319 + 0.53),
+        hash(seed * 0.917 + 0.91),
+    ) * 97.0;
+}
+
+fn value_noise(p: vec3<f32>) -> f32 {
+    let i = floor(p);
+    let f = p - i;
+    let u = f * f * (3.0 - 2.0 * f);
+
+    let a = hash_vec3(i);
+    let b = hash_vec3(i + vec3<f32>(1.0, 0.0, 0.0));
+    let c = hash_vec3(i + vec3<f32>(0.0, 1.0, 0.0));
+    let d = hash_vec3(i + vec3<f32>(1.0, 1.0, 0.0));
+    let e = hash_vec3(i + vec3<f32>(0.0, 0.0, 1.0));
+    let f_val = hash_vec3(i + vec3<f32>(1.0, 0.0, 1.0));
+    let g = hash_vec3(i + vec3<f32>(0.0, 1.0, 1.0));
+    let h = hash_vec3(i + vec3<f32>(1.0, 1.0, 1.0));
+
+    let y1 = mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+    let y2 = mix(mix(e, f_val, u.x), mix(g, h, u.x), u.y);
+    return mix(y1, y2, u.z);
+}
+
+fn fbm(p: vec3<f32>, octaves: u32) -> f32 {
+    let shifted = p + seed_offset(draw.frame.w);
+    var value = 0.0;
+    var amplitude = 0.5;
+    var frequency = 1.0;
+    for (var octave = 0u; octave < octaves; octave++) {
+        value += amplitude * value_noise(shifted * frequency);
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    return value;
+}
+
+fn smoothstep_clamped(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
+// Lighting, as in light.rs and fragment_shaders.rs
+
+// Unit direction from a surface at `offset` to the light in xyz, and how
+// much of it gets there in w
+fn incoming(light: Light, offset: vec3<f32>) -> vec4<f32> {
+    let kind = light.position.w;
+    var direction = light.position.xyz;
+    var distance = 0.0;
+    var cone = 1.0;
+    if kind != 1.0 {
+        let to_light = light.position.xyz - offset;
+        distance = max(length(to_light), 0.0001);
+        direction = to_light / distance;
+        if kind == 2.0 {
+            let along = -dot(direction, light.axis.xyz);
+            let t = clamp((along - light.cone.x) / (light.axis.w - light.cone.x), 0.0, 1.0);
+            cone = t * t * (3.0 - 2.0 * t);
+        }
+    }
+    var attenuation = 1.0;
+    if light.color.w > 0.0 {
+        let reach = distance / light.color.w;
+        attenuation = 1.0 / (1.0 + reach * reach);
+    }
+    return vec4<f32>(direction, cone * attenuation);
+}
+
+fn diffuse(light: Light, offset: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let arriving = incoming(light, offset);
+    return light.color.rgb * (max(dot(normal, arriving.xyz), 0.0) * arriving.w);
+}
+
+// TWILIGHT_WARM, TWILIGHT_COOL and TWILIGHT_GLOW from light.rs
+fn scattered(light: Light, offset: vec3<f32>, normal: vec3<f32>, width: f32) -> vec3<f32> {
+    if width <= 0.0 {
+        return diffuse(light, offset, normal);
+    }
+    let arriving = incoming(light, offset);
+    let elevation = dot(normal, arriving.xyz);
+    let warm = vec3<f32>(1.0, 0.55, 0.3);
+    let cool = vec3<f32>(0.3, 0.45, 1.0);
+    let edge = max(1.0 - (elevation / width) * (elevation / width), 0.0);
+    let band = edge * edge;
+    let across = clamp((elevation / width + 1.0) * 0.5, 0.0, 1.0);
+    let sunlit = mix(vec3<f32>(1.0), warm, band) * max(elevation, 0.0);
+    let glow = mix(cool, warm, across) * (band * 0.25);
+    return light.color.rgb * (sunlit + glow) * arriving.w;
+}
+
+fn specular(light: Light, offset: vec3<f32>, normal: vec3<f32>, shininess: f32) -> vec3<f32> {
+    let arriving = incoming(light, offset);
+    let halfway = arriving.xyz + vec3<f32>(0.0, 0.0, -1.0);
+    if dot(normal, arriving.xyz) <= 0.0 || length(halfway) < 1e-6 {
+        return vec3<f32>(0.0);
+    }
+    let facing = max(dot(normal, normalize(halfway)), 0.0);
+    return light.color.rgb * (pow(facing, shininess) * arriving.w);
+}
+
+fn to_view(position: vec3<f32>) -> vec3<f32> {
+    return (draw.model * vec4<f32>(position, 0.0)).xyz;
+}
+
+// Stars get a twilight band TWILIGHT_WIDTH (fragment_shaders.rs) times the
+// atmosphere density wide
+fn lighting(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let offset = to_view(position);
+    let stars = min(u32(draw.star_surface.w), draw.info.y);
+    let width = 0.2 * draw.star_corona.w;
+    var sum = vec3<f32>(0.0);
+    for (var index = 0u; index < draw.info.y; index++) {
+        if index < stars {
+            sum += scattered(draw.lights[index], offset, normal, width);
+        } else {
+            sum += diffuse(draw.lights[index], offset, normal);
+        }
+    }
+    return sum;
+}
+
+// GLINT_SHININESS and GLINT_STRENGTH from fragment_shaders.rs
+fn sun_glint(position: vec3<f32>, normal: vec3<f32>, water: f32) -> vec3<f32> {
+    if water <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+    let offset = to_view(position);
+    let stars = min(u32(draw.star_surface.w), draw.info.y);
+    var sum = vec3<f32>(0.0);
+    for (var index = 0u; index < stars; index++) {
+        sum += specular(draw.lights[index], offset, normal, 90.0);
+    }
+    return sum * (water * 0.9);
+}
+
+fn light_factor(light: vec3<f32>, diffuse_weight: f32, ambient_weight: f32) -> vec3<f32> {
+    return light * diffuse_weight + draw.ambient.rgb * ambient_weight;
+}
+
+fn ring_color(t: f32) -> vec3<f32> {
+    let count = draw.info.z;
+    if count == 0u {
+        return vec3<f32>(0.45, 0.4, 0.35);
+    }
+    if t <= draw.ring[0].w {
+        return draw.ring[0].rgb;
+    }
+    for (var index = 1u; index < count; index++) {
+        let lower = draw.ring[index - 1u];
+        let upper = draw.ring[index];
+        if t <= upper.w {
+            let span = max(upper.w - lower.w, 1.1920929e-7);
+            return mix(lower.rgb, upper.rgb, (t - lower.w) / span);
+        }
+    }
+    return draw.ring[count - 1u].rgb;
+}
+
+// Shaders
+
+fn star_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let intensity = max(dot(normal, vec3<f32>(0.0, 0.0, -1.0)), 0.0);
+    let base_color = draw.star_surface.rgb;
+
+    let variation = 0.1 * fbm(position * 5.0, 3u);
+    let center_dist = length(position.xy);
+    let center_glow = pow(1.0 - min(center_dist, 1.0), 2.0) * 0.3;
+    let flare = pow(normal.z * 0.5 + 0.5, 3.0) * 0.2;
+
+    let color = clamp(
+        base_color + vec3<f32>(
+            variation + center_glow + flare,
+            variation * 0.5 + center_glow * 0.8 + flare * 0.9,
+            variation * 0.3 + center_glow * 0.5,
+        ),
+        vec3<f32>(0.0),
+        vec3<f32>(1.0),
+    );
+    return color * (intensity * 0.7 + 0.3);
+}
+
+fn rocky_planet_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let lat = acos(position.y / length(position));
+
+    let continent_noise = fbm(position * 2.0, 4u);
+    let is_land = continent_noise > 0.1;
+    let climate = abs(lat / PI);
+
+    var albedo: vec3<f32>;
+    var water = 0.0;
+    if is_land {
+        let elevation = fbm(position * 4.0, 3u) * 0.5 + 0.5;
+        let base_green = vec3<f32>(0.2, 0.6, 0.2);
+        let brown = vec3<f32>(0.4, 0.3, 0.2);
+        let snow = vec3<f32>(0.9, 0.9, 0.95);
+        if climate > 0.7 {
+            albedo = base_green * 0.3 + snow * 0.7;
+        } else if climate < 0.3 {
+            albedo = base_green * 0.8 + brown * 0.2;
+        } else {
+            albedo = mix(base_green, brown, elevation * 0.5);
+        }
+    } else {
+        let ocean_depth = fbm(position * 3.0, 3u) * 0.3 + 0.7;
+        let deep_blue = vec3<f32>(0.0, 0.2, 0.5);
+        let shallow_blue = vec3<f32>(0.2, 0.4, 0.7);
+        albedo = deep_blue * ocean_depth + shallow_blue * (1.0 - ocean_depth);
+        water = 1.0;
+    }
+
+    return albedo * light_factor(light, 0.8, 0.2) + sun_glint(position, normal, water);
+}
+
+fn azure_planet_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let polar_noise = fbm(position * 4.0, 4u);
+    let ocean_noise = fbm(position * 2.5, 3u);
+
+    let ice_caps = clamp(pow(abs(position.y) / length(position), 4.0) + polar_noise * 0.3, 0.0, 1.0);
+    let ocean_mix = clamp(ocean_noise * 1.2 - 0.2, 0.0, 1.0);
+
+    let abyss = vec3<f32>(0.02, 0.18, 0.4);
+    let lagoon = vec3<f32>(0.18, 0.66, 0.96);
+    let aurora = vec3<f32>(0.5, 0.9, 1.0);
+
+    let base_water = abyss * (1.0 - ocean_mix) + lagoon * ocean_mix;
+    let cloud_bands = pow(fbm(position * 6.0, 5u), 3.0);
+    let cloud_color = vec3<f32>(0.85, 0.95, 1.0);
+    let mixed = base_water * (1.0 - cloud_bands) + cloud_color * cloud_bands;
+
+    let ice_color = aurora * (0.6 + ice_caps * 0.4);
+    let albedo = mixed * (1.0 - ice_caps) + ice_color * ice_caps;
+
+    let highlight = pow(normal.y * 0.5 + 0.5, 8.0) * 0.3;
+    let water = (1.0 - cloud_bands) * (1.0 - ice_caps);
+    return albedo * light_factor(light, 0.75, 0.25) + vec3<f32>(highlight, highlight, highlight * 0.8)
+        + sun_glint(position, normal, water);
+}
+
+// LAVA_CRUST and LAVA from fragment_shaders.rs
+fn lava_crust(t: f32) -> vec3<f32> {
+    return mix(vec3<f32>(51.0, 13.0, 13.0), vec3<f32>(189.0, 41.0, 20.0), clamp(t, 0.0, 1.0)) / 255.0;
+}
+
+fn lava(t: f32) -> vec3<f32> {
+    let ember = vec3<f32>(189.0, 41.0, 20.0);
+    if t <= 0.5 {
+        return mix(vec3<f32>(51.0, 13.0, 13.0), ember, clamp(t / 0.5, 0.0, 1.0)) / 255.0;
+    }
+    return mix(ember, vec3<f32>(255.0, 107.0, 46.0), clamp((t - 0.5) / 0.5, 0.0, 1.0)) / 255.0;
+}
+
+fn crimson_planet_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let basalt_noise = fbm(position * 3.5, 4u);
+    let fissure_noise = fbm(position * 8.0, 5u);
+
+    let crater_mask = abs(basalt_noise - 0.45);
+    let lava_mix = pow(clamp(fissure_noise * 1.4 - 0.5, 0.0, 1.0), 1.6);
+    let crater_color = lava_crust(lava_mix) * (0.5 + crater_mask * 0.4);
+    let albedo = crater_color * (1.0 - lava_mix) + lava(lava_mix) * lava_mix;
+    let glow = lava_mix * 0.4;
+    let emission = vec3<f32>(glow, glow * 0.6, glow * 0.4);
+
+    let rim_specular = pow(normal.y * 0.5 + 0.5, 8.0) * 0.3;
+    let shaded = albedo * light_factor(light, 0.8, 0.2) + emission;
+    return shaded + vec3<f32>(rim_specular, rim_specular * 0.4, 0.0);
+}
+
+// ICE_BANDS from fragment_shaders.rs
+fn ice_bands(t: f32) -> vec3<f32> {
+    let middle = vec3<f32>(70.0, 170.0, 196.0);
+    if t <= 0.5 {
+        return mix(vec3<f32>(24.0, 96.0, 130.0), middle, clamp(t / 0.5, 0.0, 1.0)) / 255.0;
+    }
+    return mix(middle, vec3<f32>(168.0, 228.0, 236.0), clamp((t - 0.5) / 0.5, 0.0, 1.0)) / 255.0;
+}
+
+fn ice_giant_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let lat = position.y / length(position);
+    let drift = fbm(position * 1.5, 3u);
+    let wisps = fbm(position * 4.0, 3u);
+
+    let band = sin((lat + (drift - 0.5) * 0.12) * 7.0) * 0.5 + 0.5;
+    let base = ice_bands(band * 0.85 + (drift - 0.5) * 0.3);
+    let lines = pow(abs(sin((lat + (drift - 0.5) * 0.05) * 60.0)), 12.0);
+    let streak = lines * smoothstep_clamped(0.5, 0.7, wisps) * 0.35;
+    let albedo = base * (1.0 - streak) + vec3<f32>(0.85, 0.95, 1.0) * streak;
+
+    return albedo * light_factor(light, 0.75, 0.25);
+}
+
+// COOLED_CRUST from fragment_shaders.rs
+fn cooled_crust(t: f32) -> vec3<f32> {
+    return mix(vec3<f32>(28.0, 22.0, 24.0), vec3<f32>(82.0, 66.0, 60.0), clamp(t, 0.0, 1.0)) / 255.0;
+}
+
+fn molten_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let wide = fbm(position * 2.5, 4u);
+    let fine = fbm(position * 6.0, 4u);
+    let crust = fbm(position * 3.0, 3u);
+
+    let crack = max(
+        1.0 - smoothstep_clamped(0.0, 0.03, abs(wide - 0.5)),
+        (1.0 - smoothstep_clamped(0.0, 0.015, abs(fine - 0.5))) * 0.8,
+    );
+    let halo = 1.0 - smoothstep_clamped(0.0, 0.14, abs(wide - 0.5));
+    let heat = max(crack, halo * 0.4);
+
+    let albedo = cooled_crust(crust * 1.4 - 0.2) * (1.0 - crack) + lava(1.0) * crack;
+    let emission = lava(heat) * (0.12 + heat * 0.88);
+    return albedo * light_factor(light, 0.7, 0.1) + emission;
+}
+
+fn zonal_wind(lat: f32) -> f32 {
+    let jets = cos(lat * PI * 4.0);
+    let equatorial = 1.0 - abs(lat);
+    return 0.05 + jets * 0.12 * (0.4 + equatorial * 0.6);
+}
+
+fn storm_field(lat: f32, lon: f32, time: f32) -> f32 {
+    var strength = 0.0;
+    for (var slot = 0u; slot < STORM_SLOTS; slot++) {
+        let slot_seed = f32(slot) * 17.31;
+        let lifetime = 40.0 + hash(slot_seed) * 50.0;
+        let clock = time + hash(slot_seed + 3.7) * lifetime;
+        let generation = floor(clock / lifetime);
+        let age = clock / lifetime - generation;
+
+        let storm_seed = slot_seed + generation * 101.7;
+        let storm_lat = (hash(storm_seed) * 2.0 - 1.0) * 0.6;
+        let spawn_lon = hash(storm_seed + 1.3) * TAU;
+        let storm_lon = spawn_lon + zonal_wind(storm_lat) * age * lifetime;
+        let radius = 0.12 + hash(storm_seed + 2.9) * 0.16;
+
+        let wrapped = lon - storm_lon;
+        var d_lon = wrapped - TAU * floor(wrapped / TAU);
+        if d_lon > PI {
+            d_lon -= TAU;
+        }
+        let d_lat = lat - storm_lat;
+        let distance = length(vec2<f32>(d_lon * 0.55, d_lat * 1.4));
+
+        let growth = smoothstep_clamped(0.0, 0.15, age) * (1.0 - smoothstep_clamped(0.7, 1.0, age));
+        let core = pow(clamp(1.0 - distance / radius, 0.0, 1.0), 1.5);
+        strength = max(strength, core * growth);
+    }
+    return strength;
+}
+
+fn gas_giant_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let time = draw.frame.z;
+
+    let lat = position.y / length(position);
+    let lon = atan2(position.z, position.x);
+
+    let advected_lon = lon - zonal_wind(lat) * time;
+    let ring_radius = sqrt(max(1.0 - lat * lat, 0.0));
+    let advected = vec3<f32>(cos(advected_lon) * ring_radius, lat, sin(advected_lon) * ring_radius);
+
+    let warp = vec3<f32>(
+        fbm(advected * 2.0 + vec3<f32>(0.0, time * 0.01, 0.0), 3u),
+        fbm(advected * 2.0 + vec3<f32>(5.2, 1.3, 2.8), 3u),
+        fbm(advected * 2.0 + vec3<f32>(1.7, 9.2, time * 0.01), 3u),
+    );
+    let warped = advected * 3.0 + warp * 1.4;
+    let swirl = (fbm(warped, 4u) * 2.0 - 1.0) * 0.3;
+
+    let band = sin((lat + swirl * 0.08) * 8.0) * 0.5 + 0.5;
+    let color_variation = fbm(warped * 1.6, 3u) * 0.2;
+
+    let storm = storm_field(lat, lon, time);
+    let storm_swirl = fbm(warped * 2.5 + vec3<f32>(0.0, storm * 3.0, 0.0), 2u);
+    let spot = clamp(storm * (0.7 + storm_swirl * 0.6), 0.0, 1.0) * 0.75;
+
+    let dark_band = vec3<f32>(0.5, 0.3, 0.2);
+    let light_band = vec3<f32>(0.8, 0.7, 0.6);
+    let red_spot = vec3<f32>(0.8, 0.3, 0.2);
+
+    let base_color = dark_band * (1.0 - band) + light_band * band;
+    let varied_color = base_color
+        + vec3<f32>(swirl, swirl * 0.5, -swirl * 0.3)
+        + vec3<f32>(color_variation, color_variation * 0.5, -color_variation * 0.3);
+    let albedo = varied_color * (1.0 - spot) + red_spot * spot;
+
+    return albedo * light_factor(light, 0.7, 0.3);
+}
+
+fn corona_shader(position: vec3<f32>) -> vec3<f32> {
+    let facing = normalize(to_view(position));
+    let rho = sqrt(max(1.0 - facing.z * facing.z, 0.0));
+    let edge = 1.0 / CORONA_SHELL;
+    var falloff: f32;
+    if rho < edge {
+        falloff = pow(rho / edge, 8.0);
+    } else {
+        falloff = pow((1.0 - rho) / (1.0 - edge), 2.0);
+    }
+
+    let angle = atan2(facing.y, facing.x);
+    let streamers = fbm(vec3<f32>(cos(angle) * 3.0, sin(angle) * 3.0, draw.frame.z * 0.08), 3u);
+    let flicker = 0.7 + 0.6 * smoothstep_clamped(0.3, 0.8, streamers);
+
+    return draw.star_corona.rgb * (falloff * flicker * 0.6);
+}
+
+fn moon_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+    let craters = fbm(position * 8.0, 4u);
+    let crater_depth = abs(craters - 0.5) * 2.0;
+    let crater = select(0.0, crater_depth * 0.3, crater_depth > 0.7);
+    let gray = clamp(0.5 - crater, 0.2, 0.8);
+    return light_factor(light, 0.9, 0.1) * gray;
+}
+
+fn ring_shader(position: vec3<f32>, normal: vec3<f32>, tex_coords: vec2<f32>) -> vec3<f32> {
+    // Thin enough to let light through, so both faces are lit
+    let light = lighting(position, normal) + lighting(position, -normal);
+    let variation = fbm(position * 10.0, 2u) * 0.1;
+    let color = ring_color(tex_coords.y) + vec3<f32>(variation);
+    return color * light_factor(light, 0.6, 0.4);
+}
+
+fn ship_shader(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let light = lighting(position, normal);
+
+    let base_gray = vec3<f32>(0.58, 0.6, 0.63);
+    let dark_plate = vec3<f32>(0.25, 0.27, 0.3);
+    let panel_variation = clamp(fbm(position * 7.0, 3u), 0.0, 1.0);
+    let panel_color = dark_plate * (1.0 - panel_variation) + base_gray * panel_variation;
+
+    let edge_highlight = pow(normal.y * 0.5 + 0.5, 6.0) * 0.25;
+    let engine_glow = abs(sin(position.y * 0.4)) * 0.05;
+    let specular = pow(max(normal.z, 0.0), 6.0) * 0.5;
+
+    let lit = panel_color * light_factor(light, 0.65, 0.35) + vec3<f32>(edge_highlight);
+    return lit + vec3<f32>(specular + engine_glow);
+}
@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use image::{ImageBuffer, Luma};
+use rayon::prelude::*;
+
+use crate::atlas::texel_direction;
+use crate::fragment_shaders::HeightFunction;
+use crate::screenshot::capture_path;
+
+// Equirectangular, twice as wide as tall: about six texels per degree
+const HEIGHTMAP_WIDTH: usize = 2048;
+const HEIGHTMAP_HEIGHT: usize = HEIGHTMAP_WIDTH / 2;
+
+// A planet's relief over its whole sphere, in fractions of its radius; the
+// same heights its terrain mesh is displaced by
+pub struct Heightmap {
+    pub heights: Vec<f32>,
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Heightmap {
+    pub fn bake(height: HeightFunction, seed: u32) -> Self {
+        let heights: Vec<f32> = (0..HEIGHTMAP_WIDTH * HEIGHTMAP_HEIGHT)
+            .into_par_iter()
+            .map(|texel| {
+                let direction = texel_direction(texel, HEIGHTMAP_WIDTH, HEIGHTMAP_HEIGHT);
+                height(direction, seed)
+            })
+            .collect();
+        let low = heights.iter().copied().fold(f32::INFINITY, f32::min);
+        let high = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        Heightmap { heights, low, high }
+    }
+
+    // 16-bit grayscale stretched over the whole range: the lowest point is
+    // black and the highest white
+    pub fn save(&self, path: &Path) -> Result<(), image::ImageError> {
+        let span = (self.high - self.low).max(f32::EPSILON);
+        let (width, height) = (HEIGHTMAP_WIDTH as u32, HEIGHTMAP_HEIGHT as u32);
+        let image = ImageBuffer::from_fn(width, height, |x, y| {
+            let value = self.heights[y as usize * HEIGHTMAP_WIDTH + x as usize];
+            Luma([((value - self.low) / span * u16::MAX as f32).round() as u16])
+        });
+        image.save(path)
+    }
+}
+
+// Bakes the relief of the planet `name` and writes it next to the
+// screenshots; returns where it went and the heights black and white stand for
+pub fn export(
+    name: &str,
+    height: HeightFunction,
+    seed: u32,
+) -> Result<(PathBuf, Heightmap), image::ImageError> {
+    let heightmap = Heightmap::bake(height, seed);
+    let path = capture_path(&format!("relieve_{}", name.to_lowercase()))?;
+    heightmap.save(&path)?;
+    Ok((path, heightmap))
+}
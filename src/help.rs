@@ -0,0 +1,100 @@
+use crate::framebuffer::Framebuffer;
+use crate::i18n::Strings;
+use crate::input::{Action, Bindings, InputFrame};
+use crate::text::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::theme::Theme;
+
+const HELP_SCALE: i32 = 2;
+const PADDING: i32 = 24;
+// Space between a key and its description, and between the two columns
+const KEY_GAP: i32 = 16;
+const COLUMN_GAP: i32 = 40;
+const PANEL_RADIUS: i32 = 12;
+const PANEL_ALPHA: f32 = 0.8;
+// The whole frame is darkened behind the panel
+const DIM_COLOR: u32 = 0x000000;
+const DIM_ALPHA: f32 = 0.55;
+
+fn text_width(text: &str, scale: i32) -> i32 {
+    text.chars().count() as i32 * (GLYPH_WIDTH + 1) * scale
+}
+
+// Overlay listing every keybinding, built from the bindings each time it is
+// drawn so it never drifts from what the keys actually do
+#[derive(Default)]
+pub struct Help {
+    pub open: bool,
+}
+
+impl Help {
+    pub fn handle(&mut self, input: &InputFrame) {
+        if input.was_pressed(Action::ToggleHelp) {
+            self.open = !self.open;
+        }
+    }
+
+    pub fn draw(
+        &self,
+        framebuffer: &mut Framebuffer,
+        bindings: &Bindings,
+        strings: &Strings,
+        theme: &Theme,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let (width, height) = (framebuffer.width as i32, framebuffer.height as i32);
+        framebuffer.fill_panel(0, 0, width, height, 0, DIM_COLOR, DIM_ALPHA, None);
+
+        let scale = framebuffer.ui(HELP_SCALE);
+        let row_height = (GLYPH_HEIGHT + 4) * scale;
+        let padding = framebuffer.ui(PADDING);
+        let column_gap = framebuffer.ui(COLUMN_GAP);
+        let lines = bindings.help_lines(strings);
+        let rows = lines.len().div_ceil(2) as i32;
+        let key_width = lines.iter().map(|(keys, _)| text_width(keys, scale)).max();
+        let description_width = lines.iter().map(|(_, text)| text_width(text, scale)).max();
+        let key_width = key_width.unwrap_or(0) + framebuffer.ui(KEY_GAP);
+        let column_width = key_width + description_width.unwrap_or(0);
+
+        let panel_width = padding * 2 + column_width * 2 + column_gap;
+        let panel_height = padding * 2 + row_height * (rows + 1) + GLYPH_HEIGHT * scale;
+        let left = (width - panel_width) / 2;
+        let top = (height - panel_height) / 2;
+        framebuffer.fill_panel(
+            left,
+            top,
+            panel_width,
+            panel_height,
+            framebuffer.ui(PANEL_RADIUS),
+            theme.panel,
+            theme.panel_alpha(PANEL_ALPHA),
+            Some(theme.accent),
+        );
+        draw_text(
+            framebuffer,
+            left + padding,
+            top + padding,
+            strings.get("help.title"),
+            theme.accent,
+            scale,
+        );
+
+        // Top to bottom down the left column, then the right one
+        for (index, (keys, description)) in lines.iter().enumerate() {
+            let (column, row) = (index as i32 / rows, index as i32 % rows);
+            let x = left + padding + column * (column_width + column_gap);
+            let y = top + padding + row_height * (row + 2);
+            draw_text(framebuffer, x, y, keys, theme.selected, scale);
+            draw_text(
+                framebuffer,
+                x + key_width,
+                y,
+                description,
+                theme.text,
+                scale,
+            );
+        }
+    }
+}
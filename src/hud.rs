@@ -0,0 +1,548 @@
+use std::f32::consts::TAU;
+
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::camera::View;
+use crate::events::{AlignmentKind, PredictedKind};
+use crate::framebuffer::Framebuffer;
+use crate::i18n::Strings;
+use crate::line::wu_line;
+use crate::map::map_planet;
+use crate::objectives::Objective;
+use crate::simulation::{Planet, Simulation, Snapshot, DAY};
+use crate::sprite::Sprite;
+use crate::text::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::theme::Theme;
+use crate::units::{round_step, SceneScale};
+
+const HUD_SCALE: i32 = 2;
+const MARGIN: i32 = 14;
+const PANEL_PADDING: i32 = 8;
+const PANEL_ALPHA: f32 = 0.55;
+const CROSSHAIR_PATH: &str = "assets/icons/mira.png";
+const CROSSHAIR_SCALE: f32 = 1.5;
+const COMPASS_RADIUS: f32 = 44.0;
+const COMPASS_SEGMENTS: usize = 48;
+const ARROW_RADIUS: i32 = 3;
+// The scale bar is the round length that fits in this many pixels
+const SCALE_BAR_WIDTH: i32 = 140;
+const SCALE_BAR_TICK: i32 = 6;
+// Gap between a measured body and the ring around it
+const MEASURE_GAP: i32 = 4;
+// Segments in the text fuel gauge
+const GAUGE_SEGMENTS: usize = 10;
+
+pub struct HudIcons {
+    pub crosshair: Sprite,
+}
+
+impl HudIcons {
+    pub fn load() -> Result<Self, image::ImageError> {
+        Ok(HudIcons {
+            crosshair: Sprite::load(CROSSHAIR_PATH)?,
+        })
+    }
+}
+
+pub fn draw_hud(
+    framebuffer: &mut Framebuffer,
+    simulation: &Simulation,
+    icons: &HudIcons,
+    strings: &Strings,
+    theme: &Theme,
+) {
+    let mut lines = Vec::new();
+
+    if simulation.gravity_enabled() {
+        lines.push(strings.get("hud.mode_gravity").to_string());
+    } else {
+        lines.push(strings.get("hud.mode_clockwork").to_string());
+    }
+    lines.push(strings.format("hud.day", &[&format!("{:.1}", simulation.time / DAY)]));
+
+    if simulation.time_scale != 1.0 {
+        lines.push(strings.format("hud.time", &[&format!("{:.2}", simulation.time_scale)]));
+    }
+
+    if let Some(tour) = &simulation.tour {
+        let planet = &simulation.planets[tour.stop];
+        lines.push(strings.format(
+            "hud.tour",
+            &[&planet.name, &(tour.stop + 1), &simulation.planets.len()],
+        ));
+        lines.extend(planet_info(planet, strings));
+    } else if let Some(index) = simulation.selected {
+        let planet = &simulation.planets[index];
+        lines.push(strings.format("hud.target", &[&planet.name]));
+        // To the surface rather than the center, what it takes to get there
+        let offset = simulation.planet_position(index) - simulation.camera.position;
+        let distance = (offset.magnitude() - planet.scale).max(0.0);
+        let distance = format_distance(distance, simulation.scene_scale, strings);
+        lines.push(strings.format("hud.distance", &[&distance]));
+        if let Some(velocity) = simulation.planet_velocity(index) {
+            let speed = format!("{:.1}", velocity.magnitude());
+            lines.push(strings.format("hud.velocity", &[&speed]));
+            lines.push(strings.get("hud.nudge").to_string());
+        }
+    }
+
+    if simulation.ship.flight_mode {
+        // Thrust pushes towards the middle of the screen
+        let sprite = &icons.crosshair;
+        let scale = CROSSHAIR_SCALE * framebuffer.ui_scale;
+        let x = framebuffer.width as i32 / 2 - (sprite.width as f32 * scale) as i32 / 2;
+        let y = framebuffer.height as i32 / 2 - (sprite.height as f32 * scale) as i32 / 2;
+        framebuffer.blit_sprite(x, y, sprite, scale);
+
+        let speed = format!("{:.1}", simulation.ship.velocity.magnitude());
+        lines.push(strings.format("hud.flight", &[&speed]));
+        let percent = format!("{:.0}", simulation.ship.fuel * 100.0);
+        lines.push(strings.format("hud.fuel", &[&gauge(simulation.ship.fuel), &percent]));
+        if simulation.ship.skimming {
+            lines.push(strings.get("hud.skimming").to_string());
+        } else if simulation.ship.fuel <= 0.0 {
+            lines.push(strings.get("hud.fuel_empty").to_string());
+        }
+        match simulation.ship_orbit() {
+            Some(orbit) => {
+                let axis = format!("{:.0}", orbit.semi_major_axis);
+                let eccentricity = format!("{:.2}", orbit.eccentricity);
+                lines.push(strings.format("hud.orbit", &[&axis, &eccentricity]));
+                let periapsis = format!("{:.0}", orbit.periapsis);
+                let apoapsis = format!("{:.0}", orbit.apoapsis);
+                lines.push(strings.format("hud.apsides", &[&periapsis, &apoapsis]));
+            }
+            None => lines.push(strings.get("hud.escape").to_string()),
+        }
+    }
+
+    if simulation.bookmarks.browsing {
+        lines.push(strings.get("hud.bookmarks").to_string());
+        for (slot, bookmark) in simulation.bookmarks.iter() {
+            lines.push(format!("{}  {}", slot + 1, bookmark.name));
+        }
+    }
+
+    if simulation.timeline.open {
+        let events = if simulation.gravity_enabled() {
+            lines.push(strings.get("hud.timeline_gravity").to_string());
+            &[][..]
+        } else {
+            lines.push(strings.get("hud.timeline").to_string());
+            simulation.timeline.events()
+        };
+        for (slot, event) in events.iter().enumerate() {
+            let name = |index: usize| simulation.planets[index].name.as_str();
+            let what = match event.kind {
+                PredictedKind::Conjunction(planet, other) => {
+                    strings.format("hud.conjunction", &[&name(planet), &name(other)])
+                }
+                PredictedKind::MoonTransit(planet) => {
+                    strings.format("hud.moon_transit", &[&name(planet)])
+                }
+            };
+            let wait = format!("{:.1}", (event.time - simulation.time).abs());
+            lines.push(strings.format("hud.event", &[&(slot + 1), &what, &wait]));
+        }
+    }
+
+    if !simulation.camera.flight_assist {
+        let speed = format!("{:.0}", simulation.camera.velocity.magnitude());
+        lines.push(strings.format("hud.assist_off", &[&speed]));
+    }
+
+    if simulation.ship.headlight {
+        lines.push(strings.get("hud.headlight").to_string());
+    }
+
+    if simulation.landing_enabled {
+        match simulation.landing() {
+            Some(landing) => lines.push(strings.format(
+                "hud.low_orbit",
+                &[
+                    &simulation.planets[landing.planet].name,
+                    &format!("{:.0}", landing.altitude),
+                ],
+            )),
+            None => lines.push(strings.get("hud.landing_hint").to_string()),
+        }
+    }
+
+    if simulation.map_view && map_planet(simulation).is_none() {
+        lines.push(strings.get("hud.map_unavailable").to_string());
+    }
+
+    if simulation.measure.active {
+        match simulation.measure.pair() {
+            Some((from, to)) => lines.push(strings.format(
+                "hud.measure",
+                &[&simulation.planets[from].name, &simulation.planets[to].name],
+            )),
+            None => lines.push(strings.get("hud.measure_hint").to_string()),
+        }
+    }
+
+    lines.push(objectives_line(simulation, strings));
+
+    // Only the most centered alignment, so a busy sky doesn't flood the HUD
+    if let Some(alignment) = simulation.alignments.first() {
+        let planet = &simulation.planets[alignment.planet].name;
+        lines.push(match alignment.kind {
+            AlignmentKind::MoonTransit => strings.format("hud.transit", &[planet]),
+            AlignmentKind::StarEclipse => {
+                strings.format("hud.eclipse", &[planet, &simulation.planets[0].name])
+            }
+        });
+    }
+
+    let longest = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as i32;
+    let (margin, padding) = (framebuffer.ui(MARGIN), framebuffer.ui(PANEL_PADDING));
+    let (scale, line_height) = text_metrics(framebuffer);
+    framebuffer.fill_panel(
+        margin - padding,
+        margin - padding,
+        longest * (GLYPH_WIDTH + 1) * scale + padding * 2,
+        lines.len() as i32 * line_height + padding,
+        padding,
+        theme.panel,
+        theme.panel_alpha(PANEL_ALPHA),
+        None,
+    );
+    for (row, line) in lines.iter().enumerate() {
+        let y = margin + row as i32 * line_height;
+        draw_text(framebuffer, margin, y, line, theme.text, scale);
+    }
+}
+
+// A bar of `#` filled up to `fraction`, like [######----]
+fn gauge(fraction: f32) -> String {
+    let filled = (fraction.clamp(0.0, 1.0) * GAUGE_SEGMENTS as f32).round() as usize;
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        "-".repeat(GAUGE_SEGMENTS - filled)
+    )
+}
+
+// How many objectives are done and the next one still to do
+fn objectives_line(simulation: &Simulation, strings: &Strings) -> String {
+    let list = simulation.objectives.list(simulation);
+    let done = list.iter().filter(|(_, done)| *done).count();
+    let next = list
+        .iter()
+        .find(|(_, done)| !done)
+        .map(|(objective, _)| match objective {
+            Objective::Visit(name) => strings.format("hud.objective_visit", &[name]),
+            Objective::StableOrbit => match simulation.objectives.holding() {
+                Some(held) => strings.format("hud.objective_holding", &[&format!("{:.0}", held)]),
+                None => strings.get("hud.objective_orbit").to_string(),
+            },
+        });
+    match next {
+        Some(next) => strings.format("hud.objectives", &[&done, &list.len(), &next]),
+        None => strings.get("hud.objectives_done").to_string(),
+    }
+}
+
+// Font scale and line spacing of the HUD text on this screen
+fn text_metrics(framebuffer: &Framebuffer) -> (i32, i32) {
+    let scale = framebuffer.ui(HUD_SCALE);
+    (scale, (GLYPH_HEIGHT + 4) * scale)
+}
+
+// Plain units in the stylized scale and pseudo-AU in the semi-realistic one
+fn format_distance(distance: f32, scale: SceneScale, strings: &Strings) -> String {
+    match scale.pseudo_au() {
+        Some(au) => unit_label(&format!("{:.2}", distance / au), scale, strings),
+        None => unit_label(&format!("{:.0}", distance), scale, strings),
+    }
+}
+
+// `amount` already in the scale's units
+fn unit_label(amount: &str, scale: SceneScale, strings: &Strings) -> String {
+    match scale {
+        SceneScale::Stylized => strings.format("hud.units", &[&amount]),
+        SceneScale::SemiRealistic => strings.format("hud.au", &[&amount]),
+    }
+}
+
+// Info panel shown while the tour circles a body
+fn planet_info(planet: &Planet, strings: &Strings) -> Vec<String> {
+    let mut lines = Vec::new();
+    match planet.temperature {
+        Some(kelvin) => lines.push(strings.format("hud.star", &[&format!("{:.0}", kelvin)])),
+        None => {
+            let radius = format!("{:.0}", planet.orbit_radius);
+            let period = planet
+                .orbit_period()
+                .map_or("-".to_string(), |period| format!("{:.1}", period));
+            lines.push(strings.format("hud.orbit_period", &[&radius, &period]));
+        }
+    }
+    lines.push(strings.format("hud.radius", &[&format!("{:.0}", planet.scale)]));
+    if let Some(period) = planet.rotation_period() {
+        lines.push(strings.format("hud.rotation", &[&format!("{:.1}", period)]));
+    }
+    let moon_periods: Vec<String> = planet
+        .moons
+        .iter()
+        .filter_map(|moon| moon.orbit_period())
+        .map(|period| format!("{:.1}", period))
+        .collect();
+    if !moon_periods.is_empty() {
+        lines.push(strings.format("hud.moon_periods", &[&moon_periods.join(" / ")]));
+    }
+    let moons = match planet.moons.len() {
+        0 => String::new(),
+        1 => strings.get("hud.moon").to_string(),
+        count => strings.format("hud.moons", &[&count]),
+    };
+    let mut extras = Vec::new();
+    if !moons.is_empty() {
+        extras.push(moons.as_str());
+    }
+    if !planet.rings.is_empty() {
+        extras.push(strings.get("hud.rings"));
+    }
+    if planet.has_aurora {
+        extras.push(strings.get("hud.aurora"));
+    }
+    if planet.height.is_some() {
+        extras.push(strings.get("hud.terrain"));
+    }
+    if !extras.is_empty() {
+        lines.push(extras.join("  "));
+    }
+    lines
+}
+
+// Orientation gizmo in the bottom-right corner: the ecliptic as a ring seen
+// from the camera, an arrow towards the star and one along the heading.
+// Solid tips point into the screen, hollow ones back towards the viewer
+pub fn draw_compass(
+    framebuffer: &mut Framebuffer,
+    simulation: &Simulation,
+    snapshot: &Snapshot,
+    view: &View,
+    theme: &Theme,
+) {
+    let (margin, padding) = (framebuffer.ui(MARGIN), framebuffer.ui(PANEL_PADDING));
+    let (scale, line_height) = text_metrics(framebuffer);
+    let radius = COMPASS_RADIUS * framebuffer.ui_scale;
+    let center = Vec2::new(
+        framebuffer.width as f32 - margin as f32 - radius,
+        framebuffer.height as f32 - margin as f32 - radius - line_height as f32,
+    );
+    // Same skew the scene is drawn with, without the zoom, in compass radii
+    let project = |direction: Vec3| view.orient(direction) / view.zoom;
+
+    // A round panel: a rounded box whose corners meet
+    let backdrop = radius as i32 + padding;
+    framebuffer.fill_panel(
+        center.x as i32 - backdrop,
+        center.y as i32 - backdrop,
+        backdrop * 2,
+        backdrop * 2,
+        backdrop,
+        theme.panel,
+        theme.panel_alpha(PANEL_ALPHA),
+        None,
+    );
+
+    // The near half of the ring is brighter than the far one
+    let ring: Vec<Vec3> = (0..=COMPASS_SEGMENTS)
+        .map(|step| {
+            let angle = step as f32 / COMPASS_SEGMENTS as f32 * TAU;
+            project(Vec3::new(angle.cos(), 0.0, angle.sin()))
+        })
+        .collect();
+    for segment in ring.windows(2) {
+        let alpha = if segment[0].z + segment[1].z < 0.0 {
+            0.9
+        } else {
+            0.35
+        };
+        let (start, end) = (segment[0].xy(), segment[1].xy());
+        compass_line(
+            framebuffer,
+            center,
+            start * radius,
+            end * radius,
+            theme.accent,
+            alpha,
+        );
+    }
+
+    let star = simulation
+        .planets
+        .iter()
+        .zip(&snapshot.bodies)
+        .find(|(planet, _)| planet.temperature.is_some())
+        .map(|(_, body)| body.position - view.position);
+    if let Some(offset) = star.filter(|offset| offset.magnitude() > 0.001) {
+        let tip = project(offset.normalize());
+        compass_arrow(framebuffer, center, tip * radius, theme.star);
+    }
+
+    // Where the camera is drifting, or where forward thrust would take it
+    // when it is still
+    let velocity = simulation.camera.velocity;
+    let heading = if velocity.magnitude() > 1.0 {
+        velocity.normalize()
+    } else {
+        Vec3::new(0.0, 0.0, -1.0)
+    };
+    compass_arrow(framebuffer, center, project(heading) * radius, theme.text);
+
+    let height = format!("Y {:+.0}", view.position.y);
+    let width = height.chars().count() as i32 * (GLYPH_WIDTH + 1) * scale;
+    draw_text(
+        framebuffer,
+        center.x as i32 - width / 2,
+        (center.y + radius) as i32 + padding * 2,
+        &height,
+        theme.text,
+        scale,
+    );
+}
+
+// `tip` is in pixels from the center
+fn compass_arrow(framebuffer: &mut Framebuffer, center: Vec2, tip: Vec3, color: u32) {
+    compass_line(framebuffer, center, Vec2::zeros(), tip.xy(), color, 1.0);
+    let end = center + tip.xy();
+    let radius = framebuffer.ui(ARROW_RADIUS);
+    if tip.z >= 0.0 {
+        framebuffer.fill_circle(end.x as i32, end.y as i32, radius, color);
+    } else {
+        framebuffer.draw_circle(end.x as i32, end.y as i32, radius, color);
+    }
+}
+
+// Line between two points given in pixels from the compass center
+fn compass_line(
+    framebuffer: &mut Framebuffer,
+    center: Vec2,
+    start: Vec2,
+    end: Vec2,
+    color: u32,
+    alpha: f32,
+) {
+    let samples = wu_line(center + start, center + end, framebuffer.ui_scale);
+    for sample in samples {
+        framebuffer.blend_overlay(sample.x, sample.y, color, sample.coverage * alpha);
+    }
+}
+
+// Bar in the bottom-left corner as long as a round distance, relabeled as
+// the zoom changes. The projection keeps `view.zoom` pixels per unit across
+// the screen at any depth, so one bar holds for everything in view
+pub fn draw_scale_bar(
+    framebuffer: &mut Framebuffer,
+    simulation: &Simulation,
+    view: &View,
+    strings: &Strings,
+    theme: &Theme,
+) {
+    let unit = simulation.scene_scale.pseudo_au().unwrap_or(1.0);
+    let step = round_step(framebuffer.ui(SCALE_BAR_WIDTH) as f32 / view.zoom / unit);
+    if step <= 0.0 {
+        return;
+    }
+    let length = (step * unit * view.zoom).round() as i32;
+    // As many decimals as the step needs, so 0.05 doesn't read as 0.1
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let amount = format!("{:.*}", decimals, step);
+    let label = unit_label(&amount, simulation.scene_scale, strings);
+
+    let (margin, padding) = (framebuffer.ui(MARGIN), framebuffer.ui(PANEL_PADDING));
+    let (scale, line_height) = text_metrics(framebuffer);
+    let (thickness, tick) = (framebuffer.ui(2).max(1), framebuffer.ui(SCALE_BAR_TICK));
+    let label_width = label.chars().count() as i32 * (GLYPH_WIDTH + 1) * scale;
+    let bottom = framebuffer.height as i32 - margin;
+    let top = bottom - tick - line_height;
+    framebuffer.fill_panel(
+        margin - padding,
+        top - padding,
+        length.max(label_width) + padding * 2,
+        bottom - top + padding * 2,
+        padding,
+        theme.panel,
+        theme.panel_alpha(PANEL_ALPHA),
+        None,
+    );
+    draw_text(framebuffer, margin, top, &label, theme.text, scale);
+    framebuffer.fill_rect(margin, bottom - thickness, length, thickness, theme.text);
+    for x in [margin, margin + length - thickness] {
+        framebuffer.fill_rect(x, bottom - tick, thickness, tick, theme.text);
+    }
+}
+
+// Rings around the bodies picked for measuring and, once there are two, a
+// line between their centers labeled with the distance as they move
+pub fn draw_measure(
+    framebuffer: &mut Framebuffer,
+    simulation: &Simulation,
+    snapshot: &Snapshot,
+    view: &View,
+    strings: &Strings,
+    theme: &Theme,
+) {
+    let center = Vec2::new(framebuffer.width as f32, framebuffer.height as f32) * 0.5;
+    let gap = framebuffer.ui(MEASURE_GAP) as f32;
+    let on_screen = |index: usize| {
+        let projected = view.project(snapshot.bodies[index].position);
+        let radius = simulation.planets[index].scale * view.zoom + gap;
+        (center + projected.xy(), radius)
+    };
+    for &index in simulation.measure.picked() {
+        let (position, radius) = on_screen(index);
+        framebuffer.draw_circle(
+            position.x as i32,
+            position.y as i32,
+            radius as i32,
+            theme.accent,
+        );
+    }
+
+    let Some((from, to)) = simulation.measure.pair() else {
+        return;
+    };
+    let ((start, start_radius), (end, end_radius)) = (on_screen(from), on_screen(to));
+    // From ring to ring, so the line doesn't cross the bodies themselves
+    let span = end - start;
+    if span.magnitude() > start_radius + end_radius {
+        let direction = span.normalize();
+        let samples = wu_line(
+            start + direction * start_radius,
+            end - direction * end_radius,
+            framebuffer.ui_scale,
+        );
+        for sample in samples {
+            framebuffer.blend_overlay(sample.x, sample.y, theme.accent, sample.coverage);
+        }
+    }
+
+    let distance = (snapshot.bodies[to].position - snapshot.bodies[from].position).magnitude();
+    let label = format_distance(distance, simulation.scene_scale, strings);
+    let padding = framebuffer.ui(PANEL_PADDING);
+    let (scale, line_height) = text_metrics(framebuffer);
+    let width = label.chars().count() as i32 * (GLYPH_WIDTH + 1) * scale;
+    let middle = (start + end) * 0.5;
+    let x = middle.x as i32 - width / 2;
+    let y = middle.y as i32 - line_height / 2;
+    framebuffer.fill_panel(
+        x - padding,
+        y - padding,
+        width + padding * 2,
+        line_height + padding,
+        padding,
+        theme.panel,
+        theme.panel_alpha(PANEL_ALPHA),
+        None,
+    );
+    draw_text(framebuffer, x, y, &label, theme.text, scale);
+}
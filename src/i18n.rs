@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io;
+
+use crate::assets;
+use crate::toml;
+
+pub const DEFAULT_LANGUAGE: &str = "es";
+const LANG_DIR: &str = "lang";
+
+// UI text for one language, from `lang/<language>.toml`. Keys are
+// `section.name`; values may hold `{}` placeholders filled in order
+pub struct Strings {
+    table: HashMap<String, String>,
+}
+
+impl Strings {
+    pub fn load(language: &str) -> io::Result<Self> {
+        let path = format!("{}/{}.toml", LANG_DIR, language);
+        let text = assets::read_to_string(&path)
+            .map_err(|error| io::Error::new(error.kind(), format!("{}: {}", path, error)))?;
+        let table = toml::parse(&text)?
+            .into_iter()
+            .filter_map(|(key, value)| value.as_str().map(|text| (key, text.to_string())))
+            .collect();
+        Ok(Strings { table })
+    }
+
+    // A missing key shows up as itself, so gaps in a translation are easy to spot
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.table.get(key).map_or(key, String::as_str)
+    }
+
+    // `get` with each `{}` replaced by the next argument; numbers are passed
+    // already formatted so every language shows the same precision
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        let mut pieces = self.get(key).split("{}");
+        let mut text = pieces.next().unwrap_or_default().to_string();
+        for (index, piece) in pieces.enumerate() {
+            if let Some(arg) = args.get(index) {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(piece);
+        }
+        text
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::HashSet;
+
+use nalgebra_glm::Vec2;
+
+use crate::i18n::Strings;
+
+// The keys the app binds. Each windowing backend maps its own key codes to
+// these, so the bindings and the help overlay don't depend on any of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    LeftShift,
+    RightShift,
+    LeftCtrl,
+    RightCtrl,
+    Equal,
+    Minus,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Period,
+    F7,
+    F8,
+    F9,
+    F10,
+    F12,
+    Enter,
+    Tab,
+    Escape,
+    // The left mouse button, bound like any key
+    MouseLeft,
+}
+
+// What a window reports to the bindings each frame. Positions and sizes are
+// in logical pixels, so a HiDPI screen moves the camera the same
+pub trait InputSource {
+    fn is_key_down(&self, key: Key) -> bool;
+    // Mouse position from the top left, when inside
+    fn mouse_position(&self) -> Option<(f32, f32)>;
+    fn size(&self) -> (usize, usize);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Ascend,
+    Descend,
+    Boost,
+    ZoomIn,
+    ZoomOut,
+    Warp(usize),
+    StoreBookmark(usize),
+    ToggleBookmarks,
+    ToggleTour,
+    TogglePhotoMode,
+    RollLeft,
+    RollRight,
+    WidenFov,
+    NarrowFov,
+    Screenshot,
+    ExportHeightmap,
+    RenderStill,
+    DumpState,
+    ToggleMap,
+    CycleTarget,
+    ToggleGravity,
+    NudgePrograde,
+    NudgeRetrograde,
+    NudgeOutward,
+    NudgeInward,
+    ToggleFlight,
+    ToggleLanding,
+    ToggleHeadlight,
+    ToggleFlightAssist,
+    WatchEvent,
+    CycleSkybox,
+    ToggleMenu,
+    MenuSelect,
+    ToggleHelp,
+    ToggleMeasure,
+    Pick,
+    ToggleTimeline,
+    RewindDay,
+    AdvanceDay,
+    Quit,
+}
+
+impl Action {
+    pub fn name(&self) -> String {
+        match self {
+            Action::Warp(index) => format!("Warp{}", index),
+            Action::StoreBookmark(slot) => format!("StoreBookmark{}", slot),
+            other => format!("{:?}", other),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        if let Some(index) = name.strip_prefix("Warp") {
+            return index.parse().ok().map(Action::Warp);
+        }
+        if let Some(slot) = name.strip_prefix("StoreBookmark") {
+            return slot.parse().ok().map(Action::StoreBookmark);
+        }
+        let action = match name {
+            "Forward" => Action::Forward,
+            "Backward" => Action::Backward,
+            "Left" => Action::Left,
+            "Right" => Action::Right,
+            "Ascend" => Action::Ascend,
+            "Descend" => Action::Descend,
+            "Boost" => Action::Boost,
+            "ZoomIn" => Action::ZoomIn,
+            "ZoomOut" => Action::ZoomOut,
+            "ToggleBookmarks" => Action::ToggleBookmarks,
+            "ToggleTour" => Action::ToggleTour,
+            "TogglePhotoMode" => Action::TogglePhotoMode,
+            "RollLeft" => Action::RollLeft,
+            "RollRight" => Action::RollRight,
+            "WidenFov" => Action::WidenFov,
+            "NarrowFov" => Action::NarrowFov,
+            "Screenshot" => Action::Screenshot,
+            "ExportHeightmap" => Action::ExportHeightmap,
+            "RenderStill" => Action::RenderStill,
+            "DumpState" => Action::DumpState,
+            "ToggleMap" => Action::ToggleMap,
+            "CycleTarget" => Action::CycleTarget,
+            "ToggleGravity" => Action::ToggleGravity,
+            "NudgePrograde" => Action::NudgePrograde,
+            "NudgeRetrograde" => Action::NudgeRetrograde,
+            "NudgeOutward" => Action::NudgeOutward,
+            "NudgeInward" => Action::NudgeInward,
+            "ToggleFlight" => Action::ToggleFlight,
+            "ToggleLanding" => Action::ToggleLanding,
+            "ToggleHeadlight" => Action::ToggleHeadlight,
+            "ToggleFlightAssist" => Action::ToggleFlightAssist,
+            "WatchEvent" => Action::WatchEvent,
+            "CycleSkybox" => Action::CycleSkybox,
+            "ToggleMenu" => Action::ToggleMenu,
+            "MenuSelect" => Action::MenuSelect,
+            "ToggleHelp" => Action::ToggleHelp,
+            "ToggleMeasure" => Action::ToggleMeasure,
+            "Pick" => Action::Pick,
+            "ToggleTimeline" => Action::ToggleTimeline,
+            "RewindDay" => Action::RewindDay,
+            "AdvanceDay" => Action::AdvanceDay,
+            "Quit" => Action::Quit,
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    // Text key for what the help overlay says the action does; every warp
+    // and bookmark slot shares one line
+    pub fn help_key(&self) -> &'static str {
+        match self {
+            Action::Forward => "actions.forward",
+            Action::Backward => "actions.backward",
+            Action::Left => "actions.left",
+            Action::Right => "actions.right",
+            Action::Ascend => "actions.ascend",
+            Action::Descend => "actions.descend",
+            Action::Boost => "actions.boost",
+            Action::ZoomIn => "actions.zoom_in",
+            Action::ZoomOut => "actions.zoom_out",
+            Action::Warp(_) => "actions.warp",
+            Action::StoreBookmark(_) => "actions.store_bookmark",
+            Action::ToggleBookmarks => "actions.toggle_bookmarks",
+            Action::ToggleTour => "actions.toggle_tour",
+            Action::TogglePhotoMode => "actions.toggle_photo_mode",
+            Action::RollLeft => "actions.roll_left",
+            Action::RollRight => "actions.roll_right",
+            Action::WidenFov => "actions.widen_fov",
+            Action::NarrowFov => "actions.narrow_fov",
+            Action::Screenshot => "actions.screenshot",
+            Action::ExportHeightmap => "actions.export_heightmap",
+            Action::RenderStill => "actions.render_still",
+            Action::DumpState => "actions.dump_state",
+            Action::ToggleMap => "actions.toggle_map",
+            Action::CycleTarget => "actions.cycle_target",
+            Action::ToggleGravity => "actions.toggle_gravity",
+            Action::NudgePrograde => "actions.nudge_prograde",
+            Action::NudgeRetrograde => "actions.nudge_retrograde",
+            Action::NudgeOutward => "actions.nudge_outward",
+            Action::NudgeInward => "actions.nudge_inward",
+            Action::ToggleFlight => "actions.toggle_flight",
+            Action::ToggleLanding => "actions.toggle_landing",
+            Action::ToggleHeadlight => "actions.toggle_headlight",
+            Action::ToggleFlightAssist => "actions.toggle_flight_assist",
+            Action::WatchEvent => "actions.watch_event",
+            Action::CycleSkybox => "actions.cycle_skybox",
+            Action::ToggleMenu => "actions.toggle_menu",
+            Action::MenuSelect => "actions.menu_select",
+            Action::ToggleHelp => "actions.toggle_help",
+            Action::ToggleMeasure => "actions.toggle_measure",
+            Action::Pick => "actions.pick",
+            Action::ToggleTimeline => "actions.toggle_timeline",
+            Action::RewindDay => "actions.rewind_day",
+            Action::AdvanceDay => "actions.advance_day",
+            Action::Quit => "actions.quit",
+        }
+    }
+}
+
+// Short name of a key for the help overlay; keys with words in their name
+// are looked up in the string table
+fn key_label(key: Key, strings: &Strings) -> String {
+    let label = match key {
+        Key::Up => strings.get("keys.up"),
+        Key::Down => strings.get("keys.down"),
+        Key::Left => strings.get("keys.left"),
+        Key::Right => strings.get("keys.right"),
+        Key::PageUp => strings.get("keys.page_up"),
+        Key::PageDown => strings.get("keys.page_down"),
+        Key::LeftShift | Key::RightShift => "SHIFT",
+        Key::LeftCtrl | Key::RightCtrl => "CTRL",
+        Key::Equal => "+",
+        Key::Minus => "-",
+        Key::LeftBracket => "[",
+        Key::RightBracket => "]",
+        // Where the keyboard prints < and >
+        Key::Comma => "<",
+        Key::Period => ">",
+        Key::Escape => "ESC",
+        Key::MouseLeft => strings.get("keys.click"),
+        other => {
+            let name = format!("{:?}", other);
+            // Digits are named Key1..Key9
+            return match name.strip_prefix("Key") {
+                Some(digit) => digit.to_string(),
+                None => name.to_uppercase(),
+            };
+        }
+    };
+    label.to_string()
+}
+
+// Snapshot of the actions active during one frame
+#[derive(Debug, Clone, Default)]
+pub struct InputFrame {
+    held: HashSet<Action>,
+    pressed: HashSet<Action>,
+    // Mouse position in logical pixels from the center (y down), when inside
+    cursor: Option<Vec2>,
+}
+
+impl InputFrame {
+    pub fn from_held(held: HashSet<Action>, previous: &InputFrame) -> Self {
+        let pressed = held
+            .iter()
+            .filter(|action| !previous.held.contains(action))
+            .copied()
+            .collect();
+        InputFrame {
+            held,
+            pressed,
+            cursor: None,
+        }
+    }
+
+    pub fn with_cursor(self, cursor: Option<Vec2>) -> Self {
+        InputFrame { cursor, ..self }
+    }
+
+    pub fn cursor(&self) -> Option<Vec2> {
+        self.cursor
+    }
+
+    pub fn held(&self) -> impl Iterator<Item = &Action> {
+        self.held.iter()
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    pub fn was_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+}
+
+pub struct Bindings {
+    entries: Vec<(Key, Action)>,
+    // Ctrl+key combinations; while Ctrl is down they replace the key's own binding
+    chords: Vec<(Key, Action)>,
+}
+
+impl Bindings {
+    pub fn poll(&self, window: &dyn InputSource, previous: &InputFrame) -> InputFrame {
+        let ctrl = window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl);
+        let chorded = |key: &Key| ctrl && self.chords.iter().any(|(chord, _)| chord == key);
+        let held = self
+            .entries
+            .iter()
+            .filter(|(key, _)| !chorded(key))
+            .chain(self.chords.iter().filter(|_| ctrl))
+            .filter(|(key, _)| window.is_key_down(*key))
+            .map(|(_, action)| *action)
+            .collect();
+        let (width, height) = window.size();
+        let cursor = window
+            .mouse_position()
+            .map(|(x, y)| Vec2::new(x - width as f32 * 0.5, y - height as f32 * 0.5));
+        InputFrame::from_held(held, previous).with_cursor(cursor)
+    }
+
+    // One line per action with every key bound to it, in binding
+    // order and chords last; runs of more than two keys are shortened to
+    // "first-last"
+    pub fn help_lines(&self, strings: &Strings) -> Vec<(String, String)> {
+        let mut lines = help_groups(&self.entries, "", strings);
+        lines.extend(help_groups(&self.chords, "CTRL+", strings));
+        lines
+    }
+}
+
+fn help_groups(bound: &[(Key, Action)], prefix: &str, strings: &Strings) -> Vec<(String, String)> {
+    let mut groups: Vec<(Vec<String>, &'static str)> = Vec::new();
+    for (key, action) in bound {
+        let (label, help_key) = (key_label(*key, strings), action.help_key());
+        match groups.iter_mut().find(|(_, key)| *key == help_key) {
+            Some((labels, _)) => {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+            None => groups.push((vec![label], help_key)),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(labels, help_key)| {
+            let keys = match labels.as_slice() {
+                [first, .., last] if labels.len() > 2 => format!("{}-{}", first, last),
+                _ => labels.join(" / "),
+            };
+            (
+                format!("{}{}", prefix, keys),
+                strings.get(help_key).to_string(),
+            )
+        })
+        .collect()
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let warp_keys = [
+            Key::Key1,
+            Key::Key2,
+            Key::Key3,
+            Key::Key4,
+            Key::Key5,
+            Key::Key6,
+            Key::Key7,
+            Key::Key8,
+            Key::Key9,
+        ];
+
+        let mut entries = vec![
+            (Key::W, Action::Forward),
+            (Key::Up, Action::Forward),
+            (Key::S, Action::Backward),
+            (Key::Down, Action::Backward),
+            (Key::A, Action::Left),
+            (Key::Left, Action::Left),
+            (Key::D, Action::Right),
+            (Key::Right, Action::Right),
+            (Key::R, Action::Ascend),
+            (Key::F, Action::Descend),
+            (Key::LeftShift, Action::Boost),
+            (Key::RightShift, Action::Boost),
+            (Key::Equal, Action::ZoomIn),
+            (Key::PageUp, Action::ZoomIn),
+            (Key::Minus, Action::ZoomOut),
+            (Key::PageDown, Action::ZoomOut),
+            (Key::Tab, Action::CycleTarget),
+            (Key::N, Action::ToggleGravity),
+            (Key::I, Action::NudgePrograde),
+            (Key::K, Action::NudgeRetrograde),
+            (Key::L, Action::NudgeOutward),
+            (Key::J, Action::NudgeInward),
+            (Key::V, Action::ToggleFlight),
+            (Key::T, Action::ToggleLanding),
+            (Key::Y, Action::ToggleHeadlight),
+            (Key::C, Action::ToggleFlightAssist),
+            (Key::M, Action::ToggleBookmarks),
+            (Key::G, Action::ToggleTour),
+            (Key::P, Action::TogglePhotoMode),
+            (Key::Z, Action::RollLeft),
+            (Key::X, Action::RollRight),
+            (Key::RightBracket, Action::WidenFov),
+            (Key::LeftBracket, Action::NarrowFov),
+            (Key::F12, Action::Screenshot),
+            (Key::F9, Action::ExportHeightmap),
+            (Key::F10, Action::RenderStill),
+            (Key::F7, Action::DumpState),
+            (Key::F8, Action::ToggleMap),
+            (Key::E, Action::WatchEvent),
+            (Key::B, Action::CycleSkybox),
+            (Key::O, Action::ToggleMenu),
+            (Key::Enter, Action::MenuSelect),
+            (Key::H, Action::ToggleHelp),
+            (Key::Q, Action::ToggleMeasure),
+            (Key::MouseLeft, Action::Pick),
+            (Key::U, Action::ToggleTimeline),
+            (Key::Comma, Action::RewindDay),
+            (Key::Period, Action::AdvanceDay),
+            (Key::Escape, Action::Quit),
+        ];
+        let mut chords = Vec::new();
+        for (index, key) in warp_keys.iter().enumerate() {
+            entries.push((*key, Action::Warp(index)));
+            chords.push((*key, Action::StoreBookmark(index)));
+        }
+
+        Bindings { entries, chords }
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::io;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::toml::Value;
+
+// Just enough JSON to read back our own files: nested objects come back
+// flattened like TOML sections, `{"camera": {"zoom": 1}}` as `camera.zoom`,
+// with the same values our TOML reader returns. `null` leaves the key out.
+pub fn parse(text: &str) -> io::Result<HashMap<String, Value>> {
+    let mut table = HashMap::new();
+    let mut chars = text.chars().peekable();
+    skip_whitespace(&mut chars);
+    expect(&mut chars, '{')?;
+    parse_object(&mut chars, "", &mut table)?;
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return Err(invalid("texto de más después del objeto"));
+    }
+    Ok(table)
+}
+
+// Reads the members of an object whose `{` is already consumed
+fn parse_object(
+    chars: &mut Peekable<Chars>,
+    prefix: &str,
+    table: &mut HashMap<String, Value>,
+) -> io::Result<()> {
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(());
+    }
+    loop {
+        skip_whitespace(chars);
+        expect(chars, '"')?;
+        let key = format!("{}{}", prefix, parse_string(chars)?);
+        skip_whitespace(chars);
+        expect(chars, ':')?;
+        skip_whitespace(chars);
+        if chars.next_if_eq(&'{').is_some() {
+            parse_object(chars, &format!("{}.", key), table)?;
+        } else if let Some(value) = parse_value(chars)? {
+            table.insert(key, value);
+        }
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => return Ok(()),
+            _ => return Err(invalid("se esperaba `,` o `}`")),
+        }
+    }
+}
+
+// A string, number, boolean or array of them; `None` for `null`
+fn parse_value(chars: &mut Peekable<Chars>) -> io::Result<Option<Value>> {
+    match chars.peek() {
+        Some('"') => {
+            chars.next();
+            Ok(Some(Value::String(parse_string(chars)?)))
+        }
+        Some('[') => {
+            chars.next();
+            parse_array(chars).map(Some)
+        }
+        Some('t' | 'f' | 'n') => {
+            let word: String =
+                std::iter::from_fn(|| chars.next_if(char::is_ascii_alphabetic)).collect();
+            match word.as_str() {
+                "true" => Ok(Some(Value::Bool(true))),
+                "false" => Ok(Some(Value::Bool(false))),
+                "null" => Ok(None),
+                _ => Err(invalid("valor inválido")),
+            }
+        }
+        _ => {
+            let number: String = std::iter::from_fn(|| {
+                chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+            })
+            .collect();
+            number
+                .parse()
+                .map(|number| Some(Value::Number(number)))
+                .map_err(|_| invalid("valor inválido"))
+        }
+    }
+}
+
+// Reads the items of an array whose `[` is already consumed
+fn parse_array(chars: &mut Peekable<Chars>) -> io::Result<Value> {
+    let mut items = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&']').is_some() {
+        return Ok(Value::Array(items));
+    }
+    loop {
+        skip_whitespace(chars);
+        if chars.peek() == Some(&'{') {
+            return Err(invalid("no se admiten objetos dentro de arreglos"));
+        }
+        items.extend(parse_value(chars)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Ok(Value::Array(items)),
+            _ => return Err(invalid("se esperaba `,` o `]`")),
+        }
+    }
+}
+
+// Reads up to the closing quote; the opening one is already consumed
+fn parse_string(chars: &mut Peekable<Chars>) -> io::Result<String> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some('b') => text.push('\u{8}'),
+                Some('f') => text.push('\u{c}'),
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let character = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| invalid("escape \\u inválido"))?;
+                    text.push(character);
+                }
+                Some(other) => text.push(other),
+                None => return Err(invalid("falta cerrar una cadena")),
+            },
+            Some(character) => text.push(character),
+            None => return Err(invalid("falta cerrar una cadena")),
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, wanted: char) -> io::Result<()> {
+    match chars.next() {
+        Some(found) if found == wanted => Ok(()),
+        _ => Err(invalid(&format!("se esperaba `{}`", wanted))),
+    }
+}
+
+// Escapes a string for writing it between quotes. JSON allows no control
+// characters inside a string, so all of them are escaped
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            control if control < ' ' => {
+                escaped.push_str(&format!("\\u{:04x}", control as u32));
+            }
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+// Writes a number, or `null` for NaN and the infinities, which JSON has no
+// spelling for
+pub fn number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_covers_control_characters() {
+        assert_eq!(
+            escape("a\"b\\c\nd\re\tf\u{8}\u{c}\u{1}\u{1f}"),
+            "a\\\"b\\\\c\\nd\\re\\tf\\b\\f\\u0001\\u001f"
+        );
+    }
+
+    #[test]
+    fn escaped_strings_read_back() {
+        let name = "toma\u{1}\t\"final\"\r\n\u{8}\u{c}\\.png";
+        let table = parse(&format!("{{\"image\": \"{}\"}}", escape(name))).unwrap();
+        assert_eq!(
+            table.get("image").and_then(|value| value.as_str()),
+            Some(name)
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// The software rasterizer and the solar system built on it. The rendering
+// pieces are public so other projects can draw with them: `Framebuffer`,
+// vertices and the triangle and line primitives, the vertex and fragment
+// shaders, meshes loaded from OBJ or generated as spheres and rings, and the
+// `Renderer` backends. The binary only opens a window and runs the `App`
+
+pub mod app;
+mod arena;
+mod assets;
+mod atlas;
+pub mod backend;
+mod belt;
+mod bookmarks;
+mod camera;
+pub mod color;
+mod config;
+mod events;
+mod expr;
+pub mod fragment;
+pub mod fragment_shaders;
+pub mod framebuffer;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod heightmap;
+mod help;
+mod hud;
+mod i18n;
+pub mod input;
+mod json;
+pub mod light;
+pub mod line;
+mod map;
+mod material;
+mod measure;
+mod menu;
+mod multiplayer;
+mod noise;
+pub mod obj;
+mod objectives;
+pub mod options;
+mod passes;
+mod physics;
+mod pixel_art;
+pub mod renderer;
+mod replay;
+mod rng;
+mod scene;
+mod screenshot;
+mod script;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod settings;
+pub mod shaders;
+mod ship;
+mod shot;
+mod simulation;
+mod skybox;
+mod spectator;
+pub mod sphere;
+mod sprite;
+mod star;
+mod state;
+mod still;
+mod telemetry;
+mod terrain;
+mod text;
+mod theme;
+mod timeline;
+mod toml;
+mod tour;
+pub mod triangle;
+mod units;
+pub mod vertex;
+
+pub use backend::Renderer;
+pub use color::Color;
+pub use framebuffer::{Framebuffer, Viewport};
+pub use obj::Obj;
+pub use renderer::{Meshes, SoftwareRenderer, Uniforms};
+pub use shaders::vertex_shader;
+pub use triangle::{triangle, triangle_with_shader};
+pub use vertex::Vertex;
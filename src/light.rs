@@ -0,0 +1,200 @@
+use nalgebra_glm::{dot, Vec3};
+
+use crate::camera::View;
+
+// From any surface point towards the camera, in the view frame
+pub const TO_CAMERA: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+// Twilight tints: sunlight reddened near the horizon, skylight scattered
+// blue past it, and how bright the band glows at the terminator
+const TWILIGHT_WARM: Vec3 = Vec3::new(1.0, 0.55, 0.3);
+const TWILIGHT_COOL: Vec3 = Vec3::new(0.3, 0.45, 1.0);
+const TWILIGHT_GLOW: f32 = 0.25;
+// How fast a shadow's edge blurs with the distance between the occluder and
+// the surface it falls on, as a share of that distance
+const PENUMBRA_SPREAD: f32 = 0.08;
+
+// Where a light shines from
+#[derive(Debug, Clone, Copy)]
+pub enum Emitter {
+    // A position: in world space for the renderer's frame lights, relative
+    // to the shaded object once handed to a shader (see `Light::relative_to`)
+    Point(Vec3),
+    // Infinitely far away in this direction of the view frame
+    Directional(Vec3),
+    // A point shining along `direction` (view frame), full strength within
+    // `inner` and gone past `outer`, both cosines of the cone's half-angle
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        inner: f32,
+        outer: f32,
+    },
+}
+
+// A sphere standing in for something that casts a shadow, like the ship;
+// placed relative to the shaded object like the lights handed to shaders
+#[derive(Debug, Clone, Copy)]
+pub struct Occluder {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub emitter: Emitter,
+    pub color: Vec3,
+    // Distance at which a point light has dimmed to half; `None` never dims
+    pub range: Option<f32>,
+}
+
+impl Light {
+    pub fn point(position: Vec3, color: Vec3) -> Self {
+        Light {
+            emitter: Emitter::Point(position),
+            color,
+            range: None,
+        }
+    }
+
+    pub fn directional(direction: Vec3, color: Vec3) -> Self {
+        Light {
+            emitter: Emitter::Directional(direction.normalize()),
+            color,
+            range: None,
+        }
+    }
+
+    // Half-angles in degrees
+    pub fn spot(position: Vec3, direction: Vec3, (inner, outer): (f32, f32), color: Vec3) -> Self {
+        Light {
+            emitter: Emitter::Spot {
+                position,
+                direction: direction.normalize(),
+                inner: inner.to_radians().cos(),
+                outer: outer.to_radians().cos(),
+            },
+            color,
+            range: None,
+        }
+    }
+
+    pub fn with_range(self, range: f32) -> Self {
+        Light {
+            range: Some(range),
+            ..self
+        }
+    }
+
+    // The same light as seen from an object centered at `origin`: positions
+    // and ranges move into the view frame the object's mesh is shaded in
+    pub fn relative_to(&self, origin: Vec3, view: &View) -> Light {
+        let emitter = match self.emitter {
+            Emitter::Point(position) => Emitter::Point(view.orient(position - origin)),
+            Emitter::Spot {
+                position,
+                direction,
+                inner,
+                outer,
+            } => Emitter::Spot {
+                position: view.orient(position - origin),
+                direction,
+                inner,
+                outer,
+            },
+            directional => directional,
+        };
+        Light {
+            emitter,
+            range: self.range.map(|range| range * view.zoom),
+            ..*self
+        }
+    }
+
+    // Diffuse light reaching a surface at `offset` from its object's center
+    pub fn diffuse(&self, offset: Vec3, normal: &Vec3) -> Vec3 {
+        let (direction, strength) = self.incoming(offset);
+        self.color * (dot(normal, &direction).max(0.0) * strength)
+    }
+
+    // Diffuse light with a twilight band `width` wide either side of the
+    // terminator, as the cosine of the light's elevation: light that took
+    // the long way through the air goes warm on the day side and glows cool
+    // past it, instead of cutting off sharply where it sets
+    pub fn scattered(&self, offset: Vec3, normal: &Vec3, width: f32) -> Vec3 {
+        if width <= 0.0 {
+            return self.diffuse(offset, normal);
+        }
+        let (direction, strength) = self.incoming(offset);
+        let elevation = dot(normal, &direction);
+        let band = (1.0 - (elevation / width).powi(2)).max(0.0).powi(2);
+        let across = ((elevation / width + 1.0) * 0.5).clamp(0.0, 1.0);
+        let sunlit = Vec3::repeat(1.0).lerp(&TWILIGHT_WARM, band) * elevation.max(0.0);
+        let glow = TWILIGHT_COOL.lerp(&TWILIGHT_WARM, across) * (band * TWILIGHT_GLOW);
+        self.color.component_mul(&(sunlit + glow)) * strength
+    }
+
+    // Share of the light that gets past `occluders` to a surface at `offset`.
+    // Each casts a round shadow whose edge softens, and whose middle lifts,
+    // the farther it is from the surface
+    pub fn visibility(&self, offset: Vec3, occluders: &[Occluder]) -> f32 {
+        let (direction, _) = self.incoming(offset);
+        occluders.iter().fold(1.0, |lit, occluder| {
+            let to_center = occluder.center - offset;
+            let along = dot(&to_center, &direction);
+            if along <= 0.0 {
+                return lit;
+            }
+            let miss = (to_center - direction * along).magnitude();
+            let blur = occluder.radius * 0.5 + along * PENUMBRA_SPREAD;
+            let edge = ((miss - occluder.radius + blur) / (2.0 * blur)).clamp(0.0, 1.0);
+            lit * edge * edge * (3.0 - 2.0 * edge)
+        })
+    }
+
+    // Blinn-Phong highlight of the light mirrored off a surface at `offset`
+    // towards the camera, which looks along +z in the view frame. Higher
+    // `shininess` makes it smaller and sharper
+    pub fn specular(&self, offset: Vec3, normal: &Vec3, shininess: f32) -> Vec3 {
+        let (direction, strength) = self.incoming(offset);
+        let half = direction + TO_CAMERA;
+        if dot(normal, &direction) <= 0.0 || half.magnitude() < 1e-6 {
+            return Vec3::zeros();
+        }
+        let facing = dot(normal, &half.normalize()).max(0.0);
+        self.color * (facing.powf(shininess) * strength)
+    }
+
+    // Unit direction from a surface at `offset` to the light, and how much
+    // of it gets there once the spot cone and the range have dimmed it
+    fn incoming(&self, offset: Vec3) -> (Vec3, f32) {
+        let (direction, distance, cone) = match self.emitter {
+            Emitter::Point(position) => {
+                let (direction, distance) = towards(position, offset);
+                (direction, distance, 1.0)
+            }
+            Emitter::Directional(direction) => (direction, 0.0, 1.0),
+            Emitter::Spot {
+                position,
+                direction: axis,
+                inner,
+                outer,
+            } => {
+                let (direction, distance) = towards(position, offset);
+                let along = -dot(&direction, &axis);
+                let cone = ((along - outer) / (inner - outer)).clamp(0.0, 1.0);
+                (direction, distance, cone * cone * (3.0 - 2.0 * cone))
+            }
+        };
+        let attenuation = self
+            .range
+            .map_or(1.0, |range| 1.0 / (1.0 + (distance / range).powi(2)));
+        (direction, cone * attenuation)
+    }
+}
+
+// Unit direction and distance from a surface point to a light
+fn towards(position: Vec3, offset: Vec3) -> (Vec3, f32) {
+    let to_light = position - offset;
+    let distance = to_light.magnitude().max(0.0001);
+    (to_light / distance, distance)
+}
@@ -1,7 +1,19 @@
+use nalgebra_glm::Vec2;
+
 use crate::color::Color;
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
 
+// One pixel touched by an anti-aliased line: `coverage` (0..1) is how much of
+// the pixel the line covers and `t` how far along the segment it lies
+#[derive(Debug, Clone, Copy)]
+pub struct LineSample {
+    pub x: i32,
+    pub y: i32,
+    pub coverage: f32,
+    pub t: f32,
+}
+
 pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
@@ -29,6 +41,7 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             y0 as f32,
             Color::new(255, 255, 255),
             z,
+            1.0,
         ));
 
         if x0 == x1 && y0 == y1 {
@@ -48,3 +61,139 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
 
     fragments
 }
+
+// Xiaolin Wu's algorithm: every step along the major axis lights the pixels
+// the ideal line crosses, weighted by how much of each one it covers. With
+// `thickness` above 1 the line becomes a band with soft edges; at 1 it is the
+// classic two-pixel Wu span.
+pub fn wu_line(start: Vec2, end: Vec2, thickness: f32) -> Vec<LineSample> {
+    let mut samples = Vec::new();
+    wu_line_into(start, end, thickness, &mut samples);
+    samples
+}
+
+// `wu_line` appending to `samples`, so callers drawing many lines can keep
+// one buffer
+pub fn wu_line_into(start: Vec2, end: Vec2, thickness: f32, samples: &mut Vec<LineSample>) {
+    let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+    let (mut a, mut b) = if steep {
+        (Vec2::new(start.y, start.x), Vec2::new(end.y, end.x))
+    } else {
+        (start, end)
+    };
+    let reversed = a.x > b.x;
+    if reversed {
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    let length = b.x - a.x;
+    let gradient = if length > f32::EPSILON {
+        (b.y - a.y) / length
+    } else {
+        0.0
+    };
+    // Measured across the major axis, a slanted band is wider than it is thick
+    let half_span = thickness.max(1.0) * (1.0 + gradient * gradient).sqrt() * 0.5;
+    let (first, last) = (a.x.round() as i32, b.x.round() as i32);
+    let span_pixels = half_span.ceil() as usize * 2 + 1;
+    samples.reserve((last - first).max(0) as usize * span_pixels + span_pixels);
+
+    for major in first..=last {
+        let minor = a.y + gradient * (major as f32 - a.x);
+        let along = if length > f32::EPSILON {
+            ((major as f32 - a.x) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let t = if reversed { 1.0 - along } else { along };
+
+        // Pixel `p` spans [p - 0.5, p + 0.5]; its coverage is the overlap
+        // with the band [minor - half_span, minor + half_span]
+        let (low, high) = (minor - half_span, minor + half_span);
+        for pixel in (low + 0.5).floor() as i32..=(high + 0.5).floor() as i32 {
+            let center = pixel as f32;
+            let coverage = (high.min(center + 0.5) - low.max(center - 0.5)).clamp(0.0, 1.0);
+            if coverage <= 0.0 {
+                continue;
+            }
+            let (x, y) = if steep {
+                (pixel, major)
+            } else {
+                (major, pixel)
+            };
+            samples.push(LineSample { x, y, coverage, t });
+        }
+    }
+}
+
+// Liang-Barsky: the part of the segment from `start` to `end` inside the
+// rectangle from `min` to `max`, as the range of t (0 at `start`, 1 at
+// `end`) it spans, or None if it misses the rectangle
+pub fn clip_segment(start: Vec2, end: Vec2, min: Vec2, max: Vec2) -> Option<(f32, f32)> {
+    let delta = end - start;
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for (direction, offset) in [
+        (-delta.x, start.x - min.x),
+        (delta.x, max.x - start.x),
+        (-delta.y, start.y - min.y),
+        (delta.y, max.y - start.y),
+    ] {
+        if direction == 0.0 {
+            // Parallel to this edge: either all inside or all outside
+            if offset < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = offset / direction;
+        if direction < 0.0 {
+            enter = enter.max(t);
+        } else {
+            exit = exit.min(t);
+        }
+    }
+    (enter <= exit).then_some((enter, exit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_keeps_segments_inside() {
+        let clipped = clip_segment(
+            Vec2::new(1.0, 1.0),
+            Vec2::new(5.0, 3.0),
+            Vec2::zeros(),
+            Vec2::new(10.0, 10.0),
+        );
+        assert_eq!(clipped, Some((0.0, 1.0)));
+    }
+
+    #[test]
+    fn clip_trims_to_the_rectangle() {
+        let (enter, exit) = clip_segment(
+            Vec2::new(-10.0, 5.0),
+            Vec2::new(30.0, 5.0),
+            Vec2::zeros(),
+            Vec2::new(10.0, 10.0),
+        )
+        .unwrap();
+        assert!((enter - 0.25).abs() < 1e-6);
+        assert!((exit - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clip_drops_segments_outside() {
+        let min = Vec2::zeros();
+        let max = Vec2::new(10.0, 10.0);
+        assert_eq!(
+            clip_segment(Vec2::new(-5.0, 20.0), Vec2::new(20.0, 20.0), min, max),
+            None
+        );
+        assert_eq!(
+            clip_segment(Vec2::new(-5.0, 8.0), Vec2::new(8.0, 25.0), min, max),
+            None
+        );
+    }
+}
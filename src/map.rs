@@ -0,0 +1,157 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::sync::Arc;
+
+use nalgebra_glm::{Vec3, Vec4};
+
+use crate::atlas::PlanetAtlas;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::i18n::Strings;
+use crate::renderer::create_model_matrix;
+use crate::simulation::{Planet, Simulation};
+use crate::text::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::theme::Theme;
+
+const MAP_SCALE: i32 = 2;
+const MAP_MARGIN: i32 = 24;
+// Grid lines every this many degrees; the equator and the prime meridian
+// are drawn stronger
+const GRID_STEP: i32 = 30;
+const GRID_ALPHA: f32 = 0.25;
+const AXIS_ALPHA: f32 = 0.55;
+const MARKER_RADIUS: i32 = 9;
+
+// The atlas the map view shows, for planets that don't keep one baked
+#[derive(Default)]
+pub struct MapCache {
+    baked: Option<(usize, Arc<PlanetAtlas>)>,
+}
+
+impl MapCache {
+    // The selected planet's atlas while the map is open; baked the first
+    // time the map shows that planet
+    pub fn atlas(&mut self, simulation: &Simulation) -> Option<Arc<PlanetAtlas>> {
+        let index = map_planet(simulation)?;
+        let surface = simulation.planets[index].surface?;
+        if self.baked.as_ref().map(|(baked, _)| *baked) != Some(index) {
+            let atlas = PlanetAtlas::bake(surface, index as u32);
+            self.baked = Some((index, Arc::new(atlas)));
+        }
+        self.baked.as_ref().map(|(_, atlas)| Arc::clone(atlas))
+    }
+}
+
+// Planet the open map shows: the selected one, if it has a surface to map
+pub fn map_planet(simulation: &Simulation) -> Option<usize> {
+    let index = simulation.selected.filter(|_| simulation.map_view)?;
+    simulation.planets[index].surface.map(|_| index)
+}
+
+// Longitude and latitude of the point on `planet` that faces the camera.
+// Meshes are turned in screen space, so it is whatever the spin brings
+// round to point back along the view axis. Screen y points down, so north
+// is object -y: the map then shows the planet the way up it is seen
+pub fn sub_camera_point(planet: &Planet, time: f32) -> (f32, f32) {
+    let rotation = create_model_matrix(Vec3::zeros(), 1.0, planet.rotation(time));
+    let facing = rotation.transpose() * Vec4::new(0.0, 0.0, -1.0, 0.0);
+    (facing.z.atan2(facing.x), -facing.y.clamp(-1.0, 1.0).asin())
+}
+
+// The whole surface unrolled as large as the screen allows, with a
+// latitude and longitude grid and a ring where the camera is looking
+pub fn draw_map(
+    framebuffer: &mut Framebuffer,
+    simulation: &Simulation,
+    atlas: &PlanetAtlas,
+    time: f32,
+    strings: &Strings,
+    theme: &Theme,
+) {
+    let Some(index) = map_planet(simulation) else {
+        return;
+    };
+    let planet = &simulation.planets[index];
+    let (screen_width, screen_height) = (framebuffer.width as i32, framebuffer.height as i32);
+    framebuffer.fill_rect(0, 0, screen_width, screen_height, theme.panel);
+
+    let (margin, scale) = (framebuffer.ui(MAP_MARGIN), framebuffer.ui(MAP_SCALE));
+    let line_height = (GLYPH_HEIGHT + 4) * scale;
+    let header = line_height * 2;
+    let width = (screen_width - margin * 2).min((screen_height - margin * 2 - header) * 2);
+    let height = width / 2;
+    let left = (screen_width - width) / 2;
+    let top = (screen_height - height + header) / 2;
+
+    for y in 0..height {
+        let lat = FRAC_PI_2 - (y as f32 + 0.5) / height as f32 * PI;
+        for x in 0..width {
+            let lon = (x as f32 + 0.5) / width as f32 * TAU - PI;
+            let direction = Vec3::new(lat.cos() * lon.cos(), -lat.sin(), lat.cos() * lon.sin());
+            let texel = atlas.sample(direction);
+            let color = texel.albedo + texel.emission;
+            let color = Color::from_float(color.x, color.y, color.z).to_hex();
+            framebuffer.plot_overlay(left + x, top + y, color);
+        }
+    }
+
+    // Meridians left to right from -180°, parallels top to bottom from 90°N
+    for degrees in (-180..=180).step_by(GRID_STEP as usize) {
+        let x = left + (width - 1) * (degrees + 180) / 360;
+        let alpha = if degrees == 0 { AXIS_ALPHA } else { GRID_ALPHA };
+        for y in top..top + height {
+            framebuffer.blend_overlay(x, y, theme.text, alpha);
+        }
+    }
+    for degrees in (-90..=90).step_by(GRID_STEP as usize) {
+        let y = top + (height - 1) * (90 - degrees) / 180;
+        let alpha = if degrees == 0 { AXIS_ALPHA } else { GRID_ALPHA };
+        for x in left..left + width {
+            framebuffer.blend_overlay(x, y, theme.text, alpha);
+        }
+        if degrees.abs() < 90 {
+            let label = format_angle(degrees as f32, "hud.north", "hud.south", strings);
+            let label_y = y - GLYPH_HEIGHT * scale / 2;
+            draw_text(
+                framebuffer,
+                left + scale * 2,
+                label_y,
+                &label,
+                theme.text,
+                scale,
+            );
+        }
+    }
+
+    let (lon, lat) = sub_camera_point(planet, time);
+    let x = left + ((lon + PI) / TAU * width as f32) as i32;
+    let y = top + ((FRAC_PI_2 - lat) / PI * height as f32) as i32;
+    let radius = framebuffer.ui(MARKER_RADIUS);
+    framebuffer.draw_circle(x, y, radius, theme.accent);
+    framebuffer.draw_circle(x, y, radius / 3, theme.accent);
+
+    let title = strings.format("hud.map", &[&planet.name]);
+    draw_text(framebuffer, left, top - header, &title, theme.accent, scale);
+    let point = strings.format(
+        "hud.map_point",
+        &[
+            &format_angle(lat.to_degrees(), "hud.north", "hud.south", strings),
+            &format_angle(lon.to_degrees(), "hud.east", "hud.west", strings),
+        ],
+    );
+    let point_width = point.chars().count() as i32 * (GLYPH_WIDTH + 1) * scale;
+    let point_x = left + width - point_width;
+    draw_text(
+        framebuffer,
+        point_x,
+        top - header,
+        &point,
+        theme.text,
+        scale,
+    );
+}
+
+// Degrees and the hemisphere's letter: `positive` from 0 up, `negative` below
+fn format_angle(degrees: f32, positive: &str, negative: &str, strings: &Strings) -> String {
+    let key = if degrees < 0.0 { negative } else { positive };
+    format!("{:.0}°{}", degrees.abs(), strings.get(key))
+}
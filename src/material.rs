@@ -0,0 +1,74 @@
+use crate::fragment_shaders::FragmentShader;
+
+// How a material's fragments land on what is already drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    // Replaces what is behind
+    Opaque,
+    // Mixes over what is behind by this opacity, times the shader's alpha
+    Alpha(f32),
+    // Adds its light on top, for glowing shells
+    Additive,
+}
+
+// Everything about drawing a mesh besides its geometry and uniforms
+#[derive(Clone, Copy)]
+pub struct Material {
+    pub shader: FragmentShader,
+    pub blend: BlendMode,
+    // Also draws the faces turned away from the camera. Closed meshes leave
+    // it off, since their front always hides them
+    pub two_sided: bool,
+    // Writes depth and the object id, hiding whatever is drawn behind it later
+    pub depth_write: bool,
+    // Multiplies the emissive color the shader returns on its way to the
+    // glow buffer; 0 keeps the material out of bloom except for brightness
+    pub emissive: f32,
+}
+
+impl Material {
+    pub fn opaque(shader: FragmentShader) -> Self {
+        Material {
+            shader,
+            blend: BlendMode::Opaque,
+            two_sided: false,
+            depth_write: true,
+            emissive: 1.0,
+        }
+    }
+
+    // Seen through, so the far side of the shell shows as well
+    pub fn additive(shader: FragmentShader) -> Self {
+        Material {
+            shader,
+            blend: BlendMode::Additive,
+            two_sided: true,
+            depth_write: false,
+            emissive: 1.0,
+        }
+    }
+
+    pub fn blended(shader: FragmentShader, opacity: f32) -> Self {
+        Material {
+            shader,
+            blend: BlendMode::Alpha(opacity),
+            two_sided: true,
+            depth_write: false,
+            emissive: 1.0,
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.blend == BlendMode::Opaque
+    }
+
+    // Draw order within a pass: solid geometry first, then what mixes over
+    // it, then the light added on top of everything
+    pub fn order(&self) -> u8 {
+        match self.blend {
+            BlendMode::Opaque => 0,
+            BlendMode::Alpha(_) => 1,
+            BlendMode::Additive => 2,
+        }
+    }
+}
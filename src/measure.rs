@@ -0,0 +1,33 @@
+// Distance readout between two bodies picked with the mouse
+#[derive(Default)]
+pub struct Measure {
+    // While on, a click picks the body under the cursor
+    pub active: bool,
+    // Picked bodies in order; a third pick starts a new pair
+    picked: Vec<usize>,
+}
+
+impl Measure {
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.picked.clear();
+    }
+
+    pub fn pick(&mut self, index: usize) {
+        if self.picked.len() == 2 || self.picked.contains(&index) {
+            self.picked.clear();
+        }
+        self.picked.push(index);
+    }
+
+    pub fn picked(&self) -> &[usize] {
+        &self.picked
+    }
+
+    pub fn pair(&self) -> Option<(usize, usize)> {
+        match self.picked.as_slice() {
+            [from, to] => Some((*from, *to)),
+            _ => None,
+        }
+    }
+}
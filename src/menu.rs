@@ -0,0 +1,369 @@
+use crate::framebuffer::Framebuffer;
+use crate::i18n::Strings;
+use crate::input::{Action, InputFrame};
+use crate::pixel_art::PixelPalette;
+use crate::settings::{
+    Settings, BLOOM_INTENSITY_RANGE, BLOOM_INTENSITY_STEP, DEPTH_OF_FIELD_STEP, FILM_GRAIN_STEP,
+    FOV_RANGE, FOV_STEP, RESOLUTION_RANGE, RESOLUTION_STEP, TIME_SCALE_STEPS, VIGNETTE_STEP,
+};
+use crate::text::{draw_text, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::theme::PalettePreset;
+
+const MENU_SCALE: i32 = 2;
+const PADDING: i32 = 24;
+const PANEL_WIDTH: i32 = 460;
+const PANEL_RADIUS: i32 = 12;
+const PANEL_ALPHA: f32 = 0.8;
+const SWITCH_RADIUS: i32 = 7;
+
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    Orbits,
+    Labels,
+    Grid,
+    Lagrange,
+    Trojans,
+    Bloom,
+    BloomIntensity,
+    Antialiasing,
+    VisibilityAssist,
+    Accumulation,
+    TimeScale,
+    Fov,
+    ResolutionScale,
+    DepthOfField,
+    Vignette,
+    FilmGrain,
+    Crt,
+    PixelArt,
+    Palette,
+    HighContrast,
+}
+
+// How a row shows its current value: on/off switches are drawn as a dot
+enum Value {
+    Switch(bool),
+    Text(String),
+}
+
+const ITEMS: [Item; 20] = [
+    Item::Orbits,
+    Item::Labels,
+    Item::Grid,
+    Item::Lagrange,
+    Item::Trojans,
+    Item::Bloom,
+    Item::BloomIntensity,
+    Item::Antialiasing,
+    Item::VisibilityAssist,
+    Item::Accumulation,
+    Item::TimeScale,
+    Item::Fov,
+    Item::ResolutionScale,
+    Item::DepthOfField,
+    Item::Vignette,
+    Item::FilmGrain,
+    Item::Crt,
+    Item::PixelArt,
+    Item::Palette,
+    Item::HighContrast,
+];
+
+impl Item {
+    // Key of the row's label in the string table
+    fn label(self) -> &'static str {
+        match self {
+            Item::Orbits => "menu.orbits",
+            Item::Labels => "menu.labels",
+            Item::Grid => "menu.grid",
+            Item::Lagrange => "menu.lagrange",
+            Item::Trojans => "menu.trojans",
+            Item::Bloom => "menu.bloom",
+            Item::BloomIntensity => "menu.bloom_intensity",
+            Item::Antialiasing => "menu.antialiasing",
+            Item::VisibilityAssist => "menu.visibility_assist",
+            Item::Accumulation => "menu.accumulation",
+            Item::TimeScale => "menu.time_scale",
+            Item::Fov => "menu.fov",
+            Item::ResolutionScale => "menu.resolution_scale",
+            Item::DepthOfField => "menu.depth_of_field",
+            Item::Vignette => "menu.vignette",
+            Item::FilmGrain => "menu.film_grain",
+            Item::Crt => "menu.crt",
+            Item::PixelArt => "menu.pixel_art",
+            Item::Palette => "menu.palette",
+            Item::HighContrast => "menu.high_contrast",
+        }
+    }
+
+    fn value(self, settings: &Settings, strings: &Strings) -> Value {
+        match self {
+            Item::Orbits => Value::Switch(settings.show_orbits),
+            Item::Labels => Value::Switch(settings.show_labels),
+            Item::Grid => Value::Switch(settings.show_grid),
+            Item::Lagrange => Value::Switch(settings.show_lagrange),
+            Item::Trojans => Value::Switch(settings.trojans),
+            Item::Bloom => Value::Switch(settings.bloom),
+            Item::BloomIntensity => Value::Text(format!("X{:.2}", settings.bloom_intensity)),
+            Item::Antialiasing => Value::Switch(settings.antialiasing),
+            Item::VisibilityAssist => Value::Switch(settings.visibility_assist),
+            Item::Accumulation => Value::Switch(settings.accumulation),
+            Item::TimeScale => Value::Text(format!("X{:.2}", settings.time_scale)),
+            Item::Fov => Value::Text(format!("{:.0}°", settings.fov)),
+            Item::ResolutionScale => {
+                Value::Text(format!("{:.0}%", settings.resolution_scale * 100.0))
+            }
+            Item::DepthOfField => Value::Text(format!("{:.0}%", settings.depth_of_field * 100.0)),
+            Item::Vignette => Value::Text(format!("{:.0}%", settings.vignette * 100.0)),
+            Item::FilmGrain => Value::Text(format!("{:.0}%", settings.film_grain * 100.0)),
+            Item::Crt => Value::Switch(settings.crt),
+            Item::PixelArt => {
+                let name = settings.pixel_art.map_or("no", PixelPalette::name);
+                Value::Text(strings.get(&format!("menu.pixel_art_{}", name)).to_string())
+            }
+            Item::Palette => {
+                let key = format!("menu.palette_{}", settings.palette.name());
+                Value::Text(strings.get(&key).to_string())
+            }
+            Item::HighContrast => Value::Switch(settings.high_contrast),
+        }
+    }
+
+    // Left/Right step by `direction` and stop at the ends; Enter (`wrap`)
+    // steps forward and starts over after the last value
+    fn adjust(self, settings: &mut Settings, direction: i32, wrap: bool) {
+        match self {
+            Item::Orbits => settings.show_orbits = !settings.show_orbits,
+            Item::Labels => settings.show_labels = !settings.show_labels,
+            Item::Grid => settings.show_grid = !settings.show_grid,
+            Item::Lagrange => settings.show_lagrange = !settings.show_lagrange,
+            Item::Trojans => settings.trojans = !settings.trojans,
+            Item::Bloom => settings.bloom = !settings.bloom,
+            Item::BloomIntensity => {
+                settings.bloom_intensity = step(
+                    settings.bloom_intensity,
+                    BLOOM_INTENSITY_STEP,
+                    BLOOM_INTENSITY_RANGE,
+                    direction,
+                    wrap,
+                )
+            }
+            Item::Antialiasing => settings.antialiasing = !settings.antialiasing,
+            Item::VisibilityAssist => settings.visibility_assist = !settings.visibility_assist,
+            Item::Accumulation => settings.accumulation = !settings.accumulation,
+            Item::TimeScale => {
+                let current = TIME_SCALE_STEPS
+                    .iter()
+                    .position(|&step| step >= settings.time_scale)
+                    .unwrap_or(TIME_SCALE_STEPS.len() - 1) as i32;
+                let last = TIME_SCALE_STEPS.len() as i32 - 1;
+                let next = match current + direction {
+                    index if index > last && wrap => 0,
+                    index => index.clamp(0, last),
+                };
+                settings.time_scale = TIME_SCALE_STEPS[next as usize];
+            }
+            Item::Fov => settings.fov = step(settings.fov, FOV_STEP, FOV_RANGE, direction, wrap),
+            Item::ResolutionScale => {
+                settings.resolution_scale = step(
+                    settings.resolution_scale,
+                    RESOLUTION_STEP,
+                    RESOLUTION_RANGE,
+                    direction,
+                    wrap,
+                )
+            }
+            Item::DepthOfField => {
+                settings.depth_of_field = step(
+                    settings.depth_of_field,
+                    DEPTH_OF_FIELD_STEP,
+                    (0.0, 1.0),
+                    direction,
+                    wrap,
+                )
+            }
+            Item::Vignette => {
+                settings.vignette = step(
+                    settings.vignette,
+                    VIGNETTE_STEP,
+                    (0.0, 1.0),
+                    direction,
+                    wrap,
+                )
+            }
+            Item::FilmGrain => {
+                settings.film_grain = step(
+                    settings.film_grain,
+                    FILM_GRAIN_STEP,
+                    (0.0, 1.0),
+                    direction,
+                    wrap,
+                )
+            }
+            Item::Crt => settings.crt = !settings.crt,
+            Item::PixelArt => {
+                // Off comes before the first palette
+                let choices: Vec<Option<PixelPalette>> = std::iter::once(None)
+                    .chain(PixelPalette::ALL.map(Some))
+                    .collect();
+                let current = choices
+                    .iter()
+                    .position(|&choice| choice == settings.pixel_art)
+                    .unwrap_or(0) as i32;
+                let last = choices.len() as i32 - 1;
+                let next = match current + direction {
+                    index if index > last && wrap => 0,
+                    index => index.clamp(0, last),
+                };
+                settings.pixel_art = choices[next as usize];
+            }
+            Item::Palette => {
+                let presets = PalettePreset::ALL;
+                let current = presets
+                    .iter()
+                    .position(|&preset| preset == settings.palette)
+                    .unwrap_or(0) as i32;
+                let last = presets.len() as i32 - 1;
+                let next = match current + direction {
+                    index if index > last && wrap => 0,
+                    index => index.clamp(0, last),
+                };
+                settings.palette = presets[next as usize];
+            }
+            Item::HighContrast => settings.high_contrast = !settings.high_contrast,
+        }
+    }
+}
+
+fn step(value: f32, amount: f32, (min, max): (f32, f32), direction: i32, wrap: bool) -> f32 {
+    let next = value + amount * direction as f32;
+    if wrap && next > max + amount * 0.5 {
+        min
+    } else {
+        next.clamp(min, max)
+    }
+}
+
+// Immediate-mode settings menu: it keeps only whether it is open and which
+// row is highlighted, and draws straight from the current `Settings`
+#[derive(Default)]
+pub struct Menu {
+    pub open: bool,
+    cursor: usize,
+}
+
+impl Menu {
+    // Returns true when a setting changed
+    pub fn handle(&mut self, input: &InputFrame, settings: &mut Settings) -> bool {
+        if input.was_pressed(Action::ToggleMenu) {
+            self.open = !self.open;
+            return false;
+        }
+        if !self.open {
+            return false;
+        }
+
+        if input.was_pressed(Action::Forward) {
+            self.cursor = (self.cursor + ITEMS.len() - 1) % ITEMS.len();
+        }
+        if input.was_pressed(Action::Backward) {
+            self.cursor = (self.cursor + 1) % ITEMS.len();
+        }
+
+        let (direction, wrap) = if input.was_pressed(Action::MenuSelect) {
+            (1, true)
+        } else if input.was_pressed(Action::Right) {
+            (1, false)
+        } else if input.was_pressed(Action::Left) {
+            (-1, false)
+        } else {
+            return false;
+        };
+
+        let before = *settings;
+        ITEMS[self.cursor].adjust(settings, direction, wrap);
+        *settings != before
+    }
+
+    pub fn draw(&self, framebuffer: &mut Framebuffer, settings: &Settings, strings: &Strings) {
+        if !self.open {
+            return;
+        }
+
+        let theme = settings.theme();
+        let scale = framebuffer.ui(MENU_SCALE);
+        let row_height = (GLYPH_HEIGHT + 6) * scale;
+        let padding = framebuffer.ui(PADDING);
+        let panel_width = framebuffer.ui(PANEL_WIDTH);
+        let switch_radius = framebuffer.ui(SWITCH_RADIUS);
+        let height = padding * 2 + row_height * (ITEMS.len() as i32 + 3);
+        let left = (framebuffer.width as i32 - panel_width) / 2;
+        let top = (framebuffer.height as i32 - height) / 2;
+        framebuffer.fill_panel(
+            left,
+            top,
+            panel_width,
+            height,
+            framebuffer.ui(PANEL_RADIUS),
+            theme.panel,
+            theme.panel_alpha(PANEL_ALPHA),
+            Some(theme.accent),
+        );
+
+        let x = left + padding;
+        draw_text(
+            framebuffer,
+            x,
+            top + padding,
+            strings.get("menu.title"),
+            theme.accent,
+            scale,
+        );
+
+        for (row, item) in ITEMS.iter().enumerate() {
+            let y = top + padding + row_height * (row as i32 + 2);
+            let color = if row == self.cursor {
+                theme.selected
+            } else {
+                theme.text
+            };
+            if row == self.cursor {
+                framebuffer.fill_rect(
+                    left + padding / 2,
+                    y - scale * 3,
+                    panel_width - padding,
+                    row_height,
+                    theme.highlight,
+                );
+            }
+            draw_text(framebuffer, x, y, strings.get(item.label()), color, scale);
+
+            let right = left + panel_width - padding;
+            match item.value(settings, strings) {
+                Value::Switch(on) => {
+                    let center_y = y + GLYPH_HEIGHT * scale / 2;
+                    let center_x = right - switch_radius;
+                    if on {
+                        framebuffer.fill_circle(center_x, center_y, switch_radius, color);
+                    } else {
+                        framebuffer.draw_circle(center_x, center_y, switch_radius, color);
+                    }
+                }
+                Value::Text(value) => {
+                    let width = value.chars().count() as i32 * (GLYPH_WIDTH + 1) * scale;
+                    draw_text(framebuffer, right - width, y, &value, color, scale);
+                }
+            }
+        }
+
+        let hint_y = top + height - padding - GLYPH_HEIGHT * scale;
+        draw_text(
+            framebuffer,
+            x,
+            hint_y,
+            strings.get("menu.hint"),
+            theme.text,
+            scale,
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use minifb::{MouseButton, MouseMode, Window, WindowOptions};
+
+use computer_graphics_v3::app::{frame_size, App, WINDOW_TITLE};
+use computer_graphics_v3::input::{InputSource, Key};
+use computer_graphics_v3::options::Options;
+
+const FRAME_DELAY: Duration = Duration::from_millis(8);
+
+// minifb can't tell the monitor's scale, so only --scale changes it
+struct DesktopWindow {
+    window: Window,
+    scale: f32,
+}
+
+// Desktop main loop
+pub fn run(options: &Options) {
+    let scale = options.scale.unwrap_or(1.0);
+    let (width, height) = frame_size(scale);
+    // Opened before loading so it shows up while the planets are baked
+    let mut window = Window::new(WINDOW_TITLE, width, height, WindowOptions::default())
+        .expect("No se pudo crear la ventana");
+
+    window.set_position(100, 100);
+    window.update();
+
+    let mut app = App::new(options, scale);
+    let mut desktop = DesktopWindow { window, scale };
+    while desktop.window.is_open() && app.frame(&desktop) {
+        desktop
+            .window
+            .update_with_buffer(&app.screen().buffer, width, height)
+            .expect("No se pudo actualizar la ventana");
+
+        std::thread::sleep(FRAME_DELAY);
+    }
+    app.shutdown();
+}
+
+impl InputSource for DesktopWindow {
+    fn is_key_down(&self, key: Key) -> bool {
+        match minifb_key(key) {
+            Some(key) => self.window.is_key_down(key),
+            None => self.window.get_mouse_down(MouseButton::Left),
+        }
+    }
+
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        self.window
+            .get_mouse_pos(MouseMode::Discard)
+            .map(|(x, y)| (x / self.scale, y / self.scale))
+    }
+
+    fn size(&self) -> (usize, usize) {
+        let (width, height) = self.window.get_size();
+        (
+            (width as f32 / self.scale) as usize,
+            (height as f32 / self.scale) as usize,
+        )
+    }
+}
+
+// None for the mouse button, which minifb reports apart from the keys
+fn minifb_key(key: Key) -> Option<minifb::Key> {
+    use minifb::Key as K;
+    let key = match key {
+        Key::Key1 => K::Key1,
+        Key::Key2 => K::Key2,
+        Key::Key3 => K::Key3,
+        Key::Key4 => K::Key4,
+        Key::Key5 => K::Key5,
+        Key::Key6 => K::Key6,
+        Key::Key7 => K::Key7,
+        Key::Key8 => K::Key8,
+        Key::Key9 => K::Key9,
+        Key::A => K::A,
+        Key::B => K::B,
+        Key::C => K::C,
+        Key::D => K::D,
+        Key::E => K::E,
+        Key::F => K::F,
+        Key::G => K::G,
+        Key::H => K::H,
+        Key::I => K::I,
+        Key::J => K::J,
+        Key::K => K::K,
+        Key::L => K::L,
+        Key::M => K::M,
+        Key::N => K::N,
+        Key::O => K::O,
+        Key::P => K::P,
+        Key::Q => K::Q,
+        Key::R => K::R,
+        Key::S => K::S,
+        Key::T => K::T,
+        Key::U => K::U,
+        Key::V => K::V,
+        Key::W => K::W,
+        Key::X => K::X,
+        Key::Y => K::Y,
+        Key::Z => K::Z,
+        Key::Up => K::Up,
+        Key::Down => K::Down,
+        Key::Left => K::Left,
+        Key::Right => K::Right,
+        Key::PageUp => K::PageUp,
+        Key::PageDown => K::PageDown,
+        Key::LeftShift => K::LeftShift,
+        Key::RightShift => K::RightShift,
+        Key::LeftCtrl => K::LeftCtrl,
+        Key::RightCtrl => K::RightCtrl,
+        Key::Equal => K::Equal,
+        Key::Minus => K::Minus,
+        Key::LeftBracket => K::LeftBracket,
+        Key::RightBracket => K::RightBracket,
+        Key::Comma => K::Comma,
+        Key::Period => K::Period,
+        Key::F7 => K::F7,
+        Key::F8 => K::F8,
+        Key::F9 => K::F9,
+        Key::F10 => K::F10,
+        Key::F12 => K::F12,
+        Key::Enter => K::Enter,
+        Key::Tab => K::Tab,
+        Key::Escape => K::Escape,
+        Key::MouseLeft => return None,
+    };
+    Some(key)
+}
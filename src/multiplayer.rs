@@ -0,0 +1,756 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use nalgebra_glm::Vec2;
+use web_time::Instant;
+
+use crate::input::{Action, InputFrame};
+use crate::simulation::{Pilot, Simulation};
+use crate::units::SceneScale;
+
+// Players a hosted game waits for unless --players says otherwise
+pub const DEFAULT_PLAYERS: usize = 2;
+// Steps between sampling a player's input and running it everywhere, time
+// for it to reach the others: 50 ms at 120 steps a second
+const INPUT_DELAY: u64 = 6;
+// Most steps one datagram carries; the rest follow once these are acknowledged
+const BATCH: usize = 32;
+// A player silent this long has left, and so has a silent host
+const TIMEOUT: Duration = Duration::from_secs(5);
+// How often a joining player knocks again, and for how long
+const KNOCK_INTERVAL: Duration = Duration::from_millis(200);
+const JOIN_TIMEOUT: Duration = Duration::from_secs(30);
+const DATAGRAM: usize = 65507;
+
+// Experimental multiplayer: every player runs the same deterministic system
+// in lockstep on the fixed timestep. Each samples its input, sends it off
+// and runs it `INPUT_DELAY` steps later, once the host has everyone's input
+// for that step and has sent the bundle back. There is one system, stepped
+// by the host's input alone; every other player only flies their camera and
+// ship through it, and each keeps everyone else's to step with their input
+pub struct Multiplayer {
+    session: Session,
+    // Everyone else's camera and ship, by player; `None` for this player
+    // and for those who left
+    others: Vec<Option<Remote>>,
+    // Time scale to send with the next input; only the host's counts
+    time_scale: Option<f32>,
+}
+
+struct Remote {
+    pilot: Pilot,
+    input: InputFrame,
+}
+
+impl Multiplayer {
+    // `shared` builds the system every player starts from, whose camera and
+    // ship each of the others starts with. The host's time scale reaches
+    // everyone with its first input
+    pub fn new<F>(session: Session, mut shared: F, time_scale: f32) -> Self
+    where
+        F: FnMut() -> Simulation,
+    {
+        let others = (0..session.players)
+            .map(|player| {
+                (player != session.player).then(|| Remote {
+                    pilot: shared().into_pilot(),
+                    input: InputFrame::default(),
+                })
+            })
+            .collect();
+        let host = session.player == 0;
+        Multiplayer {
+            session,
+            others,
+            time_scale: host.then_some(time_scale),
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.session.player == 0
+    }
+
+    // A change made in the host's menu goes out with the next input, so
+    // every player's clock changes at the same step
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        if self.is_host() {
+            self.time_scale = Some(time_scale);
+        }
+    }
+
+    // Sends this step's input and, once the next step has everyone's, runs
+    // it on `simulation` player by player, the host first, and returns this
+    // player's input for it; `None` while someone's is still on its way.
+    // `previous` is this player's input for the step before
+    pub fn advance(
+        &mut self,
+        simulation: &mut Simulation,
+        input: &InputFrame,
+        previous: &InputFrame,
+        delta: f32,
+    ) -> io::Result<Option<InputFrame>> {
+        self.session.receive()?;
+        let mut bundle = None;
+        if self.session.is_ready() {
+            bundle = self.session.take(NetFrame {
+                held: input.held().copied().collect(),
+                cursor: input.cursor(),
+                time_scale: self.time_scale.take(),
+            });
+        }
+        self.session.send()?;
+        let Some(frames) = bundle else {
+            return Ok(None);
+        };
+
+        let mut own = InputFrame::default();
+        for (player, frame) in frames.into_iter().enumerate() {
+            let Some(frame) = frame else {
+                if self.others[player].take().is_some() {
+                    eprintln!("El jugador {} dejó la partida", player + 1);
+                }
+                continue;
+            };
+            if player == 0 {
+                if let Some(time_scale) = frame.time_scale {
+                    simulation.time_scale = time_scale;
+                }
+            }
+            if player == self.session.player {
+                own = frame.input(previous);
+                step(simulation, player, &own, delta);
+            } else if let Some(remote) = self.others[player].as_mut() {
+                remote.input = frame.input(&remote.input);
+                simulation.swap_pilot(&mut remote.pilot);
+                step(simulation, player, &remote.input, delta);
+                simulation.swap_pilot(&mut remote.pilot);
+            }
+        }
+        simulation.fleet = self
+            .others
+            .iter()
+            .flatten()
+            .map(|remote| remote.pilot.ship_position())
+            .collect();
+        Ok(Some(own))
+    }
+}
+
+// The host's steps move the system along; everyone else's only their ship
+fn step(simulation: &mut Simulation, player: usize, input: &InputFrame, delta: f32) {
+    if player == 0 {
+        simulation.update(input, delta);
+    } else {
+        simulation.update_pilot(input, delta);
+    }
+}
+
+// One player's input for one step, as it goes over the wire: the held
+// actions by name, then `cursor=<x>,<y>` and, from the host,
+// `time_scale=<scale>` when there are
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetFrame {
+    held: HashSet<Action>,
+    cursor: Option<Vec2>,
+    time_scale: Option<f32>,
+}
+
+impl NetFrame {
+    // The step's input, pressed against the previous one
+    fn input(&self, previous: &InputFrame) -> InputFrame {
+        InputFrame::from_held(self.held.clone(), previous).with_cursor(self.cursor)
+    }
+
+    fn encode(&self) -> String {
+        let mut parts: Vec<String> = self.held.iter().map(Action::name).collect();
+        parts.sort();
+        if let Some(cursor) = self.cursor {
+            parts.push(format!("cursor={},{}", cursor.x, cursor.y));
+        }
+        if let Some(time_scale) = self.time_scale {
+            parts.push(format!("time_scale={}", time_scale));
+        }
+        parts.join(" ")
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let mut frame = NetFrame::default();
+        for part in text.split_whitespace() {
+            if let Some(cursor) = part.strip_prefix("cursor=") {
+                let (x, y) = parse_pair(cursor)?;
+                frame.cursor = Some(Vec2::new(x, y));
+            } else if let Some(time_scale) = part.strip_prefix("time_scale=") {
+                frame.time_scale = Some(time_scale.parse().ok()?);
+            } else {
+                frame.held.insert(Action::from_name(part)?);
+            }
+        }
+        Some(frame)
+    }
+}
+
+// What the shared system is built from. The host's goes out with the
+// welcome and everyone builds from it, so a player whose config.toml says
+// otherwise can't fall out of step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct World {
+    pub seed: u64,
+    pub scale: SceneScale,
+    // None keeps the built-in star
+    pub star_mass: Option<f32>,
+    pub kepler: bool,
+}
+
+// Every player's frame for one step, `None` for those who left
+type Bundle = Vec<Option<NetFrame>>;
+
+// A bundle on one line: the frames by player, split by `|`, `-` for those
+// who left
+fn encode_bundle(bundle: &Bundle) -> String {
+    bundle
+        .iter()
+        .map(|frame| {
+            frame
+                .as_ref()
+                .map_or_else(|| "-".to_string(), NetFrame::encode)
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn decode_bundle(line: &str, players: usize) -> Option<Bundle> {
+    let bundle = line
+        .split('|')
+        .map(|frame| match frame {
+            "-" => Some(None),
+            frame => NetFrame::decode(frame).map(Some),
+        })
+        .collect::<Option<Bundle>>()?;
+    (bundle.len() == players).then_some(bundle)
+}
+
+fn parse_pair(text: &str) -> Option<(f32, f32)> {
+    let (a, b) = text.split_once(',')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+// The UDP side of a game. Datagrams are text: a header line, then one frame
+// or bundle per line for consecutive steps from the one in the header.
+// Joining players knock with `hola` until the host answers `bienvenido
+// <player> <players> <seed> <scale> <star mass or -> <kepler>`; then they send `entrada <ack> <first>` with
+// their frames not yet in a bundle, and the host answers `pasos <first>`
+// with the bundles from `<ack>`, the first one they lack. Whatever is lost
+// goes again in the next datagram
+pub struct Session {
+    socket: UdpSocket,
+    buffer: Vec<u8>,
+    // This player's number, 0 for the host, and how many there are
+    pub player: usize,
+    pub players: usize,
+    pub world: World,
+    // Next step to run, and the bundles ready from it on
+    step: u64,
+    ready: BTreeMap<u64, Bundle>,
+    role: Role,
+}
+
+enum Role {
+    Host {
+        // Players 1 and up
+        clients: Vec<Client>,
+        // Frames in by step, for the steps not bundled yet
+        pending: BTreeMap<u64, Vec<Option<NetFrame>>>,
+        // Next step to bundle, and every bundle some client still lacks,
+        // encoded, from `sent_from`
+        next: u64,
+        sent: VecDeque<String>,
+        sent_from: u64,
+    },
+    Client {
+        host: SocketAddr,
+        // Since the last datagram from the host, once the game started
+        heard: Option<Instant>,
+        // This player's frames not in a bundle yet, from `sent_from`
+        sent: VecDeque<NetFrame>,
+        sent_from: u64,
+    },
+}
+
+struct Client {
+    address: SocketAddr,
+    // First step whose bundle it lacks
+    acked: u64,
+    heard: Instant,
+    gone: bool,
+}
+
+impl Session {
+    // Listens on `port` and waits, before returning, until `players - 1`
+    // others joined
+    pub fn host(port: u16, players: usize, world: World) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        let mut buffer = vec![0; DATAGRAM];
+        let mut clients: Vec<Client> = Vec::new();
+        eprintln!("Esperando jugadores en el puerto {} (1/{})", port, players);
+        while clients.len() + 1 < players {
+            let (length, address) = socket.recv_from(&mut buffer)?;
+            if &buffer[..length] != b"hola" {
+                continue;
+            }
+            let player = match clients.iter().position(|client| client.address == address) {
+                Some(index) => index + 1,
+                None => {
+                    clients.push(Client {
+                        address,
+                        acked: 0,
+                        heard: Instant::now(),
+                        gone: false,
+                    });
+                    eprintln!(
+                        "Jugador {} conectado desde {} ({}/{})",
+                        clients.len() + 1,
+                        address,
+                        clients.len() + 1,
+                        players
+                    );
+                    clients.len()
+                }
+            };
+            socket.send_to(welcome(player, players, &world).as_bytes(), address)?;
+        }
+        socket.set_nonblocking(true)?;
+        // Waiting in the lobby doesn't count as silence
+        for client in &mut clients {
+            client.heard = Instant::now();
+        }
+        Ok(Session {
+            socket,
+            buffer,
+            player: 0,
+            players,
+            world,
+            step: 0,
+            ready: BTreeMap::new(),
+            role: Role::Host {
+                clients,
+                pending: BTreeMap::new(),
+                next: 0,
+                sent: VecDeque::new(),
+                sent_from: 0,
+            },
+        })
+    }
+
+    // Knocks on the host at `address` (`host:port`) until it answers
+    pub fn join(address: &str) -> io::Result<Self> {
+        let host = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "dirección inválida"))?;
+        let any = if host.is_ipv4() { "0.0.0.0" } else { "::" };
+        let socket = UdpSocket::bind((any, 0))?;
+        socket.connect(host)?;
+        socket.set_read_timeout(Some(KNOCK_INTERVAL))?;
+        let mut buffer = vec![0; DATAGRAM];
+        eprintln!("Conectando con {}...", host);
+        let started = Instant::now();
+        let (player, players, world) = loop {
+            if started.elapsed() > JOIN_TIMEOUT {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "el anfitrión no respondió",
+                ));
+            }
+            socket.send(b"hola")?;
+            let length = match socket.recv(&mut buffer) {
+                Ok(length) => length,
+                Err(error) if is_transient(&error) => continue,
+                Err(error) => return Err(error),
+            };
+            let text = String::from_utf8_lossy(&buffer[..length]);
+            if let Some(welcome) = parse_welcome(&text) {
+                break welcome;
+            }
+        };
+        socket.set_read_timeout(None)?;
+        socket.set_nonblocking(true)?;
+        eprintln!("Eres el jugador {} de {}", player + 1, players);
+        Ok(Session {
+            socket,
+            buffer,
+            player,
+            players,
+            world,
+            step: 0,
+            ready: BTreeMap::new(),
+            role: Role::Client {
+                host,
+                heard: None,
+                sent: VecDeque::new(),
+                sent_from: INPUT_DELAY,
+            },
+        })
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready.contains_key(&self.step)
+    }
+
+    // The next step's bundle; `local` is this player's frame for the step
+    // `INPUT_DELAY` after it
+    fn take(&mut self, local: NetFrame) -> Option<Bundle> {
+        let bundle = self.ready.remove(&self.step)?;
+        let step = self.step + INPUT_DELAY;
+        match &mut self.role {
+            Role::Host { pending, .. } => {
+                pending
+                    .entry(step)
+                    .or_insert_with(|| vec![None; self.players])[0] = Some(local);
+            }
+            Role::Client { sent, .. } => sent.push_back(local),
+        }
+        self.step += 1;
+        Some(bundle)
+    }
+
+    // Reads every datagram waiting; the host then bundles every step it has
+    // all the frames of, and drops the players it stopped hearing from
+    fn receive(&mut self) -> io::Result<()> {
+        loop {
+            let (length, address) = match self.socket.recv_from(&mut self.buffer) {
+                Ok(received) => received,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if is_transient(&error) => continue,
+                Err(error) => return Err(error),
+            };
+            let text = String::from_utf8_lossy(&self.buffer[..length]).into_owned();
+            self.handle(address, &text)?;
+        }
+
+        let players = self.players;
+        match &mut self.role {
+            Role::Host {
+                clients,
+                pending,
+                next,
+                sent,
+                ..
+            } => {
+                for (index, client) in clients.iter_mut().enumerate() {
+                    if !client.gone && client.heard.elapsed() > TIMEOUT {
+                        client.gone = true;
+                        eprintln!("El jugador {} dejó de responder", index + 2);
+                    }
+                }
+                loop {
+                    let bundle = if *next < INPUT_DELAY {
+                        vec![Some(NetFrame::default()); players]
+                    } else {
+                        let complete = pending.get(next).is_some_and(|frames| {
+                            frames.iter().enumerate().all(|(player, frame)| {
+                                frame.is_some() || (player > 0 && clients[player - 1].gone)
+                            })
+                        });
+                        // Left in place until the rest of the frames come
+                        if !complete {
+                            break;
+                        }
+                        let Some(mut frames) = pending.remove(next) else {
+                            break;
+                        };
+                        for (client, frame) in clients.iter().zip(&mut frames[1..]) {
+                            if client.gone {
+                                *frame = None;
+                            }
+                        }
+                        frames
+                    };
+                    sent.push_back(encode_bundle(&bundle));
+                    self.ready.insert(*next, bundle);
+                    *next += 1;
+                }
+            }
+            Role::Client { heard, .. } => {
+                if heard.is_some_and(|heard| heard.elapsed() > TIMEOUT) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "el anfitrión dejó de responder",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, address: SocketAddr, text: &str) -> io::Result<()> {
+        let mut lines = text.split('\n');
+        let mut header = lines.next().unwrap_or_default().split_whitespace();
+        let kind = header.next();
+        let mut number = || header.next().and_then(|value| value.parse::<u64>().ok());
+        match &mut self.role {
+            Role::Host {
+                clients,
+                pending,
+                next,
+                ..
+            } => {
+                let Some(index) = clients
+                    .iter()
+                    .position(|client| client.address == address && !client.gone)
+                else {
+                    return Ok(());
+                };
+                let client = &mut clients[index];
+                match kind {
+                    // The welcome got lost
+                    Some("hola") => {
+                        let welcome = welcome(index + 1, self.players, &self.world);
+                        self.socket.send_to(welcome.as_bytes(), address)?;
+                    }
+                    Some("entrada") => {
+                        let (Some(acked), Some(first)) = (number(), number()) else {
+                            return Ok(());
+                        };
+                        client.heard = Instant::now();
+                        client.acked = client.acked.max(acked);
+                        for (step, line) in (first..).zip(lines) {
+                            let Some(frame) = NetFrame::decode(line) else {
+                                break;
+                            };
+                            if step >= *next {
+                                pending
+                                    .entry(step)
+                                    .or_insert_with(|| vec![None; self.players])[index + 1] =
+                                    Some(frame);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Role::Client { host, heard, .. } => {
+                if address != *host || kind != Some("pasos") {
+                    return Ok(());
+                }
+                let Some(first) = number() else {
+                    return Ok(());
+                };
+                *heard = Some(Instant::now());
+                for (step, line) in (first..).zip(lines) {
+                    if step < self.step || self.ready.contains_key(&step) {
+                        continue;
+                    }
+                    let Some(bundle) = decode_bundle(line, self.players) else {
+                        break;
+                    };
+                    self.ready.insert(step, bundle);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Sends whatever the other side lacks, or just a sign of life
+    fn send(&mut self) -> io::Result<()> {
+        match &mut self.role {
+            Role::Host {
+                clients,
+                sent,
+                sent_from,
+                ..
+            } => {
+                // Bundles every client has are done with
+                let oldest = clients
+                    .iter()
+                    .filter(|client| !client.gone)
+                    .map(|client| client.acked)
+                    .min()
+                    .unwrap_or(*sent_from + sent.len() as u64);
+                while *sent_from < oldest && sent.pop_front().is_some() {
+                    *sent_from += 1;
+                }
+                for client in clients.iter().filter(|client| !client.gone) {
+                    let first = client.acked.max(*sent_from);
+                    let mut datagram = format!("pasos {}", first);
+                    for bundle in sent.iter().skip((first - *sent_from) as usize).take(BATCH) {
+                        datagram.push('\n');
+                        datagram.push_str(bundle);
+                    }
+                    send_datagram(&self.socket, &datagram, Some(client.address))?;
+                }
+            }
+            Role::Client {
+                sent, sent_from, ..
+            } => {
+                // Every step with a bundle had this player's frame in it
+                let mut acked = self.step;
+                while self.ready.contains_key(&acked) {
+                    acked += 1;
+                }
+                while *sent_from < acked && sent.pop_front().is_some() {
+                    *sent_from += 1;
+                }
+                let mut datagram = format!("entrada {} {}", acked, sent_from);
+                for frame in sent.iter().take(BATCH) {
+                    datagram.push('\n');
+                    datagram.push_str(&frame.encode());
+                }
+                send_datagram(&self.socket, &datagram, None)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn welcome(player: usize, players: usize, world: &World) -> String {
+    format!(
+        "bienvenido {} {} {} {} {} {}",
+        player,
+        players,
+        world.seed,
+        world.scale.name(),
+        world
+            .star_mass
+            .map_or_else(|| "-".to_string(), |mass| mass.to_string()),
+        world.kepler,
+    )
+}
+
+fn parse_welcome(text: &str) -> Option<(usize, usize, World)> {
+    let mut parts = text.split_whitespace();
+    if parts.next()? != "bienvenido" {
+        return None;
+    }
+    let player = parts.next()?.parse().ok()?;
+    let players = parts.next()?.parse().ok()?;
+    let world = World {
+        seed: parts.next()?.parse().ok()?,
+        scale: SceneScale::named(parts.next()?)?,
+        star_mass: match parts.next()? {
+            "-" => None,
+            mass => Some(mass.parse().ok()?),
+        },
+        kepler: parts.next()?.parse().ok()?,
+    };
+    (parts.next().is_none() && player < players).then_some((player, players, world))
+}
+
+// Sends to `address`, or to the host a joining player's socket is connected
+// to when `None`: macOS and the BSDs refuse `send_to` on a connected socket.
+// A full socket buffer drops the datagram like the network would
+fn send_datagram(
+    socket: &UdpSocket,
+    datagram: &str,
+    address: Option<SocketAddr>,
+) -> io::Result<()> {
+    let sent = match address {
+        Some(address) => socket.send_to(datagram.as_bytes(), address),
+        None => socket.send(datagram.as_bytes()),
+    };
+    match sent {
+        Err(error) if error.kind() == io::ErrorKind::WouldBlock || is_transient(&error) => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+// What a UDP socket reports for a peer not listening (yet), or a read that
+// timed out; none of it ends the game by itself
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::WouldBlock
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(held: &[Action], cursor: Option<Vec2>, time_scale: Option<f32>) -> NetFrame {
+        NetFrame {
+            held: held.iter().copied().collect(),
+            cursor,
+            time_scale,
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let frames = [
+            NetFrame::default(),
+            frame(
+                &[Action::Forward, Action::Boost, Action::Warp(3)],
+                Some(Vec2::new(12.5, -3.0)),
+                Some(0.25),
+            ),
+            frame(&[Action::StoreBookmark(1)], None, None),
+        ];
+        for frame in frames {
+            assert_eq!(NetFrame::decode(&frame.encode()), Some(frame));
+        }
+        assert_eq!(NetFrame::decode("NoSuchAction"), None);
+        assert_eq!(NetFrame::decode("cursor=1"), None);
+    }
+
+    #[test]
+    fn bundles_round_trip_with_players_who_left() {
+        let bundle = vec![
+            Some(frame(&[Action::Left], None, Some(2.0))),
+            None,
+            Some(NetFrame::default()),
+        ];
+        let line = encode_bundle(&bundle);
+
+        assert_eq!(line.split('|').nth(1), Some("-"));
+        assert_eq!(decode_bundle(&line, 3), Some(bundle));
+    }
+
+    #[test]
+    fn bundles_for_another_player_count_are_rejected() {
+        let line = encode_bundle(&vec![Some(NetFrame::default()), None]);
+
+        assert_eq!(decode_bundle(&line, 3), None);
+        assert_eq!(decode_bundle(&line, 1), None);
+        assert!(decode_bundle(&line, 2).is_some());
+    }
+
+    #[test]
+    fn welcomes_round_trip() {
+        let world = World {
+            seed: u64::MAX,
+            scale: SceneScale::SemiRealistic,
+            star_mass: Some(8_000_000.5),
+            kepler: true,
+        };
+        assert_eq!(parse_welcome(&welcome(2, 3, &world)), Some((2, 3, world)));
+
+        let world = World {
+            star_mass: None,
+            scale: SceneScale::Stylized,
+            ..world
+        };
+        assert_eq!(parse_welcome(&welcome(1, 2, &world)), Some((1, 2, world)));
+    }
+
+    #[test]
+    fn malformed_welcomes_are_rejected() {
+        for text in [
+            "",
+            "hola",
+            "bienvenido 1 2",
+            "bienvenido 1 2 42 estilizada - true extra",
+            "bienvenido uno 2 42 estilizada - true",
+            "bienvenido 1 2 42 enorme - true",
+            "bienvenido 1 2 42 estilizada pesada true",
+            "bienvenido 1 2 42 estilizada - quizas",
+            "bienvenido 2 2 42 estilizada - true",
+        ] {
+            assert_eq!(parse_welcome(text), None, "{:?}", text);
+        }
+    }
+}
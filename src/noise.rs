@@ -0,0 +1,286 @@
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+
+// Noise shared by the shaders, the terrain, the skybox and expression files.
+// Every function takes a seed: 0 is the original pattern and any other value
+// samples an unrelated-looking part of the same field, so bodies that share a
+// shader don't come out identical.
+
+// Cube-edge directions simplex noise picks its gradients from
+const GRADIENTS: [Vec3; 12] = [
+    Vec3::new(1.0, 1.0, 0.0),
+    Vec3::new(-1.0, 1.0, 0.0),
+    Vec3::new(1.0, -1.0, 0.0),
+    Vec3::new(-1.0, -1.0, 0.0),
+    Vec3::new(1.0, 0.0, 1.0),
+    Vec3::new(-1.0, 0.0, 1.0),
+    Vec3::new(1.0, 0.0, -1.0),
+    Vec3::new(-1.0, 0.0, -1.0),
+    Vec3::new(0.0, 1.0, 1.0),
+    Vec3::new(0.0, -1.0, 1.0),
+    Vec3::new(0.0, 1.0, -1.0),
+    Vec3::new(0.0, -1.0, -1.0),
+];
+
+// Pseudo-random value in [0, 1)
+pub fn hash(n: f32) -> f32 {
+    let x = (n * 12.9898).sin() * 43758.5453;
+    x - x.floor()
+}
+
+fn hash_vec3(p: Vec3) -> f32 {
+    let n = p.x * 12.9898 + p.y * 78.233 + p.z * 45.164;
+    hash(n)
+}
+
+// Where a seed moves the sampling point; seed 0 stays put
+fn offset(seed: u32) -> Vec3 {
+    if seed == 0 {
+        return Vec3::zeros();
+    }
+    let s = seed as f32;
+    Vec3::new(
+        hash(s * 0.731 + 0.17),
+        hash(s * 1.319 + 0.53),
+        hash(s * 0.917 + 0.91),
+    ) * 97.0
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Value noise in [0, 1): hashed lattice corners, smoothly interpolated
+pub fn noise(p: Vec3, seed: u32) -> f32 {
+    value_noise(p + offset(seed))
+}
+
+fn value_noise(p: Vec3) -> f32 {
+    let i = Vec3::new(p.x.floor(), p.y.floor(), p.z.floor());
+    let f = p - i;
+    let u = Vec3::new(fade(f.x), fade(f.y), fade(f.z));
+
+    // Hash values at corners
+    let a = hash_vec3(i);
+    let b = hash_vec3(Vec3::new(i.x + 1.0, i.y, i.z));
+    let c = hash_vec3(Vec3::new(i.x, i.y + 1.0, i.z));
+    let d = hash_vec3(Vec3::new(i.x + 1.0, i.y + 1.0, i.z));
+    let e = hash_vec3(Vec3::new(i.x, i.y, i.z + 1.0));
+    let f_val = hash_vec3(Vec3::new(i.x + 1.0, i.y, i.z + 1.0));
+    let g = hash_vec3(Vec3::new(i.x, i.y + 1.0, i.z + 1.0));
+    let h = hash_vec3(Vec3::new(i.x + 1.0, i.y + 1.0, i.z + 1.0));
+
+    // Trilinear interpolation
+    let x1 = a + (b - a) * u.x;
+    let x2 = c + (d - c) * u.x;
+    let y1 = x1 + (x2 - x1) * u.y;
+
+    let x3 = e + (f_val - e) * u.x;
+    let x4 = g + (h - g) * u.x;
+    let y2 = x3 + (x4 - x3) * u.y;
+
+    y1 + (y2 - y1) * u.z
+}
+
+// Fractal Brownian motion: octaves of value noise, each twice as fine and
+// half as strong as the last
+pub fn fbm(p: Vec3, octaves: u32, seed: u32) -> f32 {
+    let p = p + offset(seed);
+    let mut value = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves {
+        value += amplitude * value_noise(p * frequency);
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    value
+}
+
+// Simplex noise in [0, 1]: no grid-aligned streaks and cheaper per octave
+// than value noise's eight corners
+pub fn simplex(p: Vec3, seed: u32) -> f32 {
+    simplex_noise(p + offset(seed))
+}
+
+fn simplex_noise(p: Vec3) -> f32 {
+    const SKEW: f32 = 1.0 / 3.0;
+    const UNSKEW: f32 = 1.0 / 6.0;
+
+    let s = (p.x + p.y + p.z) * SKEW;
+    let cell = Vec3::new((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+    let t = (cell.x + cell.y + cell.z) * UNSKEW;
+    let first = p - cell + Vec3::repeat(t);
+
+    // The two middle corners of the tetrahedron the point falls in
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+    let z = Vec3::new(0.0, 0.0, 1.0);
+    let (second, third) = if first.x >= first.y {
+        if first.y >= first.z {
+            (x, x + y)
+        } else if first.x >= first.z {
+            (x, x + z)
+        } else {
+            (z, x + z)
+        }
+    } else if first.y < first.z {
+        (z, y + z)
+    } else if first.x < first.z {
+        (y, y + z)
+    } else {
+        (y, x + y)
+    };
+
+    let corners = [Vec3::zeros(), second, third, Vec3::repeat(1.0)];
+    let mut sum = 0.0;
+    for (k, corner) in corners.iter().enumerate() {
+        let d = first - corner + Vec3::repeat(k as f32 * UNSKEW);
+        let falloff = 0.6 - d.magnitude_squared();
+        if falloff > 0.0 {
+            let gradient = GRADIENTS[(hash_vec3(cell + corner) * 12.0) as usize % 12];
+            sum += falloff.powi(4) * gradient.dot(&d);
+        }
+    }
+
+    (0.5 + sum * 16.0).clamp(0.0, 1.0)
+}
+
+// Ridged multifractal: sharp crests where simplex noise crosses its midpoint,
+// with each octave weighted by the one before so detail gathers on the ridges.
+// Mountain chains and lava fissures rather than fbm's rolling hills
+pub fn ridged(p: Vec3, octaves: u32, seed: u32) -> f32 {
+    let p = p + offset(seed);
+    let mut value = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+    let mut weight = 1.0;
+
+    for _ in 0..octaves {
+        let crest = 1.0 - (simplex_noise(p * frequency) * 2.0 - 1.0).abs();
+        let signal = crest * crest * weight;
+        weight = (signal * 2.0).clamp(0.0, 1.0);
+        value += signal * amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    value
+}
+
+// Domain warping: `p` pushed up to `amount` along a smooth fbm field, so
+// whatever is sampled at the result swirls and folds like clouds or strata
+pub fn warp(p: Vec3, amount: f32, seed: u32) -> Vec3 {
+    let field = Vec3::new(
+        fbm(p, 3, seed),
+        fbm(p + Vec3::new(5.2, 1.3, 2.8), 3, seed),
+        fbm(p + Vec3::new(1.7, 9.2, 4.1), 3, seed),
+    );
+    p + (field - Vec3::repeat(0.5)) * 2.0 * amount
+}
+
+// One fbm field a shader samples: `fbm(position * scale, octaves, seed)`
+#[derive(Debug, Clone, Copy)]
+pub struct NoiseLayer {
+    pub scale: f32,
+    pub octaves: u32,
+}
+
+impl NoiseLayer {
+    pub const fn new(scale: f32, octaves: u32) -> Self {
+        NoiseLayer { scale, octaves }
+    }
+
+    pub fn sample(&self, position: Vec3, seed: u32) -> f32 {
+        fbm(position * self.scale, self.octaves, seed)
+    }
+}
+
+// A body's noise layers evaluated once over its unit sphere and stored as
+// cubemaps, so shaders read a bilinear lookup instead of running high-octave
+// fbm for every fragment. Fragments all lie on (or just above) the surface,
+// which makes a cubemap much cheaper than a full 3D volume for the same detail
+pub struct NoiseCube {
+    size: usize,
+    // Per layer: six faces of size x size texels, face after face
+    layers: Vec<Vec<f32>>,
+}
+
+impl NoiseCube {
+    pub fn bake(layers: &[NoiseLayer], seed: u32, size: usize) -> Self {
+        let layers = layers
+            .iter()
+            .map(|layer| {
+                let mut texels = vec![0.0; 6 * size * size];
+                texels
+                    .par_chunks_mut(size)
+                    .enumerate()
+                    .for_each(|(row, texels)| {
+                        let (face, y) = (row / size, row % size);
+                        let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                        for (x, texel) in texels.iter_mut().enumerate() {
+                            let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                            *texel = layer.sample(face_direction(face, u, v), seed);
+                        }
+                    });
+                texels
+            })
+            .collect();
+        NoiseCube { size, layers }
+    }
+
+    // Layer `layer` in the direction of `position`
+    pub fn sample(&self, layer: usize, position: Vec3) -> f32 {
+        let (face, u, v) = cube_face(position);
+        let last = self.size as f32 - 1.0;
+        let x = ((u + 1.0) * 0.5 * self.size as f32 - 0.5).clamp(0.0, last);
+        let y = ((v + 1.0) * 0.5 * self.size as f32 - 0.5).clamp(0.0, last);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.size - 1), (y0 + 1).min(self.size - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let texels = &self.layers[layer][face * self.size * self.size..];
+        let at = |x: usize, y: usize| texels[y * self.size + x];
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        top + (bottom - top) * ty
+    }
+}
+
+// Unit direction through (u, v), both in [-1, 1], on cube face `face`:
+// +X, -X, +Y, -Y, +Z, -Z
+fn face_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    let direction = match face {
+        0 => Vec3::new(1.0, v, -u),
+        1 => Vec3::new(-1.0, v, u),
+        2 => Vec3::new(u, 1.0, -v),
+        3 => Vec3::new(u, -1.0, v),
+        4 => Vec3::new(u, v, 1.0),
+        _ => Vec3::new(-u, v, -1.0),
+    };
+    direction.normalize()
+}
+
+// Inverse of `face_direction`
+fn cube_face(p: Vec3) -> (usize, f32, f32) {
+    let (ax, ay, az) = (p.x.abs(), p.y.abs(), p.z.abs());
+    if ax >= ay && ax >= az {
+        if p.x > 0.0 {
+            (0, -p.z / ax, p.y / ax)
+        } else {
+            (1, p.z / ax, p.y / ax)
+        }
+    } else if ay >= az {
+        if p.y > 0.0 {
+            (2, p.x / ay, -p.z / ay)
+        } else {
+            (3, p.x / ay, p.z / ay)
+        }
+    } else if p.z > 0.0 {
+        (4, p.x / az, p.y / az)
+    } else {
+        (5, -p.x / az, p.y / az)
+    }
+}
@@ -1,3 +1,6 @@
+use std::io::Cursor;
+
+use crate::assets;
 use crate::vertex::Vertex;
 use nalgebra_glm::{Vec2, Vec3};
 use tobj;
@@ -15,13 +18,16 @@ struct Mesh {
 
 impl Obj {
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(
-            filename,
+        let bytes = assets::read(filename).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+        // The ship's shader doesn't use the .mtl materials
+        let (models, _) = tobj::load_obj_buf(
+            &mut Cursor::new(bytes),
             &tobj::LoadOptions {
                 single_index: true,
                 triangulate: true,
                 ..Default::default()
             },
+            |_| Ok(Default::default()),
         )?;
 
         let meshes = models
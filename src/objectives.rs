@@ -0,0 +1,151 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::simulation::Simulation;
+use crate::toml;
+
+pub const OBJECTIVES_PATH: &str = "objectives.toml";
+// A planet counts as visited once the camera is within this many of its
+// collision radii
+const VISIT_REACH: f32 = 2.0;
+// Seconds the ship has to coast on a stable orbit for it to count
+const ORBIT_HOLD: f32 = 5.0;
+// A stable orbit keeps its closest pass this many of the star's collision
+// radii out, clear of the heat that would push the ship off it
+const ORBIT_CLEARANCE: f32 = 1.5;
+
+// What is left to do in the system, and what is already done
+pub enum Objective {
+    Visit(String),
+    StableOrbit,
+}
+
+// Goals that turn the system into something to explore: visit every
+// planet and hold a stable orbit around the star in flight mode. Progress
+// is kept by planet name, so it survives restarts and scripted spawns
+#[derive(Default)]
+pub struct Objectives {
+    visited: Vec<String>,
+    orbited: bool,
+    // How long the current stable orbit has been held
+    holding: f32,
+    // Something was completed since the last save
+    changed: bool,
+}
+
+impl Objectives {
+    // A missing file just means nothing has been done yet
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Objectives::default())
+            }
+            Err(error) => return Err(error),
+        };
+        let table = toml::parse(&text)?;
+        let visited = table
+            .get("visited")
+            .and_then(|value| value.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let orbited = table
+            .get("orbit")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        Ok(Objectives {
+            visited,
+            orbited,
+            ..Objectives::default()
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let names: Vec<String> = self
+            .visited
+            .iter()
+            .map(|name| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        let output = format!(
+            "# Misiones cumplidas. Borrar este archivo empieza de nuevo.\n\
+             visited = [{}]\norbit = {}\n",
+            names.join(", "),
+            self.orbited,
+        );
+        fs::write(path, output)
+    }
+
+    // Checks what the camera and ship are doing against what is left
+    pub fn update(&mut self, simulation: &Simulation, delta: f32) {
+        for (index, planet) in simulation.planets.iter().enumerate() {
+            if planet.temperature.is_some() || self.visited.contains(&planet.name) {
+                continue;
+            }
+            let distance =
+                (simulation.camera.position - simulation.planet_position(index)).magnitude();
+            if distance < planet.collision_radius * VISIT_REACH {
+                self.visited.push(planet.name.clone());
+                self.changed = true;
+            }
+        }
+
+        if self.orbited {
+            return;
+        }
+        if stable_orbit(simulation) {
+            self.holding += delta;
+            if self.holding >= ORBIT_HOLD {
+                self.orbited = true;
+                self.changed = true;
+            }
+        } else {
+            self.holding = 0.0;
+        }
+    }
+
+    // Whether anything was completed since the last call, to save it
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    // Every objective in the system in order, with whether it is done
+    pub fn list(&self, simulation: &Simulation) -> Vec<(Objective, bool)> {
+        let mut list: Vec<(Objective, bool)> = simulation
+            .planets
+            .iter()
+            .filter(|planet| planet.temperature.is_none())
+            .map(|planet| {
+                let done = self.visited.contains(&planet.name);
+                (Objective::Visit(planet.name.clone()), done)
+            })
+            .collect();
+        list.push((Objective::StableOrbit, self.orbited));
+        list
+    }
+
+    // Seconds of the stable orbit held so far, while it is being held
+    pub fn holding(&self) -> Option<f32> {
+        (self.holding > 0.0 && !self.orbited).then_some(self.holding)
+    }
+}
+
+// Bound around the star, never dipping into its heat nor leaving the system
+fn stable_orbit(simulation: &Simulation) -> bool {
+    let Some(star) = simulation.planets.first() else {
+        return false;
+    };
+    if !simulation.ship.flight_mode {
+        return false;
+    }
+    simulation.ship_orbit().is_some_and(|orbit| {
+        orbit.periapsis > star.collision_radius * ORBIT_CLEARANCE
+            && orbit.apoapsis < simulation.extent()
+    })
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use crate::backend::Backend;
+use crate::config::CONFIG_PATH;
+use crate::i18n::DEFAULT_LANGUAGE;
+use crate::multiplayer::DEFAULT_PLAYERS;
+
+pub const DEFAULT_SEED: u64 = 0x5EED_2024;
+
+pub struct Options {
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub stats: Option<PathBuf>,
+    pub skybox: Option<PathBuf>,
+    pub config: PathBuf,
+    pub script: Option<PathBuf>,
+    pub seed: u64,
+    // Off with --no-bake: no atlases or noise cubemaps, every planet shaded
+    // procedurally
+    pub bake: bool,
+    // Interface language, the name of a file in `lang/`
+    pub language: String,
+    // Who draws the frames; the software renderer unless --backend gpu
+    pub backend: Backend,
+    // Physical pixels per logical one, from --scale; otherwise the window
+    // asks the monitor
+    pub scale: Option<f32>,
+    // Simulated seconds to start at, from --time; negative runs the orbits back
+    pub time: Option<f32>,
+    // Sidecar of a screenshot to start from, with its seed, time and camera
+    pub restore_shot: Option<PathBuf>,
+    // Port to stream the window to spectators on, from --spectate
+    pub spectate: Option<u16>,
+    // Whether other machines may watch too, from --spectate-public; only
+    // this one otherwise
+    pub spectate_public: bool,
+    // Multiplayer: the port to host a game on, or the host to join
+    pub host: Option<u16>,
+    pub join: Option<String>,
+    // Players a hosted game waits for, the host included
+    pub players: usize,
+}
+
+impl Options {
+    pub fn from_args() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut options = Options {
+            record: None,
+            replay: None,
+            stats: None,
+            skybox: None,
+            config: PathBuf::from(CONFIG_PATH),
+            script: None,
+            seed: DEFAULT_SEED,
+            bake: true,
+            language: DEFAULT_LANGUAGE.to_string(),
+            backend: Backend::Software,
+            scale: None,
+            time: None,
+            restore_shot: None,
+            spectate: None,
+            spectate_public: false,
+            host: None,
+            join: None,
+            players: DEFAULT_PLAYERS,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => options.record = args.next().map(PathBuf::from),
+                "--replay" => options.replay = args.next().map(PathBuf::from),
+                "--stats" => options.stats = args.next().map(PathBuf::from),
+                "--script" => options.script = args.next().map(PathBuf::from),
+                "--restore-shot" => options.restore_shot = args.next().map(PathBuf::from),
+                "--skybox" => options.skybox = args.next().map(PathBuf::from),
+                "--no-bake" => options.bake = false,
+                "--config" => match args.next() {
+                    Some(path) => options.config = PathBuf::from(path),
+                    None => eprintln!("--config requiere una ruta"),
+                },
+                "--lang" => match args.next() {
+                    Some(language) => options.language = language,
+                    None => eprintln!("--lang requiere un idioma (es, en)"),
+                },
+                "--backend" => match args.next().as_deref().and_then(Backend::named) {
+                    Some(backend) => options.backend = backend,
+                    None => eprintln!("--backend requiere software o gpu"),
+                },
+                "--scale" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(scale) if scale >= 0.5 => options.scale = Some(scale),
+                    _ => eprintln!("--scale requiere un número desde 0.5 (1, 1.5, 2...)"),
+                },
+                "--time" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(time) => options.time = Some(time),
+                    None => eprintln!("--time requiere un número de segundos (puede ser negativo)"),
+                },
+                "--spectate" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(port) => options.spectate = Some(port),
+                    None => eprintln!("--spectate requiere un puerto (8080...)"),
+                },
+                "--spectate-public" => options.spectate_public = true,
+                "--host" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(port) => options.host = Some(port),
+                    None => eprintln!("--host requiere un puerto (7777...)"),
+                },
+                "--join" => match args.next() {
+                    Some(address) => options.join = Some(address),
+                    None => eprintln!("--join requiere la dirección del anfitrión (ip:puerto)"),
+                },
+                "--players" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(players) if players >= 2 => options.players = players,
+                    _ => eprintln!("--players requiere un número desde 2"),
+                },
+                "--seed" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(seed) => options.seed = seed,
+                    None => eprintln!("--seed requiere un número entero"),
+                },
+                other => eprintln!("Argumento desconocido ignorado: {}", other),
+            }
+        }
+
+        // Every copy of a player has to start from the same state and only
+        // follow its input
+        let multiplayer = options.host.is_some() || options.join.is_some();
+        let solo_only = options.replay.is_some()
+            || options.script.is_some()
+            || options.time.is_some()
+            || options.restore_shot.is_some();
+        if multiplayer && solo_only {
+            eprintln!("En multijugador se ignoran --replay, --script, --time y --restore-shot");
+            options.replay = None;
+            options.script = None;
+            options.time = None;
+            options.restore_shot = None;
+        }
+
+        options
+    }
+}
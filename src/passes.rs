@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+// The steps a frame is drawn in, in the order they run. New effects go in
+// the pass whose inputs they need, or in a new variant placed accordingly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    // Clear and background
+    Skybox,
+    // Bodies and the ship, writing depth and object ids
+    Opaque,
+    // The belt's many small bodies and impostors
+    Particles,
+    // Translucent rings, farthest first over what is behind them
+    Transparent,
+    // Screen-space effects on the finished scene: heat shimmer, occlusion,
+    // bloom, depth of field, edge smoothing and accumulation
+    Post,
+    // Orbits, selection, labels and HUD on the overlay layers
+    Overlay,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 6] = [
+        Pass::Skybox,
+        Pass::Opaque,
+        Pass::Particles,
+        Pass::Transparent,
+        Pass::Post,
+        Pass::Overlay,
+    ];
+
+    // Name in the config file and the stats columns
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Skybox => "skybox",
+            Pass::Opaque => "opaque",
+            Pass::Particles => "particles",
+            Pass::Transparent => "transparent",
+            Pass::Post => "post",
+            Pass::Overlay => "overlay",
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// Which passes run and how long each took last frame; a skipped pass reads
+// as zero
+pub struct RenderGraph {
+    enabled: [bool; Pass::ALL.len()],
+    timings: [Duration; Pass::ALL.len()],
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        RenderGraph {
+            enabled: [true; Pass::ALL.len()],
+            timings: [Duration::ZERO; Pass::ALL.len()],
+        }
+    }
+}
+
+impl RenderGraph {
+    pub fn set_enabled(&mut self, pass: Pass, enabled: bool) {
+        self.enabled[pass.index()] = enabled;
+    }
+
+    pub fn is_enabled(&self, pass: Pass) -> bool {
+        self.enabled[pass.index()]
+    }
+
+    pub fn timing(&self, pass: Pass) -> Duration {
+        self.timings[pass.index()]
+    }
+
+    pub fn record(&mut self, pass: Pass, elapsed: Duration) {
+        self.timings[pass.index()] = elapsed;
+    }
+}
@@ -0,0 +1,194 @@
+use std::f32::consts::FRAC_PI_3;
+
+use nalgebra_glm::Vec3;
+
+// Gravitational constant in scene units; chosen together with the masses in
+// `body_mass` so that the default system stays bound.
+pub const GRAVITY: f32 = 1.0;
+const SOFTENING: f32 = 25.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub mass: f32,
+}
+
+pub struct GravityWorld {
+    pub bodies: Vec<Body>,
+}
+
+impl GravityWorld {
+    pub fn new(bodies: Vec<Body>) -> Self {
+        GravityWorld { bodies }
+    }
+
+    pub fn accelerations(&self) -> Vec<Vec3> {
+        self.bodies
+            .iter()
+            .enumerate()
+            .map(|(index, body)| {
+                self.bodies
+                    .iter()
+                    .enumerate()
+                    .filter(|(other_index, _)| *other_index != index)
+                    .fold(Vec3::zeros(), |acc, (_, other)| {
+                        acc + attraction(body.position, other.position, other.mass)
+                    })
+            })
+            .collect()
+    }
+
+    // Semi-implicit Euler: velocities first, then positions with the new
+    // velocities. Backwards the two halves swap places, which undoes a
+    // forward step exactly, so running time back retraces the same orbits
+    pub fn step(&mut self, delta: f32) {
+        if delta < 0.0 {
+            self.drift(delta);
+            self.kick(delta);
+        } else {
+            self.kick(delta);
+            self.drift(delta);
+        }
+    }
+
+    fn kick(&mut self, delta: f32) {
+        let accelerations = self.accelerations();
+        for (body, acceleration) in self.bodies.iter_mut().zip(accelerations) {
+            body.velocity += acceleration * delta;
+        }
+    }
+
+    fn drift(&mut self, delta: f32) {
+        for body in &mut self.bodies {
+            body.position += body.velocity * delta;
+        }
+    }
+}
+
+// Acceleration at `position` caused by a point mass at `source`
+pub fn attraction(position: Vec3, source: Vec3, mass: f32) -> Vec3 {
+    let offset = source - position;
+    let distance_squared = offset.magnitude_squared() + SOFTENING * SOFTENING;
+    offset * (GRAVITY * mass / (distance_squared * distance_squared.sqrt()))
+}
+
+// Speed of a circular orbit of `radius` around a body of `mass`
+pub fn circular_speed(mass: f32, radius: f32) -> f32 {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+    (GRAVITY * mass / radius).sqrt()
+}
+
+// Angular speed of that orbit, as Kepler's third law gives it
+pub fn kepler_speed(mass: f32, radius: f32) -> f32 {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+    circular_speed(mass, radius) / radius
+}
+
+// L4 and L5 of a body orbiting `center`: the points a sixth of a turn ahead
+// of it and behind it, each making an equilateral triangle with the two.
+// `direction` is the sign of the body's orbit speed, so L4 is the leading one
+pub fn lagrange_points(center: Vec3, body: Vec3, direction: f32) -> [Vec3; 2] {
+    let offset = body - center;
+    let turn = |angle: f32| {
+        let (sin, cos) = angle.sin_cos();
+        center
+            + Vec3::new(
+                offset.x * cos - offset.z * sin,
+                offset.y,
+                offset.x * sin + offset.z * cos,
+            )
+    };
+    let lead = FRAC_PI_3 * direction.signum();
+    [turn(lead), turn(-lead)]
+}
+
+// Summed pull of every (position, mass) attractor
+pub fn gravity_at(position: Vec3, attractors: &[(Vec3, f32)]) -> Vec3 {
+    attractors
+        .iter()
+        .fold(Vec3::zeros(), |acc, (source, mass)| {
+            acc + attraction(position, *source, *mass)
+        })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitElements {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub periapsis: f32,
+    pub apoapsis: f32,
+}
+
+// Two-body orbit of a test particle around `mass`; `None` means the path escapes
+pub fn orbit_elements(offset: Vec3, velocity: Vec3, mass: f32) -> Option<OrbitElements> {
+    let mu = GRAVITY * mass;
+    let radius = offset.magnitude();
+    if radius <= 0.0 || mu <= 0.0 {
+        return None;
+    }
+
+    let energy = velocity.magnitude_squared() * 0.5 - mu / radius;
+    if energy >= 0.0 {
+        return None;
+    }
+
+    let semi_major_axis = -mu / (2.0 * energy);
+    let angular_momentum = offset.cross(&velocity).magnitude_squared();
+    let eccentricity = (1.0 + 2.0 * energy * angular_momentum / (mu * mu))
+        .max(0.0)
+        .sqrt();
+
+    Some(OrbitElements {
+        semi_major_axis,
+        eccentricity,
+        periapsis: semi_major_axis * (1.0 - eccentricity),
+        apoapsis: semi_major_axis * (1.0 + eccentricity),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepping_back_retraces_the_orbit() {
+        let star = Body {
+            position: Vec3::zeros(),
+            velocity: Vec3::zeros(),
+            mass: 5.0e6,
+        };
+        let planet = Body {
+            position: Vec3::new(300.0, 0.0, 0.0),
+            velocity: Vec3::new(0.0, 0.0, circular_speed(star.mass, 300.0)),
+            mass: 10.0,
+        };
+        let mut world = GravityWorld::new(vec![star, planet]);
+        for _ in 0..240 {
+            world.step(1.0 / 120.0);
+        }
+        assert!((world.bodies[1].position - planet.position).magnitude() > 10.0);
+        for _ in 0..240 {
+            world.step(-1.0 / 120.0);
+        }
+        assert!((world.bodies[1].position - planet.position).magnitude() < 0.01);
+        assert!((world.bodies[1].velocity - planet.velocity).magnitude() < 0.01);
+    }
+
+    #[test]
+    fn lagrange_points_form_equilateral_triangles() {
+        let (star, planet) = (Vec3::new(10.0, 0.0, -5.0), Vec3::new(310.0, 0.0, -5.0));
+        let [l4, l5] = lagrange_points(star, planet, 1.0);
+        for point in [l4, l5] {
+            assert!(((point - star).magnitude() - 300.0).abs() < 0.01);
+            assert!(((point - planet).magnitude() - 300.0).abs() < 0.01);
+        }
+        // Counterclockwise orbits run towards +z from +x, so L4 leads there
+        assert!(l4.z > star.z && l5.z < star.z);
+        assert_eq!(lagrange_points(star, planet, -1.0), [l5, l4]);
+    }
+}
@@ -0,0 +1,96 @@
+// Pixel-art output: the scene rendered a couple hundred rows tall, its
+// colors snapped to a small palette with ordered dithering and stretched
+// over the window with hard pixel edges
+
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// Rows the scene is rendered at, whatever the window's size
+pub const PIXEL_ART_HEIGHT: f32 = 200.0;
+// How far, in 0-1 color units, the dither pattern nudges a color before it
+// snaps; enough to blend neighbors of a four-color palette
+const DITHER_SPREAD: f32 = 0.2;
+
+// 4x4 Bayer matrix: thresholds spread as evenly as possible over every
+// small patch of pixels
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+const PICO8: [u32; 16] = [
+    0x000000, 0x1D2B53, 0x7E2553, 0x008751, 0xAB5236, 0x5F574F, 0xC2C3C7, 0xFFF1E8, 0xFF004D,
+    0xFFA300, 0xFFEC27, 0x00E436, 0x29ADFF, 0x83769C, 0xFF77A8, 0xFFCCAA,
+];
+const GAMEBOY: [u32; 4] = [0x0F380F, 0x306230, 0x8BAC0F, 0x9BBC0F];
+// CGA's high-intensity palette 1, the one of cyan and magenta
+const CGA: [u32; 4] = [0x000000, 0x55FFFF, 0xFF55FF, 0xFFFFFF];
+
+// Colors a pixel-art frame is limited to, chosen in the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelPalette {
+    Pico8,
+    GameBoy,
+    Cga,
+}
+
+impl PixelPalette {
+    pub const ALL: [PixelPalette; 3] = [
+        PixelPalette::Pico8,
+        PixelPalette::GameBoy,
+        PixelPalette::Cga,
+    ];
+
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    // Name in the config file, also the key of its menu text
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelPalette::Pico8 => "pico8",
+            PixelPalette::GameBoy => "gameboy",
+            PixelPalette::Cga => "cga",
+        }
+    }
+
+    pub fn colors(self) -> &'static [u32] {
+        match self {
+            PixelPalette::Pico8 => &PICO8,
+            PixelPalette::GameBoy => &GAMEBOY,
+            PixelPalette::Cga => &CGA,
+        }
+    }
+
+    // Snaps every pixel to the nearest color of the palette, nudged first
+    // by the dither pattern so gradients turn into patterns of the colors
+    // on either side
+    pub fn quantize(self, framebuffer: &mut Framebuffer) {
+        let palette: Vec<(u32, Vec3)> = self
+            .colors()
+            .iter()
+            .map(|&hex| (hex, Color::from_hex(hex).to_vec3()))
+            .collect();
+        framebuffer.rows_mut().for_each(|row| {
+            for (x, pixel) in row.color.iter_mut().enumerate() {
+                let threshold = (BAYER[row.y % 4][x % 4] + 0.5) / 16.0 - 0.5;
+                let color = Color::from_hex(*pixel)
+                    .to_vec3()
+                    .add_scalar(threshold * DITHER_SPREAD);
+                let nearest = palette.iter().min_by(|(_, a), (_, b)| {
+                    (a - color)
+                        .norm_squared()
+                        .total_cmp(&(b - color).norm_squared())
+                });
+                if let Some(&(hex, _)) = nearest {
+                    *pixel = hex;
+                }
+            }
+        });
+    }
+}
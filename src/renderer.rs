@@ -0,0 +1,1931 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+use rayon::prelude::*;
+use web_time::Instant;
+
+use crate::arena::{FrameArena, LineBuffers};
+use crate::atlas::{average_surface, PlanetAtlas, ATLAS_MAX_RADIUS};
+use crate::backend::Renderer;
+use crate::belt::BeltBody;
+use crate::camera::View;
+use crate::color::{Color, Palette};
+use crate::expr::{ExpressionShaders, Program};
+use crate::fragment_shaders::{
+    atlas_shader, aurora_shader, corona_shader, expression_shader, moon_shader, ring_shader,
+    ship_shader, FragmentShader, Surface,
+};
+use crate::framebuffer::{Framebuffer, RenderTarget, Shimmer, Viewport};
+use crate::hud::{draw_compass, draw_hud, draw_measure, draw_scale_bar, HudIcons};
+use crate::i18n::Strings;
+use crate::light::{Light, Occluder};
+use crate::line::{clip_segment, wu_line_into};
+use crate::map::{draw_map, map_planet, MapCache};
+use crate::material::{BlendMode, Material};
+use crate::noise::NoiseCube;
+use crate::passes::{Pass, RenderGraph};
+use crate::physics::lagrange_points;
+use crate::settings::Settings;
+use crate::shaders::vertex_shader;
+use crate::ship::ship_offset;
+use crate::simulation::{BodyState, Landing, MeshKind, Planet, Simulation, Snapshot};
+use crate::skybox::{Environment, Skybox};
+use crate::sphere::{generate_ring, generate_sphere};
+use crate::star::StarLight;
+use crate::terrain::{camera_focus, TerrainMesh};
+use crate::text::{draw_text, GLYPH_HEIGHT};
+use crate::theme::Theme;
+use crate::triangle::triangle_with_shader;
+use crate::units::round_step;
+use crate::vertex::Vertex;
+
+const SELECTION_OUTLINE: i32 = 3;
+// Aurora shell radius relative to the planet it surrounds
+pub const AURORA_SHELL: f32 = 1.08;
+// Corona shell radius relative to its star
+pub const CORONA_SHELL: f32 = 1.35;
+// Contact darkening: pixel radius, depth range of occluders, max darkening
+const AO_RADIUS: i32 = 10;
+const AO_RANGE: f32 = 120.0;
+const AO_STRENGTH: f32 = 0.45;
+// Bloom: brightness a channel must pass to glow, and how much glow is added
+const BLOOM_THRESHOLD: f32 = 0.7;
+const BLOOM_STRENGTH: f32 = 1.6;
+// Near a star: color the screen's border is tinted towards, and how much at
+// full heat
+const HEAT_TINT: u32 = 0xFF5A1E;
+const HEAT_TINT_STRENGTH: f32 = 0.65;
+// Hot air over a star, reaching this many of its radii out from the center
+const STAR_SHIMMER: f32 = 1.0;
+const STAR_SHIMMER_REACH: f32 = 1.5;
+// Exhaust behind the ship's engines in model space, how far it spreads
+// (model units too) and how strong it is at full throttle
+const ENGINE_EXHAUST: Vec4 = Vec4::new(0.0, 0.0, -4.2, 1.0);
+const ENGINE_SHIMMER_RADIUS: f32 = 1.4;
+const ENGINE_SHIMMER: f32 = 0.8;
+// Ship mesh units to pixels at zoom 1
+const SHIP_SCALE: f32 = 90.0;
+// The corona is seen through both faces of its shell, so it glows at half
+const CORONA_GLOW: f32 = 0.5;
+const LABEL_SCALE: i32 = 2;
+const LABEL_GAP: i32 = 6;
+const ORBIT_SEGMENTS: usize = 360;
+const ORBIT_THICKNESS: f32 = 1.0;
+// Half the diagonal of the L4/L5 diamonds, in logical pixels
+const LAGRANGE_MARKER: i32 = 5;
+const TRAJECTORY_THICKNESS: f32 = 2.0;
+const TRAJECTORY_COLOR: u32 = 0x66FF8C;
+const WARP_COLOR: u32 = 0x44CCFF;
+// Visibility assist: bodies drawn smaller than this radius in pixels get a
+// brighter unlit side, up to the boost for the tiniest ones
+const ASSIST_RADIUS: f32 = 60.0;
+const ASSIST_MAX_BOOST: f32 = 4.0;
+// Still shots average up to this many jittered frames; past it new frames
+// keep blending in with that weight
+pub const ACCUMULATION_SAMPLES: u32 = 64;
+// Texels along each cubemap face edge when noise layers are baked
+const NOISE_CUBE_SIZE: usize = 256;
+// Ship headlight: aim in the view frame (ahead, up the screen like the nose),
+// cone half-angles in degrees, reach in world units and color
+const HEADLIGHT_DIRECTION: Vec3 = Vec3::new(0.0, -0.2, 1.0);
+const HEADLIGHT_CONE: (f32, f32) = (25.0, 50.0);
+const HEADLIGHT_RANGE: f32 = 400.0;
+const HEADLIGHT_COLOR: Vec3 = Vec3::new(1.2, 1.15, 1.0);
+// The ship's shadow: a sphere this share of its scale, cast on bodies whose
+// center is within this many of their radii from it
+const SHIP_SHADOW_RADIUS: f32 = 0.45;
+const SHIP_SHADOW_REACH: f32 = 3.0;
+// Orbit opacity at or closer than the near distance, and at or past the far one
+const ORBIT_FADE_NEAR: (f32, f32) = (200.0, 0.9);
+const ORBIT_FADE_FAR: (f32, f32) = (1200.0, 0.2);
+// Ecliptic grid: about this many circles out to the edge of the system, one
+// spoke every `GRID_SPOKES`-th of a turn, and its color and opacity nearby,
+// fading to nothing at `GRID_FADE_FAR`
+const GRID_CIRCLES: f32 = 8.0;
+const GRID_SPOKES: usize = 12;
+const GRID_SEGMENTS: usize = 180;
+const GRID_COLOR: u32 = 0x6F86A8;
+const GRID_OPACITY: f32 = 0.35;
+const GRID_FADE_FAR: f32 = 4000.0;
+// Belt bodies closer than this and at least this many pixels wide get a mesh
+const BELT_MESH_DISTANCE: f32 = 400.0;
+const BELT_MESH_RADIUS: f32 = 3.0;
+// Share of its own color a sub-pixel body keeps even when unlit
+const IMPOSTOR_FLOOR: f32 = 0.45;
+// Planets narrower than the first radius in pixels are drawn as impostors;
+// up to the second one the impostor fades out over the mesh
+const IMPOSTOR_RADIUS: f32 = 3.0;
+const IMPOSTOR_FADE_RADIUS: f32 = 8.0;
+// How far in front of the mesh a fading impostor sits, so it passes the
+// depth test against the mesh it covers
+const IMPOSTOR_DEPTH_BIAS: f32 = 1.0;
+// Planets mirrored on the hull are lit from some side or other; this is
+// about how much of their face is in daylight on average
+const REFLECTED_DAYLIGHT: f32 = 0.5;
+
+#[derive(Default)]
+pub struct Uniforms {
+    pub model_matrix: Mat4,
+    pub time: f32,
+    // Program run by `expression_shader`, when the planet has one assigned
+    pub expression: Option<Arc<Program>>,
+    // Colors of the star being drawn
+    pub star: StarLight,
+    // Every light in the scene, placed relative to the object being drawn
+    pub lights: Vec<Light>,
+    // How many of `lights` are stars; they come first
+    pub stars: usize,
+    // Tint of the unlit side, taken from the system's stars
+    pub ambient: Vec3,
+    // Density of the body's atmosphere, which softens its terminator into
+    // a twilight band; 0 for airless bodies
+    pub atmosphere: f32,
+    // Noise seed, so bodies sharing a shader don't look identical
+    pub seed: u32,
+    // The body's noise layers baked ahead, when baking is on
+    pub noise: Option<Arc<NoiseCube>>,
+    // Surface read by `atlas_shader`, when the planet is drawn from its atlas
+    pub atlas: Option<Arc<PlanetAtlas>>,
+    // Color profile of the ring being drawn
+    pub ring: Option<Palette<'static>>,
+    // Sky and bodies around the object, for shaders that reflect them
+    pub environment: Option<Arc<Environment>>,
+    // What casts shadows on the object from its stars, placed like `lights`
+    pub occluders: Vec<Occluder>,
+}
+
+impl Uniforms {
+    // Model-space point as an offset from the object's center in the view
+    // frame (see `View::orient`), where lights and normals live
+    pub fn to_view(&self, position: Vec3) -> Vec3 {
+        (self.model_matrix * Vec4::new(position.x, position.y, position.z, 0.0)).xyz()
+    }
+}
+
+// Everything that has to stay the same between frames for accumulation to
+// keep averaging instead of starting over
+#[derive(PartialEq)]
+struct Shot {
+    view: View,
+    time: f32,
+    settings: Settings,
+    selected: Option<usize>,
+    // Sum of every terrain mesh's generation, so new patches start over
+    terrain: u32,
+}
+
+// What every pass of one frame reads, worked out before the first runs
+pub struct Frame<'a> {
+    pub simulation: &'a Simulation,
+    pub snapshot: &'a Snapshot,
+    pub settings: &'a Settings,
+    // Window-sized view the labels are placed with
+    pub view: View,
+    // Scaled to the scene's resolution and jittered for accumulation
+    pub scene_view: View,
+    // `scene_view` without the jitter, for orbits and trajectories
+    pub line_view: View,
+    pub photo: bool,
+    // Photo mode or a tour flying the camera: the frame is meant to be
+    // looked at as a picture
+    pub cinematic: bool,
+    pub samples: u32,
+    pub theme: Theme,
+}
+
+impl<'a> Frame<'a> {
+    // `ui_scale` is the screen's pixels per logical pixel and `scale` the
+    // scene's width over the screen's; `jitter` moves the camera inside a
+    // pixel for accumulated sample number `samples`
+    pub fn new(
+        simulation: &'a Simulation,
+        snapshot: &'a Snapshot,
+        settings: &'a Settings,
+        ui_scale: f32,
+        scale: f32,
+        jitter: Vec2,
+        samples: u32,
+    ) -> Self {
+        let view = snapshot
+            .view
+            .magnified(snapshot.view.fov_magnification() * ui_scale);
+        let scene_view = View {
+            jitter,
+            ..view.magnified(scale)
+        };
+        // Lines skip the jitter: they are already anti-aliased and stay out
+        // of the accumulated average
+        let line_view = View {
+            jitter: Vec2::zeros(),
+            ..scene_view
+        };
+        Frame {
+            simulation,
+            snapshot,
+            settings,
+            view,
+            scene_view,
+            line_view,
+            // Photo mode leaves the ship behind along with the rest of the UI
+            photo: simulation.photo_mode(),
+            cinematic: simulation.photo_mode() || simulation.tour.is_some(),
+            samples,
+            theme: settings.theme(),
+        }
+    }
+}
+
+// World-space lights for one frame: the stars, then the headlight and the
+// fill light
+pub struct SceneLights {
+    pub lights: Vec<Light>,
+    // How many of `lights` are stars
+    pub stars: usize,
+    pub ambient: Vec3,
+    visibility_assist: bool,
+    // Where the ship is, while it is drawn, to shadow what it flies over
+    ship: Option<Vec3>,
+}
+
+impl SceneLights {
+    // Every star is a point light with its temperature's color; the unlit
+    // side takes their average tint
+    pub fn gather(frame: &Frame, fill_light: Option<Light>) -> Self {
+        let (simulation, snapshot) = (frame.simulation, frame.snapshot);
+        let mut lights = Vec::new();
+        let bodies = simulation.planets.iter().zip(&snapshot.bodies);
+        for (planet, body) in bodies {
+            if let Some(temperature) = planet.temperature {
+                let star = StarLight::from_temperature(temperature);
+                lights.push(Light::point(body.position, star.light));
+            }
+        }
+        let stars = lights.len();
+
+        let ambient = if lights.is_empty() {
+            Vec3::new(1.0, 1.0, 1.0)
+        } else {
+            let total = lights
+                .iter()
+                .fold(Vec3::zeros(), |sum, light| sum + light.color);
+            total / lights.len() as f32
+        };
+
+        let ship = ship_position(&frame.scene_view, snapshot.time);
+        if simulation.ship.headlight {
+            let headlight = Light::spot(ship, HEADLIGHT_DIRECTION, HEADLIGHT_CONE, HEADLIGHT_COLOR);
+            lights.push(headlight.with_range(HEADLIGHT_RANGE));
+        }
+        lights.extend(fill_light);
+
+        SceneLights {
+            lights,
+            stars,
+            ambient,
+            visibility_assist: frame.settings.visibility_assist,
+            ship: (!frame.photo).then_some(ship),
+        }
+    }
+
+    // Dim white light from the camera that keeps night sides readable; 0 turns it off
+    pub fn fill_light(intensity: f32) -> Option<Light> {
+        (intensity > 0.0)
+            .then(|| Light::directional(Vec3::new(0.0, 0.0, -1.0), Vec3::repeat(intensity)))
+    }
+
+    // The lights as seen from an object centered at `origin`
+    pub fn relative_to(&self, origin: Vec3, view: &View) -> Vec<Light> {
+        self.lights
+            .iter()
+            .map(|light| light.relative_to(origin, view))
+            .collect()
+    }
+
+    // The ship as a shadow caster for a body of `radius` centered at `origin`,
+    // in the same frame as `relative_to`; none once it is far from the body
+    pub fn occluders(&self, origin: Vec3, radius: f32, view: &View) -> Vec<Occluder> {
+        self.ship
+            .filter(|ship| (ship - origin).magnitude() < radius * SHIP_SHADOW_REACH)
+            .map(|ship| Occluder {
+                center: view.orient(ship - origin),
+                radius: SHIP_SCALE * SHIP_SHADOW_RADIUS * view.zoom,
+            })
+            .into_iter()
+            .collect()
+    }
+
+    // Ambient tint for a body drawn `radius` pixels wide; close-ups keep the
+    // physical darkness, far specks are lifted when the assist is on
+    pub fn ambient_for(&self, radius: f32) -> Vec3 {
+        if !self.visibility_assist {
+            return self.ambient;
+        }
+        let smallness = (1.0 - radius / ASSIST_RADIUS).clamp(0.0, 1.0);
+        self.ambient * (1.0 + (ASSIST_MAX_BOOST - 1.0) * smallness)
+    }
+}
+
+impl Default for SceneLights {
+    fn default() -> Self {
+        SceneLights {
+            lights: Vec::new(),
+            stars: 0,
+            ambient: Vec3::new(1.0, 1.0, 1.0),
+            visibility_assist: false,
+            ship: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct History {
+    colors: Vec<[f32; 3]>,
+    samples: u32,
+    shot: Option<Shot>,
+}
+
+pub struct Meshes {
+    star: Vec<Vertex>,
+    rocky: Vec<Vertex>,
+    gas: Vec<Vertex>,
+    moon: Vec<Vertex>,
+    // Coarse sphere for belt bodies close enough to need more than a disc
+    belt: Vec<Vertex>,
+    ship: Vec<Vertex>,
+}
+
+impl Meshes {
+    pub fn new(ship: Vec<Vertex>) -> Self {
+        Meshes::with_detail(ship, 1)
+    }
+
+    // Spheres `detail` times as finely divided, for offline stills
+    pub fn with_detail(ship: Vec<Vertex>, detail: u32) -> Self {
+        Meshes {
+            star: generate_sphere(1.0, 70 * detail),
+            rocky: generate_sphere(1.0, 50 * detail),
+            gas: generate_sphere(1.0, 60 * detail),
+            moon: generate_sphere(1.0, 35 * detail),
+            belt: generate_sphere(1.0, 10 * detail),
+            ship,
+        }
+    }
+
+    pub fn get(&self, kind: MeshKind) -> &[Vertex] {
+        match kind {
+            MeshKind::Star => &self.star,
+            MeshKind::Rocky => &self.rocky,
+            MeshKind::GasGiant => &self.gas,
+            MeshKind::Moon => &self.moon,
+        }
+    }
+
+    pub fn ship(&self) -> &[Vertex] {
+        &self.ship
+    }
+}
+
+// Per-frame counters, reset at the start of every `render_frame`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub triangles: usize,
+    pub fragments: usize,
+    pub culled_objects: usize,
+}
+
+impl FrameStats {
+    pub fn add(&mut self, (triangles, fragments): (usize, usize)) {
+        self.triangles += triangles;
+        self.fragments += fragments;
+    }
+}
+
+// The reference renderer: every pass rasterized and shaded on the CPU
+pub struct SoftwareRenderer {
+    // The scene, rendered at the resolution scale from the settings and
+    // shown over the whole screen
+    pub scene: RenderTarget,
+    // Window-sized image: the scene stretched to fit, plus HUD and menus
+    pub screen: Framebuffer,
+    pub stats: FrameStats,
+    // UI text in the chosen language, shared with the menus drawn on `screen`
+    strings: Rc<Strings>,
+    graph: RenderGraph,
+    skybox: Skybox,
+    // The skybox shrunk for reflections, rebaked when it changes
+    environment: Environment,
+    meshes: Meshes,
+    terrain: HashMap<usize, TerrainMesh>,
+    // Ring geometry per (planet index, ring index), built on first sight
+    rings: HashMap<(usize, usize), Vec<Vertex>>,
+    // Baked noise layers per planet index; None computes them per fragment
+    noise: Option<HashMap<usize, Arc<NoiseCube>>>,
+    // Baked surface atlases per planet index; None shades every planet
+    // procedurally at any distance
+    atlases: Option<HashMap<usize, Arc<PlanetAtlas>>>,
+    // Atlas for the map view when the planet has none baked
+    map: MapCache,
+    expressions: ExpressionShaders,
+    // Average look of each planet, computed the first time it is an impostor
+    impostors: HashMap<usize, Surface>,
+    icons: HudIcons,
+    lights: SceneLights,
+    fill_light: Option<Light>,
+    history: History,
+    // Blended draws of the running pass, held back until its solid geometry
+    // is down
+    queue: Vec<Queued>,
+    // Scratch buffers every draw reuses instead of allocating its own
+    arena: FrameArena,
+    // The clock film grain runs on
+    started: Instant,
+}
+
+// A draw waiting in `SoftwareRenderer::queue`
+struct Queued {
+    uniforms: Uniforms,
+    mesh: MeshRef,
+    material: Material,
+    id: u32,
+}
+
+// Geometry a queued draw refers to, looked up when it is drawn
+#[derive(Clone, Copy)]
+enum MeshRef {
+    Body(MeshKind),
+    // Planet index and ring index, as keyed in `SoftwareRenderer::rings`
+    Ring(usize, usize),
+}
+
+impl SoftwareRenderer {
+    pub fn new(
+        width: usize,
+        height: usize,
+        skybox: Skybox,
+        ship_vertices: Vec<Vertex>,
+        icons: HudIcons,
+        strings: Rc<Strings>,
+    ) -> Self {
+        let mut scene = RenderTarget::new(width, height);
+        scene.framebuffer.set_background_color(0x000000);
+
+        SoftwareRenderer {
+            scene,
+            screen: Framebuffer::new(width, height),
+            stats: FrameStats::default(),
+            strings,
+            graph: RenderGraph::default(),
+            environment: skybox.environment(),
+            skybox,
+            meshes: Meshes::new(ship_vertices),
+            terrain: HashMap::new(),
+            rings: HashMap::new(),
+            noise: None,
+            atlases: None,
+            map: MapCache::default(),
+            expressions: ExpressionShaders::new(&[]),
+            impostors: HashMap::new(),
+            icons,
+            lights: SceneLights::default(),
+            fill_light: None,
+            history: History::default(),
+            queue: Vec::new(),
+            arena: FrameArena::default(),
+            started: Instant::now(),
+        }
+    }
+
+    // Blocks until every terrain build in flight is done
+    pub fn wait_for_terrain(&mut self) {
+        for terrain in self.terrain.values_mut() {
+            terrain.wait();
+        }
+    }
+
+    // Swaps the sphere meshes for ones `detail` times finer
+    pub fn set_mesh_detail(&mut self, detail: u32) {
+        let ship = std::mem::take(&mut self.meshes.ship);
+        self.meshes = Meshes::with_detail(ship, detail);
+    }
+
+    // Solid draws go straight in, since the depth test sorts them; blended
+    // ones wait for `flush` so they land over everything they can cover
+    fn submit(&mut self, uniforms: Uniforms, mesh: MeshRef, material: Material) {
+        let id = self.scene.framebuffer.current_id();
+        if material.is_opaque() {
+            let vertices = mesh_vertices(&self.meshes, &self.rings, mesh);
+            let counts = draw(
+                &mut self.scene.framebuffer,
+                &mut self.arena,
+                &uniforms,
+                vertices,
+                &material,
+            );
+            self.stats.add(counts);
+        } else {
+            self.queue.push(Queued {
+                uniforms,
+                mesh,
+                material,
+                id,
+            });
+        }
+    }
+
+    // Draws what the pass queued sorted by material: mixing surfaces, then
+    // the light added over them. Each kind keeps the order it was queued in
+    fn flush(&mut self) {
+        let mut queue = std::mem::take(&mut self.queue);
+        queue.sort_by_key(|queued| queued.material.order());
+        for queued in queue.drain(..) {
+            self.scene.framebuffer.set_current_id(queued.id);
+            let vertices = mesh_vertices(&self.meshes, &self.rings, queued.mesh);
+            let counts = draw(
+                &mut self.scene.framebuffer,
+                &mut self.arena,
+                &queued.uniforms,
+                vertices,
+                &queued.material,
+            );
+            self.stats.add(counts);
+        }
+        self.scene.framebuffer.set_current_id(0);
+        self.queue = queue;
+    }
+
+    fn baked_noise(&mut self, index: usize, planet: &Planet) -> Option<Arc<NoiseCube>> {
+        if planet.noise_layers.is_empty() {
+            return None;
+        }
+        let cubes = self.noise.as_mut()?;
+        let cube = cubes.entry(index).or_insert_with(|| {
+            Arc::new(NoiseCube::bake(
+                planet.noise_layers,
+                index as u32,
+                NOISE_CUBE_SIZE,
+            ))
+        });
+        Some(Arc::clone(cube))
+    }
+
+    fn baked_atlas(&mut self, index: usize, planet: &Planet) -> Option<Arc<PlanetAtlas>> {
+        let surface = planet.surface?;
+        let atlases = self.atlases.as_mut()?;
+        let atlas = atlases
+            .entry(index)
+            .or_insert_with(|| Arc::new(PlanetAtlas::bake(surface, index as u32)));
+        Some(Arc::clone(atlas))
+    }
+
+    fn run_pass(&mut self, pass: Pass, frame: &Frame) {
+        let (simulation, snapshot, settings) = (frame.simulation, frame.snapshot, frame.settings);
+        let scene_view = &frame.scene_view;
+        let time = snapshot.time;
+        match pass {
+            Pass::Skybox => {
+                self.skybox
+                    .draw(&mut self.scene.framebuffer, time, scene_view.roll);
+            }
+            Pass::Opaque => {
+                let bodies = simulation.planets.iter().zip(&snapshot.bodies);
+                for (index, (planet, body)) in bodies.enumerate() {
+                    let landing = snapshot.landing.filter(|landing| landing.planet == index);
+                    self.draw_planet(index, planet, body, scene_view, time, landing);
+                }
+                if !frame.photo {
+                    self.draw_ship(simulation, snapshot, scene_view);
+                }
+                self.draw_fleet(simulation, snapshot, scene_view);
+            }
+            Pass::Particles => {
+                self.draw_belt(&simulation.belt, scene_view, time);
+                if settings.trojans {
+                    self.draw_belt(&simulation.trojans, scene_view, time);
+                }
+            }
+            Pass::Transparent => {
+                // Translucent rings blend over whatever ended up behind them,
+                // so they go after every body, farthest first
+                let mut translucent: Vec<(usize, f32)> = snapshot
+                    .bodies
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| {
+                        let rings = &simulation.planets[*index].rings;
+                        rings.iter().any(|ring| ring.opacity < 1.0)
+                    })
+                    .map(|(index, body)| (index, (body.position - scene_view.position).magnitude()))
+                    .collect();
+                translucent.sort_by(|a, b| b.1.total_cmp(&a.1));
+                for (index, _) in translucent {
+                    let (planet, body) = (&simulation.planets[index], &snapshot.bodies[index]);
+                    self.draw_rings(index, planet, body, scene_view, time, true);
+                }
+            }
+            Pass::Post => {
+                post_process(&mut self.scene.framebuffer, frame);
+                if settings.accumulation {
+                    let pixels = self.scene.framebuffer.width * self.scene.framebuffer.height;
+                    self.history.colors.resize(pixels, [0.0; 3]);
+                    self.scene
+                        .framebuffer
+                        .accumulate(&mut self.history.colors, frame.samples);
+                }
+                let seconds = self.started.elapsed().as_secs_f32();
+                lens_effects(&mut self.scene.framebuffer, frame, seconds);
+            }
+            Pass::Overlay => {
+                // Planets drawn from an atlas already have one to show
+                let map = match map_planet(simulation) {
+                    Some(index) => self
+                        .baked_atlas(index, &simulation.planets[index])
+                        .or_else(|| self.map.atlas(simulation)),
+                    None => None,
+                };
+                draw_overlay(
+                    &mut self.scene.framebuffer,
+                    &mut self.screen,
+                    &mut self.arena.lines,
+                    frame,
+                    &self.icons,
+                    &self.strings,
+                    map.as_deref(),
+                )
+            }
+        }
+    }
+
+    // Uniforms for an object centered at `origin`, lit by the frame's lights
+    fn uniforms(&self, model_matrix: Mat4, time: f32, origin: Vec3, view: &View) -> Uniforms {
+        Uniforms {
+            model_matrix,
+            time,
+            expression: None,
+            star: StarLight::default(),
+            lights: self.lights.relative_to(origin, view),
+            stars: self.lights.stars,
+            ambient: self.lights.ambient,
+            atmosphere: 0.0,
+            seed: 0,
+            noise: None,
+            atlas: None,
+            ring: None,
+            environment: None,
+            occluders: Vec::new(),
+        }
+    }
+
+    // `landing` swaps the smooth mesh for displaced terrain
+    fn draw_planet(
+        &mut self,
+        index: usize,
+        planet: &Planet,
+        body: &BodyState,
+        view: &View,
+        time: f32,
+        landing: Option<Landing>,
+    ) {
+        let viewport = self.scene.framebuffer.viewport;
+        let screen_position = world_to_screen(body.position, view, &viewport);
+        let scale = planet.scale * view.zoom;
+        let extent = planet
+            .rings
+            .iter()
+            .fold(scale, |extent, ring| extent.max(scale * ring.outer_radius));
+
+        if is_offscreen(screen_position, extent, &viewport) {
+            self.stats.culled_objects += 1;
+        } else {
+            let rotation = planet.rotation(time);
+            let model_matrix = create_model_matrix(screen_position, scale, rotation);
+            // Tiny planets are a shaded disc; a little larger, the disc fades
+            // out over the mesh. Stars always keep their mesh and corona
+            let mesh_weight = if planet.temperature.is_none() {
+                ((scale - IMPOSTOR_RADIUS) / (IMPOSTOR_FADE_RADIUS - IMPOSTOR_RADIUS))
+                    .clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            let expression = self.expressions.get(&planet.name);
+            // Far planets read their atlas; close-ups and planets without
+            // one run the full shader
+            let atlas = if expression.is_none() && scale <= ATLAS_MAX_RADIUS {
+                self.baked_atlas(index, planet)
+            } else {
+                None
+            };
+            let (shader, noise): (FragmentShader, _) = if expression.is_some() {
+                (expression_shader, None)
+            } else if atlas.is_some() {
+                (atlas_shader, None)
+            } else {
+                (planet.shader, self.baked_noise(index, planet))
+            };
+            let uniforms = Uniforms {
+                expression,
+                star: planet
+                    .temperature
+                    .map_or_else(StarLight::default, StarLight::from_temperature),
+                ambient: self.lights.ambient_for(scale),
+                atmosphere: planet.atmosphere_density,
+                seed: index as u32,
+                noise,
+                atlas,
+                occluders: self.lights.occluders(body.position, planet.scale, view),
+                ..self.uniforms(model_matrix, time, body.position, view)
+            };
+            self.scene.framebuffer.set_current_id(object_id(index));
+            // The terrain is built on a worker thread; until it is ready the
+            // body keeps its regular sphere
+            let terrain = match (landing, planet.height) {
+                (Some(landing), Some(height)) => {
+                    // The camera comes down from the collision radius to
+                    // skim the surface point at the middle of the screen
+                    let (center_x, center_y) = viewport.center();
+                    let offset =
+                        Vec2::new(center_x - screen_position.x, center_y - screen_position.y);
+                    let above =
+                        (planet.collision_radius / planet.scale - 1.0) * (1.0 - landing.blend);
+                    let terrain = self
+                        .terrain
+                        .entry(index)
+                        .or_insert_with(|| TerrainMesh::new(height, index as u32));
+                    terrain.update(camera_focus(offset / scale, above, rotation));
+                    terrain.is_ready().then_some((landing, &*terrain))
+                }
+                _ => None,
+            };
+            match terrain {
+                Some((landing, terrain)) => {
+                    // Bodies are seen from along the view axis as if from
+                    // infinitely far, so the horizon is the limb and
+                    // patches on the far side are skipped
+                    let eye = camera_focus(Vec2::zeros(), 0.0, rotation);
+                    // Taken out of the arena while the draw borrows it
+                    let mut vertices = std::mem::take(&mut self.arena.terrain);
+                    let hidden =
+                        terrain.displaced(landing.blend, eye, f32::INFINITY, &mut vertices);
+                    self.stats.culled_objects += hidden;
+                    self.stats.add(draw(
+                        &mut self.scene.framebuffer,
+                        &mut self.arena,
+                        &uniforms,
+                        &vertices,
+                        &Material::opaque(shader),
+                    ));
+                    self.arena.terrain = vertices;
+                }
+                None if mesh_weight > 0.0 => self.stats.add(draw(
+                    &mut self.scene.framebuffer,
+                    &mut self.arena,
+                    &uniforms,
+                    self.meshes.get(planet.mesh),
+                    &Material::opaque(shader),
+                )),
+                None => {}
+            }
+            if mesh_weight < 1.0 {
+                let surface = self.impostor_surface(index, planet);
+                draw_impostor(
+                    &mut self.scene.framebuffer,
+                    screen_position,
+                    scale,
+                    surface,
+                    1.0 - mesh_weight,
+                    &uniforms,
+                );
+            }
+
+            if planet.has_aurora && mesh_weight > 0.0 {
+                let aurora_matrix =
+                    create_model_matrix(screen_position, scale * AURORA_SHELL, rotation);
+                let aurora_uniforms = Uniforms {
+                    seed: index as u32,
+                    ..self.uniforms(aurora_matrix, time, body.position, view)
+                };
+                self.submit(
+                    aurora_uniforms,
+                    MeshRef::Body(planet.mesh),
+                    Material::additive(aurora_shader),
+                );
+            }
+
+            if planet.temperature.is_some() {
+                let corona_uniforms = Uniforms {
+                    model_matrix: create_model_matrix(
+                        screen_position,
+                        scale * CORONA_SHELL,
+                        rotation,
+                    ),
+                    ..uniforms
+                };
+                self.submit(
+                    corona_uniforms,
+                    MeshRef::Body(planet.mesh),
+                    Material {
+                        emissive: CORONA_GLOW,
+                        ..Material::additive(corona_shader)
+                    },
+                );
+            }
+
+            self.draw_rings(index, planet, body, view, time, false);
+            self.scene.framebuffer.set_current_id(0);
+        }
+
+        for (moon_index, (moon, &moon_world)) in
+            planet.moons.iter().zip(&body.moon_positions).enumerate()
+        {
+            let moon_screen = world_to_screen(moon_world, view, &viewport);
+            let moon_scale = moon.scale * view.zoom;
+            if is_offscreen(moon_screen, moon_scale, &viewport) {
+                self.stats.culled_objects += 1;
+                continue;
+            }
+            let moon_matrix = create_model_matrix(moon_screen, moon_scale, moon.rotation(time));
+            let mut moon_uniforms = Uniforms {
+                ambient: self.lights.ambient_for(moon_scale),
+                seed: index as u32 + moon_index as u32 * 17,
+                ..self.uniforms(moon_matrix, time, moon_world, view)
+            };
+            // Starlight only: the fill light and headlight would wash out
+            // the crescent and gibbous phases
+            moon_uniforms.lights.truncate(self.lights.stars);
+            self.scene
+                .framebuffer
+                .set_current_id(moon_id(index, moon_index));
+            self.stats.add(draw(
+                &mut self.scene.framebuffer,
+                &mut self.arena,
+                &moon_uniforms,
+                self.meshes.get(moon.mesh),
+                &Material::opaque(moon.shader),
+            ));
+            self.scene.framebuffer.set_current_id(0);
+        }
+    }
+
+    // Either the solid rings, drawn with their planet, or the translucent
+    // ones, blended over the finished scene
+    fn draw_rings(
+        &mut self,
+        index: usize,
+        planet: &Planet,
+        body: &BodyState,
+        view: &View,
+        time: f32,
+        translucent: bool,
+    ) {
+        let viewport = self.scene.framebuffer.viewport;
+        let screen_position = world_to_screen(body.position, view, &viewport);
+        let scale = planet.scale * view.zoom;
+        let rings = planet
+            .rings
+            .iter()
+            .enumerate()
+            .filter(|(_, ring)| (ring.opacity < 1.0) == translucent);
+
+        for (slot, ring) in rings {
+            if is_offscreen(screen_position, scale * ring.outer_radius, &viewport) {
+                continue;
+            }
+            let ring_matrix = create_model_matrix(screen_position, scale, ring.rotation(time));
+            let ring_uniforms = Uniforms {
+                ambient: self.lights.ambient_for(scale),
+                seed: index as u32,
+                ring: Some(ring.colors),
+                ..self.uniforms(ring_matrix, time, body.position, view)
+            };
+            self.rings
+                .entry((index, slot))
+                .or_insert_with(|| generate_ring(ring.inner_radius, ring.outer_radius, 120));
+            // Flat, so both faces are seen
+            let material = if translucent {
+                Material::blended(ring_shader, ring.opacity)
+            } else {
+                self.scene.framebuffer.set_current_id(ring_id(index));
+                Material {
+                    two_sided: true,
+                    ..Material::opaque(ring_shader)
+                }
+            };
+            self.submit(ring_uniforms, MeshRef::Ring(index, slot), material);
+        }
+    }
+
+    // Planets without a surface function fall back to their orbit color,
+    // which is picked to match how they look
+    fn impostor_surface(&mut self, index: usize, planet: &Planet) -> Surface {
+        *self
+            .impostors
+            .entry(index)
+            .or_insert_with(|| match planet.surface {
+                Some(surface) => average_surface(surface, index as u32),
+                None => Surface::matte(Color::from_hex(planet.orbit_color).to_vec3()),
+            })
+    }
+
+    // Belt bodies are star-lit discs shaded like spheres, or single points
+    // when smaller than a pixel; only near the camera do they get a mesh
+    fn draw_belt(&mut self, belt: &[BeltBody], view: &View, time: f32) {
+        let viewport = self.scene.framebuffer.viewport;
+        for (index, body) in belt.iter().enumerate() {
+            let world = body.position(time);
+            let screen = world_to_screen(world, view, &viewport);
+            let radius = body.scale * view.zoom;
+            if is_offscreen(screen, radius.max(1.0), &viewport) {
+                self.stats.culled_objects += 1;
+                continue;
+            }
+
+            let mut uniforms = self.uniforms(Mat4::identity(), time, world, view);
+            uniforms.lights.truncate(self.lights.stars);
+            uniforms.ambient = self.lights.ambient_for(radius);
+            if screen.z < BELT_MESH_DISTANCE && radius >= BELT_MESH_RADIUS {
+                let rotation = Vec3::new(0.0, time * 0.3 + body.phase, body.phase);
+                let lit = Uniforms {
+                    model_matrix: create_model_matrix(screen, radius, rotation),
+                    seed: index as u32,
+                    ..uniforms
+                };
+                self.stats.add(draw(
+                    &mut self.scene.framebuffer,
+                    &mut self.arena,
+                    &lit,
+                    &self.meshes.belt,
+                    &Material::opaque(moon_shader),
+                ));
+            } else {
+                let surface = Surface::matte(body.color);
+                self.scene.framebuffer.set_current_id(0);
+                draw_impostor(
+                    &mut self.scene.framebuffer,
+                    screen,
+                    radius,
+                    surface,
+                    1.0,
+                    &uniforms,
+                );
+            }
+        }
+    }
+
+    // The sky as seen from the ship, with every body painted in where it
+    // hangs: stars in their own color, planets in their average one
+    fn ship_environment(
+        &mut self,
+        simulation: &Simulation,
+        bodies: &[BodyState],
+        view: &View,
+        ship_world: Vec3,
+    ) -> Environment {
+        let mut environment = self.environment.rolled(view.roll);
+        for (index, (planet, body)) in simulation.planets.iter().zip(bodies).enumerate() {
+            let offset = body.position - ship_world;
+            let distance = offset.magnitude();
+            if distance <= planet.scale {
+                continue;
+            }
+            let color = match planet.temperature {
+                Some(temperature) => StarLight::from_temperature(temperature).surface,
+                None => {
+                    let surface = self.impostor_surface(index, planet);
+                    surface.albedo * REFLECTED_DAYLIGHT + surface.emission
+                }
+            };
+            let radius = (planet.scale / distance).asin();
+            environment.add_disc(view.orient(offset), radius, color);
+        }
+        environment
+    }
+
+    fn draw_ship(&mut self, simulation: &Simulation, snapshot: &Snapshot, view: &View) {
+        let viewport = self.scene.framebuffer.viewport;
+        let (ship_world, ship_matrix) =
+            ship_transform(view, snapshot.ship_bank, snapshot.time, &viewport);
+        self.draw_ship_at(simulation, snapshot, view, ship_world, ship_matrix);
+    }
+
+    // The other players' ships, level and facing away like this one's
+    fn draw_fleet(&mut self, simulation: &Simulation, snapshot: &Snapshot, view: &View) {
+        let viewport = self.scene.framebuffer.viewport;
+        let scale = SHIP_SCALE * view.zoom;
+        for &world in &simulation.fleet {
+            let screen = world_to_screen(world, view, &viewport);
+            if is_offscreen(screen, scale, &viewport) {
+                self.stats.culled_objects += 1;
+                continue;
+            }
+            let matrix = create_model_matrix(screen, scale, Vec3::new(0.2, PI, 0.0));
+            self.draw_ship_at(simulation, snapshot, view, world, matrix);
+        }
+    }
+
+    fn draw_ship_at(
+        &mut self,
+        simulation: &Simulation,
+        snapshot: &Snapshot,
+        view: &View,
+        ship_world: Vec3,
+        ship_matrix: Mat4,
+    ) {
+        let time = snapshot.time;
+        let environment = self.ship_environment(simulation, &snapshot.bodies, view, ship_world);
+        let ship_uniforms = Uniforms {
+            environment: Some(Arc::new(environment)),
+            ..self.uniforms(ship_matrix, time, ship_world, view)
+        };
+        self.stats.add(draw(
+            &mut self.scene.framebuffer,
+            &mut self.arena,
+            &ship_uniforms,
+            self.meshes.ship(),
+            &Material::opaque(ship_shader),
+        ));
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn render_frame(&mut self, simulation: &Simulation, snapshot: &Snapshot, settings: &Settings) {
+        let full = Viewport::full(self.screen.width, self.screen.height);
+        self.scene.fit(full, settings.scene_scale(full.height));
+        let shot = Shot {
+            view: snapshot.view,
+            time: snapshot.time,
+            settings: *settings,
+            selected: simulation.selected,
+            terrain: self
+                .terrain
+                .values()
+                .fold(0, |sum, terrain| sum.wrapping_add(terrain.generation())),
+        };
+        let pixels = self.scene.framebuffer.width * self.scene.framebuffer.height;
+        let still = settings.accumulation
+            && self.history.shot.as_ref() == Some(&shot)
+            && self.history.colors.len() == pixels;
+        let samples = if still {
+            (self.history.samples + 1).min(ACCUMULATION_SAMPLES)
+        } else {
+            1
+        };
+        // Each extra sample looks through a different spot inside the pixel
+        let jitter = if samples > 1 {
+            let index = self.history.samples;
+            Vec2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+        } else {
+            Vec2::zeros()
+        };
+        let scale = self.scene.framebuffer.width as f32 / self.screen.width as f32;
+        let frame = Frame::new(
+            simulation,
+            snapshot,
+            settings,
+            self.screen.ui_scale,
+            scale,
+            jitter,
+            samples,
+        );
+
+        self.lights = SceneLights::gather(&frame, self.fill_light);
+        self.stats = FrameStats::default();
+        self.arena.reset();
+        // The skybox repaints the whole frame, so clearing its colors first
+        // would be wasted
+        if self.graph.is_enabled(Pass::Skybox) && self.skybox.covers_frame() {
+            self.scene.framebuffer.clear_keeping_color();
+        } else {
+            self.scene.framebuffer.clear();
+        }
+
+        for pass in Pass::ALL {
+            if !self.graph.is_enabled(pass) {
+                self.graph.record(pass, Duration::ZERO);
+                continue;
+            }
+            let start = Instant::now();
+            self.run_pass(pass, &frame);
+            self.flush();
+            self.graph.record(pass, start.elapsed());
+        }
+
+        self.history.samples = samples;
+        self.history.shot = Some(shot);
+        self.scene.present(&mut self.screen);
+        self.screen.composite_overlay();
+    }
+
+    fn screen(&self) -> &Framebuffer {
+        &self.screen
+    }
+
+    fn screen_mut(&mut self) -> &mut Framebuffer {
+        &mut self.screen
+    }
+
+    fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    fn graph(&self) -> &RenderGraph {
+        &self.graph
+    }
+
+    fn graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.graph
+    }
+
+    fn set_skybox(&mut self, skybox: Skybox) {
+        self.environment = skybox.environment();
+        self.skybox = skybox;
+    }
+
+    fn set_expression_shaders(&mut self, expressions: ExpressionShaders) {
+        self.expressions = expressions;
+    }
+
+    fn set_fill_light(&mut self, intensity: f32) {
+        self.fill_light = SceneLights::fill_light(intensity);
+    }
+
+    // Trades a few megabytes per planet for skipping high-octave fbm in
+    // every fragment; the cubemaps are baked here for the planets given and
+    // on first sight for any added later
+    fn set_noise_baking(&mut self, enabled: bool, planets: &[Planet]) {
+        self.noise = enabled.then(HashMap::new);
+        for (index, planet) in planets.iter().enumerate() {
+            self.baked_noise(index, planet);
+        }
+    }
+
+    // Bakes every planet with a static surface into an atlas now, and any
+    // added later on first sight
+    fn set_atlas_baking(&mut self, enabled: bool, planets: &[Planet]) {
+        self.atlases = enabled.then(HashMap::new);
+        for (index, planet) in planets.iter().enumerate() {
+            self.baked_atlas(index, planet);
+        }
+    }
+
+    fn reload_expression_shaders(&mut self) {
+        self.expressions.reload_if_changed();
+    }
+}
+
+// Screen-space effects on the finished scene, in order: shimmer over hot
+// air, contact darkening, bloom, the heated border near a star, depth of
+// field (photo mode only), edge smoothing and the CRT filter
+pub fn post_process(framebuffer: &mut Framebuffer, frame: &Frame) {
+    let settings = frame.settings;
+    let heat = frame.snapshot.heat;
+    framebuffer.keep_unprocessed();
+    let spots = shimmer_spots(frame, &framebuffer.viewport);
+    framebuffer.shimmer(frame.snapshot.time, heat, &spots);
+    framebuffer.ambient_occlusion(AO_RADIUS, AO_RANGE, AO_STRENGTH);
+    if settings.bloom {
+        framebuffer.bloom(BLOOM_THRESHOLD, BLOOM_STRENGTH * settings.bloom_intensity);
+    }
+    if heat > 0.0 {
+        framebuffer.tint_edges(HEAT_TINT, heat * HEAT_TINT_STRENGTH);
+    }
+    if frame.photo {
+        let focus = framebuffer.center_depth();
+        framebuffer.depth_of_field(focus, settings.depth_of_field);
+    }
+    if settings.antialiasing {
+        framebuffer.smooth_edges();
+    }
+    if settings.crt {
+        // Kept again, so the tube shows every effect above
+        framebuffer.keep_unprocessed();
+        framebuffer.crt();
+    }
+}
+
+// Vignette and film grain, in photo mode and tours only, then the
+// pixel-art palette. They go after accumulation so the grain isn't
+// averaged away and the palette holds, and the grain moves with `seconds`
+// of real time, so it stays alive while the world holds still
+pub fn lens_effects(framebuffer: &mut Framebuffer, frame: &Frame, seconds: f32) {
+    if frame.cinematic {
+        framebuffer.vignette(frame.settings.vignette);
+        framebuffer.film_grain(seconds, frame.settings.film_grain);
+    }
+    // Last, so nothing adds colors outside the palette
+    if let Some(palette) = frame.settings.pixel_art {
+        palette.quantize(framebuffer);
+    }
+}
+
+// Where the air shimmers: over every star and behind the ship's engines
+// while it thrusts
+fn shimmer_spots(frame: &Frame, viewport: &Viewport) -> Vec<Shimmer> {
+    let (simulation, snapshot, view) = (frame.simulation, frame.snapshot, &frame.scene_view);
+    let mut spots: Vec<Shimmer> = simulation
+        .planets
+        .iter()
+        .zip(&snapshot.bodies)
+        .filter(|(planet, _)| planet.temperature.is_some())
+        .map(|(planet, body)| {
+            let center = world_to_screen(body.position, view, viewport);
+            Shimmer {
+                x: center.x,
+                y: center.y,
+                radius: planet.scale * view.zoom * STAR_SHIMMER_REACH,
+                strength: STAR_SHIMMER,
+            }
+        })
+        .filter(|spot| !is_offscreen(Vec3::new(spot.x, spot.y, 0.0), spot.radius, viewport))
+        .collect();
+
+    if !frame.photo && snapshot.throttle > 0.01 {
+        let (_, ship_matrix) = ship_transform(view, snapshot.ship_bank, snapshot.time, viewport);
+        let exhaust = ship_matrix * ENGINE_EXHAUST;
+        spots.push(Shimmer {
+            x: exhaust.x,
+            y: exhaust.y,
+            radius: ENGINE_SHIMMER_RADIUS * SHIP_SCALE * view.zoom,
+            strength: ENGINE_SHIMMER * snapshot.throttle,
+        });
+    }
+    spots
+}
+
+// Lines and the selection go on the scene's overlay layer, tested
+// against the bodies' depth so they hide behind them; labels and the
+// HUD go on the screen's, which the scene is later blitted under. The open
+// map view covers all of it
+pub fn draw_overlay(
+    scene: &mut Framebuffer,
+    screen: &mut Framebuffer,
+    lines: &mut LineBuffers,
+    frame: &Frame,
+    icons: &HudIcons,
+    strings: &Strings,
+    map: Option<&PlanetAtlas>,
+) {
+    let (simulation, snapshot, theme) = (frame.simulation, frame.snapshot, &frame.theme);
+    if let Some(atlas) = map {
+        draw_map(screen, simulation, atlas, snapshot.time, strings, theme);
+        return;
+    }
+    let line_view = &frame.line_view;
+    if frame.settings.show_grid {
+        draw_grid(scene, lines, simulation.extent(), line_view);
+    }
+    if frame.settings.show_orbits {
+        for (index, planet) in simulation.planets.iter().enumerate() {
+            let color = theme.orbit_color(&simulation.planets, index);
+            draw_orbit(scene, lines, planet, color, line_view);
+        }
+    }
+    if frame.settings.show_lagrange && !frame.photo {
+        draw_lagrange(scene, screen, lines, frame);
+    }
+
+    if simulation.ship.flight_mode && !frame.photo {
+        draw_trajectory(scene, lines, &simulation.ship.prediction, line_view);
+    }
+
+    if let Some(index) = simulation.selected.filter(|_| !frame.photo) {
+        let color = theme.orbit_color(&simulation.planets, index);
+        scene.outline(
+            &[object_id(index), ring_id(index)],
+            SELECTION_OUTLINE,
+            color,
+        );
+    }
+
+    if let Some(progress) = simulation.camera.warp_progress().filter(|_| !frame.photo) {
+        draw_warp_overlay(scene, lines, progress);
+    }
+
+    if frame.settings.show_labels {
+        draw_labels(screen, simulation, snapshot, &frame.view, theme);
+    }
+    if !frame.photo {
+        draw_measure(screen, simulation, snapshot, &frame.view, strings, theme);
+        draw_hud(screen, simulation, icons, strings, theme);
+        draw_compass(screen, simulation, snapshot, &snapshot.view, theme);
+        draw_scale_bar(screen, simulation, &frame.view, strings, theme);
+    }
+}
+
+// Low-discrepancy sequence in [0, 1): consecutive indices spread evenly
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+// The ship floats a fixed distance ahead of the camera, bobbing gently
+pub fn ship_position(view: &View, time: f32) -> Vec3 {
+    view.position + ship_offset() + Vec3::new(0.0, 20.0 * (time * 2.0).sin(), 0.0)
+}
+
+// World position of the ship and the matrix placing its mesh on screen
+pub fn ship_transform(view: &View, bank: f32, time: f32, viewport: &Viewport) -> (Vec3, Mat4) {
+    let world = ship_position(view, time);
+    let screen = world_to_screen(world, view, viewport);
+    let matrix = create_model_matrix(
+        screen,
+        SHIP_SCALE * view.zoom,
+        Vec3::new(0.2 + (time * 1.5).sin() * 0.1, PI, bank),
+    );
+    (world, matrix)
+}
+
+pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    let (sin_x, cos_x) = rotation.x.sin_cos();
+    let (sin_y, cos_y) = rotation.y.sin_cos();
+    let (sin_z, cos_z) = rotation.z.sin_cos();
+
+    let rotation_matrix_x = Mat4::new(
+        1.0, 0.0, 0.0, 0.0, 0.0, cos_x, -sin_x, 0.0, 0.0, sin_x, cos_x, 0.0, 0.0, 0.0, 0.0, 1.0,
+    );
+
+    let rotation_matrix_y = Mat4::new(
+        cos_y, 0.0, sin_y, 0.0, 0.0, 1.0, 0.0, 0.0, -sin_y, 0.0, cos_y, 0.0, 0.0, 0.0, 0.0, 1.0,
+    );
+
+    let rotation_matrix_z = Mat4::new(
+        cos_z, -sin_z, 0.0, 0.0, sin_z, cos_z, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+    );
+
+    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+
+    let transform_matrix = Mat4::new(
+        scale,
+        0.0,
+        0.0,
+        translation.x,
+        0.0,
+        scale,
+        0.0,
+        translation.y,
+        0.0,
+        0.0,
+        scale,
+        translation.z,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    transform_matrix * rotation_matrix
+}
+
+// Rasterizes `vertex_array` with `material` and lays the fragments over the
+// frame as its blend mode says. Returns the number of triangles rasterized
+// and fragments shaded
+fn draw(
+    framebuffer: &mut Framebuffer,
+    arena: &mut FrameArena,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    material: &Material,
+) -> (usize, usize) {
+    let viewport = framebuffer.viewport;
+    let triangles = rasterize(arena, uniforms, vertex_array, material, &viewport);
+    let fragments = arena.batches.iter().map(Vec::len).sum();
+
+    for fragment in arena.batches.iter().flatten() {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        let color = fragment.color.to_hex();
+        let mut emissive = fragment.emissive.to_vec3() * material.emissive;
+        if material.blend == BlendMode::Additive {
+            emissive *= fragment.alpha;
+        }
+        framebuffer.set_current_emissive([emissive.x, emissive.y, emissive.z]);
+        match material.blend {
+            BlendMode::Opaque if fragment.alpha >= 1.0 && material.depth_write => {
+                framebuffer.set_current_color(color);
+                framebuffer.point(x, y, fragment.depth);
+                continue;
+            }
+            // Translucent fragments of a solid mesh mix over what is behind
+            BlendMode::Opaque => framebuffer.blend(x, y, fragment.depth, color, fragment.alpha),
+            BlendMode::Alpha(opacity) => {
+                framebuffer.blend(x, y, fragment.depth, color, opacity * fragment.alpha)
+            }
+            BlendMode::Additive => {
+                let color = fragment.color * fragment.alpha;
+                framebuffer.blend_add(x, y, fragment.depth, color.to_hex());
+            }
+        }
+        if material.depth_write {
+            framebuffer.write_depth(x, y, fragment.depth);
+        }
+    }
+
+    arena.recycle();
+    (triangles, fragments)
+}
+
+// Shades the mesh into the arena's fragment lists, in mesh order, and
+// returns how many triangles it has
+fn rasterize(
+    arena: &mut FrameArena,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    material: &Material,
+    viewport: &Viewport,
+) -> usize {
+    let FrameArena {
+        vertices,
+        batches,
+        pool,
+        ..
+    } = arena;
+    vertices.clear();
+    vertices.extend(
+        vertex_array
+            .iter()
+            .map(|vertex| vertex_shader(vertex, uniforms)),
+    );
+
+    let shaded = vertices
+        .par_chunks(3)
+        .filter(|chunk| chunk.len() == 3)
+        // Facing the camera means pointing down -z; a face with every
+        // corner turned away is hidden by the front of a closed mesh
+        .filter(|chunk| {
+            material.two_sided
+                || chunk
+                    .iter()
+                    .any(|vertex| vertex.transformed_normal.z <= 0.0)
+        })
+        .fold(
+            || pool.take(),
+            |mut fragments, chunk| {
+                triangle_with_shader(
+                    &chunk[0],
+                    &chunk[1],
+                    &chunk[2],
+                    uniforms,
+                    material.shader,
+                    viewport,
+                    &mut fragments,
+                );
+                fragments
+            },
+        );
+    batches.par_extend(shaded);
+
+    vertices.len() / 3
+}
+
+fn mesh_vertices<'a>(
+    meshes: &'a Meshes,
+    rings: &'a HashMap<(usize, usize), Vec<Vertex>>,
+    mesh: MeshRef,
+) -> &'a [Vertex] {
+    match mesh {
+        MeshRef::Body(kind) => meshes.get(kind),
+        MeshRef::Ring(planet, ring) => &rings[&(planet, ring)],
+    }
+}
+
+// Every planet owns a block of consecutive ids (body, ring, then its moons),
+// starting at 1 so the cleared id buffer never matches a body
+const IDS_PER_PLANET: u32 = 16;
+
+pub fn object_id(planet_index: usize) -> u32 {
+    planet_index as u32 * IDS_PER_PLANET + 1
+}
+
+pub fn ring_id(planet_index: usize) -> u32 {
+    object_id(planet_index) + 1
+}
+
+// Moons past the end of the block share its last id
+pub fn moon_id(planet_index: usize, moon_index: usize) -> u32 {
+    object_id(planet_index) + 2 + (moon_index as u32).min(IDS_PER_PLANET - 3)
+}
+
+// A sphere of `radius` pixels faked on a flat disc: each pixel takes the
+// normal the sphere would have there. Below a pixel it is a lone point.
+// Under full opacity it is blended over what is already drawn, just in front
+// of the mesh it is fading into
+fn draw_impostor(
+    framebuffer: &mut Framebuffer,
+    center: Vec3,
+    radius: f32,
+    surface: Surface,
+    opacity: f32,
+    uniforms: &Uniforms,
+) {
+    let shade = |normal: Vec3| {
+        let light = uniforms.lights.iter().fold(Vec3::zeros(), |sum, light| {
+            sum + light.diffuse(normal * radius, &normal)
+        });
+        surface
+            .albedo
+            .component_mul(&(light * 0.9 + uniforms.ambient * 0.1))
+            + surface.emission
+    };
+    let emission = surface.emission;
+    framebuffer.set_current_emissive([emission.x, emission.y, emission.z]);
+    let mut plot = |x: f32, y: f32, depth: f32, lit: Vec3| {
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        let color = Color::from_float(lit.x, lit.y, lit.z).to_hex();
+        if opacity >= 1.0 {
+            framebuffer.set_current_color(color);
+            framebuffer.point(x as usize, y as usize, depth);
+        } else {
+            let depth = depth - IMPOSTOR_DEPTH_BIAS;
+            framebuffer.blend(x as usize, y as usize, depth, color, opacity);
+        }
+    };
+
+    if radius < 1.0 {
+        // Seen from afar only the lit fraction matters: average over a
+        // few directions facing the camera, with a floor so belts still
+        // read as bands of specks against the night side
+        let normals = [
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.7, 0.0, -0.7),
+            Vec3::new(-0.7, 0.0, -0.7),
+            Vec3::new(0.0, 0.7, -0.7),
+            Vec3::new(0.0, -0.7, -0.7),
+        ];
+        let lit: Vec3 = normals.iter().map(|normal| shade(*normal)).sum();
+        let lit = (lit / normals.len() as f32).sup(&(surface.albedo * IMPOSTOR_FLOOR));
+        plot(center.x, center.y, center.z, lit);
+        return;
+    }
+
+    let extent = radius.ceil() as i32;
+    for dy in -extent..=extent {
+        for dx in -extent..=extent {
+            let (x, y) = (dx as f32 / radius, dy as f32 / radius);
+            let reach = x * x + y * y;
+            if reach > 1.0 {
+                continue;
+            }
+            // Facing the camera means pointing down -z in the view frame
+            let normal = Vec3::new(x, y, -(1.0 - reach).sqrt());
+            plot(
+                center.x + dx as f32,
+                center.y + dy as f32,
+                center.z + normal.z * radius,
+                shade(normal),
+            );
+        }
+    }
+}
+
+pub fn is_offscreen(center: Vec3, radius: f32, viewport: &Viewport) -> bool {
+    center.x + radius < viewport.x as f32
+        || center.y + radius < viewport.y as f32
+        || center.x - radius > (viewport.x + viewport.width) as f32
+        || center.y - radius > (viewport.y + viewport.height) as f32
+}
+
+// Screen position of `world`, around the center of `viewport`
+pub fn world_to_screen(world: Vec3, view: &View, viewport: &Viewport) -> Vec3 {
+    let projected = view.project(world);
+    let (center_x, center_y) = viewport.center();
+    Vec3::new(center_x + projected.x, center_y + projected.y, projected.z)
+}
+
+// Orbits are anti-aliased, depth tested against the bodies so they pass
+// behind planets, and fade with distance from the camera
+fn draw_orbit(
+    framebuffer: &mut Framebuffer,
+    lines: &mut LineBuffers,
+    planet: &Planet,
+    color: u32,
+    view: &View,
+) {
+    if planet.orbit_radius <= 1.0 {
+        return;
+    }
+
+    let viewport = framebuffer.viewport;
+    let points: Vec<Vec3> = (0..=ORBIT_SEGMENTS)
+        .map(|i| {
+            let t = i as f32 / ORBIT_SEGMENTS as f32 * TAU;
+            let world = Vec3::new(
+                planet.orbit_radius * t.cos(),
+                0.0,
+                planet.orbit_radius * t.sin(),
+            );
+            world_to_screen(world, view, &viewport)
+        })
+        .collect();
+
+    draw_polyline(framebuffer, lines, &points, ORBIT_THICKNESS, |_, depth| {
+        (color, orbit_opacity(depth))
+    });
+}
+
+// Circles at a round spacing and spokes from the center on the y = 0
+// plane, out to `extent`. Lines fade with their distance from the camera
+// and towards the edge, so the grid reads as a floor without cluttering
+fn draw_grid(framebuffer: &mut Framebuffer, lines: &mut LineBuffers, extent: f32, view: &View) {
+    let spacing = round_step(extent / GRID_CIRCLES);
+    if spacing <= 0.0 {
+        return;
+    }
+    let viewport = framebuffer.viewport;
+    let outer = (extent / spacing).ceil() * spacing;
+    let opacity = |radius: f32, depth: f32| {
+        let edge = 1.0 - radius / (outer + spacing);
+        let distance = 1.0 - (depth / GRID_FADE_FAR).min(1.0);
+        GRID_OPACITY * edge * distance
+    };
+
+    let mut radius = spacing;
+    while radius <= outer {
+        let points: Vec<Vec3> = (0..=GRID_SEGMENTS)
+            .map(|i| {
+                let t = i as f32 / GRID_SEGMENTS as f32 * TAU;
+                let world = Vec3::new(radius * t.cos(), 0.0, radius * t.sin());
+                world_to_screen(world, view, &viewport)
+            })
+            .collect();
+        draw_polyline(framebuffer, lines, &points, ORBIT_THICKNESS, |_, depth| {
+            (GRID_COLOR, opacity(radius, depth))
+        });
+        radius += spacing;
+    }
+
+    for spoke in 0..GRID_SPOKES {
+        let t = spoke as f32 / GRID_SPOKES as f32 * TAU;
+        let direction = Vec3::new(t.cos(), 0.0, t.sin());
+        // Split up so the depth, and with it the fade, follows the line
+        let points: Vec<Vec3> = (0..=GRID_SEGMENTS / 4)
+            .map(|i| {
+                let along = outer * i as f32 / (GRID_SEGMENTS / 4) as f32;
+                world_to_screen(direction * along, view, &viewport)
+            })
+            .collect();
+        draw_polyline(
+            framebuffer,
+            lines,
+            &points,
+            ORBIT_THICKNESS,
+            |progress, depth| (GRID_COLOR, opacity(progress * outer, depth)),
+        );
+    }
+}
+
+fn orbit_opacity(depth: f32) -> f32 {
+    let (near, near_alpha) = ORBIT_FADE_NEAR;
+    let (far, far_alpha) = ORBIT_FADE_FAR;
+    let t = ((depth - near) / (far - near)).clamp(0.0, 1.0);
+    near_alpha + (far_alpha - near_alpha) * t
+}
+
+// Predicted ship path, fading out towards the end of the prediction window
+fn draw_trajectory(
+    framebuffer: &mut Framebuffer,
+    lines: &mut LineBuffers,
+    path: &[Vec3],
+    view: &View,
+) {
+    let viewport = framebuffer.viewport;
+    let points: Vec<Vec3> = path
+        .iter()
+        .map(|point| world_to_screen(*point, view, &viewport))
+        .collect();
+    draw_polyline(
+        framebuffer,
+        lines,
+        &points,
+        TRAJECTORY_THICKNESS,
+        |progress, _| (TRAJECTORY_COLOR, 1.0 - progress),
+    );
+}
+
+// Anti-aliased, depth-tested line through screen-space `points` (z is depth).
+// `style` maps how far along the line a pixel is (0..1) and its depth to a
+// color and opacity. Consecutive segments share their end pixels; keeping the
+// strongest coverage per pixel avoids blending the joints twice. Segments are
+// clipped to the viewport first, so orbits running far off screen when zoomed
+// in cost only the pixels that show
+fn draw_polyline(
+    framebuffer: &mut Framebuffer,
+    lines: &mut LineBuffers,
+    points: &[Vec3],
+    thickness: f32,
+    style: impl Fn(f32, f32) -> (u32, f32),
+) {
+    let viewport = framebuffer.viewport;
+    let margin = thickness.max(1.0) + 1.0;
+    let min = Vec2::new(viewport.x as f32 - margin, viewport.y as f32 - margin);
+    let max = Vec2::new(
+        (viewport.x + viewport.width) as f32 + margin,
+        (viewport.y + viewport.height) as f32 + margin,
+    );
+
+    let segments = points.len().saturating_sub(1).max(1) as f32;
+    let LineBuffers { samples, coverage } = lines;
+    coverage.clear();
+    for (index, segment) in points.windows(2).enumerate() {
+        let (start, end) = (segment[0], segment[1]);
+        let (from, to) = (Vec2::new(start.x, start.y), Vec2::new(end.x, end.y));
+        let Some((enter, exit)) = clip_segment(from, to, min, max) else {
+            continue;
+        };
+        samples.clear();
+        wu_line_into(
+            from + (to - from) * enter,
+            from + (to - from) * exit,
+            thickness,
+            samples,
+        );
+        for sample in samples.iter() {
+            // Back to how far along the whole segment the sample is
+            let t = enter + (exit - enter) * sample.t;
+            let depth = start.z + (end.z - start.z) * t;
+            let progress = (index as f32 + t) / segments;
+            let pixel = coverage
+                .entry((sample.x, sample.y))
+                .or_insert((0.0, depth, progress));
+            if sample.coverage > pixel.0 {
+                *pixel = (sample.coverage, depth, progress);
+            }
+        }
+    }
+
+    for (&(x, y), &(coverage, depth, progress)) in coverage.iter() {
+        let (color, opacity) = style(progress, depth);
+        framebuffer.blend_overlay_at(x, y, depth, color, coverage * opacity);
+    }
+}
+
+// Diamonds on every planet's L4 and L5 in its orbit color, depth tested
+// like the orbits, each named beside it on the screen. They are worked out
+// from where the bodies are, so in gravity mode they follow the real orbits
+fn draw_lagrange(
+    scene: &mut Framebuffer,
+    screen: &mut Framebuffer,
+    lines: &mut LineBuffers,
+    frame: &Frame,
+) {
+    let (simulation, snapshot) = (frame.simulation, frame.snapshot);
+    let Some(star) = snapshot.bodies.first() else {
+        return;
+    };
+    let (scene_viewport, screen_viewport) = (scene.viewport, screen.viewport);
+    let size = scene.ui(LAGRANGE_MARKER) as f32;
+    let (scale, gap) = (screen.ui(1), screen.ui(LABEL_GAP));
+    let bodies = simulation.planets.iter().zip(&snapshot.bodies);
+    for (index, (planet, body)) in bodies.enumerate() {
+        if planet.orbit_radius <= 0.0 {
+            continue;
+        }
+        let color = frame.theme.orbit_color(&simulation.planets, index);
+        let points = lagrange_points(star.position, body.position, planet.orbit_speed);
+        for (point, name) in points.into_iter().zip(["L4", "L5"]) {
+            let center = world_to_screen(point, &frame.line_view, &scene_viewport);
+            if is_offscreen(center, size, &scene_viewport) {
+                continue;
+            }
+            let corners = [
+                (0.0, -1.0),
+                (1.0, 0.0),
+                (0.0, 1.0),
+                (-1.0, 0.0),
+                (0.0, -1.0),
+            ]
+            .map(|(x, y)| center + Vec3::new(x * size, y * size, 0.0));
+            draw_polyline(scene, lines, &corners, ORBIT_THICKNESS, |_, depth| {
+                (color, orbit_opacity(depth).max(0.6))
+            });
+
+            let label = world_to_screen(point, &frame.view, &screen_viewport);
+            draw_text(
+                screen,
+                label.x as i32 + gap,
+                label.y as i32 - GLYPH_HEIGHT * scale / 2,
+                name,
+                label_color(color),
+                scale,
+            );
+        }
+    }
+}
+
+// Planet names beside their bodies, drawn at full resolution on the screen
+fn draw_labels(
+    screen: &mut Framebuffer,
+    simulation: &Simulation,
+    snapshot: &Snapshot,
+    view: &View,
+    theme: &Theme,
+) {
+    let viewport = screen.viewport;
+    let (scale, gap) = (screen.ui(LABEL_SCALE), screen.ui(LABEL_GAP));
+    for (index, (planet, body)) in simulation.planets.iter().zip(&snapshot.bodies).enumerate() {
+        let position = world_to_screen(body.position, view, &viewport);
+        let radius = planet.scale * view.zoom;
+        if is_offscreen(position, radius, &viewport) {
+            continue;
+        }
+        draw_text(
+            screen,
+            (position.x + radius) as i32 + gap,
+            position.y as i32 - GLYPH_HEIGHT * scale / 2,
+            &planet.name,
+            label_color(theme.orbit_color(&simulation.planets, index)),
+            scale,
+        );
+    }
+}
+
+// Orbit hue, brightened so dim orbit colors still read as text
+fn label_color(orbit_color: u32) -> u32 {
+    let (hue, saturation, value) = Color::from_hex(orbit_color).to_hsv();
+    Color::from_hsv(hue, saturation * 0.6, value.max(0.95)).to_hex()
+}
+
+fn draw_warp_overlay(framebuffer: &mut Framebuffer, lines: &mut LineBuffers, progress: f32) {
+    let center = Vec3::new(
+        framebuffer.width as f32 * 0.5,
+        framebuffer.height as f32 * 0.5,
+        f32::MIN,
+    );
+    let radius = progress * framebuffer.width as f32 * 0.4;
+
+    for angle in (0..360).step_by(10) {
+        let theta = (angle as f32).to_radians();
+        let tip = center + Vec3::new(theta.cos() * radius, theta.sin() * radius, 0.0);
+        draw_polyline(framebuffer, lines, &[center, tip], 1.0, |_, _| {
+            (WARP_COLOR, 1.0)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use nalgebra_glm as glm;
+
+    fn assert_close(actual: &Mat4, expected: &Mat4) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-4, "{actual} != {expected}");
+        }
+    }
+
+    // Rotates about x first, then y, then z; scales, then translates
+    fn reference(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+        glm::translation(&translation)
+            * glm::scaling(&Vec3::new(scale, scale, scale))
+            * glm::rotation(rotation.z, &Vec3::z())
+            * glm::rotation(rotation.y, &Vec3::y())
+            * glm::rotation(rotation.x, &Vec3::x())
+    }
+
+    #[test]
+    fn model_matrix_without_rotation_scales_and_translates() {
+        let translation = Vec3::new(600.0, 400.0, 25.0);
+        let matrix = create_model_matrix(translation, 3.0, Vec3::zeros());
+        let point = matrix * Vec4::new(1.0, -2.0, 0.5, 1.0);
+        assert!((point.xyz() - Vec3::new(603.0, 394.0, 26.5)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn model_matrix_matches_glm() {
+        let cases = [
+            (Vec3::zeros(), 1.0, Vec3::new(FRAC_PI_2, 0.0, 0.0)),
+            (Vec3::zeros(), 1.0, Vec3::new(0.0, FRAC_PI_2, 0.0)),
+            (Vec3::zeros(), 1.0, Vec3::new(0.0, 0.0, FRAC_PI_2)),
+            (Vec3::new(10.0, -4.0, 2.0), 0.5, Vec3::new(0.3, -1.2, 2.5)),
+            (
+                Vec3::new(-300.0, 80.0, 900.0),
+                45.0,
+                Vec3::new(PI, 0.7, -0.1),
+            ),
+        ];
+        for (translation, scale, rotation) in cases {
+            assert_close(
+                &create_model_matrix(translation, scale, rotation),
+                &reference(translation, scale, rotation),
+            );
+        }
+    }
+
+    #[test]
+    fn model_matrix_keeps_normals_unit_length() {
+        let matrix = create_model_matrix(Vec3::new(5.0, 5.0, 5.0), 1.0, Vec3::new(0.4, 1.1, -2.0));
+        let normal = (matrix * Vec4::new(0.0, 0.0, 1.0, 0.0)).xyz();
+        assert!((normal.magnitude() - 1.0).abs() < 1e-5);
+    }
+}
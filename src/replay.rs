@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use nalgebra_glm::Vec2;
+
+use crate::input::{Action, InputFrame};
+
+// Replay files are plain text: a `seed <n>` header followed by one
+// `frame <delta> <actions...>` line per simulation step, ending in
+// `cursor=<x>,<y>` when the mouse was over the window.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P, seed: u64) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "seed {}", seed)?;
+        Ok(Recorder { writer })
+    }
+
+    pub fn record(&mut self, delta: f32, input: &InputFrame) -> io::Result<()> {
+        let mut names: Vec<String> = input.held().map(|action| action.name()).collect();
+        names.sort();
+        if let Some(cursor) = input.cursor() {
+            names.push(format!("cursor={},{}", cursor.x, cursor.y));
+        }
+        writeln!(self.writer, "frame {} {}", delta, names.join(" "))
+    }
+}
+
+struct ReplayFrame {
+    delta: f32,
+    held: HashSet<Action>,
+    cursor: Option<Vec2>,
+}
+
+pub struct Replay {
+    pub seed: u64,
+    frames: Vec<ReplayFrame>,
+    cursor: usize,
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut seed = None;
+        let mut frames = Vec::new();
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("seed") => seed = parts.next().and_then(|value| value.parse().ok()),
+                Some("frame") => {
+                    let delta = parts
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| invalid_line(number, "delta inválido"))?;
+                    let mut held = HashSet::new();
+                    let mut cursor = None;
+                    for name in parts {
+                        if let Some(position) = name.strip_prefix("cursor=") {
+                            cursor = Some(
+                                parse_cursor(position)
+                                    .ok_or_else(|| invalid_line(number, "cursor inválido"))?,
+                            );
+                            continue;
+                        }
+                        held.insert(
+                            Action::from_name(name)
+                                .ok_or_else(|| invalid_line(number, "acción desconocida"))?,
+                        );
+                    }
+                    frames.push(ReplayFrame {
+                        delta,
+                        held,
+                        cursor,
+                    });
+                }
+                Some(_) => return Err(invalid_line(number, "entrada desconocida")),
+                None => {}
+            }
+        }
+
+        let seed = seed.ok_or_else(|| invalid_line(0, "falta la semilla"))?;
+        Ok(Replay {
+            seed,
+            frames,
+            cursor: 0,
+        })
+    }
+
+    pub fn next_frame(&mut self, previous: &InputFrame) -> Option<(f32, InputFrame)> {
+        let frame = self.frames.get(self.cursor)?;
+        self.cursor += 1;
+        let input = InputFrame::from_held(frame.held.clone(), previous).with_cursor(frame.cursor);
+        Some((frame.delta, input))
+    }
+}
+
+fn parse_cursor(text: &str) -> Option<Vec2> {
+    let (x, y) = text.split_once(',')?;
+    Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+fn invalid_line(number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("línea {}: {}", number + 1, message),
+    )
+}
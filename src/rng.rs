@@ -0,0 +1,58 @@
+// Seeded random numbers for everything generated from the game's seed.
+// Each consumer draws from its own named stream, so the belt, the starfield
+// and whatever comes later are reproducible from one seed and don't change
+// when another of them draws more or fewer numbers, or runs first.
+
+// xoshiro256**: fast, small state and good enough statistics for placing
+// things; not for anything that has to be unpredictable
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    // The stream `name` of `seed`; the same pair always gives the same numbers
+    pub fn stream(seed: u64, name: &str) -> Self {
+        let mut mix = seed ^ fnv1a(name);
+        let state = [(); 4].map(|_| splitmix64(&mut mix));
+        Rng { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+        result
+    }
+
+    // Uniform in [0, 1)
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Uniform in [min, max)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.unit()
+    }
+}
+
+// Spreads the seed over the whole state, so nearby seeds and an all-zero
+// state (which xoshiro can't leave) never happen
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn fnv1a(name: &str) -> u64 {
+    name.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
@@ -0,0 +1,339 @@
+use nalgebra_glm::Vec3;
+
+use crate::belt::{generate_belt, BeltBody, BeltDef, TrojanDef};
+use crate::color::{Color, Palette};
+use crate::fragment_shaders::{
+    azure_planet_height, azure_planet_shader, azure_planet_surface, crimson_planet_height,
+    crimson_planet_shader, crimson_planet_surface, gas_giant_shader, ice_giant_shader,
+    ice_giant_surface, molten_height, molten_shader, molten_surface, moon_shader,
+    rocky_planet_height, rocky_planet_shader, rocky_planet_surface, star_shader, FragmentShader,
+    HeightFunction, SurfaceFunction, AZURE_LAYERS, CRIMSON_LAYERS, ICE_GIANT_LAYERS, MOLTEN_LAYERS,
+    ROCKY_LAYERS,
+};
+use crate::noise::NoiseLayer;
+use crate::simulation::{MeshKind, Moon, Planet, RingDef};
+use crate::star::SUN_TEMPERATURE;
+
+// Ring color profiles, from the inner edge to the outer one
+const DUST_RING: Palette = Palette::new(&[
+    (0.0, Color::new(102, 89, 76)),
+    (1.0, Color::new(127, 115, 102)),
+]);
+const ICE_RING: Palette = Palette::new(&[
+    (0.0, Color::new(150, 190, 220)),
+    (0.6, Color::new(205, 230, 250)),
+    (1.0, Color::new(235, 245, 255)),
+]);
+
+// Icy leftovers past Cryon, with a handful of dwarf planets among them
+const KUIPER_BELT: BeltDef = BeltDef {
+    count: 4000,
+    dwarf_planets: 5,
+    inner_radius: 1350.0,
+    outer_radius: 1800.0,
+    thickness: 90.0,
+    inner_speed: 0.085,
+    scale: (1.0, 3.5),
+    dwarf_scale: (8.0, 14.0),
+    colors: (Vec3::new(0.55, 0.48, 0.42), Vec3::new(0.82, 0.86, 0.92)),
+};
+
+pub fn kuiper_belt(seed: u64) -> Vec<BeltBody> {
+    generate_belt(&KUIPER_BELT, seed)
+}
+
+// Dark rocky clusters at every planet's L4 and L5, shown from the menu
+pub const TROJANS: TrojanDef = TrojanDef {
+    count: 28,
+    spread: 0.3,
+    width: 0.05,
+    scale: (1.5, 4.0),
+    colors: (Vec3::new(0.38, 0.33, 0.29), Vec3::new(0.6, 0.55, 0.5)),
+};
+
+// A planet's look as one named set: mesh, shaders, relief and orbit color.
+// Bodies described as data (a script's `planeta` lines) pick one by name
+#[derive(Clone, Copy)]
+pub struct Preset {
+    pub name: &'static str,
+    pub mesh: MeshKind,
+    pub shader: FragmentShader,
+    pub surface: Option<SurfaceFunction>,
+    pub height: Option<HeightFunction>,
+    pub noise_layers: &'static [NoiseLayer],
+    pub atmosphere_density: f32,
+    pub orbit_color: u32,
+}
+
+pub const PRESETS: [Preset; 5] = [
+    Preset {
+        name: "rocoso",
+        mesh: MeshKind::Rocky,
+        shader: rocky_planet_shader,
+        surface: Some(rocky_planet_surface),
+        height: Some(rocky_planet_height),
+        noise_layers: &ROCKY_LAYERS,
+        atmosphere_density: 0.8,
+        orbit_color: 0x66FFCC,
+    },
+    Preset {
+        name: "volcanico",
+        mesh: MeshKind::Rocky,
+        shader: crimson_planet_shader,
+        surface: Some(crimson_planet_surface),
+        height: Some(crimson_planet_height),
+        noise_layers: &CRIMSON_LAYERS,
+        atmosphere_density: 0.4,
+        orbit_color: 0xFF4433,
+    },
+    Preset {
+        name: "gaseoso",
+        mesh: MeshKind::GasGiant,
+        shader: gas_giant_shader,
+        surface: None,
+        height: None,
+        noise_layers: &[],
+        atmosphere_density: 1.6,
+        orbit_color: 0xCC8844,
+    },
+    Preset {
+        name: "helado",
+        mesh: MeshKind::GasGiant,
+        shader: ice_giant_shader,
+        surface: Some(ice_giant_surface),
+        height: None,
+        noise_layers: &ICE_GIANT_LAYERS,
+        atmosphere_density: 1.4,
+        orbit_color: 0x55CCDD,
+    },
+    Preset {
+        name: "fundido",
+        mesh: MeshKind::Rocky,
+        shader: molten_shader,
+        surface: Some(molten_surface),
+        height: Some(molten_height),
+        noise_layers: &MOLTEN_LAYERS,
+        atmosphere_density: 0.3,
+        orbit_color: 0xFFAA22,
+    },
+];
+
+pub fn preset(name: &str) -> Option<Preset> {
+    PRESETS.into_iter().find(|preset| preset.name == name)
+}
+
+pub fn default_system() -> Vec<Planet> {
+    vec![
+        Planet {
+            name: "Helios".to_string(),
+            orbit_radius: 0.0,
+            orbit_speed: 0.0,
+            rotation_speed: 0.25,
+            scale: 140.0,
+            phase: 0.0,
+            orbit_color: 0xFFAA44,
+            collision_radius: 160.0,
+            mesh: MeshKind::Star,
+            shader: star_shader,
+            surface: None,
+            height: None,
+            noise_layers: &[],
+            atmosphere_density: 0.0,
+            moons: Vec::new(),
+            rings: Vec::new(),
+            has_aurora: false,
+            temperature: Some(SUN_TEMPERATURE),
+            mass: None,
+        },
+        Planet {
+            name: "Azura".to_string(),
+            orbit_radius: 240.0,
+            orbit_speed: 0.62,
+            rotation_speed: 0.95,
+            scale: 60.0,
+            phase: 0.35,
+            orbit_color: 0x55D0FF,
+            collision_radius: 80.0,
+            mesh: MeshKind::Rocky,
+            shader: azure_planet_shader,
+            surface: Some(azure_planet_surface),
+            height: Some(azure_planet_height),
+            noise_layers: &AZURE_LAYERS,
+            atmosphere_density: 1.0,
+            moons: Vec::new(),
+            rings: Vec::new(),
+            has_aurora: true,
+            temperature: None,
+            mass: None,
+        },
+        Planet {
+            name: "Aurelia".to_string(),
+            orbit_radius: 340.0,
+            orbit_speed: 0.46,
+            rotation_speed: 1.0,
+            scale: 80.0,
+            phase: 1.0,
+            orbit_color: 0x66FFCC,
+            collision_radius: 95.0,
+            mesh: MeshKind::Rocky,
+            shader: rocky_planet_shader,
+            surface: Some(rocky_planet_surface),
+            height: Some(rocky_planet_height),
+            noise_layers: &ROCKY_LAYERS,
+            atmosphere_density: 0.8,
+            moons: vec![Moon {
+                orbit_radius: 140.0,
+                orbit_speed: 1.5,
+                rotation_speed: 0.6,
+                scale: 28.0,
+                phase: 0.6,
+                mesh: MeshKind::Moon,
+                shader: moon_shader,
+                tidally_locked: true,
+            }],
+            rings: Vec::new(),
+            has_aurora: false,
+            temperature: None,
+            mass: None,
+        },
+        Planet {
+            name: "Zephyrus".to_string(),
+            orbit_radius: 500.0,
+            orbit_speed: 0.32,
+            rotation_speed: 0.4,
+            scale: 130.0,
+            phase: 2.2,
+            orbit_color: 0xCC8844,
+            collision_radius: 170.0,
+            mesh: MeshKind::GasGiant,
+            shader: gas_giant_shader,
+            surface: None,
+            height: None,
+            noise_layers: &[],
+            atmosphere_density: 1.6,
+            moons: Vec::new(),
+            rings: vec![RingDef {
+                inner_radius: 1.38,
+                outer_radius: 2.77,
+                tilt: 0.24,
+                rotation_speed: 0.15,
+                colors: DUST_RING,
+                opacity: 1.0,
+            }],
+            has_aurora: true,
+            temperature: None,
+            mass: None,
+        },
+        Planet {
+            name: "Pyra".to_string(),
+            orbit_radius: 640.0,
+            orbit_speed: 0.29,
+            rotation_speed: 1.1,
+            scale: 78.0,
+            phase: 0.7,
+            orbit_color: 0xFF4433,
+            collision_radius: 100.0,
+            mesh: MeshKind::Rocky,
+            shader: crimson_planet_shader,
+            surface: Some(crimson_planet_surface),
+            height: Some(crimson_planet_height),
+            noise_layers: &CRIMSON_LAYERS,
+            atmosphere_density: 0.4,
+            moons: vec![Moon {
+                orbit_radius: 125.0,
+                orbit_speed: 1.6,
+                rotation_speed: 0.8,
+                scale: 26.0,
+                phase: 1.2,
+                mesh: MeshKind::Moon,
+                shader: moon_shader,
+                tidally_locked: false,
+            }],
+            rings: Vec::new(),
+            has_aurora: false,
+            temperature: None,
+            mass: None,
+        },
+        Planet {
+            name: "Cryon".to_string(),
+            orbit_radius: 820.0,
+            orbit_speed: 0.18,
+            rotation_speed: 0.5,
+            scale: 110.0,
+            phase: 3.4,
+            orbit_color: 0x55CCFF,
+            collision_radius: 140.0,
+            mesh: MeshKind::GasGiant,
+            shader: gas_giant_shader,
+            surface: None,
+            height: None,
+            noise_layers: &[],
+            // A family of small moons beyond the rings, the inner ones faster
+            atmosphere_density: 1.4,
+            moons: vec![
+                Moon {
+                    orbit_radius: 310.0,
+                    orbit_speed: 1.3,
+                    rotation_speed: 0.9,
+                    scale: 18.0,
+                    phase: 0.0,
+                    mesh: MeshKind::Moon,
+                    shader: moon_shader,
+                    tidally_locked: true,
+                },
+                Moon {
+                    orbit_radius: 350.0,
+                    orbit_speed: 1.0,
+                    rotation_speed: 0.7,
+                    scale: 14.0,
+                    phase: 2.1,
+                    mesh: MeshKind::Moon,
+                    shader: moon_shader,
+                    tidally_locked: true,
+                },
+                Moon {
+                    orbit_radius: 390.0,
+                    orbit_speed: 0.8,
+                    rotation_speed: 0.5,
+                    scale: 22.0,
+                    phase: 4.0,
+                    mesh: MeshKind::Moon,
+                    shader: moon_shader,
+                    tidally_locked: true,
+                },
+                Moon {
+                    orbit_radius: 430.0,
+                    orbit_speed: 0.6,
+                    rotation_speed: 0.4,
+                    scale: 11.0,
+                    phase: 5.3,
+                    mesh: MeshKind::Moon,
+                    shader: moon_shader,
+                    tidally_locked: false,
+                },
+            ],
+            // Thin, translucent ice bands with a gap between them
+            rings: vec![
+                RingDef {
+                    inner_radius: 1.45,
+                    outer_radius: 2.05,
+                    tilt: -0.35,
+                    rotation_speed: 0.1,
+                    colors: ICE_RING,
+                    opacity: 0.6,
+                },
+                RingDef {
+                    inner_radius: 2.2,
+                    outer_radius: 2.55,
+                    tilt: -0.35,
+                    rotation_speed: 0.08,
+                    colors: ICE_RING,
+                    opacity: 0.35,
+                },
+            ],
+            has_aurora: false,
+            temperature: None,
+            mass: None,
+        },
+    ]
+}
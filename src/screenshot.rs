@@ -0,0 +1,37 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{Rgb, RgbImage};
+
+use crate::framebuffer::Framebuffer;
+
+pub const SCREENSHOT_DIR: &str = "capturas";
+
+// Writes the frame as a PNG named after the current time and returns its path
+pub fn save(framebuffer: &Framebuffer) -> Result<PathBuf, image::ImageError> {
+    save_as(framebuffer, "foto")
+}
+
+// Same as `save`, with the file name starting with `prefix`
+pub fn save_as(framebuffer: &Framebuffer, prefix: &str) -> Result<PathBuf, image::ImageError> {
+    let path = capture_path(prefix)?;
+
+    let width = framebuffer.width;
+    let image = RgbImage::from_fn(width as u32, framebuffer.height as u32, |x, y| {
+        let pixel = framebuffer.buffer[y as usize * width + x as usize];
+        Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+    });
+    image.save(&path)?;
+    Ok(path)
+}
+
+// A new file in the captures folder, `<prefix>_<milliseconds>.png`
+pub fn capture_path(prefix: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(SCREENSHOT_DIR)?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    Ok(Path::new(SCREENSHOT_DIR).join(format!("{}_{}.png", prefix, millis)))
+}
@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use nalgebra_glm::Vec3;
+
+use crate::scene::{preset, Preset};
+#[cfg(feature = "scripting")]
+use crate::scripting::RhaiScript;
+use crate::settings::MAX_TIME_SCALE;
+use crate::simulation::{Planet, Simulation};
+use crate::star::class_temperature;
+
+// A scene script given with --script: `.rhai` files are rhai programs (with
+// the `scripting` feature), anything else a command timeline
+pub enum Script {
+    Timeline(Timeline),
+    #[cfg(feature = "scripting")]
+    Rhai(Box<RhaiScript>),
+}
+
+impl Script {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "rhai")
+        {
+            #[cfg(feature = "scripting")]
+            return RhaiScript::load(path).map(|script| Script::Rhai(Box::new(script)));
+            #[cfg(not(feature = "scripting"))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "compilado sin la característica scripting (cargo run --release --features scripting)",
+            ));
+        }
+        Timeline::load(path).map(Script::Timeline)
+    }
+
+    // Runs whatever is due by now; returns false once the script is over
+    pub fn advance(&mut self, simulation: &mut Simulation, delta: f32) -> bool {
+        match self {
+            Script::Timeline(timeline) => timeline.advance(simulation, delta),
+            #[cfg(feature = "scripting")]
+            Script::Rhai(script) => script.advance(simulation, delta),
+        }
+    }
+}
+
+// Timeline files are plain text: one `<seconds> <command> <arguments...>`
+// line per action, run when that much simulation time has passed since the
+// start. There are no variables or loops; rhai scripts drive the same
+// commands from a program.
+//
+//   0    escala 2
+//   1.5  camara 0 80 -400
+//   3    warp Zephyrus
+//   4    zoom 1.4
+//   6    planeta Nereo 920 0.15 60 gaseoso   (any name from `scene::PRESETS`)
+//   8    estrella M          (spectral class or temperature in kelvin)
+//   10   fecha -300          (sets the simulation clock, back or forward)
+//   12   kepler 8000000      (Kepler speeds from here on; the star mass is optional)
+pub enum Command {
+    TimeScale(f32),
+    Camera(Vec3),
+    Zoom(f32),
+    Warp(String),
+    Select(String),
+    StarTemperature(f32),
+    SetTime(f32),
+    Kepler(Option<f32>),
+    Spawn {
+        name: String,
+        orbit_radius: f32,
+        orbit_speed: f32,
+        scale: f32,
+        preset: Preset,
+    },
+}
+
+pub struct Timeline {
+    commands: Vec<(f32, Command)>,
+    cursor: usize,
+    clock: f32,
+}
+
+impl Timeline {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut commands = Vec::new();
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or("");
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.is_empty() {
+                continue;
+            }
+            let time: f32 = parts[0]
+                .parse()
+                .map_err(|_| invalid_line(number, "tiempo inválido"))?;
+            let command =
+                parse_command(&parts[1..]).map_err(|message| invalid_line(number, message))?;
+            commands.push((time, command));
+        }
+
+        commands.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Timeline {
+            commands,
+            cursor: 0,
+            clock: 0.0,
+        })
+    }
+
+    // Runs every command due by now; returns false once the script is over
+    pub fn advance(&mut self, simulation: &mut Simulation, delta: f32) -> bool {
+        self.clock += delta;
+        while let Some((time, command)) = self.commands.get(self.cursor) {
+            if *time > self.clock {
+                break;
+            }
+            execute(command, simulation);
+            self.cursor += 1;
+        }
+        self.cursor < self.commands.len()
+    }
+}
+
+fn parse_command(parts: &[&str]) -> Result<Command, &'static str> {
+    let number = |index: usize| -> Result<f32, &'static str> {
+        parts
+            .get(index)
+            .and_then(|value| value.parse().ok())
+            .ok_or("se esperaba un número")
+    };
+    let word = |index: usize| -> Result<String, &'static str> {
+        parts
+            .get(index)
+            .map(|value| value.to_string())
+            .ok_or("falta un nombre")
+    };
+
+    let command = match parts.first().copied() {
+        Some("escala") => Command::TimeScale(number(1)?),
+        Some("camara") => Command::Camera(Vec3::new(number(1)?, number(2)?, number(3)?)),
+        Some("zoom") => Command::Zoom(number(1)?),
+        Some("warp") => Command::Warp(word(1)?),
+        Some("seleccionar") => Command::Select(word(1)?),
+        Some("estrella") => {
+            let class = word(1)?;
+            Command::StarTemperature(star_temperature(&class)?)
+        }
+        Some("fecha") => Command::SetTime(number(1)?),
+        Some("kepler") => Command::Kepler(parts.get(1).map(|_| number(1)).transpose()?),
+        Some("planeta") => Command::Spawn {
+            name: word(1)?,
+            orbit_radius: number(2)?,
+            orbit_speed: number(3)?,
+            scale: number(4)?,
+            preset: planet_preset(parts.get(5).copied().unwrap_or(""))?,
+        },
+        Some(_) => return Err("comando desconocido"),
+        None => return Err("falta el comando"),
+    };
+    Ok(command)
+}
+
+// A spectral class, or a temperature in kelvin
+pub fn star_temperature(class: &str) -> Result<f32, &'static str> {
+    class
+        .parse()
+        .ok()
+        .or_else(|| class_temperature(class))
+        .ok_or("clase espectral: O, B, A, F, G, K o M")
+}
+
+pub fn planet_preset(name: &str) -> Result<Preset, &'static str> {
+    preset(name).ok_or("tipo de planeta: rocoso, volcanico, gaseoso, helado o fundido")
+}
+
+pub fn execute(command: &Command, simulation: &mut Simulation) {
+    match command {
+        Command::TimeScale(scale) => {
+            simulation.time_scale = scale.clamp(-MAX_TIME_SCALE, MAX_TIME_SCALE)
+        }
+        Command::Camera(target) => {
+            simulation.warp_camera(*target);
+        }
+        Command::Zoom(zoom) => simulation.camera.zoom = zoom.clamp(0.35, 1.8),
+        Command::Warp(name) => match simulation.planet_index(name) {
+            Some(index) => simulation.warp_to(index),
+            None => eprintln!("Guion: no existe el planeta {}", name),
+        },
+        Command::Select(name) => match simulation.planet_index(name) {
+            Some(index) => simulation.selected = Some(index),
+            None => eprintln!("Guion: no existe el planeta {}", name),
+        },
+        Command::StarTemperature(kelvin) => simulation.set_star_temperature(*kelvin),
+        Command::SetTime(time) => simulation.set_time(*time),
+        Command::Kepler(mass) => {
+            if let Some(mass) = mass {
+                simulation.set_star_mass(*mass);
+            }
+            simulation.apply_kepler();
+        }
+        Command::Spawn {
+            name,
+            orbit_radius,
+            orbit_speed,
+            scale,
+            preset,
+        } => simulation.spawn(spawned_planet(
+            name,
+            *orbit_radius,
+            *orbit_speed,
+            *scale,
+            preset,
+        )),
+    }
+}
+
+fn spawned_planet(
+    name: &str,
+    orbit_radius: f32,
+    orbit_speed: f32,
+    scale: f32,
+    preset: &Preset,
+) -> Planet {
+    Planet {
+        name: name.to_string(),
+        orbit_radius,
+        orbit_speed,
+        rotation_speed: 0.6,
+        scale,
+        phase: 0.0,
+        orbit_color: preset.orbit_color,
+        collision_radius: scale * 1.25,
+        mesh: preset.mesh,
+        shader: preset.shader,
+        surface: preset.surface,
+        height: preset.height,
+        noise_layers: preset.noise_layers,
+        atmosphere_density: preset.atmosphere_density,
+        moons: Vec::new(),
+        rings: Vec::new(),
+        has_aurora: false,
+        temperature: None,
+        mass: None,
+    }
+}
+
+fn invalid_line(number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("línea {}: {}", number + 1, message),
+    )
+}
@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use nalgebra_glm::Vec3;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FnPtr, Scope, AST, FLOAT};
+
+use crate::script::{execute, planet_preset, star_temperature, Command};
+use crate::simulation::Simulation;
+
+// Keeps a runaway loop in a script from freezing the window
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// What the engine's functions share with the script running them: the
+// commands queued by the last call, the closures waiting for their second and
+// the simulation clock when the step started
+#[derive(Default)]
+struct State {
+    commands: Vec<Command>,
+    timers: Vec<(f32, FnPtr)>,
+    time: f32,
+}
+
+// A rhai program driving the scene:
+//
+//   zoom(0.8);
+//   en(2, || warp("Aurelia"));          // seconds since the script started
+//   planeta("Nereo", 950, 0.2, 70, "volcanico");
+//   fn paso(t) { escala(1 + t / 10); }  // every step, if the script has it
+//
+// The top level runs once at the start. The functions queue the same commands
+// a timeline has, and they run on the simulation once the script returns.
+pub struct RhaiScript {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Rc<RefCell<State>>,
+    started: bool,
+    has_step: bool,
+    clock: f32,
+}
+
+impl RhaiScript {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let state = Rc::new(RefCell::new(State::default()));
+        let engine = engine(&state);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        let has_step = ast
+            .iter_functions()
+            .any(|function| function.name == "paso" && function.params.len() == 1);
+
+        Ok(RhaiScript {
+            engine,
+            ast,
+            scope: Scope::new(),
+            state,
+            started: false,
+            has_step,
+            clock: 0.0,
+        })
+    }
+
+    // Runs whatever is due by now; returns false once nothing is left to run
+    // or the script fails
+    pub fn advance(&mut self, simulation: &mut Simulation, delta: f32) -> bool {
+        self.clock += delta;
+        self.state.borrow_mut().time = simulation.time;
+        let result = self.run();
+
+        let commands = std::mem::take(&mut self.state.borrow_mut().commands);
+        for command in &commands {
+            execute(command, simulation);
+        }
+        if let Err(error) = result {
+            eprintln!("Guion: {}", error);
+            return false;
+        }
+        self.has_step || !self.state.borrow().timers.is_empty()
+    }
+
+    fn run(&mut self) -> Result<(), Box<EvalAltResult>> {
+        if !self.started {
+            self.started = true;
+            self.engine.run_ast_with_scope(&mut self.scope, &self.ast)?;
+        }
+
+        // Timers stay sorted, and a closure may add more while it runs
+        loop {
+            let due = {
+                let mut state = self.state.borrow_mut();
+                match state.timers.first() {
+                    Some((time, _)) if *time <= self.clock => Some(state.timers.remove(0).1),
+                    _ => None,
+                }
+            };
+            let Some(callback) = due else {
+                break;
+            };
+            // Whatever closures and `paso` return is ignored
+            let _ = callback.call::<Dynamic>(&self.engine, &self.ast, ())?;
+        }
+
+        if self.has_step {
+            // The top level already ran; only the function is called here
+            let _ = self.engine.call_fn_with_options::<Dynamic>(
+                CallFnOptions::new().eval_ast(false),
+                &mut self.scope,
+                &self.ast,
+                "paso",
+                (self.clock as FLOAT,),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn engine(state: &Rc<RefCell<State>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let queue = |state: &Rc<RefCell<State>>| {
+        let state = Rc::clone(state);
+        move |command: Command| state.borrow_mut().commands.push(command)
+    };
+
+    let push = queue(state);
+    engine.register_fn("escala", move |scale: Dynamic| {
+        push(Command::TimeScale(number(&scale)?));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("camara", move |x: Dynamic, y: Dynamic, z: Dynamic| {
+        push(Command::Camera(Vec3::new(
+            number(&x)?,
+            number(&y)?,
+            number(&z)?,
+        )));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("zoom", move |zoom: Dynamic| {
+        push(Command::Zoom(number(&zoom)?));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("warp", move |name: &str| {
+        push(Command::Warp(name.to_string()))
+    });
+    let push = queue(state);
+    engine.register_fn("seleccionar", move |name: &str| {
+        push(Command::Select(name.to_string()))
+    });
+    // A spectral class as a string, or a temperature in kelvin
+    let push = queue(state);
+    engine.register_fn("estrella", move |class: Dynamic| {
+        let temperature = match class.clone().into_string() {
+            Ok(class) => star_temperature(&class)?,
+            Err(_) => number(&class)?,
+        };
+        push(Command::StarTemperature(temperature));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("fecha", move |time: Dynamic| {
+        push(Command::SetTime(number(&time)?));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("kepler", move || push(Command::Kepler(None)));
+    let push = queue(state);
+    engine.register_fn("kepler", move |mass: Dynamic| {
+        push(Command::Kepler(Some(number(&mass)?)));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn(
+        "planeta",
+        move |name: &str, radius: Dynamic, speed: Dynamic, scale: Dynamic, kind: &str| {
+            push(Command::Spawn {
+                name: name.to_string(),
+                orbit_radius: number(&radius)?,
+                orbit_speed: number(&speed)?,
+                scale: number(&scale)?,
+                preset: planet_preset(kind)?,
+            });
+            Ok::<_, Box<EvalAltResult>>(())
+        },
+    );
+
+    let timers = Rc::clone(state);
+    engine.register_fn("en", move |time: Dynamic, callback: FnPtr| {
+        let time = number(&time)?;
+        let timers = &mut timers.borrow_mut().timers;
+        let index = timers.partition_point(|(due, _)| *due <= time);
+        timers.insert(index, (time, callback));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let clock = Rc::clone(state);
+    engine.register_fn("tiempo", move || clock.borrow().time as FLOAT);
+
+    engine
+}
+
+// Scripts may write `950` as well as `950.0`
+fn number(value: &Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    value
+        .as_float()
+        .map(|value| value as f32)
+        .or_else(|_| value.as_int().map(|value| value as f32))
+        .map_err(|_| "se esperaba un número".into())
+}
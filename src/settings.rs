@@ -0,0 +1,134 @@
+// User-facing options edited from the menu (O) and saved to the config file
+
+use crate::camera::REFERENCE_FOV;
+use crate::pixel_art::{PixelPalette, PIXEL_ART_HEIGHT};
+use crate::theme::{PalettePreset, Theme};
+
+pub const MAX_TIME_SCALE: f32 = 20.0;
+// Steps the menu walks through; 0 pauses the world and below it runs backwards
+pub const TIME_SCALE_STEPS: [f32; 15] = [
+    -MAX_TIME_SCALE,
+    -10.0,
+    -5.0,
+    -2.0,
+    -1.0,
+    -0.5,
+    -0.25,
+    0.0,
+    0.25,
+    0.5,
+    1.0,
+    2.0,
+    5.0,
+    10.0,
+    MAX_TIME_SCALE,
+];
+pub const FOV_RANGE: (f32, f32) = (30.0, 110.0);
+pub const FOV_STEP: f32 = 5.0;
+pub const RESOLUTION_RANGE: (f32, f32) = (0.25, 1.0);
+pub const RESOLUTION_STEP: f32 = 0.25;
+pub const BLOOM_INTENSITY_RANGE: (f32, f32) = (0.25, 3.0);
+pub const BLOOM_INTENSITY_STEP: f32 = 0.25;
+pub const DEPTH_OF_FIELD_STEP: f32 = 0.1;
+pub const VIGNETTE_STEP: f32 = 0.1;
+pub const FILM_GRAIN_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub show_orbits: bool,
+    pub show_labels: bool,
+    // Faded circles and spokes on the ecliptic plane, for a sense of scale
+    pub show_grid: bool,
+    // Markers on every planet's L4 and L5 points
+    pub show_lagrange: bool,
+    // Small asteroid clusters around those points
+    pub trojans: bool,
+    pub bloom: bool,
+    // Multiplies how much glow bloom adds
+    pub bloom_intensity: f32,
+    pub antialiasing: bool,
+    // Brightens the unlit side of small, distant bodies so they stay findable
+    pub visibility_assist: bool,
+    // Averages jittered frames while nothing moves, for smooth still shots
+    pub accumulation: bool,
+    pub time_scale: f32,
+    // Horizontal field of view in degrees the camera starts with; kept in
+    // sync with it so changes made with [ and ] are saved too
+    pub fov: f32,
+    // Fraction of the window resolution the scene is rendered at
+    pub resolution_scale: f32,
+    // Blur away from the focus in photo mode, 0 (off) to 1
+    pub depth_of_field: f32,
+    // Darkened corners and moving grain in photo mode and tours, 0 (off)
+    // to 1
+    pub vignette: f32,
+    pub film_grain: f32,
+    // Old television look: scanlines, a bulging screen and colors that
+    // split toward the edges
+    pub crt: bool,
+    // Renders a couple hundred rows tall in the palette's colors; None
+    // for the usual output
+    pub pixel_art: Option<PixelPalette>,
+    // Orbit and HUD colors, with presets safe for red-green color blindness
+    pub palette: PalettePreset,
+    // White text on nearly opaque panels
+    pub high_contrast: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            show_orbits: true,
+            show_labels: false,
+            show_grid: false,
+            show_lagrange: false,
+            trojans: false,
+            bloom: false,
+            bloom_intensity: 1.0,
+            antialiasing: false,
+            visibility_assist: false,
+            accumulation: false,
+            time_scale: 1.0,
+            fov: REFERENCE_FOV,
+            resolution_scale: 1.0,
+            depth_of_field: 0.0,
+            vignette: 0.0,
+            film_grain: 0.0,
+            crt: false,
+            pixel_art: None,
+            palette: PalettePreset::Original,
+            high_contrast: false,
+        }
+    }
+}
+
+impl Settings {
+    // Keeps values read from disk inside the ranges the menu offers
+    pub fn clamped(mut self) -> Self {
+        self.time_scale = self.time_scale.clamp(-MAX_TIME_SCALE, MAX_TIME_SCALE);
+        self.fov = self.fov.clamp(FOV_RANGE.0, FOV_RANGE.1);
+        self.resolution_scale = self
+            .resolution_scale
+            .clamp(RESOLUTION_RANGE.0, RESOLUTION_RANGE.1);
+        self.bloom_intensity = self
+            .bloom_intensity
+            .clamp(BLOOM_INTENSITY_RANGE.0, BLOOM_INTENSITY_RANGE.1);
+        self.depth_of_field = self.depth_of_field.clamp(0.0, 1.0);
+        self.vignette = self.vignette.clamp(0.0, 1.0);
+        self.film_grain = self.film_grain.clamp(0.0, 1.0);
+        self
+    }
+
+    // Fraction of a window `height` pixels tall the scene is rendered at;
+    // pixel art overrides the resolution setting
+    pub fn scene_scale(&self, height: usize) -> f32 {
+        match self.pixel_art {
+            Some(_) => (PIXEL_ART_HEIGHT / height as f32).min(1.0),
+            None => self.resolution_scale,
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        Theme::new(self.palette, self.high_contrast)
+    }
+}
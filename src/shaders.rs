@@ -1,5 +1,5 @@
 use crate::vertex::Vertex;
-use crate::Uniforms;
+use crate::renderer::Uniforms;
 use nalgebra_glm::{Mat3, Vec3, Vec4};
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
@@ -24,10 +24,9 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         uniforms.model_matrix[9],
         uniforms.model_matrix[10],
     );
-    let normal_matrix = model_mat3
-        .transpose()
-        .try_inverse()
-        .unwrap_or(Mat3::identity());
+    // `model_mat3` is already transposed (built from the columns), so its
+    // inverse is the inverse-transpose normals need
+    let normal_matrix = model_mat3.try_inverse().unwrap_or(Mat3::identity());
 
     let transformed_normal = normal_matrix * vertex.normal;
 
@@ -0,0 +1,170 @@
+use nalgebra_glm::Vec3;
+
+use crate::input::{Action, InputFrame};
+use crate::physics::gravity_at;
+
+const THRUST: f32 = 90.0;
+const BOOST: f32 = 2.2;
+const PREDICTION_STEPS: usize = 480;
+const PREDICTION_STEP: f32 = 1.0 / 24.0;
+// A star heats the hull from this many of its collision radii in, fully at
+// the collision radius itself
+const HEAT_RANGE: f32 = 1.5;
+// Past this much heat the star pushes the ship back, up to `HEAT_PUSH` at
+// full heat: more than gravity and full thrust can hold against
+const HEAT_PUSH_START: f32 = 0.6;
+const HEAT_PUSH: f32 = 400.0;
+// Share of a full tank burned per second of thrust, times `BOOST` while
+// boosting, and by each warp made in flight mode
+const BURN_RATE: f32 = 0.025;
+const WARP_FUEL: f32 = 0.15;
+// A gas giant's upper atmosphere reaches this many of its collision radii
+// out; skimming through it scoops fuel, faster the deeper the ship dips
+const SKIM_RANGE: f32 = 1.3;
+const SKIM_RATE: f32 = 0.08;
+
+// Where the ship model sits relative to the camera it carries
+pub fn ship_offset() -> Vec3 {
+    Vec3::new(0.0, 0.0, -140.0)
+}
+
+pub struct Ship {
+    pub flight_mode: bool,
+    pub headlight: bool,
+    pub velocity: Vec3,
+    pub prediction: Vec<Vec3>,
+    // From 0 out of every star's reach to 1 at a star's collision radius
+    pub heat: f32,
+    // From 0 for an empty tank to 1 for a full one
+    pub fuel: f32,
+    // Scooping fuel from a gas giant's atmosphere this step
+    pub skimming: bool,
+}
+
+impl Ship {
+    pub fn new() -> Self {
+        Ship {
+            flight_mode: false,
+            headlight: false,
+            velocity: Vec3::zeros(),
+            prediction: Vec::new(),
+            heat: 0.0,
+            fuel: 1.0,
+            skimming: false,
+        }
+    }
+
+    // Returns the normalized thrust direction, or zero when coasting
+    pub fn thrust(&mut self, input: &InputFrame, delta: f32) -> Vec3 {
+        let mut direction = Vec3::zeros();
+        if input.is_held(Action::Forward) {
+            direction.z -= 1.0;
+        }
+        if input.is_held(Action::Backward) {
+            direction.z += 1.0;
+        }
+        if input.is_held(Action::Left) {
+            direction.x -= 1.0;
+        }
+        if input.is_held(Action::Right) {
+            direction.x += 1.0;
+        }
+        if input.is_held(Action::Ascend) {
+            direction.y += 1.0;
+        }
+        if input.is_held(Action::Descend) {
+            direction.y -= 1.0;
+        }
+
+        // With the tank dry the ship can only coast
+        if direction.magnitude() == 0.0 || self.fuel <= 0.0 {
+            return Vec3::zeros();
+        }
+
+        let direction = direction.normalize();
+        let boost = if input.is_held(Action::Boost) {
+            BOOST
+        } else {
+            1.0
+        };
+        self.velocity += direction * THRUST * boost * delta;
+        self.fuel = (self.fuel - BURN_RATE * boost * delta).max(0.0);
+        direction
+    }
+
+    pub fn integrate(&mut self, position: &mut Vec3, attractors: &[(Vec3, f32)], delta: f32) {
+        self.velocity += gravity_at(*position, attractors) * delta;
+        *position += self.velocity * delta;
+    }
+
+    // Takes the heat of the closest star, given as (center, collision
+    // radius), and gets pushed away from it when too close
+    pub fn heat_up(&mut self, position: Vec3, stars: &[(Vec3, f32)], delta: f32) {
+        let hottest = stars
+            .iter()
+            .map(|&(center, radius)| {
+                let distance = (position - center).magnitude();
+                let heat = (HEAT_RANGE * radius - distance) / ((HEAT_RANGE - 1.0) * radius);
+                (center, heat.clamp(0.0, 1.0))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((center, heat)) = hottest else {
+            self.heat = 0.0;
+            return;
+        };
+        self.heat = heat;
+
+        let away = position - center;
+        if heat > HEAT_PUSH_START && away.magnitude() > 0.0 {
+            let push = (heat - HEAT_PUSH_START) / (1.0 - HEAT_PUSH_START);
+            self.velocity += away.normalize() * HEAT_PUSH * push * delta;
+        }
+    }
+
+    // Scoops fuel while inside the upper atmosphere of a gas giant, given as
+    // (center, collision radius)
+    pub fn skim(&mut self, position: Vec3, giants: &[(Vec3, f32)], delta: f32) {
+        let depth = giants
+            .iter()
+            .map(|&(center, radius)| {
+                let distance = (position - center).magnitude();
+                (SKIM_RANGE * radius - distance) / ((SKIM_RANGE - 1.0) * radius)
+            })
+            .fold(0.0, f32::max)
+            .min(1.0);
+        self.skimming = depth > 0.0 && self.fuel < 1.0;
+        if self.skimming {
+            self.fuel = (self.fuel + SKIM_RATE * depth * delta).min(1.0);
+        }
+    }
+
+    // Pays for a warp out of the tank; false when there isn't enough
+    pub fn burn_warp(&mut self) -> bool {
+        if self.fuel < WARP_FUEL {
+            return false;
+        }
+        self.fuel -= WARP_FUEL;
+        true
+    }
+}
+
+// Coasts forward from `start` with `velocity`, refilling `path`;
+// `attractors_at` fills its buffer with the attractors at a time offset from
+// now, so the whole prediction reuses one buffer.
+pub fn predict_path<F>(path: &mut Vec<Vec3>, start: Vec3, velocity: Vec3, mut attractors_at: F)
+where
+    F: FnMut(f32, &mut Vec<(Vec3, f32)>),
+{
+    path.clear();
+    path.reserve(PREDICTION_STEPS);
+    let mut attractors = Vec::new();
+    let mut position = start;
+    let mut velocity = velocity;
+
+    for step in 0..PREDICTION_STEPS {
+        attractors_at(step as f32 * PREDICTION_STEP, &mut attractors);
+        velocity += gravity_at(position, &attractors) * PREDICTION_STEP;
+        position += velocity * PREDICTION_STEP;
+        path.push(position);
+    }
+}
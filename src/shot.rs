@@ -0,0 +1,139 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use nalgebra_glm::Vec3;
+
+use crate::json;
+use crate::simulation::Simulation;
+
+// Width of a full-frame sensor in millimeters, which the field of view is
+// turned into a familiar focal length against
+const SENSOR_WIDTH: f32 = 36.0;
+
+// Everything needed to put the camera back where a screenshot was taken,
+// saved as JSON next to the image
+pub struct Shot {
+    pub seed: u64,
+    pub time: f32,
+    pub position: Vec3,
+    pub zoom: f32,
+    pub tilt: f32,
+    pub roll: f32,
+    pub fov: f32,
+    // Bodies under gravity drift from their clockwork orbits, so a restored
+    // shot can only place them where the clockwork has them
+    pub gravity: bool,
+}
+
+impl Shot {
+    pub fn capture(simulation: &Simulation) -> Self {
+        let camera = &simulation.camera;
+        Shot {
+            seed: simulation.seed,
+            time: simulation.time,
+            position: camera.position,
+            zoom: camera.zoom,
+            tilt: camera.tilt,
+            roll: camera.roll,
+            fov: camera.fov,
+            gravity: simulation.gravity_enabled(),
+        }
+    }
+
+    // Writes `<image>.json` beside the image and returns its path. The seed
+    // is a string: as a JSON number it would lose digits past 2^53
+    pub fn save_beside(&self, image: &Path) -> io::Result<PathBuf> {
+        let path = image.with_extension("json");
+        let name = image
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        // Non-finite values are written as `null`, which `load` rejects
+        // instead of the file not parsing at all
+        let output = format!(
+            "{{\n  \"image\": \"{}\",\n  \"seed\": \"{}\",\n  \"time\": {},\n  \"gravity\": {},\n  \
+             \"camera\": {{\n    \"position\": [{}, {}, {}],\n    \"zoom\": {},\n    \
+             \"tilt\": {},\n    \"roll\": {},\n    \"fov\": {},\n    \"focal_length\": {}\n  }}\n}}\n",
+            json::escape(&name),
+            self.seed,
+            json::number(self.time),
+            self.gravity,
+            json::number(self.position.x),
+            json::number(self.position.y),
+            json::number(self.position.z),
+            json::number(self.zoom),
+            json::number(self.tilt),
+            json::number(self.roll),
+            json::number(self.fov),
+            // To a tenth of a millimeter, as lenses are labeled
+            json::number((focal_length(self.fov) * 10.0).round() / 10.0),
+        );
+        fs::write(&path, output)?;
+        Ok(path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let table = json::parse(&fs::read_to_string(path)?)?;
+        let missing = |key: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("falta `{}` o no es válido", key),
+            )
+        };
+        let number = |key: &str| {
+            table
+                .get(key)
+                .and_then(|value| value.as_f32())
+                .ok_or_else(|| missing(key))
+        };
+        let seed = table
+            .get("seed")
+            .and_then(|value| value.as_str())
+            .and_then(|seed| seed.parse().ok())
+            .ok_or_else(|| missing("seed"))?;
+        let position = table
+            .get("camera.position")
+            .and_then(|value| value.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_f32())
+                    .collect::<Vec<_>>()
+            });
+        let Some(&[x, y, z]) = position.as_deref() else {
+            return Err(missing("camera.position"));
+        };
+        Ok(Shot {
+            seed,
+            time: number("time")?,
+            position: Vec3::new(x, y, z),
+            zoom: number("camera.zoom")?,
+            tilt: number("camera.tilt")?,
+            roll: number("camera.roll")?,
+            fov: number("camera.fov")?,
+            gravity: table
+                .get("gravity")
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false),
+        })
+    }
+
+    // Sets the clock and the camera, and holds the world still in photo
+    // mode so the shot can be taken again
+    pub fn restore(&self, simulation: &mut Simulation) {
+        simulation.set_time(self.time);
+        let camera = &mut simulation.camera;
+        camera.position = self.position;
+        camera.zoom = self.zoom;
+        camera.tilt = self.tilt;
+        camera.roll = self.roll;
+        camera.fov = self.fov;
+        simulation.enter_photo_mode();
+    }
+}
+
+// Full-frame equivalent focal length, in millimeters, of a horizontal field
+// of view in degrees
+pub fn focal_length(fov: f32) -> f32 {
+    SENSOR_WIDTH * 0.5 / (fov.to_radians() * 0.5).tan()
+}
@@ -0,0 +1,1142 @@
+use std::f32::consts::{PI, TAU};
+
+use nalgebra_glm::{Vec2, Vec3};
+
+use crate::belt::{generate_trojans, BeltBody};
+use crate::bookmarks::{Bookmark, Bookmarks, BOOKMARK_SLOTS};
+use crate::camera::{Camera, View};
+use crate::color::Palette;
+use crate::events::{find_alignments, Alignment, PredictedKind};
+use crate::fragment_shaders::{FragmentShader, HeightFunction, SurfaceFunction};
+use crate::input::{Action, InputFrame};
+use crate::measure::Measure;
+use crate::noise::NoiseLayer;
+use crate::objectives::Objectives;
+use crate::physics::{
+    circular_speed, kepler_speed, orbit_elements, Body, GravityWorld, OrbitElements,
+};
+use crate::rng::Rng;
+use crate::scene::TROJANS;
+use crate::ship::{predict_path, ship_offset, Ship};
+use crate::timeline::{Timeline, TIMELINE_SLOTS};
+use crate::tour::Tour;
+use crate::units::SceneScale;
+
+const STAR_MASS: f32 = 5.0e6;
+const NUDGE_ACCELERATION: f32 = 40.0;
+const LANDING_ZOOM: f32 = 2.5;
+const SURFACE_CLEARANCE: f32 = 1.12;
+// What the time jumps call a day: one turn of a body spinning at rate 1,
+// like the rocky world with a moon
+pub const DAY: f32 = TAU;
+// Stylized moons run well ahead of Kepler's law so they visibly move; one
+// faster than this many times its planet's pull allows is flagged
+const MOON_SPEED_SLACK: f32 = 50.0;
+// Bodies smaller than this on screen are still picked this close, in pixels
+const PICK_SLACK: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshKind {
+    Star,
+    Rocky,
+    GasGiant,
+    Moon,
+}
+
+pub struct Moon {
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub rotation_speed: f32,
+    pub scale: f32,
+    pub phase: f32,
+    pub mesh: MeshKind,
+    pub shader: FragmentShader,
+    // Turns once per orbit, always showing its planet the same face
+    pub tidally_locked: bool,
+}
+
+impl Moon {
+    fn angle(&self, time: f32) -> f32 {
+        time * self.orbit_speed + self.phase
+    }
+
+    pub fn orbit_period(&self) -> Option<f32> {
+        period(self.orbit_speed)
+    }
+
+    pub fn offset(&self, time: f32) -> Vec3 {
+        let angle = self.angle(time);
+        Vec3::new(
+            self.orbit_radius * angle.cos(),
+            0.0,
+            self.orbit_radius * angle.sin(),
+        )
+    }
+
+    // Rotation of the moon's mesh; a locked moon keeps its +X side pointed
+    // back at the planet
+    pub fn rotation(&self, time: f32) -> Vec3 {
+        if self.tidally_locked {
+            Vec3::new(0.0, PI - self.angle(time), 0.0)
+        } else {
+            Vec3::new(
+                time * self.rotation_speed,
+                time * self.rotation_speed * 0.5,
+                0.0,
+            )
+        }
+    }
+}
+
+pub struct RingDef {
+    // Edges of the ring, in radii of its planet
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    // Tilt of the ring plane, in radians
+    pub tilt: f32,
+    pub rotation_speed: f32,
+    // Color from the inner edge (0) to the outer one (1)
+    pub colors: Palette<'static>,
+    // 1 is solid; lower values let what is behind show through
+    pub opacity: f32,
+}
+
+impl RingDef {
+    pub fn rotation(&self, time: f32) -> Vec3 {
+        Vec3::new(self.tilt, 0.0, time * self.rotation_speed)
+    }
+}
+
+pub struct Planet {
+    pub name: String,
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub rotation_speed: f32,
+    pub scale: f32,
+    pub phase: f32,
+    pub orbit_color: u32,
+    pub collision_radius: f32,
+    pub mesh: MeshKind,
+    pub shader: FragmentShader,
+    // The shader's lighting-independent part, for shaders static enough to
+    // bake into an atlas
+    pub surface: Option<SurfaceFunction>,
+    pub height: Option<HeightFunction>,
+    // The shader's static noise layers, which the renderer may bake ahead
+    pub noise_layers: &'static [NoiseLayer],
+    // How thick its air is: 0 for airless bodies, about 1 for an Earth-like
+    // one. It widens the twilight band along the terminator
+    pub atmosphere_density: f32,
+    pub moons: Vec<Moon>,
+    pub rings: Vec<RingDef>,
+    pub has_aurora: bool,
+    // Surface temperature in kelvin; only stars have one, and it sets the
+    // color of the light they cast
+    pub temperature: Option<f32>,
+    // Mass given outright instead of worked out from the size; the config
+    // sets the star's
+    pub mass: Option<f32>,
+}
+
+impl Planet {
+    // The star dominates; planet masses only perturb each other slightly
+    pub fn mass(&self) -> f32 {
+        if let Some(mass) = self.mass {
+            mass
+        } else if self.orbit_radius == 0.0 {
+            STAR_MASS
+        } else {
+            self.scale.powi(3) * 0.02
+        }
+    }
+
+    pub fn rotation(&self, time: f32) -> Vec3 {
+        Vec3::new(0.0, self.rotation_speed * time, self.rotation_speed * 0.3)
+    }
+
+    // Seconds per orbit; None for the star and bodies that hold still
+    pub fn orbit_period(&self) -> Option<f32> {
+        period(self.orbit_speed).filter(|_| self.orbit_radius > 0.0)
+    }
+
+    // Seconds per turn about its own axis
+    pub fn rotation_period(&self) -> Option<f32> {
+        period(self.rotation_speed)
+    }
+
+    pub fn position(&self, time: f32) -> Vec3 {
+        if self.orbit_radius == 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        let angle = time * self.orbit_speed + self.phase;
+        Vec3::new(
+            self.orbit_radius * angle.cos(),
+            0.0,
+            self.orbit_radius * angle.sin(),
+        )
+    }
+}
+
+// Seconds per turn at `speed` radians per second, either way round
+pub fn period(speed: f32) -> Option<f32> {
+    (speed != 0.0).then(|| TAU / speed.abs())
+}
+
+// Sets a planet's speed around a star of `star_mass`, and its moons' around
+// the planet, from Kepler's third law; each keeps its direction
+fn kepler_orbit(planet: &mut Planet, star_mass: f32) {
+    if planet.orbit_radius <= 0.0 {
+        return;
+    }
+    let direction = if planet.orbit_speed < 0.0 { -1.0 } else { 1.0 };
+    planet.orbit_speed = kepler_speed(star_mass, planet.orbit_radius) * direction;
+    let mass = planet.mass();
+    for moon in &mut planet.moons {
+        let direction = if moon.orbit_speed < 0.0 { -1.0 } else { 1.0 };
+        moon.orbit_speed = kepler_speed(mass, moon.orbit_radius) * direction;
+    }
+}
+
+// Camera inside a planet's collision radius with landing enabled; `blend`
+// ramps from 0 at the collision radius to 1 at the surface.
+#[derive(Debug, Clone, Copy)]
+pub struct Landing {
+    pub planet: usize,
+    pub blend: f32,
+    pub altitude: f32,
+}
+
+// Positions of every body and the camera at one simulation step
+#[derive(Clone)]
+pub struct BodyState {
+    pub position: Vec3,
+    // One per moon, in the order of `Planet::moons`
+    pub moon_positions: Vec<Vec3>,
+}
+
+#[derive(Clone)]
+pub struct Snapshot {
+    pub time: f32,
+    pub bodies: Vec<BodyState>,
+    pub view: View,
+    pub ship_bank: f32,
+    pub landing: Option<Landing>,
+    // The ship's heat from nearby stars, 0 outside flight mode
+    pub heat: f32,
+    // 1 while the camera is being moved, easing to 0 once it coasts
+    pub throttle: f32,
+}
+
+impl Snapshot {
+    pub fn lerp(&self, next: &Snapshot, alpha: f32) -> Snapshot {
+        let bodies = self
+            .bodies
+            .iter()
+            .zip(&next.bodies)
+            .map(|(a, b)| BodyState {
+                position: a.position + (b.position - a.position) * alpha,
+                moon_positions: a
+                    .moon_positions
+                    .iter()
+                    .zip(&b.moon_positions)
+                    .map(|(from, to)| from + (to - from) * alpha)
+                    .collect(),
+            })
+            .collect();
+
+        Snapshot {
+            time: self.time + (next.time - self.time) * alpha,
+            bodies,
+            view: self.view.lerp(&next.view, alpha),
+            ship_bank: self.ship_bank + (next.ship_bank - self.ship_bank) * alpha,
+            heat: self.heat + (next.heat - self.heat) * alpha,
+            throttle: self.throttle + (next.throttle - self.throttle) * alpha,
+            landing: match (self.landing, next.landing) {
+                (Some(from), Some(to)) if from.planet == to.planet => Some(Landing {
+                    planet: to.planet,
+                    blend: from.blend + (to.blend - from.blend) * alpha,
+                    altitude: from.altitude + (to.altitude - from.altitude) * alpha,
+                }),
+                _ => next.landing,
+            },
+        }
+    }
+}
+
+// What a player of a multiplayer game steers through the shared system on
+// their own: camera, ship and whatever they are looking at. The other
+// players' are swapped into the simulation for their part of each step
+pub struct Pilot {
+    camera: Camera,
+    ship: Ship,
+    selected: Option<usize>,
+    landing_enabled: bool,
+    alignments: Vec<Alignment>,
+    bookmarks: Bookmarks,
+    objectives: Objectives,
+    tour: Option<Tour>,
+    measure: Measure,
+    timeline: Timeline,
+    map_view: bool,
+    photo: Option<View>,
+    previous: Snapshot,
+    current: Snapshot,
+}
+
+impl Pilot {
+    // Where the ship is drawn, ahead of the camera
+    pub fn ship_position(&self) -> Vec3 {
+        self.camera.position + ship_offset()
+    }
+}
+
+pub struct Simulation {
+    pub planets: Vec<Planet>,
+    pub belt: Vec<BeltBody>,
+    // Clusters at every planet's L4 and L5, shown from the menu
+    pub trojans: Vec<BeltBody>,
+    // Proportions the scene was converted to; bodies spawned later are too
+    pub scene_scale: SceneScale,
+    pub camera: Camera,
+    pub time: f32,
+    pub seed: u64,
+    pub selected: Option<usize>,
+    pub ship: Ship,
+    pub landing_enabled: bool,
+    pub alignments: Vec<Alignment>,
+    pub bookmarks: Bookmarks,
+    pub objectives: Objectives,
+    pub tour: Option<Tour>,
+    pub measure: Measure,
+    pub timeline: Timeline,
+    // Full-screen map of the selected planet's surface
+    pub map_view: bool,
+    // How fast bodies move relative to the fixed step; the camera ignores it
+    pub time_scale: f32,
+    // Orbit speeds follow Kepler's third law, for bodies spawned later too
+    pub kepler: bool,
+    // Where the other players' ships are, in a multiplayer game
+    pub fleet: Vec<Vec3>,
+    gravity: Option<GravityWorld>,
+    // Gameplay camera to return to when photo mode is on
+    photo: Option<View>,
+    previous: Snapshot,
+    current: Snapshot,
+}
+
+impl Simulation {
+    pub fn new(planets: Vec<Planet>, seed: u64) -> Self {
+        let camera = Camera::new();
+        let snapshot = Snapshot {
+            time: 0.0,
+            bodies: Vec::new(),
+            view: camera.view(),
+            ship_bank: 0.0,
+            landing: None,
+            heat: 0.0,
+            throttle: 0.0,
+        };
+        let mut simulation = Simulation {
+            planets,
+            belt: Vec::new(),
+            trojans: Vec::new(),
+            scene_scale: SceneScale::Stylized,
+            camera,
+            time: 0.0,
+            seed,
+            selected: None,
+            ship: Ship::new(),
+            landing_enabled: false,
+            alignments: Vec::new(),
+            bookmarks: Bookmarks::default(),
+            objectives: Objectives::default(),
+            tour: None,
+            measure: Measure::default(),
+            timeline: Timeline::default(),
+            map_view: false,
+            time_scale: 1.0,
+            kepler: false,
+            fleet: Vec::new(),
+            gravity: None,
+            photo: None,
+            previous: snapshot.clone(),
+            current: snapshot,
+        };
+        simulation.scatter_trojans();
+        simulation.current = simulation.capture();
+        simulation.previous = simulation.current.clone();
+        simulation.fit_camera();
+        simulation
+    }
+
+    // Converts the bodies, still in stylized units, to `scale`; meant to be
+    // called once, after the belt is in place and before the first update
+    pub fn set_scene_scale(&mut self, scale: SceneScale) {
+        for planet in &mut self.planets {
+            scale.apply_to_planet(planet);
+        }
+        for body in &mut self.belt {
+            scale.apply_to_belt_body(body);
+        }
+        self.scene_scale = scale;
+        self.scatter_trojans();
+        self.current = self.capture();
+        self.previous = self.current.clone();
+        self.fit_camera();
+    }
+
+    // Farthest any body gets from the center: planets with their moons and
+    // rings, and the belt
+    pub fn extent(&self) -> f32 {
+        let planets = self.planets.iter().map(|planet| {
+            let moons = planet
+                .moons
+                .iter()
+                .map(|moon| moon.orbit_radius + moon.scale)
+                .fold(0.0, f32::max);
+            let rings = planet
+                .rings
+                .iter()
+                .map(|ring| ring.outer_radius)
+                .fold(1.0, f32::max);
+            planet.orbit_radius + moons.max(planet.scale * rings)
+        });
+        let belt = self.belt.iter().map(|body| body.orbit_radius + body.scale);
+        planets.chain(belt).fold(0.0, f32::max)
+    }
+
+    fn fit_camera(&mut self) {
+        let extent = self.extent();
+        self.camera.fit_extent(extent);
+    }
+
+    pub fn update(&mut self, input: &InputFrame, delta: f32) {
+        self.step(input, delta, true);
+    }
+
+    // A step for a multiplayer player other than the host: their camera and
+    // ship move through the system, but the clock and the bodies only follow
+    // the host
+    pub fn update_pilot(&mut self, input: &InputFrame, delta: f32) {
+        self.step(input, delta, false);
+    }
+
+    // The camera and ship of a fresh simulation, for another player starting
+    // out in it
+    pub fn into_pilot(self) -> Pilot {
+        Pilot {
+            camera: self.camera,
+            ship: self.ship,
+            selected: self.selected,
+            landing_enabled: self.landing_enabled,
+            alignments: self.alignments,
+            bookmarks: self.bookmarks,
+            objectives: self.objectives,
+            tour: self.tour,
+            measure: self.measure,
+            timeline: self.timeline,
+            map_view: self.map_view,
+            photo: self.photo,
+            previous: self.previous,
+            current: self.current,
+        }
+    }
+
+    // Trades this player's camera and ship for another's; swapping again
+    // trades them back
+    pub fn swap_pilot(&mut self, pilot: &mut Pilot) {
+        std::mem::swap(&mut self.camera, &mut pilot.camera);
+        std::mem::swap(&mut self.ship, &mut pilot.ship);
+        std::mem::swap(&mut self.selected, &mut pilot.selected);
+        std::mem::swap(&mut self.landing_enabled, &mut pilot.landing_enabled);
+        std::mem::swap(&mut self.alignments, &mut pilot.alignments);
+        std::mem::swap(&mut self.bookmarks, &mut pilot.bookmarks);
+        std::mem::swap(&mut self.objectives, &mut pilot.objectives);
+        std::mem::swap(&mut self.tour, &mut pilot.tour);
+        std::mem::swap(&mut self.measure, &mut pilot.measure);
+        std::mem::swap(&mut self.timeline, &mut pilot.timeline);
+        std::mem::swap(&mut self.map_view, &mut pilot.map_view);
+        std::mem::swap(&mut self.photo, &mut pilot.photo);
+        std::mem::swap(&mut self.previous, &mut pilot.previous);
+        std::mem::swap(&mut self.current, &mut pilot.current);
+    }
+
+    // `world` is false for a player who moves through the system without
+    // moving its clock or bodies
+    fn step(&mut self, input: &InputFrame, delta: f32, world: bool) {
+        if input.was_pressed(Action::TogglePhotoMode) {
+            self.toggle_photo_mode();
+        }
+        if self.photo.is_some() {
+            // The world holds still while a shot is framed
+            let anchor = self.zoom_anchor(input);
+            self.camera.handle_photo_input(input, delta, anchor);
+            let snapshot = self.capture();
+            self.previous = std::mem::replace(&mut self.current, snapshot);
+            return;
+        }
+
+        if world {
+            let world_delta = delta * self.time_scale;
+            self.time += world_delta;
+
+            if input.was_pressed(Action::ToggleGravity) {
+                self.toggle_gravity();
+            }
+            if self.gravity.is_some() {
+                // Pushes act in real time, whichever way the clock runs
+                self.apply_nudges(input, world_delta.abs());
+            }
+            if let Some(gravity) = self.gravity.as_mut() {
+                gravity.step(world_delta);
+            }
+        }
+
+        if input.was_pressed(Action::ToggleLanding) {
+            self.landing_enabled = !self.landing_enabled;
+        }
+
+        if input.was_pressed(Action::ToggleFlightAssist) {
+            self.camera.flight_assist = !self.camera.flight_assist;
+        }
+
+        if input.was_pressed(Action::ToggleHeadlight) {
+            self.ship.headlight = !self.ship.headlight;
+        }
+
+        if input.was_pressed(Action::ToggleFlight) {
+            self.ship.flight_mode = !self.ship.flight_mode;
+            self.ship.velocity = Vec3::zeros();
+            self.camera.velocity = Vec3::zeros();
+            self.ship.prediction.clear();
+            self.ship.heat = 0.0;
+            self.ship.skimming = false;
+        }
+
+        // Steering by hand or warping somewhere else ends the tour
+        let steering = [
+            Action::Forward,
+            Action::Backward,
+            Action::Left,
+            Action::Right,
+            Action::Ascend,
+            Action::Descend,
+        ]
+        .iter()
+        .any(|action| input.is_held(*action));
+        if steering || self.camera.is_warping() {
+            self.tour = None;
+        } else if input.was_pressed(Action::ToggleTour) && self.tour.take().is_none() {
+            self.tour = Some(Tour::new(self));
+        }
+
+        let blockers = self.blockers();
+        if let Some(mut tour) = self.tour.take() {
+            if tour.advance(self, delta) {
+                self.tour = Some(tour);
+            }
+        } else if self.ship.flight_mode && !self.camera.is_warping() {
+            let direction = self.ship.thrust(input, delta);
+            self.camera.track_direction(direction);
+            // The ship is the camera here, so zoom never drags it sideways
+            self.camera.handle_zoom(input, delta, None);
+            self.camera.handle_lens(input, delta);
+            // The ship flies ahead of the camera, so gravity pulls on it there
+            let mut attractors = Vec::new();
+            self.attractors_at(self.time, &mut attractors);
+            let mut position = self.ship_position();
+            self.ship.integrate(&mut position, &attractors, delta);
+            self.camera.position = position - ship_offset();
+        } else {
+            let anchor = self.zoom_anchor(input);
+            self.camera.handle_input(input, delta, anchor);
+        }
+        self.camera.advance_warp(delta);
+        if self.camera.resolve_collisions(&blockers) {
+            self.ship.velocity *= 0.2;
+            self.camera.velocity *= 0.2;
+        }
+        if self.ship.flight_mode {
+            let stars = self.stars();
+            self.ship.heat_up(self.ship_position(), &stars, delta);
+            let giants = self.giants();
+            self.ship.skim(self.ship_position(), &giants, delta);
+        }
+
+        if input.was_pressed(Action::ToggleBookmarks) {
+            self.bookmarks.browsing = !self.bookmarks.browsing;
+        }
+        for slot in 0..BOOKMARK_SLOTS {
+            if input.was_pressed(Action::StoreBookmark(slot)) {
+                self.store_bookmark(slot);
+            }
+        }
+
+        if world && input.was_pressed(Action::RewindDay) {
+            self.set_time(self.time - DAY);
+        }
+        if world && input.was_pressed(Action::AdvanceDay) {
+            self.set_time(self.time + DAY);
+        }
+
+        if input.was_pressed(Action::ToggleTimeline) {
+            self.timeline.toggle();
+        }
+        // Predictions follow the clockwork orbits, which gravity leaves behind
+        let timeline = self.timeline.open && self.gravity.is_none();
+        for index in 0..self.planets.len().max(BOOKMARK_SLOTS) {
+            if !input.was_pressed(Action::Warp(index)) {
+                continue;
+            }
+            if self.bookmarks.browsing {
+                self.recall_bookmark(index);
+            } else if timeline && index < TIMELINE_SLOTS {
+                if world {
+                    self.jump_to_event(index);
+                }
+            } else if index < self.planets.len() {
+                self.warp_to(index);
+            }
+        }
+
+        if self.ship.flight_mode {
+            let (time, scale) = (self.time, self.time_scale);
+            let mut path = std::mem::take(&mut self.ship.prediction);
+            predict_path(
+                &mut path,
+                self.ship_position(),
+                self.ship.velocity,
+                |offset, attractors| self.attractors_at(time + offset * scale, attractors),
+            );
+            self.ship.prediction = path;
+        }
+
+        if input.was_pressed(Action::WatchEvent) {
+            if let Some(alignment) = self.alignments.first().copied() {
+                if self.warp_camera(alignment.viewpoint()) {
+                    self.ship.velocity = Vec3::zeros();
+                }
+                self.selected = Some(alignment.planet);
+            }
+        }
+
+        if input.was_pressed(Action::ToggleMeasure) {
+            self.measure.toggle();
+        }
+        if input.was_pressed(Action::ToggleMap) {
+            self.map_view = !self.map_view;
+        }
+        if self.measure.active && input.was_pressed(Action::Pick) {
+            if let Some(index) = input.cursor().and_then(|cursor| self.planet_under(cursor)) {
+                self.measure.pick(index);
+            }
+        }
+
+        if input.was_pressed(Action::CycleTarget) && !self.planets.is_empty() {
+            let next = self
+                .selected
+                .map_or(0, |index| (index + 1) % self.planets.len());
+            self.selected = Some(next);
+        }
+
+        if timeline {
+            self.timeline
+                .update(&self.planets, self.time, self.time_scale < 0.0);
+        }
+
+        let mut objectives = std::mem::take(&mut self.objectives);
+        objectives.update(self, delta);
+        self.objectives = objectives;
+
+        let snapshot = self.capture();
+        self.alignments = find_alignments(&self.planets, &snapshot.bodies, &snapshot.view);
+        self.previous = std::mem::replace(&mut self.current, snapshot);
+    }
+
+    // Orbit speeds from Kepler's third law instead of the scene's: planets
+    // around the star's mass and moons around their planet's, which leaves
+    // the moons far slower than the stylized scene has them
+    pub fn apply_kepler(&mut self) {
+        self.kepler = true;
+        let star_mass = self.planets.first().map_or(0.0, |star| star.mass());
+        for planet in &mut self.planets {
+            kepler_orbit(planet, star_mass);
+        }
+        self.scatter_trojans();
+        self.timeline.invalidate();
+        self.current = self.capture();
+        self.previous = self.current.clone();
+    }
+
+    // Moons going round faster than their planet could hold them, even
+    // allowing for the stylized scene's slack
+    pub fn orbit_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for planet in &self.planets {
+            for (number, moon) in planet.moons.iter().enumerate() {
+                let kepler = kepler_speed(planet.mass(), moon.orbit_radius);
+                if moon.orbit_speed.abs() <= kepler * MOON_SPEED_SLACK {
+                    continue;
+                }
+                let (Some(actual), Some(expected)) = (moon.orbit_period(), period(kepler)) else {
+                    continue;
+                };
+                warnings.push(format!(
+                    "luna {} de {}: una vuelta cada {:.1} s, su planeta la sostiene a {:.0} s",
+                    number + 1,
+                    planet.name,
+                    actual,
+                    expected,
+                ));
+            }
+        }
+        warnings
+    }
+
+    // Moves the clock anywhere, back as well as forward. The clockwork orbits
+    // are worked out from the time alone; in gravity mode the bodies start
+    // again from where those orbits put them
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+        if self.gravity.take().is_some() {
+            self.toggle_gravity();
+        }
+        self.timeline.invalidate();
+        // Bodies don't sweep across the screen to where the jump took them
+        self.current = self.capture();
+        self.previous = self.current.clone();
+    }
+
+    // Sets the clock to the listed event in `slot` and selects its planet
+    fn jump_to_event(&mut self, slot: usize) {
+        if let Some(event) = self.timeline.events().get(slot).copied() {
+            self.set_time(event.time);
+            self.selected = Some(match event.kind {
+                PredictedKind::Conjunction(planet, _) | PredictedKind::MoonTransit(planet) => {
+                    planet
+                }
+            });
+        }
+    }
+
+    // Blends the last two steps; `alpha` is the fraction of a step left in the accumulator
+    pub fn interpolated(&self, alpha: f32) -> Snapshot {
+        self.previous.lerp(&self.current, alpha.clamp(0.0, 1.0))
+    }
+
+    // Adds a body mid-run, given in stylized units like the scene; in gravity
+    // mode it starts on a circular orbit
+    pub fn spawn(&mut self, mut planet: Planet) {
+        self.scene_scale.apply_to_planet(&mut planet);
+        if self.kepler {
+            let star_mass = self.planets.first().map_or(0.0, |star| star.mass());
+            kepler_orbit(&mut planet, star_mass);
+        }
+        self.timeline.invalidate();
+        if let Some(world) = self.gravity.as_mut() {
+            let position = planet.position(self.time);
+            let star_mass = world.bodies.first().map_or(0.0, |star| star.mass);
+            let radius = position.magnitude();
+            let tangent = if radius > 0.0 {
+                Vec3::new(-position.z, 0.0, position.x) / radius
+            } else {
+                Vec3::zeros()
+            };
+            world.bodies.push(Body {
+                position,
+                velocity: tangent * circular_speed(star_mass, radius),
+                mass: planet.mass(),
+            });
+        }
+        self.planets.push(planet);
+        self.scatter_trojans();
+    }
+
+    // Trojans share their planet's orbit, so they are placed again whenever
+    // the orbits change; each planet draws from its own stream and keeps the
+    // same clusters
+    fn scatter_trojans(&mut self) {
+        let scale = self.scene_scale;
+        self.trojans = self
+            .planets
+            .iter()
+            .filter(|planet| planet.orbit_radius > 0.0)
+            .flat_map(|planet| {
+                let mut rng = Rng::stream(self.seed, &format!("trojans {}", planet.name));
+                let mut bodies = generate_trojans(
+                    &TROJANS,
+                    planet.orbit_radius,
+                    planet.orbit_speed,
+                    planet.phase,
+                    &mut rng,
+                );
+                for body in &mut bodies {
+                    body.scale = scale.size(body.scale);
+                }
+                bodies
+            })
+            .collect();
+    }
+
+    pub fn planet_index(&self, name: &str) -> Option<usize> {
+        self.planets
+            .iter()
+            .position(|planet| planet.name.eq_ignore_ascii_case(name))
+    }
+
+    // Changes every star in the system; the renderer recolors their light
+    pub fn set_star_temperature(&mut self, kelvin: f32) {
+        for planet in &mut self.planets {
+            if let Some(temperature) = planet.temperature.as_mut() {
+                *temperature = kelvin.clamp(1000.0, 40000.0);
+            }
+        }
+    }
+
+    // Weighs the star anew; in Kepler mode every orbit speeds up or slows
+    // down to match
+    pub fn set_star_mass(&mut self, mass: f32) {
+        for planet in &mut self.planets {
+            if planet.orbit_radius == 0.0 {
+                planet.mass = Some(mass.max(1.0));
+            }
+        }
+        if let Some(world) = self.gravity.as_mut() {
+            for (body, planet) in world.bodies.iter_mut().zip(&self.planets) {
+                body.mass = planet.mass();
+            }
+        }
+        if self.kepler {
+            self.apply_kepler();
+        }
+    }
+
+    // In flight mode a warp burns fuel; false when the tank can't pay for it
+    // and the ship stays where it is
+    pub fn warp_camera(&mut self, target: Vec3) -> bool {
+        if self.ship.flight_mode && !self.ship.burn_warp() {
+            return false;
+        }
+        let blockers = self.blockers();
+        self.camera.start_warp(target, &blockers);
+        true
+    }
+
+    pub fn warp_to(&mut self, index: usize) {
+        if self.warp_camera(self.planet_position(index)) {
+            self.ship.velocity = Vec3::zeros();
+        }
+        self.selected = Some(index);
+    }
+
+    // Named after the closest body so the list in the HUD says where it is
+    pub fn store_bookmark(&mut self, slot: usize) {
+        let position = self.camera.position;
+        let nearest = (0..self.planets.len()).min_by(|a, b| {
+            let distance = |index| (self.planet_position(index) - position).magnitude();
+            distance(*a).total_cmp(&distance(*b))
+        });
+        let name = nearest.map_or_else(
+            || "ESPACIO".to_string(),
+            |index| format!("CERCA DE {}", self.planets[index].name.to_uppercase()),
+        );
+        self.bookmarks.store(
+            slot,
+            Bookmark {
+                name,
+                position,
+                zoom: self.camera.zoom,
+                tilt: self.camera.tilt,
+            },
+        );
+    }
+
+    pub fn recall_bookmark(&mut self, slot: usize) {
+        if let Some(bookmark) = self.bookmarks.get(slot) {
+            self.camera.zoom = bookmark.zoom;
+            self.camera.tilt = bookmark.tilt;
+            let position = bookmark.position;
+            if self.warp_camera(position) {
+                self.ship.velocity = Vec3::zeros();
+            }
+        }
+    }
+
+    // Point zooming keeps in place: the locked target, or else the cursor
+    fn zoom_anchor(&self, input: &InputFrame) -> Option<Vec2> {
+        if let Some(index) = self.selected {
+            let offset = self.planet_position(index) - self.camera.position;
+            return Some(Vec2::new(offset.x, offset.y));
+        }
+        input
+            .cursor()
+            .map(|cursor| self.camera.offset_under(cursor))
+    }
+
+    // Body drawn under `cursor` (logical pixels from the center, y down), the
+    // nearest one where several overlap
+    pub fn planet_under(&self, cursor: Vec2) -> Option<usize> {
+        let view = self.camera.view();
+        let view = view.magnified(view.fov_magnification());
+        (0..self.planets.len())
+            .filter_map(|index| {
+                let projected = view.project(self.planet_position(index));
+                let radius = (self.planets[index].scale * view.zoom).max(PICK_SLACK);
+                let hit = (projected.xy() - cursor).magnitude() <= radius;
+                hit.then_some((index, projected.z))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    pub fn photo_mode(&self) -> bool {
+        self.photo.is_some()
+    }
+
+    pub fn gravity_enabled(&self) -> bool {
+        self.gravity.is_some()
+    }
+
+    pub fn planet_position(&self, index: usize) -> Vec3 {
+        match &self.gravity {
+            Some(world) => world.bodies[index].position,
+            None => self.planets[index].position(self.time),
+        }
+    }
+
+    pub fn planet_velocity(&self, index: usize) -> Option<Vec3> {
+        self.gravity
+            .as_ref()
+            .map(|world| world.bodies[index].velocity)
+    }
+
+    // Fills `attractors` with the (position, mass) of every planet at `time`;
+    // in gravity mode the bodies are assumed to stay where they are now.
+    pub fn attractors_at(&self, time: f32, attractors: &mut Vec<(Vec3, f32)>) {
+        attractors.clear();
+        attractors.extend(self.planets.iter().enumerate().map(|(index, planet)| {
+            let position = match &self.gravity {
+                Some(world) => world.bodies[index].position,
+                None => planet.position(time),
+            };
+            (position, planet.mass())
+        }));
+    }
+
+    // Where the ship is drawn, ahead of the camera; its physics happen here
+    pub fn ship_position(&self) -> Vec3 {
+        self.camera.position + ship_offset()
+    }
+
+    // Ship orbit around the star; `None` when it is on an escape path
+    pub fn ship_orbit(&self) -> Option<OrbitElements> {
+        let star = self.planets.first()?;
+        let star_velocity = self.planet_velocity(0).unwrap_or_else(Vec3::zeros);
+        orbit_elements(
+            self.ship_position() - self.planet_position(0),
+            self.ship.velocity - star_velocity,
+            star.mass(),
+        )
+    }
+
+    pub fn landing(&self) -> Option<Landing> {
+        if !self.landing_enabled {
+            return None;
+        }
+
+        self.planets
+            .iter()
+            .enumerate()
+            .filter(|(_, planet)| planet.height.is_some())
+            .filter_map(|(index, planet)| {
+                let offset = self.camera.position - self.planet_position(index);
+                let distance = (offset.x * offset.x + offset.z * offset.z).sqrt();
+                if distance >= planet.collision_radius {
+                    return None;
+                }
+                let surface = planet.scale * SURFACE_CLEARANCE;
+                let span = (planet.collision_radius - surface).max(1.0);
+                Some(Landing {
+                    planet: index,
+                    blend: ((planet.collision_radius - distance) / span).clamp(0.0, 1.0),
+                    altitude: (distance - planet.scale).max(0.0),
+                })
+            })
+            .min_by(|a, b| a.altitude.total_cmp(&b.altitude))
+    }
+
+    // Center and collision radius of every star
+    pub fn stars(&self) -> Vec<(Vec3, f32)> {
+        self.planets
+            .iter()
+            .enumerate()
+            .filter(|(_, planet)| planet.temperature.is_some())
+            .map(|(index, planet)| (self.planet_position(index), planet.collision_radius))
+            .collect()
+    }
+
+    // Gas and ice giants, as (center, collision radius), for skimming fuel
+    pub fn giants(&self) -> Vec<(Vec3, f32)> {
+        self.planets
+            .iter()
+            .enumerate()
+            .filter(|(_, planet)| planet.mesh == MeshKind::GasGiant)
+            .map(|(index, planet)| (self.planet_position(index), planet.collision_radius))
+            .collect()
+    }
+
+    pub fn blockers(&self) -> Vec<(Vec3, f32)> {
+        let mut blockers = Vec::new();
+        for (index, planet) in self.planets.iter().enumerate() {
+            let position = self.planet_position(index);
+            let radius = if self.landing_enabled && planet.height.is_some() {
+                planet.scale * SURFACE_CLEARANCE
+            } else {
+                planet.collision_radius
+            };
+            blockers.push((position, radius));
+
+            for moon in &planet.moons {
+                blockers.push((position + moon.offset(self.time), moon.scale * 0.6));
+            }
+        }
+        blockers
+    }
+
+    pub fn enter_photo_mode(&mut self) {
+        if self.photo.is_none() {
+            self.toggle_photo_mode();
+        }
+    }
+
+    // Photo mode detaches the camera; leaving it puts the camera back where it was
+    fn toggle_photo_mode(&mut self) {
+        match self.photo.take() {
+            Some(view) => {
+                self.camera.position = view.position;
+                self.camera.velocity = Vec3::zeros();
+                self.camera.zoom = view.zoom;
+                self.camera.tilt = view.tilt;
+                self.camera.roll = view.roll;
+            }
+            None => {
+                self.photo = Some(self.camera.view());
+                self.tour = None;
+            }
+        }
+    }
+
+    // Switching to gravity seeds each body on a circular orbit around the star;
+    // switching back snaps every body onto its clockwork circle.
+    fn toggle_gravity(&mut self) {
+        if self.gravity.take().is_some() {
+            return;
+        }
+
+        let star_mass = self.planets.first().map_or(0.0, |star| star.mass());
+        let mut bodies: Vec<Body> = self
+            .planets
+            .iter()
+            .map(|planet| {
+                let position = planet.position(self.time);
+                let radius = position.magnitude();
+                let tangent = if radius > 0.0 {
+                    Vec3::new(-position.z, 0.0, position.x) / radius
+                } else {
+                    Vec3::zeros()
+                };
+                let direction = if planet.orbit_speed < 0.0 { -1.0 } else { 1.0 };
+                Body {
+                    position,
+                    velocity: tangent * circular_speed(star_mass, radius) * direction,
+                    mass: planet.mass(),
+                }
+            })
+            .collect();
+
+        // Give the star the opposite momentum so the system's barycenter stays put
+        if let Some((star, others)) = bodies.split_first_mut() {
+            let momentum = others
+                .iter()
+                .fold(Vec3::zeros(), |acc, body| acc + body.velocity * body.mass);
+            star.velocity = -momentum / star.mass;
+        }
+        self.gravity = Some(GravityWorld::new(bodies));
+    }
+
+    fn apply_nudges(&mut self, input: &InputFrame, delta: f32) {
+        let (Some(index), Some(world)) = (self.selected, self.gravity.as_mut()) else {
+            return;
+        };
+        let star_position = world.bodies[0].position;
+        let star_velocity = world.bodies[0].velocity;
+        let body = &mut world.bodies[index];
+
+        let relative_velocity = body.velocity - star_velocity;
+        let prograde = if relative_velocity.magnitude() > 0.001 {
+            relative_velocity.normalize()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let offset = body.position - star_position;
+        let outward = if offset.magnitude() > 0.001 {
+            offset.normalize()
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        };
+
+        let mut push = Vec3::zeros();
+        if input.is_held(Action::NudgePrograde) {
+            push += prograde;
+        }
+        if input.is_held(Action::NudgeRetrograde) {
+            push -= prograde;
+        }
+        if input.is_held(Action::NudgeOutward) {
+            push += outward;
+        }
+        if input.is_held(Action::NudgeInward) {
+            push -= outward;
+        }
+        body.velocity += push * NUDGE_ACCELERATION * delta;
+    }
+
+    fn capture(&self) -> Snapshot {
+        let bodies = self
+            .planets
+            .iter()
+            .enumerate()
+            .map(|(index, planet)| {
+                let position = self.planet_position(index);
+                BodyState {
+                    position,
+                    moon_positions: planet
+                        .moons
+                        .iter()
+                        .map(|moon| position + moon.offset(self.time))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let landing = self.landing();
+        let mut view = self.camera.view();
+        if let Some(landing) = landing {
+            view.zoom *= 1.0 + landing.blend * LANDING_ZOOM;
+        }
+
+        Snapshot {
+            time: self.time,
+            bodies,
+            view,
+            ship_bank: -self.camera.last_direction.x * 0.4,
+            landing,
+            heat: if self.ship.flight_mode {
+                self.ship.heat
+            } else {
+                0.0
+            },
+            throttle: self.camera.last_direction.magnitude(),
+        }
+    }
+}
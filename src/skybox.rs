@@ -1,13 +1,55 @@
+use std::f32::consts::{PI, TAU};
+use std::fs;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 
 use image::ImageReader;
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+use web_time::Instant;
 
+use crate::color::Color;
 use crate::framebuffer::Framebuffer;
+use crate::noise::fbm;
+use crate::rng::Rng;
 
-pub struct Skybox {
-    width: usize,
-    height: usize,
-    pixels: Vec<u32>,
+const SKY_COLOR: Vec3 = Vec3::new(0.004, 0.006, 0.02);
+const PROCEDURAL_PREFIX: &str = "procedural:";
+const RELOAD_CHECK: Duration = Duration::from_secs(1);
+
+pub struct StarfieldPreset {
+    star_count: usize,
+    band_strength: f32,
+    band_color: Vec3,
+}
+
+impl StarfieldPreset {
+    pub fn named(name: &str) -> Option<Self> {
+        let preset = match name {
+            "estrellas" => StarfieldPreset {
+                star_count: 4500,
+                band_strength: 1.0,
+                band_color: Vec3::new(0.28, 0.25, 0.33),
+            },
+            "nebulosa" => StarfieldPreset {
+                star_count: 3000,
+                band_strength: 1.6,
+                band_color: Vec3::new(0.42, 0.16, 0.3),
+            },
+            "vacio" => StarfieldPreset {
+                star_count: 1200,
+                band_strength: 0.0,
+                band_color: Vec3::zeros(),
+            },
+            _ => return None,
+        };
+        Some(preset)
+    }
+}
+
+pub enum Skybox {
+    Image(SkyImage),
+    Starfield(Starfield),
 }
 
 impl Skybox {
@@ -24,25 +66,522 @@ impl Skybox {
             pixels.push(((r as u32) << 16) | ((g as u32) << 8) | (b as u32));
         }
 
-        Ok(Skybox {
+        Ok(Skybox::Image(SkyImage {
             width: width as usize,
             height: height as usize,
             pixels,
-        })
+            scaled: Vec::new(),
+            scaled_size: (0, 0),
+            backdrop: Backdrop::default(),
+        }))
+    }
+
+    pub fn starfield(seed: u64, preset: &StarfieldPreset) -> Self {
+        Skybox::Starfield(Starfield::generate(seed, preset))
+    }
+
+    // `source` is an image path or `procedural:<preset>`
+    pub fn from_source(source: &str, seed: u64) -> Result<Self, String> {
+        match source.strip_prefix(PROCEDURAL_PREFIX) {
+            Some(name) => StarfieldPreset::named(name)
+                .map(|preset| Skybox::starfield(seed, &preset))
+                .ok_or_else(|| format!("preset desconocido `{}`", name)),
+            None => Skybox::load(source).map_err(|error| error.to_string()),
+        }
     }
 
-    pub fn draw(&self, framebuffer: &mut Framebuffer) {
+    // `roll` turns the sky about the screen center along with the camera
+    pub fn draw(&mut self, framebuffer: &mut Framebuffer, time: f32, roll: f32) {
+        match self {
+            Skybox::Image(image) => image.draw(framebuffer, roll),
+            Skybox::Starfield(starfield) => starfield.draw(framebuffer, time, roll),
+        }
+    }
+
+    // Whether `draw` paints every pixel, so the frame needs no clearing
+    // under it
+    pub fn covers_frame(&self) -> bool {
+        match self {
+            Skybox::Image(image) => !image.pixels.is_empty(),
+            Skybox::Starfield(_) => true,
+        }
+    }
+
+    // The sky shrunk to an environment map for reflections: images are
+    // averaged down, starfields bake their band small and drop the stars in
+    pub fn environment(&self) -> Environment {
+        let mut environment = Environment::new();
+        match self {
+            Skybox::Image(SkyImage {
+                width,
+                height,
+                pixels,
+                ..
+            }) => {
+                if pixels.is_empty() {
+                    return environment;
+                }
+                let mut counts = vec![0u32; ENVIRONMENT_WIDTH * ENVIRONMENT_HEIGHT];
+                for (index, &pixel) in pixels.iter().enumerate() {
+                    let x = index % width * ENVIRONMENT_WIDTH / width;
+                    let y = index / width * ENVIRONMENT_HEIGHT / height;
+                    let texel = y * ENVIRONMENT_WIDTH + x;
+                    environment.texels[texel] += Color::from_hex(pixel).to_vec3();
+                    counts[texel] += 1;
+                }
+                for (texel, count) in environment.texels.iter_mut().zip(counts) {
+                    *texel /= count.max(1) as f32;
+                }
+            }
+            Skybox::Starfield(starfield) => {
+                let band = bake_band(
+                    ENVIRONMENT_WIDTH,
+                    ENVIRONMENT_HEIGHT,
+                    starfield.band_strength,
+                    starfield.band_color,
+                );
+                for (texel, pixel) in environment.texels.iter_mut().zip(band) {
+                    *texel = Color::from_hex(pixel).to_vec3();
+                }
+                for star in &starfield.stars {
+                    let x = (star.u * ENVIRONMENT_WIDTH as f32) as usize % ENVIRONMENT_WIDTH;
+                    let y =
+                        ((star.v * ENVIRONMENT_HEIGHT as f32) as usize).min(ENVIRONMENT_HEIGHT - 1);
+                    environment.texels[y * ENVIRONMENT_WIDTH + x] +=
+                        star.tint * (star.brightness * STAR_REFLECTION);
+                }
+            }
+        }
+        environment
+    }
+}
+
+// Size of the environment map; reflections off a curved hull only need the
+// sky's broad shapes, and the few bright stars as points
+const ENVIRONMENT_WIDTH: usize = 128;
+const ENVIRONMENT_HEIGHT: usize = ENVIRONMENT_WIDTH / 2;
+const ENVIRONMENT_TEXEL: f32 = TAU / ENVIRONMENT_WIDTH as f32;
+// How much of a star's brightness lands in the texel it falls in
+const STAR_REFLECTION: f32 = 0.5;
+
+// The sky around an object in the same equirectangular layout as the
+// skybox, for shaders to look up along a direction of the view frame: the
+// middle of the map lies straight ahead (+z) and north is up (-y)
+#[derive(Clone)]
+pub struct Environment {
+    texels: Vec<Vec3>,
+}
+
+impl Environment {
+    fn new() -> Self {
+        Environment {
+            texels: vec![Vec3::zeros(); ENVIRONMENT_WIDTH * ENVIRONMENT_HEIGHT],
+        }
+    }
+
+    // Bilinear lookup along `direction`
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.normalize();
+        let u = (0.5 + direction.x.atan2(direction.z) / TAU) * ENVIRONMENT_WIDTH as f32 - 0.5;
+        let v = ((0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI) * ENVIRONMENT_HEIGHT as f32
+            - 0.5)
+            .clamp(0.0, ENVIRONMENT_HEIGHT as f32 - 1.0);
+
+        let (x0, y0) = (u.floor(), v.floor());
+        let (tx, ty) = (u - x0, v - y0);
+        // Longitude wraps around, latitude stops at the poles
+        let column = |x: f32| (x as i32).rem_euclid(ENVIRONMENT_WIDTH as i32) as usize;
+        let (left, right) = (column(x0), column(x0 + 1.0));
+        let top = y0 as usize;
+        let bottom = (top + 1).min(ENVIRONMENT_HEIGHT - 1);
+
+        let at = |x: usize, y: usize| self.texels[y * ENVIRONMENT_WIDTH + x];
+        let upper = at(left, top) + (at(right, top) - at(left, top)) * tx;
+        let lower = at(left, bottom) + (at(right, bottom) - at(left, bottom)) * tx;
+        upper + (lower - upper) * ty
+    }
+
+    // The same sky once the camera has rolled, turned the way `Skybox::draw`
+    // turns the background
+    pub fn rolled(&self, roll: f32) -> Environment {
+        if roll == 0.0 {
+            return self.clone();
+        }
+        let (sin, cos) = roll.sin_cos();
+        let texels = (0..self.texels.len())
+            .map(|texel| {
+                let direction = environment_direction(texel);
+                self.sample(Vec3::new(
+                    direction.x * cos + direction.y * sin,
+                    direction.y * cos - direction.x * sin,
+                    direction.z,
+                ))
+            })
+            .collect();
+        Environment { texels }
+    }
+
+    // Paints a body seen towards `direction` as a disc `radius` radians
+    // across; ones smaller than a texel fade with the share they cover
+    pub fn add_disc(&mut self, direction: Vec3, radius: f32, color: Vec3) {
+        let direction = direction.normalize();
+        let coverage = (radius / ENVIRONMENT_TEXEL).min(1.0).powi(2);
+        let reach = (radius + ENVIRONMENT_TEXEL).min(PI).cos();
+        for (index, texel) in self.texels.iter_mut().enumerate() {
+            let toward = environment_direction(index).dot(&direction);
+            if toward < reach {
+                continue;
+            }
+            let angle = toward.clamp(-1.0, 1.0).acos();
+            let edge = ((radius - angle) / ENVIRONMENT_TEXEL + 0.5).clamp(0.0, 1.0);
+            *texel = texel.lerp(&color, edge * coverage);
+        }
+    }
+}
+
+// View-frame direction through the middle of an environment texel
+fn environment_direction(texel: usize) -> Vec3 {
+    let u = ((texel % ENVIRONMENT_WIDTH) as f32 + 0.5) / ENVIRONMENT_WIDTH as f32;
+    let v = ((texel / ENVIRONMENT_WIDTH) as f32 + 0.5) / ENVIRONMENT_HEIGHT as f32;
+    let (lon, lat) = ((u - 0.5) * TAU, (v - 0.5) * PI);
+    Vec3::new(lat.cos() * lon.sin(), lat.sin(), lat.cos() * lon.cos())
+}
+
+struct Star {
+    // Equirectangular coordinates in [0, 1), same layout as the image skybox
+    u: f32,
+    v: f32,
+    brightness: f32,
+    tint: Vec3,
+    twinkle_speed: f32,
+    twinkle_phase: f32,
+}
+
+// Seeded stars over the whole sky plus a Milky Way band; the band is baked
+// once per framebuffer size since it never changes.
+pub struct Starfield {
+    stars: Vec<Star>,
+    band_strength: f32,
+    band_color: Vec3,
+    band: Vec<u32>,
+    band_size: (usize, usize),
+    backdrop: Backdrop,
+}
+
+impl Starfield {
+    pub fn generate(seed: u64, preset: &StarfieldPreset) -> Self {
+        let mut rng = Rng::stream(seed, "starfield");
+        let mut stars = Vec::with_capacity(preset.star_count);
+
+        for _ in 0..preset.star_count {
+            // Uniform on the sphere: z uniform in [-1, 1], longitude uniform
+            let z = rng.range(-1.0, 1.0);
+            let lon = rng.unit() * TAU;
+            let lat = z.asin();
+
+            // Dim stars vastly outnumber bright ones
+            let magnitude = -1.0 + 7.0 * rng.unit().sqrt();
+            let brightness = (2.512f32.powf(-magnitude) * 2.5).min(1.0);
+
+            let temperature = rng.unit();
+            let tint = if temperature < 0.2 {
+                Vec3::new(0.75, 0.85, 1.0)
+            } else if temperature > 0.85 {
+                Vec3::new(1.0, 0.8, 0.6)
+            } else {
+                Vec3::new(1.0, 0.97, 0.92)
+            };
+
+            stars.push(Star {
+                u: lon / TAU,
+                v: 0.5 - lat / PI,
+                brightness,
+                tint,
+                twinkle_speed: 1.5 + rng.unit() * 4.0,
+                twinkle_phase: rng.unit() * TAU,
+            });
+        }
+
+        Starfield {
+            stars,
+            band_strength: preset.band_strength,
+            band_color: preset.band_color,
+            band: Vec::new(),
+            band_size: (0, 0),
+            backdrop: Backdrop::default(),
+        }
+    }
+
+    fn draw(&mut self, framebuffer: &mut Framebuffer, time: f32, roll: f32) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        if self.band_size != (width, height) {
+            self.band = bake_band(width, height, self.band_strength, self.band_color);
+            self.band_size = (width, height);
+        }
+        self.backdrop.draw(framebuffer, &self.band, roll);
+
+        let (sin, cos) = roll.sin_cos();
+        let center = (width as f32 * 0.5, height as f32 * 0.5);
+        for star in &self.stars {
+            let (dx, dy) = (
+                star.u * width as f32 - center.0,
+                star.v * height as f32 - center.1,
+            );
+            let x = (center.0 + dx * cos - dy * sin) as i32;
+            let y = (center.1 + dx * sin + dy * cos) as i32;
+            // Only the fainter stars scintillate noticeably
+            let twinkle = (time * star.twinkle_speed + star.twinkle_phase).sin();
+            let brightness = star.brightness * (1.0 + twinkle * 0.3 * (1.0 - star.brightness));
+            let core = star.tint * brightness;
+            add_light(framebuffer, x, y, core);
+
+            if star.brightness > 0.6 {
+                let halo = core * 0.35;
+                add_light(framebuffer, x + 1, y, halo);
+                add_light(framebuffer, x - 1, y, halo);
+                add_light(framebuffer, x, y + 1, halo);
+                add_light(framebuffer, x, y - 1, halo);
+            }
+        }
+    }
+}
+
+// An image skybox, scaled to the framebuffer with bilinear filtering once
+// per size rather than sampled for every pixel of every frame
+pub struct SkyImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+    scaled: Vec<u32>,
+    scaled_size: (usize, usize),
+    backdrop: Backdrop,
+}
+
+impl SkyImage {
+    fn draw(&mut self, framebuffer: &mut Framebuffer, roll: f32) {
         if self.pixels.is_empty() {
             return;
         }
+        let size = (framebuffer.width, framebuffer.height);
+        if self.scaled_size != size {
+            self.scaled = self.resample(size.0, size.1);
+            self.scaled_size = size;
+        }
+        self.backdrop.draw(framebuffer, &self.scaled, roll);
+    }
+
+    // The whole image at `width` x `height`, each pixel blending the four
+    // texels around its center. Longitude wraps around the seam, latitude
+    // stops at the poles
+    fn resample(&self, width: usize, height: usize) -> Vec<u32> {
+        let mut scaled = vec![0; width * height];
+        scaled
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let v = ((y as f32 + 0.5) / height as f32 * self.height as f32 - 0.5)
+                    .clamp(0.0, self.height as f32 - 1.0);
+                let top = v as usize;
+                let bottom = (top + 1).min(self.height - 1);
+                let ty = v - top as f32;
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let u = (x as f32 + 0.5) / width as f32 * self.width as f32 - 0.5;
+                    let x0 = u.floor();
+                    let tx = u - x0;
+                    let left = (x0 as i32).rem_euclid(self.width as i32) as usize;
+                    let right = (left + 1) % self.width;
+                    let at = |x: usize, y: usize| {
+                        Color::from_hex(self.pixels[y * self.width + x]).to_vec3()
+                    };
+                    let upper = at(left, top).lerp(&at(right, top), tx);
+                    let lower = at(left, bottom).lerp(&at(right, bottom), tx);
+                    let color = upper.lerp(&lower, ty);
+                    *pixel = Color::from_float(color.x, color.y, color.z).to_hex();
+                }
+            });
+        scaled
+    }
+}
+
+// The sky as last painted behind everything else, turned with the camera's
+// roll. While the window size and the roll stay the same it is copied in
+// whole instead of being sampled again pixel by pixel
+#[derive(Default)]
+struct Backdrop {
+    pixels: Vec<u32>,
+    // Width, height and roll it was painted for
+    key: Option<(usize, usize, f32)>,
+}
+
+impl Backdrop {
+    // `sky` is the unrolled sky at the framebuffer's size, copied as is
+    // while the view is level
+    fn draw(&mut self, framebuffer: &mut Framebuffer, sky: &[u32], roll: f32) {
+        if roll == 0.0 {
+            copy_rows(framebuffer, sky);
+            return;
+        }
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        if self.key != Some((width, height, roll)) {
+            self.pixels.resize(width * height, 0);
+            self.pixels
+                .par_chunks_mut(width)
+                .enumerate()
+                .for_each(|(y, row)| {
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let (u, v) = unrolled(x, y, width, height, roll);
+                        let src_x = (u * width as f32) as usize % width;
+                        let src_y = ((v * height as f32) as usize).min(height - 1);
+                        *pixel = sky[src_y * width + src_x];
+                    }
+                });
+            self.key = Some((width, height, roll));
+        }
+        copy_rows(framebuffer, &self.pixels);
+    }
+}
+
+// Copies a framebuffer-sized image in, its rows split across the workers
+fn copy_rows(framebuffer: &mut Framebuffer, source: &[u32]) {
+    let width = framebuffer.width;
+    framebuffer
+        .rows_mut()
+        .zip(source.par_chunks(width))
+        .for_each(|(row, source)| row.color.copy_from_slice(source));
+}
+
+// Sky coordinates (u wraps around, v is clamped by the caller) seen at a
+// screen pixel once the view is rolled
+fn unrolled(x: usize, y: usize, width: usize, height: usize, roll: f32) -> (f32, f32) {
+    let (sin, cos) = roll.sin_cos();
+    let (dx, dy) = (
+        x as f32 - width as f32 * 0.5,
+        y as f32 - height as f32 * 0.5,
+    );
+    let u = (width as f32 * 0.5 + dx * cos + dy * sin) / width as f32;
+    let v = (height as f32 * 0.5 - dx * sin + dy * cos) / height as f32;
+    (u.rem_euclid(1.0), v.max(0.0))
+}
+
+fn add_light(framebuffer: &mut Framebuffer, x: i32, y: i32, light: Vec3) {
+    if x < 0 || y < 0 || x as usize >= framebuffer.width || y as usize >= framebuffer.height {
+        return;
+    }
+    let index = y as usize * framebuffer.width + x as usize;
+    let sum =
+        Color::from_hex(framebuffer.buffer[index]) + Color::from_float(light.x, light.y, light.z);
+    framebuffer.buffer[index] = sum.to_hex();
+}
+
+// Glowing band along a tilted great circle, broken up by noise and a dark dust lane
+fn bake_band(width: usize, height: usize, strength: f32, tint: Vec3) -> Vec<u32> {
+    let band_normal = Vec3::new(0.35, 1.0, 0.2).normalize();
+    let mut pixels = vec![0; width * height];
+
+    pixels
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let lat = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let lon = (x as f32 + 0.5) / width as f32 * TAU;
+                let direction = Vec3::new(lat.cos() * lon.cos(), lat.sin(), lat.cos() * lon.sin());
+
+                let offset = direction.dot(&band_normal);
+                let band = (-(offset / 0.22).powi(2)).exp();
+                let clouds = fbm(direction * 4.0, 5, 0);
+                let lane = (-(offset / 0.05).powi(2)).exp()
+                    * fbm(direction * 9.0 + Vec3::new(3.1, 0.0, 1.7), 3, 0);
+                let glow = band * (0.3 + clouds * 0.7) * (1.0 - lane * 0.8);
+
+                let color = SKY_COLOR + tint * glow * strength;
+                *pixel = Color::from_float(color.x, color.y, color.z).to_hex();
+            }
+        });
+
+    pixels
+}
+
+// The backgrounds listed in the config, cycled with B. Sources are read from
+// disk every time they are selected, and the current image is reloaded when
+// its file changes, so skyboxes can be edited while the app runs.
+pub struct SkyboxLibrary {
+    sources: Vec<String>,
+    index: usize,
+    seed: u64,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl SkyboxLibrary {
+    pub fn new(sources: Vec<String>, seed: u64) -> Self {
+        SkyboxLibrary {
+            sources,
+            index: 0,
+            seed,
+            modified: None,
+            last_check: Instant::now(),
+        }
+    }
+
+    // Falls back to the default starfield when nothing in the list loads
+    pub fn current(&mut self) -> Skybox {
+        for _ in 0..self.sources.len() {
+            if let Some(skybox) = self.load_current() {
+                return skybox;
+            }
+            self.index = (self.index + 1) % self.sources.len();
+        }
+        let preset = StarfieldPreset::named("estrellas").expect("preset por defecto");
+        Skybox::starfield(self.seed, &preset)
+    }
+
+    // `sources` replaces the list, so edits to the config show up on the next press
+    pub fn next(&mut self, sources: Vec<String>) -> Option<Skybox> {
+        if sources.is_empty() {
+            return None;
+        }
+        let current = self.sources.get(self.index).cloned();
+        self.sources = sources;
+        self.index = match current.and_then(|name| self.sources.iter().position(|s| *s == name)) {
+            Some(position) => (position + 1) % self.sources.len(),
+            None => 0,
+        };
+        self.load_current()
+    }
+
+    pub fn reload_if_changed(&mut self) -> Option<Skybox> {
+        if self.last_check.elapsed() < RELOAD_CHECK {
+            return None;
+        }
+        self.last_check = Instant::now();
 
-        for y in 0..framebuffer.height {
-            let src_y = y * self.height / framebuffer.height;
-            for x in 0..framebuffer.width {
-                let src_x = x * self.width / framebuffer.width;
-                let color = self.pixels[src_y * self.width + src_x];
-                framebuffer.plot_overlay(x as i32, y as i32, color);
+        let modified = modified_time(self.sources.get(self.index)?);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.load_current()
+    }
+
+    fn load_current(&mut self) -> Option<Skybox> {
+        let source = self.sources.get(self.index)?;
+        match Skybox::from_source(source, self.seed) {
+            Ok(skybox) => {
+                self.modified = modified_time(source);
+                eprintln!("Skybox: {}", source);
+                Some(skybox)
+            }
+            Err(error) => {
+                eprintln!("No se pudo cargar la skybox {}: {}", source, error);
+                None
             }
         }
     }
 }
+
+fn modified_time(source: &str) -> Option<SystemTime> {
+    if source.starts_with(PROCEDURAL_PREFIX) {
+        return None;
+    }
+    fs::metadata(source).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,212 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::ExtendedColorType;
+use web_time::Instant;
+
+use crate::framebuffer::Framebuffer;
+
+// Frames sent to spectators at most this often, about 15 a second
+const SPECTATOR_INTERVAL: Duration = Duration::from_millis(66);
+const JPEG_QUALITY: u8 = 70;
+const BOUNDARY: &str = "cuadro";
+// Most connections served at once, each with a thread; more are turned away
+const MAX_VIEWERS: usize = 8;
+// How long a viewer has to send its request, and how long one that stopped
+// reading keeps its place
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+// Longest request read; a browser's is well under this
+const MAX_REQUEST: usize = 8 * 1024;
+
+// Serves what the window shows as an MJPEG stream over HTTP, so a browser
+// (or anything that plays MJPEG) can watch the running simulation. Frames
+// are only copied while someone watches, and JPEG-encoded on a thread of
+// their own; a frame that arrives while the last one is still encoding is
+// skipped rather than slowing the window down
+pub struct Spectator {
+    frames: SyncSender<RawFrame>,
+    stream: Arc<Stream>,
+    last_sent: Option<Instant>,
+}
+
+struct RawFrame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+// The newest encoded frame, which every viewer's thread waits on
+struct Stream {
+    latest: Mutex<Encoded>,
+    fresh: Condvar,
+    // Connections open, and those of them that asked for the stream
+    connections: AtomicUsize,
+    viewers: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Encoded {
+    number: u64,
+    jpeg: Arc<Vec<u8>>,
+}
+
+impl Spectator {
+    // Listens on `port` of this machine only, or of every interface when
+    // `public`
+    pub fn serve(port: u16, public: bool) -> io::Result<Self> {
+        let address = if public { "0.0.0.0" } else { "127.0.0.1" };
+        let listener = TcpListener::bind((address, port))?;
+        let stream = Arc::new(Stream {
+            latest: Mutex::new(Encoded::default()),
+            fresh: Condvar::new(),
+            connections: AtomicUsize::new(0),
+            viewers: AtomicUsize::new(0),
+        });
+        let (frames, raw) = mpsc::sync_channel(1);
+
+        let encoder = Arc::clone(&stream);
+        thread::spawn(move || encode(raw, &encoder));
+        let server = Arc::clone(&stream);
+        thread::spawn(move || {
+            for mut connection in listener.incoming().flatten() {
+                if server.connections.fetch_add(1, Ordering::Relaxed) >= MAX_VIEWERS {
+                    server.connections.fetch_sub(1, Ordering::Relaxed);
+                    let _ = connection.write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\n\r\n",
+                    );
+                    continue;
+                }
+                let stream = Arc::clone(&server);
+                thread::spawn(move || {
+                    if let Err(error) = watch(connection, &stream) {
+                        eprintln!("Espectador desconectado: {}", error);
+                    }
+                    stream.connections.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+        });
+
+        Ok(Spectator {
+            frames,
+            stream,
+            last_sent: None,
+        })
+    }
+
+    // Called once per frame with the finished screen
+    pub fn publish(&mut self, framebuffer: &Framebuffer) {
+        if self.stream.viewers.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_sent
+            .is_some_and(|sent| now.duration_since(sent) < SPECTATOR_INTERVAL)
+        {
+            return;
+        }
+        let frame = RawFrame {
+            width: framebuffer.width,
+            height: framebuffer.height,
+            pixels: framebuffer.buffer.clone(),
+        };
+        if self.frames.try_send(frame).is_ok() {
+            self.last_sent = Some(now);
+        }
+    }
+}
+
+fn encode(frames: Receiver<RawFrame>, stream: &Stream) {
+    for frame in frames {
+        let rgb: Vec<u8> = frame
+            .pixels
+            .iter()
+            .flat_map(|&pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+            .collect();
+        let mut jpeg = Vec::new();
+        let encoded = JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).encode(
+            &rgb,
+            frame.width as u32,
+            frame.height as u32,
+            ExtendedColorType::Rgb8,
+        );
+        if let Err(error) = encoded {
+            eprintln!("No se pudo codificar el cuadro del espectador: {}", error);
+            continue;
+        }
+        let mut latest = stream.latest.lock().unwrap_or_else(PoisonError::into_inner);
+        latest.number += 1;
+        latest.jpeg = Arc::new(jpeg);
+        stream.fresh.notify_all();
+    }
+}
+
+// Answers a GET with the stream, one JPEG per new frame, until the viewer
+// goes away or stops reading; only then does it count as a viewer
+fn watch(mut connection: TcpStream, stream: &Stream) -> io::Result<()> {
+    if !read_request(&mut connection)? {
+        return connection.write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n");
+    }
+    connection.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.viewers.fetch_add(1, Ordering::Relaxed);
+    let watched = send_frames(connection, stream);
+    stream.viewers.fetch_sub(1, Ordering::Relaxed);
+    watched
+}
+
+// Reads the request up to the blank line that ends it, giving up after
+// `MAX_REQUEST` bytes or `CONNECTION_TIMEOUT`; whether it is a GET
+fn read_request(connection: &mut TcpStream) -> io::Result<bool> {
+    let deadline = Instant::now() + CONNECTION_TIMEOUT;
+    let mut request = Vec::new();
+    let mut chunk = [0; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() || request.len() >= MAX_REQUEST {
+            return Ok(false);
+        }
+        connection.set_read_timeout(Some(left))?;
+        let read = connection.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(false);
+        }
+        request.extend_from_slice(&chunk[..read]);
+    }
+    Ok(request.starts_with(b"GET "))
+}
+
+fn send_frames(mut connection: TcpStream, stream: &Stream) -> io::Result<()> {
+    write!(
+        connection,
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        BOUNDARY
+    )?;
+    let mut seen = 0;
+    loop {
+        let jpeg = {
+            let latest = stream.latest.lock().unwrap_or_else(PoisonError::into_inner);
+            let latest = stream
+                .fresh
+                .wait_while(latest, |latest| latest.number == seen)
+                .unwrap_or_else(PoisonError::into_inner);
+            seen = latest.number;
+            Arc::clone(&latest.jpeg)
+        };
+        write!(
+            connection,
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len()
+        )?;
+        connection.write_all(&jpeg)?;
+        connection.write_all(b"\r\n")?;
+        connection.flush()?;
+    }
+}
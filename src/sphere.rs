@@ -1,3 +1,5 @@
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
+
 use crate::vertex::Vertex;
 use nalgebra_glm::{Vec2, Vec3};
 
@@ -97,3 +99,313 @@ pub fn generate_ring(inner_radius: f32, outer_radius: f32, segments: u32) -> Vec
 
     indexed_vertices
 }
+
+// How finely a cube-sphere is split around the point it is built for
+#[derive(Debug, Clone, Copy)]
+pub struct CubeSphereLod {
+    // Quads along each side of a patch
+    pub resolution: u32,
+    // Times a cube face may be split in four
+    pub max_depth: u32,
+    // A patch splits while the focus is closer than this many patch widths
+    pub split_distance: f32,
+}
+
+// One quadtree leaf of a cube-sphere: a grid of quads plus a skirt hanging
+// under its edges, which hides the cracks against coarser neighbours
+pub struct SpherePatch {
+    // Unit direction to the patch's middle and the widest angle from there
+    // to one of its corners
+    pub center: Vec3,
+    pub angular_radius: f32,
+    pub vertices: Vec<Vertex>,
+    // Index in `vertices` where the skirt's triangles start
+    pub skirt: usize,
+}
+
+impl SpherePatch {
+    // Cone test: whether the whole patch is past the horizon of an eye
+    // `distance` radii from the sphere's center towards `direction`. Relief
+    // up to `relief` radii high can still peek over it; an eye infinitely
+    // far away sees exactly a hemisphere
+    pub fn beyond_horizon(&self, direction: Vec3, distance: f32, relief: f32) -> bool {
+        if distance <= 1.0 {
+            return false;
+        }
+        let horizon = (1.0 / distance).acos() + (1.0 / (1.0 + relief.max(0.0))).acos();
+        let angle = self.center.dot(&direction).clamp(-1.0, 1.0).acos();
+        angle - self.angular_radius > horizon
+    }
+}
+
+struct CubeFace {
+    normal: Vec3,
+    u: Vec3,
+    v: Vec3,
+}
+
+impl CubeFace {
+    // Point on the unit sphere for face coordinates in -1..1
+    fn direction(&self, a: f32, b: f32) -> Vec3 {
+        cube_to_sphere(self.normal + self.u * a + self.v * b)
+    }
+}
+
+// Spreads the cube's points over the sphere more evenly than normalizing
+fn cube_to_sphere(p: Vec3) -> Vec3 {
+    let (x2, y2, z2) = (p.x * p.x, p.y * p.y, p.z * p.z);
+    Vec3::new(
+        p.x * (1.0 - y2 / 2.0 - z2 / 2.0 + y2 * z2 / 3.0).sqrt(),
+        p.y * (1.0 - z2 / 2.0 - x2 / 2.0 + z2 * x2 / 3.0).sqrt(),
+        p.z * (1.0 - x2 / 2.0 - y2 / 2.0 + x2 * y2 / 3.0).sqrt(),
+    )
+    .normalize()
+}
+
+// Same mapping as `generate_sphere`
+fn sphere_tex_coords(direction: Vec3) -> Vec2 {
+    let u = direction.z.atan2(direction.x).rem_euclid(TAU) / TAU;
+    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+    Vec2::new(u, v)
+}
+
+struct PatchBuilder<'a> {
+    radius: f32,
+    focus: Vec3,
+    lod: &'a CubeSphereLod,
+    patches: Vec<SpherePatch>,
+}
+
+impl PatchBuilder<'_> {
+    // `corner` is the patch's lowest face coordinates and `size` its width,
+    // 2 for a whole face
+    fn visit(&mut self, face: &CubeFace, corner: Vec2, size: f32, depth: u32) {
+        let half = size / 2.0;
+        let center = face.direction(corner.x + half, corner.y + half);
+        // A face spans a quarter turn
+        let width = size * FRAC_PI_4 * self.radius;
+        let distance = (self.focus - center * self.radius).magnitude();
+        if depth < self.lod.max_depth && distance < width * self.lod.split_distance {
+            for (a, b) in [(0.0, 0.0), (half, 0.0), (0.0, half), (half, half)] {
+                let corner = corner + Vec2::new(a, b);
+                self.visit(face, corner, half, depth + 1);
+            }
+        } else {
+            let patch = self.patch(face, corner, size, center);
+            self.patches.push(patch);
+        }
+    }
+
+    fn patch(&self, face: &CubeFace, corner: Vec2, size: f32, center: Vec3) -> SpherePatch {
+        let resolution = self.lod.resolution.max(1);
+        let row = resolution + 1;
+        let step = size / resolution as f32;
+        let directions: Vec<Vec3> = (0..row * row)
+            .map(|i| {
+                let (x, y) = ((i % row) as f32, (i / row) as f32);
+                face.direction(corner.x + x * step, corner.y + y * step)
+            })
+            .collect();
+        let vertex = |direction: Vec3, radius: f32| {
+            Vertex::new(direction * radius, direction, sphere_tex_coords(direction))
+        };
+
+        let mut vertices = Vec::new();
+        for y in 0..resolution {
+            for x in 0..resolution {
+                let current = (y * row + x) as usize;
+                let next = current + 1;
+                let below = current + row as usize;
+                let below_next = below + 1;
+                for index in [current, below, next, next, below, below_next] {
+                    vertices.push(vertex(directions[index], self.radius));
+                }
+            }
+        }
+
+        // The skirt drops one quad's width below the edges, walking each
+        // side of the grid in turn
+        let skirt = vertices.len();
+        let lowered = self.radius * (1.0 - step * FRAC_PI_4);
+        let last = resolution;
+        let edge = |side: u32, i: u32| match side {
+            0 => i,
+            1 => i * row + last,
+            2 => last * row + last - i,
+            _ => (last - i) * row,
+        };
+        for side in 0..4 {
+            for i in 0..resolution {
+                let a = directions[edge(side, i) as usize];
+                let b = directions[edge(side, i + 1) as usize];
+                vertices.extend([
+                    vertex(a, self.radius),
+                    vertex(b, self.radius),
+                    vertex(a, lowered),
+                    vertex(b, self.radius),
+                    vertex(b, lowered),
+                    vertex(a, lowered),
+                ]);
+            }
+        }
+
+        let angular_radius = [(0, 0), (last, 0), (0, last), (last, last)]
+            .iter()
+            .map(|(x, y)| center.dot(&directions[(y * row + x) as usize]))
+            .fold(1.0f32, f32::min)
+            .clamp(-1.0, 1.0)
+            .acos();
+
+        SpherePatch {
+            center,
+            angular_radius,
+            vertices,
+            skirt,
+        }
+    }
+}
+
+// A sphere built from the six faces of a cube, each a quadtree whose patches
+// keep splitting while `focus` (in the sphere's own space) is close to them:
+// dense around the point being approached, coarse everywhere else
+pub fn generate_cube_sphere(radius: f32, focus: Vec3, lod: &CubeSphereLod) -> Vec<SpherePatch> {
+    let faces = [
+        (Vec3::x(), Vec3::z(), Vec3::y()),
+        (-Vec3::x(), Vec3::y(), Vec3::z()),
+        (Vec3::y(), Vec3::x(), Vec3::z()),
+        (-Vec3::y(), Vec3::z(), Vec3::x()),
+        (Vec3::z(), Vec3::y(), Vec3::x()),
+        (-Vec3::z(), Vec3::x(), Vec3::y()),
+    ];
+    let mut builder = PatchBuilder {
+        radius,
+        focus,
+        lod,
+        patches: Vec::new(),
+    };
+    for (normal, u, v) in faces {
+        let face = CubeFace { normal, u, v };
+        builder.visit(&face, Vec2::new(-1.0, -1.0), 2.0, 0);
+    }
+    builder.patches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphere_has_two_triangles_per_quad() {
+        for segments in [3, 8, 20] {
+            let vertices = generate_sphere(1.0, segments);
+            assert_eq!(vertices.len(), (segments * segments * 6) as usize);
+        }
+    }
+
+    #[test]
+    fn sphere_vertices_sit_on_the_surface_with_unit_normals() {
+        let radius = 2.5;
+        for vertex in generate_sphere(radius, 16) {
+            assert!((vertex.position.magnitude() - radius).abs() < 1e-4);
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-5);
+            // Normals point straight out
+            assert!((vertex.normal - vertex.position / radius).magnitude() < 1e-4);
+        }
+    }
+
+    const LOD: CubeSphereLod = CubeSphereLod {
+        resolution: 4,
+        max_depth: 5,
+        split_distance: 1.5,
+    };
+
+    // Width of a patch's first quad, which shrinks by half at every split
+    fn quad_width(patch: &SpherePatch) -> f32 {
+        (patch.vertices[2].position - patch.vertices[0].position).magnitude()
+    }
+
+    #[test]
+    fn far_cube_sphere_keeps_one_patch_per_face() {
+        let patches = generate_cube_sphere(1.0, Vec3::new(0.0, 0.0, -100.0), &LOD);
+        assert_eq!(patches.len(), 6);
+        for patch in &patches {
+            assert_eq!(patch.skirt, 4 * 4 * 6);
+            assert_eq!(patch.vertices.len(), patch.skirt + 4 * 4 * 6);
+        }
+    }
+
+    #[test]
+    fn cube_sphere_splits_towards_the_focus() {
+        let radius = 2.0;
+        let focus = Vec3::new(0.3, -0.2, -1.0).normalize() * radius * 1.01;
+        let patches = generate_cube_sphere(radius, focus, &LOD);
+        assert!(patches.len() > 6);
+
+        let (near, far): (Vec<&SpherePatch>, Vec<&SpherePatch>) = patches
+            .iter()
+            .partition(|patch| (patch.vertices[0].position - focus).magnitude() < 0.1);
+        let finest = patches.iter().map(quad_width).fold(f32::MAX, f32::min);
+        let coarsest = patches.iter().map(quad_width).fold(0.0, f32::max);
+        assert!(near.iter().all(|patch| quad_width(patch) < finest * 1.5));
+        assert!(far.iter().any(|patch| quad_width(patch) > coarsest / 1.5));
+        assert!(coarsest > finest * 2f32.powi(LOD.max_depth as i32 - 1));
+
+        for patch in &patches {
+            for vertex in &patch.vertices[..patch.skirt] {
+                assert!((vertex.position.magnitude() - radius).abs() < 1e-4);
+                assert!((vertex.normal - vertex.position / radius).magnitude() < 1e-4);
+            }
+            for vertex in &patch.vertices[patch.skirt..] {
+                assert!(vertex.position.magnitude() <= radius + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn horizon_shrinks_as_the_eye_comes_down() {
+        let patches = generate_cube_sphere(1.0, Vec3::new(0.0, 0.0, -1.02), &LOD);
+        let eye = Vec3::new(0.0, 0.0, -1.0);
+        let hidden = |distance: f32| {
+            patches
+                .iter()
+                .filter(|patch| patch.beyond_horizon(eye, distance, 0.0))
+                .count()
+        };
+        // From far away only the back half goes; skimming the surface, most
+        // of the sphere does, but never the patch right underneath
+        assert!(hidden(f32::INFINITY) > 0);
+        assert!(hidden(1.02) > hidden(2.0));
+        assert!(hidden(2.0) > hidden(f32::INFINITY));
+        assert!(hidden(1.02) < patches.len());
+        assert_eq!(hidden(0.5), 0);
+
+        for patch in &patches {
+            if patch.beyond_horizon(eye, f32::INFINITY, 0.0) {
+                let nearest = patch.vertices[..patch.skirt]
+                    .iter()
+                    .map(|vertex| vertex.normal.dot(&eye))
+                    .fold(f32::MIN, f32::max);
+                assert!(nearest < 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn ring_has_two_triangles_per_segment() {
+        for segments in [3, 60, 120] {
+            let vertices = generate_ring(1.2, 2.0, segments);
+            assert_eq!(vertices.len(), (segments * 6) as usize);
+        }
+    }
+
+    #[test]
+    fn ring_stays_flat_between_its_radii() {
+        let (inner, outer) = (1.2, 2.0);
+        for vertex in generate_ring(inner, outer, 60) {
+            let distance = vertex.position.xz().magnitude();
+            assert!(distance > inner - 1e-4 && distance < outer + 1e-4);
+            assert_eq!(vertex.position.y, 0.0);
+            assert_eq!(vertex.normal, Vec3::new(0.0, 1.0, 0.0));
+        }
+    }
+}
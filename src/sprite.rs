@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use crate::assets;
+
+// Small RGBA image for HUD icons; pixels are stored as 0xAARRGGBB
+pub struct Sprite {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Sprite {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, image::ImageError> {
+        let img = image::load_from_memory(&assets::read(path)?)?.to_rgba8();
+        let (width, height) = img.dimensions();
+        let pixels = img
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.0;
+                u32::from_be_bytes([a, r, g, b])
+            })
+            .collect();
+
+        Ok(Sprite {
+            width: width as usize,
+            height: height as usize,
+            pixels,
+        })
+    }
+}
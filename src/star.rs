@@ -0,0 +1,78 @@
+use nalgebra_glm::Vec3;
+
+use crate::color::{Color, Palette};
+
+// Temperature of the default system's sun, in kelvin
+pub const SUN_TEMPERATURE: f32 = 5800.0;
+
+// Blackbody colors by temperature in kelvin (Mitchell Charity's table)
+const BLACKBODY: Palette = Palette::new(&[
+    (1000.0, Color::new(255, 56, 0)),
+    (2000.0, Color::new(255, 137, 18)),
+    (3000.0, Color::new(255, 180, 107)),
+    (4000.0, Color::new(255, 209, 163)),
+    (5000.0, Color::new(255, 228, 206)),
+    (6000.0, Color::new(255, 243, 239)),
+    (6500.0, Color::new(255, 249, 253)),
+    (7000.0, Color::new(245, 243, 255)),
+    (8000.0, Color::new(227, 233, 255)),
+    (10000.0, Color::new(204, 219, 255)),
+    (15000.0, Color::new(181, 205, 255)),
+    (20000.0, Color::new(168, 197, 255)),
+    (40000.0, Color::new(155, 188, 255)),
+]);
+
+// Colors a star derives from its temperature: its own surface, the light it
+// casts on everything else, and the glow around it
+#[derive(Debug, Clone, Copy)]
+pub struct StarLight {
+    pub surface: Vec3,
+    pub light: Vec3,
+    pub corona: Vec3,
+}
+
+impl StarLight {
+    pub fn from_temperature(kelvin: f32) -> Self {
+        let body = BLACKBODY.sample(kelvin);
+        let (hue, saturation, _) = body.to_hsv();
+
+        // White-balanced against the sun, so the default system keeps its
+        // look while hotter stars light it blue and cooler ones red
+        let balanced = body
+            .to_vec3()
+            .component_div(&BLACKBODY.sample(SUN_TEMPERATURE).to_vec3());
+        let light = balanced / balanced.max();
+
+        // Real blackbody colors are washed out; the disk and corona read
+        // better with their hue exaggerated
+        let surface = Color::from_hsv(hue, (saturation * 2.0 + 0.5).min(1.0), 1.0);
+        let corona = Color::from_hsv(hue, (saturation * 1.5 + 0.3).min(1.0), 1.0);
+
+        StarLight {
+            surface: surface.to_vec3(),
+            light,
+            corona: corona.to_vec3(),
+        }
+    }
+}
+
+impl Default for StarLight {
+    fn default() -> Self {
+        StarLight::from_temperature(SUN_TEMPERATURE)
+    }
+}
+
+// Typical temperature of a Morgan-Keenan spectral class ("G", "M5", "b")
+pub fn class_temperature(class: &str) -> Option<f32> {
+    let temperature = match class.chars().next()?.to_ascii_uppercase() {
+        'O' => 40000.0,
+        'B' => 20000.0,
+        'A' => 8500.0,
+        'F' => 6500.0,
+        'G' => SUN_TEMPERATURE,
+        'K' => 4500.0,
+        'M' => 3200.0,
+        _ => return None,
+    };
+    Some(temperature)
+}
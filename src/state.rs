@@ -0,0 +1,120 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use nalgebra_glm::Vec3;
+
+use crate::events::{predict_events, PredictedKind};
+use crate::json;
+use crate::screenshot;
+use crate::simulation::Simulation;
+use crate::timeline::{HORIZON, TIMELINE_SLOTS};
+
+// The running scene as one line of JSON, for tools, screen readers or
+// grading scripts to read: the clock, the camera and the ship, every body
+// with its distance from the camera, the selection and the alignments
+// coming up. Distances are in scene units; `units_per_au` converts them in
+// the semi-realistic scale and is null in the stylized one
+pub fn to_json(simulation: &Simulation) -> String {
+    let camera = &simulation.camera;
+    let ship = &simulation.ship;
+    let name = |index: usize| format!("\"{}\"", json::escape(&simulation.planets[index].name));
+    let distance = |index: usize| (simulation.planet_position(index) - camera.position).magnitude();
+
+    let bodies: Vec<String> = (0..simulation.planets.len())
+        .map(|index| {
+            format!(
+                "{{\"name\":{},\"position\":{},\"distance\":{},\"radius\":{}}}",
+                name(index),
+                array(simulation.planet_position(index)),
+                json::number(distance(index)),
+                json::number(simulation.planets[index].scale),
+            )
+        })
+        .collect();
+    let selected = simulation.selected.map_or_else(
+        || "null".to_string(),
+        |index| {
+            format!(
+                "{{\"index\":{},\"name\":{},\"distance\":{}}}",
+                index,
+                name(index),
+                json::number(distance(index))
+            )
+        },
+    );
+    // Predictions follow the clockwork orbits, which gravity leaves behind
+    let mut events = Vec::new();
+    if !simulation.gravity_enabled() {
+        let horizon = if simulation.time_scale < 0.0 {
+            -HORIZON
+        } else {
+            HORIZON
+        };
+        let predicted = predict_events(&simulation.planets, simulation.time, horizon);
+        for event in predicted.iter().take(TIMELINE_SLOTS) {
+            let what = match event.kind {
+                PredictedKind::Conjunction(inner, outer) => format!(
+                    "\"kind\":\"conjunction\",\"bodies\":[{},{}]",
+                    name(inner),
+                    name(outer)
+                ),
+                PredictedKind::MoonTransit(planet) => {
+                    format!("\"kind\":\"moon_transit\",\"bodies\":[{}]", name(planet))
+                }
+            };
+            events.push(format!(
+                "{{{},\"time\":{},\"in\":{}}}",
+                what,
+                json::number(event.time),
+                json::number(event.time - simulation.time)
+            ));
+        }
+    }
+    let units_per_au = simulation
+        .scene_scale
+        .pseudo_au()
+        .map_or_else(|| "null".to_string(), json::number);
+
+    format!(
+        "{{\"seed\":\"{}\",\"time\":{},\"time_scale\":{},\"photo_mode\":{},\"gravity\":{},\
+         \"units_per_au\":{},\"camera\":{{\"position\":{},\"zoom\":{},\"tilt\":{},\"roll\":{},\
+         \"fov\":{}}},\"ship\":{{\"flight_mode\":{},\"velocity\":{},\"speed\":{},\"fuel\":{},\
+         \"heat\":{}}},\"selected\":{},\"bodies\":[{}],\"events\":[{}]}}",
+        simulation.seed,
+        json::number(simulation.time),
+        json::number(simulation.time_scale),
+        simulation.photo_mode(),
+        simulation.gravity_enabled(),
+        units_per_au,
+        array(camera.position),
+        json::number(camera.zoom),
+        json::number(camera.tilt),
+        json::number(camera.roll),
+        json::number(camera.fov),
+        ship.flight_mode,
+        array(ship.velocity),
+        json::number(ship.velocity.magnitude()),
+        json::number(ship.fuel),
+        json::number(ship.heat),
+        selected,
+        bodies.join(","),
+        events.join(","),
+    )
+}
+
+// Keeps a copy as `estado_<milliseconds>.json` in the captures folder
+pub fn save(json: &str) -> io::Result<PathBuf> {
+    let path = screenshot::capture_path("estado")?.with_extension("json");
+    fs::write(&path, format!("{}\n", json))?;
+    Ok(path)
+}
+
+fn array(vector: Vec3) -> String {
+    format!(
+        "[{},{},{}]",
+        json::number(vector.x),
+        json::number(vector.y),
+        json::number(vector.z)
+    )
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use web_time::Instant;
+
+use crate::backend::Renderer;
+use crate::config::Config;
+use crate::expr::ExpressionShaders;
+use crate::framebuffer::Framebuffer;
+use crate::hud::HudIcons;
+use crate::i18n::Strings;
+use crate::passes::Pass;
+use crate::renderer::{SoftwareRenderer, ACCUMULATION_SAMPLES};
+use crate::screenshot;
+use crate::settings::Settings;
+use crate::simulation::{Simulation, Snapshot};
+use crate::skybox::Skybox;
+use crate::vertex::Vertex;
+
+// The still is this many times the window's resolution on each side
+const STILL_SCALE: usize = 4;
+// Sphere meshes this many times finer than the live ones
+const STILL_MESH_DETAIL: u32 = 3;
+
+// An offscreen software renderer set up for one high-quality picture of the
+// current view: larger, with finer spheres, every planet shaded by its full
+// procedural shader instead of baked noise or atlases, and no HUD
+pub struct Still {
+    renderer: SoftwareRenderer,
+}
+
+impl Still {
+    pub fn new(
+        screen: &Framebuffer,
+        skybox: Skybox,
+        ship: Vec<Vertex>,
+        strings: Rc<Strings>,
+        config: &Config,
+    ) -> Result<Self, image::ImageError> {
+        let mut renderer = SoftwareRenderer::new(
+            screen.width * STILL_SCALE,
+            screen.height * STILL_SCALE,
+            skybox,
+            ship,
+            HudIcons::load()?,
+            strings,
+        );
+        renderer.screen.ui_scale = screen.ui_scale * STILL_SCALE as f32;
+        renderer.set_mesh_detail(STILL_MESH_DETAIL);
+        renderer.set_expression_shaders(ExpressionShaders::new(&config.shaders));
+        renderer.set_fill_light(config.fill_light);
+        // Orbits, labels and the HUD stay out of the picture
+        renderer.graph_mut().set_enabled(Pass::Overlay, false);
+        for &pass in &config.disabled_passes {
+            renderer.graph_mut().set_enabled(pass, false);
+        }
+        Ok(Still { renderer })
+    }
+
+    // Averages every jittered sample of the frame with all post effects on
+    // and saves it as a PNG; takes a while, since nothing here is realtime
+    pub fn render(
+        mut self,
+        simulation: &Simulation,
+        snapshot: &Snapshot,
+        settings: &Settings,
+    ) -> Result<PathBuf, image::ImageError> {
+        let settings = Settings {
+            bloom: true,
+            antialiasing: true,
+            accumulation: true,
+            resolution_scale: 1.0,
+            ..*settings
+        };
+        let start = Instant::now();
+        // Landing terrain only starts building once a frame draws the body;
+        // waiting for it keeps the plain sphere out of the samples
+        if snapshot.landing.is_some() {
+            self.renderer.render_frame(simulation, snapshot, &settings);
+            self.renderer.wait_for_terrain();
+        }
+        for _ in 0..ACCUMULATION_SAMPLES {
+            self.renderer.render_frame(simulation, snapshot, &settings);
+        }
+        let path = screenshot::save_as(self.renderer.screen(), "foto_alta")?;
+        eprintln!(
+            "Foto de alta calidad: {}x{}, {} muestras, {:.1} s",
+            self.renderer.screen.width,
+            self.renderer.screen.height,
+            ACCUMULATION_SAMPLES,
+            start.elapsed().as_secs_f32(),
+        );
+        Ok(path)
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::passes::{Pass, RenderGraph};
+use crate::renderer::FrameStats;
+
+enum Format {
+    Csv,
+    Json,
+}
+
+// Streams one record per rendered frame; the format follows the file extension
+pub struct Telemetry {
+    writer: BufWriter<File>,
+    format: Format,
+    frame: u64,
+}
+
+impl Telemetry {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let format = match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            _ => Format::Csv,
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        match format {
+            Format::Csv => {
+                write!(writer, "frame,frame_ms,triangles,fragments,culled_objects")?;
+                for pass in Pass::ALL {
+                    write!(writer, ",{}_ms", pass.name())?;
+                }
+                writeln!(writer)?;
+            }
+            Format::Json => writeln!(writer, "[")?,
+        }
+
+        Ok(Telemetry {
+            writer,
+            format,
+            frame: 0,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        frame_time: f32,
+        stats: &FrameStats,
+        graph: &RenderGraph,
+    ) -> io::Result<()> {
+        let frame_ms = frame_time * 1000.0;
+        let pass_ms = |pass: Pass| graph.timing(pass).as_secs_f32() * 1000.0;
+        match self.format {
+            Format::Csv => {
+                write!(
+                    self.writer,
+                    "{},{:.3},{},{},{}",
+                    self.frame, frame_ms, stats.triangles, stats.fragments, stats.culled_objects
+                )?;
+                for pass in Pass::ALL {
+                    write!(self.writer, ",{:.3}", pass_ms(pass))?;
+                }
+                writeln!(self.writer)?;
+            }
+            Format::Json => {
+                let separator = if self.frame == 0 { "" } else { ",\n" };
+                write!(
+                    self.writer,
+                    "{}  {{\"frame\": {}, \"frame_ms\": {:.3}, \"triangles\": {}, \"fragments\": {}, \"culled_objects\": {}",
+                    separator,
+                    self.frame,
+                    frame_ms,
+                    stats.triangles,
+                    stats.fragments,
+                    stats.culled_objects
+                )?;
+                for pass in Pass::ALL {
+                    write!(
+                        self.writer,
+                        ", \"{}_ms\": {:.3}",
+                        pass.name(),
+                        pass_ms(pass)
+                    )?;
+                }
+                write!(self.writer, "}}")?;
+            }
+        }
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Format::Json = self.format {
+            let _ = writeln!(self.writer, "\n]");
+        }
+        let _ = self.writer.flush();
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, KeyboardEnhancementFlags, MouseButton, MouseEventKind,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color as TermColor, Colors, Print, ResetColor, SetColors};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen, SetTitle};
+use crossterm::{execute, queue};
+
+use computer_graphics_v3::app::{frame_size, App, WINDOW_TITLE};
+use computer_graphics_v3::framebuffer::Framebuffer;
+use computer_graphics_v3::input::{InputSource, Key};
+use computer_graphics_v3::options::Options;
+
+// Every cell is sampled from a block of the frame, so it's rendered small
+// unless --scale asks otherwise
+const TERMINAL_SCALE: f32 = 0.5;
+// About 30 frames a second, which a terminal over SSH keeps up with
+const FRAME_DELAY: Duration = Duration::from_millis(33);
+// Most terminals only report presses, repeated while a key is held, so a key
+// counts as down this long after its last one; longer than the delay before
+// the repeats start, or a held key would flicker up and down
+const KEY_HOLD: Duration = Duration::from_millis(500);
+
+// The frame drawn into the terminal, two pixels per cell: the top one as the
+// foreground of a '▀' and the bottom one as its background
+struct TerminalScreen {
+    out: Stdout,
+    // Frame size in physical pixels, and physical pixels per logical one
+    frame: (usize, usize),
+    scale: f32,
+    columns: u16,
+    rows: u16,
+    // Keys down, each with when it was last pressed or repeated
+    keys: HashMap<Key, Instant>,
+    // Whether the terminal reports releases, so keys stay down until then
+    releases: bool,
+    enhanced: bool,
+    mouse: Option<(u16, u16)>,
+    mouse_down: bool,
+    quit: bool,
+    // The escape codes of a frame, written in one go
+    output: Vec<u8>,
+}
+
+// Terminal main loop
+pub fn run(options: &Options) {
+    let scale = options.scale.unwrap_or(TERMINAL_SCALE);
+    // Loaded before taking over the terminal, so its messages stay readable
+    let mut app = App::new(options, scale);
+    let mut screen =
+        TerminalScreen::open(frame_size(scale), scale).expect("No se pudo preparar la terminal");
+    loop {
+        screen.poll().expect("No se pudo leer la terminal");
+        if screen.quit || !app.frame(&screen) {
+            break;
+        }
+        screen
+            .draw(app.screen())
+            .expect("No se pudo dibujar en la terminal");
+
+        std::thread::sleep(FRAME_DELAY);
+    }
+    drop(screen);
+    app.shutdown();
+}
+
+impl TerminalScreen {
+    fn open(frame: (usize, usize), scale: f32) -> io::Result<Self> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(
+            out,
+            EnterAlternateScreen,
+            SetTitle(WINDOW_TITLE),
+            Hide,
+            EnableMouseCapture
+        )?;
+        // Terminals that speak the kitty keyboard protocol report releases
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                )
+            )?;
+        }
+        let (columns, rows) = terminal::size()?;
+        Ok(TerminalScreen {
+            out,
+            frame,
+            scale,
+            columns,
+            rows,
+            keys: HashMap::new(),
+            releases: enhanced || cfg!(windows),
+            enhanced,
+            mouse: None,
+            mouse_down: false,
+            quit: false,
+            output: Vec::new(),
+        })
+    }
+
+    // Reads every event waiting, without blocking
+    fn poll(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) => self.key(key),
+                Event::Mouse(mouse) => {
+                    match mouse.kind {
+                        MouseEventKind::Down(MouseButton::Left) => self.mouse_down = true,
+                        MouseEventKind::Up(MouseButton::Left) => self.mouse_down = false,
+                        _ => {}
+                    }
+                    self.mouse = Some((mouse.column, mouse.row));
+                }
+                Event::Resize(columns, rows) => {
+                    self.columns = columns;
+                    self.rows = rows;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, event: KeyEvent) {
+        // Raw mode swallows the interrupt, so Ctrl+C quits here
+        if event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+        let now = Instant::now();
+        // Modifiers only come along with other keys
+        for (modifier, key) in [
+            (KeyModifiers::SHIFT, Key::LeftShift),
+            (KeyModifiers::CONTROL, Key::LeftCtrl),
+        ] {
+            if event.modifiers.contains(modifier) && event.kind != KeyEventKind::Release {
+                self.keys.insert(key, now);
+            } else {
+                self.keys.remove(&key);
+            }
+        }
+        let Some(key) = app_key(event.code) else {
+            return;
+        };
+        match event.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                self.keys.insert(key, now);
+            }
+            KeyEventKind::Release => {
+                self.keys.remove(&key);
+            }
+        }
+    }
+
+    // Where the frame sits in the grid of half-cell pixels: left, top,
+    // width and height, as large as fits with its aspect kept
+    fn picture(&self) -> (usize, usize, usize, usize) {
+        let (columns, rows) = (self.columns as usize, self.rows as usize * 2);
+        let (width, height) = self.frame;
+        let fit = (columns as f32 / width as f32).min(rows as f32 / height as f32);
+        let fitted_width = ((width as f32 * fit).round() as usize).clamp(1, columns.max(1));
+        let fitted_height = ((height as f32 * fit).round() as usize).clamp(1, rows.max(1));
+        (
+            (columns - fitted_width.min(columns)) / 2,
+            (rows - fitted_height.min(rows)) / 2,
+            fitted_width,
+            fitted_height,
+        )
+    }
+
+    fn draw(&mut self, screen: &Framebuffer) -> io::Result<()> {
+        let (left, top, width, height) = self.picture();
+        // Each half-cell pixel averages the block of the frame under it,
+        // black around the picture
+        let pixel = |x: usize, y: usize| -> u32 {
+            if x < left || y < top || x >= left + width || y >= top + height {
+                return 0;
+            }
+            let (x, y) = (x - left, y - top);
+            let x0 = x * screen.width / width;
+            let x1 = ((x + 1) * screen.width / width).max(x0 + 1);
+            let y0 = y * screen.height / height;
+            let y1 = ((y + 1) * screen.height / height).max(y0 + 1);
+            average(screen, x0..x1, y0..y1)
+        };
+        self.output.clear();
+        let mut last = None;
+        for row in 0..self.rows {
+            queue!(self.output, MoveTo(0, row))?;
+            for column in 0..self.columns as usize {
+                let y = row as usize * 2;
+                let colors = (pixel(column, y), pixel(column, y + 1));
+                if last != Some(colors) {
+                    queue!(
+                        self.output,
+                        SetColors(Colors::new(rgb(colors.0), rgb(colors.1)))
+                    )?;
+                    last = Some(colors);
+                }
+                queue!(self.output, Print('▀'))?;
+            }
+        }
+        queue!(self.output, ResetColor)?;
+        self.out.write_all(&self.output)?;
+        self.out.flush()
+    }
+}
+
+impl Drop for TerminalScreen {
+    // Hands the terminal back as it was, also when the app panics
+    fn drop(&mut self) {
+        if self.enhanced {
+            let _ = execute!(self.out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(
+            self.out,
+            DisableMouseCapture,
+            ResetColor,
+            Show,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl InputSource for TerminalScreen {
+    fn is_key_down(&self, key: Key) -> bool {
+        if key == Key::MouseLeft {
+            return self.mouse_down;
+        }
+        self.keys
+            .get(&key)
+            .is_some_and(|pressed| self.releases || pressed.elapsed() < KEY_HOLD)
+    }
+
+    // The middle of the cell under the pointer, in the frame's logical pixels
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        let (column, row) = self.mouse?;
+        let (left, top, width, height) = self.picture();
+        let x = column as f32 + 0.5 - left as f32;
+        let y = row as f32 * 2.0 + 1.0 - top as f32;
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return None;
+        }
+        Some((
+            x * self.frame.0 as f32 / width as f32 / self.scale,
+            y * self.frame.1 as f32 / height as f32 / self.scale,
+        ))
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (
+            (self.frame.0 as f32 / self.scale) as usize,
+            (self.frame.1 as f32 / self.scale) as usize,
+        )
+    }
+}
+
+fn average(
+    screen: &Framebuffer,
+    columns: std::ops::Range<usize>,
+    rows: std::ops::Range<usize>,
+) -> u32 {
+    let mut sum = [0u32; 3];
+    let count = (columns.len() * rows.len()) as u32;
+    for y in rows {
+        let row = &screen.buffer[y * screen.width..(y + 1) * screen.width];
+        for &color in &row[columns.clone()] {
+            sum[0] += (color >> 16) & 0xFF;
+            sum[1] += (color >> 8) & 0xFF;
+            sum[2] += color & 0xFF;
+        }
+    }
+    ((sum[0] / count) << 16) | ((sum[1] / count) << 8) | (sum[2] / count)
+}
+
+fn rgb(color: u32) -> TermColor {
+    TermColor::Rgb {
+        r: (color >> 16) as u8,
+        g: (color >> 8) as u8,
+        b: color as u8,
+    }
+}
+
+// The key a terminal reports, shifted characters as their unshifted key
+fn app_key(code: KeyCode) -> Option<Key> {
+    let key = match code {
+        KeyCode::Char(character) => match character.to_ascii_lowercase() {
+            letter @ 'a'..='z' => LETTERS[(letter as u8 - b'a') as usize],
+            digit @ '1'..='9' => DIGITS[(digit as u8 - b'1') as usize],
+            '=' | '+' => Key::Equal,
+            '-' | '_' => Key::Minus,
+            '[' | '{' => Key::LeftBracket,
+            ']' | '}' => Key::RightBracket,
+            ',' | '<' => Key::Comma,
+            '.' | '>' => Key::Period,
+            _ => return None,
+        },
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::F(7) => Key::F7,
+        KeyCode::F(8) => Key::F8,
+        KeyCode::F(9) => Key::F9,
+        KeyCode::F(10) => Key::F10,
+        KeyCode::F(12) => Key::F12,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab | KeyCode::BackTab => Key::Tab,
+        KeyCode::Esc => Key::Escape,
+        _ => return None,
+    };
+    Some(key)
+}
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+const DIGITS: [Key; 9] = [
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+];
@@ -0,0 +1,218 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use rayon::prelude::*;
+
+use crate::fragment_shaders::HeightFunction;
+use crate::renderer::create_model_matrix;
+use crate::sphere::{generate_cube_sphere, CubeSphereLod, SpherePatch};
+use crate::vertex::Vertex;
+
+const TERRAIN_LOD: CubeSphereLod = CubeSphereLod {
+    resolution: 8,
+    max_depth: 7,
+    split_distance: 2.0,
+};
+// The patches are rebuilt once the camera has moved this fraction of its
+// height above the surface, which never counts as less than the minimum
+const REBUILD_DISTANCE: f32 = 0.25;
+const MIN_HEIGHT: f32 = 0.01;
+
+// A cube-sphere patch plus the relief its height function puts on top of it
+struct TerrainPatch {
+    base: SpherePatch,
+    // The highest the relief rises on this patch, in radii
+    peak: f32,
+    heights: Vec<f32>,
+    face_normals: Vec<Vec3>,
+}
+
+impl TerrainPatch {
+    fn new(patch: SpherePatch, height: HeightFunction, seed: u32) -> Self {
+        let heights: Vec<f32> = patch
+            .vertices
+            .iter()
+            .map(|vertex| height(vertex.normal, seed))
+            .collect();
+        let peak = heights.iter().copied().fold(0.0, f32::max);
+
+        let mut face_normals = Vec::with_capacity(patch.vertices.len());
+        for (index, (triangle, relief)) in
+            patch.vertices.chunks(3).zip(heights.chunks(3)).enumerate()
+        {
+            if triangle.len() < 3 {
+                break;
+            }
+            // The skirt is only seen through cracks; it takes the surface's
+            // shading rather than a wall's
+            if index * 3 >= patch.skirt {
+                face_normals.extend(triangle.iter().map(|vertex| vertex.normal));
+                continue;
+            }
+            let a = triangle[0].position * (1.0 + relief[0]);
+            let b = triangle[1].position * (1.0 + relief[1]);
+            let c = triangle[2].position * (1.0 + relief[2]);
+            let mut normal = (b - a).cross(&(c - a));
+            if normal.magnitude() < 1e-6 {
+                normal = triangle[0].normal;
+            } else if normal.dot(&triangle[0].normal) < 0.0 {
+                normal = -normal;
+            }
+            let normal = normal.normalize();
+            face_normals.extend([normal, normal, normal]);
+        }
+
+        TerrainPatch {
+            base: patch,
+            peak,
+            heights,
+            face_normals,
+        }
+    }
+}
+
+// A unit cube-sphere split finest under the camera, displaced by the body's
+// height function. The patches are built off the render path: until the
+// first ones arrive there is nothing to draw, and after that the old ones
+// stay up while their replacements are built.
+pub struct TerrainMesh {
+    height: HeightFunction,
+    seed: u32,
+    // Where the camera was when the patches were last asked for
+    focus: Option<Vec3>,
+    patches: Vec<TerrainPatch>,
+    building: Option<Receiver<Vec<TerrainPatch>>>,
+    // Bumped every time new patches are swapped in
+    generation: u32,
+}
+
+impl TerrainMesh {
+    // `seed` has to match the one the body's shader is drawn with
+    pub fn new(height: HeightFunction, seed: u32) -> Self {
+        TerrainMesh {
+            height,
+            seed,
+            focus: None,
+            patches: Vec::new(),
+            building: None,
+            generation: 0,
+        }
+    }
+
+    // False until the first patches are built
+    pub fn is_ready(&self) -> bool {
+        !self.patches.is_empty()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    // Blocks until the build in flight, if any, is done
+    pub fn wait(&mut self) {
+        if let Some(building) = self.building.take() {
+            if let Ok(patches) = building.recv() {
+                self.swap(patches);
+            }
+        }
+    }
+
+    fn swap(&mut self, patches: Vec<TerrainPatch>) {
+        self.patches = patches;
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    // `focus` is the camera in the body's object space, in radii; the
+    // patches are only rebuilt once it has moved far enough to matter, and
+    // one build runs at a time
+    pub fn update(&mut self, focus: Vec3) {
+        if let Some(building) = &self.building {
+            match building.try_recv() {
+                Ok(patches) => self.swap(patches),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {}
+            }
+            self.building = None;
+        }
+        if let Some(last) = self.focus {
+            let height = (focus.magnitude() - 1.0).max(MIN_HEIGHT);
+            if (focus - last).magnitude() < height * REBUILD_DISTANCE {
+                return;
+            }
+        }
+        self.focus = Some(focus);
+
+        let (height, seed) = (self.height, self.seed);
+        let (sender, receiver) = mpsc::channel();
+        let build = move || {
+            let patches = generate_cube_sphere(1.0, focus, &TERRAIN_LOD)
+                .into_par_iter()
+                .map(|patch| TerrainPatch::new(patch, height, seed))
+                .collect();
+            // Nobody is waiting any more if the mesh was dropped meanwhile
+            let _ = sender.send(patches);
+        };
+        // The browser build has no worker threads, so it builds right away
+        // and the patches are picked up on the next update
+        #[cfg(not(target_arch = "wasm32"))]
+        rayon::spawn(build);
+        #[cfg(target_arch = "wasm32")]
+        build();
+        self.building = Some(receiver);
+    }
+
+    // Fills `vertices` with the mesh; `amount` fades the relief in (0 =
+    // smooth sphere, 1 = full terrain). Patches past the horizon of an eye
+    // `distance` radii away towards `direction` are skipped; returns how
+    // many were
+    pub fn displaced(
+        &self,
+        amount: f32,
+        direction: Vec3,
+        distance: f32,
+        vertices: &mut Vec<Vertex>,
+    ) -> usize {
+        let amount = amount.clamp(0.0, 1.0);
+        vertices.clear();
+        let mut hidden = 0;
+        for patch in &self.patches {
+            if patch
+                .base
+                .beyond_horizon(direction, distance, patch.peak * amount)
+            {
+                hidden += 1;
+                continue;
+            }
+            let displaced = patch
+                .base
+                .vertices
+                .iter()
+                .zip(&patch.heights)
+                .zip(&patch.face_normals)
+                .map(|((vertex, height), face_normal)| {
+                    let position = vertex.position * (1.0 + height * amount);
+                    let normal =
+                        (vertex.normal * (1.0 - amount) + face_normal * amount).normalize();
+                    Vertex::new(position, normal, vertex.tex_coords)
+                });
+            vertices.extend(displaced);
+        }
+        hidden
+    }
+}
+
+// The camera in a body's object space, in radii: `height` above the surface
+// point drawn at `offset` from the body's middle (in radii on screen), or
+// above its edge once the offset leaves the disc. Meshes are turned in screen
+// space, where the camera looks along +z
+pub fn camera_focus(offset: Vec2, height: f32, rotation: Vec3) -> Vec3 {
+    let across = offset.magnitude();
+    let surface = if across >= 1.0 {
+        Vec3::new(offset.x / across, offset.y / across, 0.0)
+    } else {
+        Vec3::new(offset.x, offset.y, -(1.0 - across * across).sqrt())
+    };
+    let point = surface * (1.0 + height.max(0.0));
+    let rotation = create_model_matrix(Vec3::zeros(), 1.0, rotation);
+    (rotation.transpose() * Vec4::new(point.x, point.y, point.z, 0.0)).xyz()
+}
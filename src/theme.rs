@@ -0,0 +1,145 @@
+use crate::color::Color;
+use crate::simulation::Planet;
+
+// Okabe-Ito colors, told apart with every common kind of color blindness
+const BLUE: Color = Color::new(0, 114, 178);
+const SKY_BLUE: Color = Color::new(86, 180, 233);
+const YELLOW: Color = Color::new(240, 228, 66);
+const ORANGE: Color = Color::new(230, 159, 0);
+const VERMILLION: Color = Color::new(213, 94, 0);
+const REDDISH_PURPLE: Color = Color::new(204, 121, 167);
+const BLUISH_GREEN: Color = Color::new(0, 158, 115);
+
+// Orbit colors handed out in planet order, neighbors as far apart as
+// possible. Protanopes see red as dark, so vermillion gives way to white
+const DEUTERANOPIA_ORBITS: [Color; 7] = [
+    YELLOW,
+    SKY_BLUE,
+    ORANGE,
+    BLUE,
+    REDDISH_PURPLE,
+    BLUISH_GREEN,
+    VERMILLION,
+];
+const PROTANOPIA_ORBITS: [Color; 7] = [
+    YELLOW,
+    SKY_BLUE,
+    ORANGE,
+    BLUE,
+    REDDISH_PURPLE,
+    BLUISH_GREEN,
+    Color::new(240, 240, 240),
+];
+
+// Panels are nearly opaque in high contrast so nothing shows through the text
+const HIGH_CONTRAST_PANEL_ALPHA: f32 = 0.92;
+
+// Colors the orbits and the HUD are drawn with, chosen in the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PalettePreset {
+    // Each planet's own orbit color
+    Original,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 3] = [
+        PalettePreset::Original,
+        PalettePreset::Deuteranopia,
+        PalettePreset::Protanopia,
+    ];
+
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    // Name in the config file, also the key of its menu text
+    pub fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Original => "original",
+            PalettePreset::Deuteranopia => "deuteranopia",
+            PalettePreset::Protanopia => "protanopia",
+        }
+    }
+
+    fn orbits(self) -> Option<&'static [Color]> {
+        match self {
+            PalettePreset::Original => None,
+            PalettePreset::Deuteranopia => Some(&DEUTERANOPIA_ORBITS),
+            PalettePreset::Protanopia => Some(&PROTANOPIA_ORBITS),
+        }
+    }
+}
+
+// Overlay colors for one frame, from the palette and contrast settings
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub text: u32,
+    // Titles, borders and the compass ring
+    pub accent: u32,
+    // Highlighted values: the selected menu row, keys in the help
+    pub selected: u32,
+    // Behind the selected menu row
+    pub highlight: u32,
+    pub panel: u32,
+    // Arrow towards the star on the compass
+    pub star: u32,
+    orbits: Option<&'static [Color]>,
+    high_contrast: bool,
+}
+
+impl Theme {
+    pub fn new(preset: PalettePreset, high_contrast: bool) -> Self {
+        // The color-blind presets trade the HUD's accents for Okabe-Ito ones
+        let (accent, selected, star) = match preset {
+            PalettePreset::Original => (
+                Color::new(68, 204, 255),
+                Color::new(255, 217, 102),
+                Color::new(255, 204, 85),
+            ),
+            _ => (SKY_BLUE, YELLOW, ORANGE),
+        };
+        let mut theme = Theme {
+            text: Color::new(204, 230, 255).to_hex(),
+            accent: accent.to_hex(),
+            selected: selected.to_hex(),
+            highlight: Color::new(26, 42, 68).to_hex(),
+            panel: Color::new(5, 10, 20).to_hex(),
+            star: star.to_hex(),
+            orbits: preset.orbits(),
+            high_contrast,
+        };
+        if high_contrast {
+            theme.text = Color::new(255, 255, 255).to_hex();
+            theme.accent = theme.text;
+            theme.highlight = Color::new(60, 60, 60).to_hex();
+            theme.panel = Color::black().to_hex();
+        }
+        theme
+    }
+
+    // Panel opacity a screen asks for, raised in high contrast
+    pub fn panel_alpha(&self, alpha: f32) -> f32 {
+        if self.high_contrast {
+            alpha.max(HIGH_CONTRAST_PANEL_ALPHA)
+        } else {
+            alpha
+        }
+    }
+
+    // Orbit color of planet `index`: its own, or the preset's colors in turn.
+    // High contrast brings it to full brightness
+    pub fn orbit_color(&self, planets: &[Planet], index: usize) -> u32 {
+        let color = match self.orbits {
+            None => Color::from_hex(planets[index].orbit_color),
+            Some(colors) => colors[index % colors.len()],
+        };
+        if self.high_contrast {
+            let (hue, saturation, _) = color.to_hsv();
+            Color::from_hsv(hue, saturation, 1.0).to_hex()
+        } else {
+            color.to_hex()
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::events::{predict_events, Predicted};
+use crate::simulation::Planet;
+
+// How far ahead the timeline looks, in simulated seconds; long enough for
+// the slowest neighbors to line up again
+pub const HORIZON: f32 = 240.0;
+// One per digit key, 1 to 9
+pub const TIMELINE_SLOTS: usize = 9;
+
+// Upcoming conjunctions and transits, listed while open. With the clock
+// running backwards, upcoming means earlier
+#[derive(Default)]
+pub struct Timeline {
+    // While on, the digit keys jump to events instead of warping to planets
+    pub open: bool,
+    events: Vec<Predicted>,
+    // Simulated time the list is worked out again at; None when it's stale
+    expires: Option<f32>,
+    backwards: bool,
+}
+
+impl Timeline {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.invalidate();
+    }
+
+    // The orbits changed, or time jumped
+    pub fn invalidate(&mut self) {
+        self.expires = None;
+    }
+
+    // The list is worked out again once its soonest event is reached,
+    // halfway through the horizon when nothing is coming, or when the clock
+    // turns around
+    pub fn update(&mut self, planets: &[Planet], time: f32, backwards: bool) {
+        let reached = |expires: f32| {
+            if backwards {
+                time <= expires
+            } else {
+                time >= expires
+            }
+        };
+        let stale = backwards != self.backwards || self.expires.is_none_or(reached);
+        if !self.open || !stale {
+            return;
+        }
+        let horizon = if backwards { -HORIZON } else { HORIZON };
+        self.events = predict_events(planets, time, horizon);
+        self.events.truncate(TIMELINE_SLOTS);
+        self.expires = Some(
+            self.events
+                .first()
+                .map_or(time + horizon * 0.5, |event| event.time),
+        );
+        self.backwards = backwards;
+    }
+
+    pub fn events(&self) -> &[Predicted] {
+        &self.events
+    }
+}
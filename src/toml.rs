@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::io;
+
+// Just enough TOML for our own files: `[section]` headers, `key = value`
+// pairs, `#` comments, and strings, numbers, booleans or arrays of them as
+// values; arrays may span several lines. Keys inside a section come back as
+// `section.key`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Value::Number(number) => Some(*number as f32),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(flag) => Some(*flag),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(text: &str) -> io::Result<HashMap<String, Value>> {
+    let mut table = HashMap::new();
+    let mut section = String::new();
+    let mut pending = String::new();
+
+    for (number, line) in text.lines().enumerate() {
+        // Arrays may span several lines; gather them until the brackets close
+        pending.push_str(strip_comment(line).trim());
+        if open_brackets(&pending) > 0 {
+            continue;
+        }
+        let line = std::mem::take(&mut pending);
+        let line = line.as_str();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            section = format!("{}.", name.trim());
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid_line(number, "se esperaba `clave = valor`"))?;
+        let value =
+            parse_value(value.trim()).ok_or_else(|| invalid_line(number, "valor inválido"))?;
+        table.insert(format!("{}{}", section, key.trim()), value);
+    }
+
+    if !pending.is_empty() {
+        return Err(invalid_line(
+            text.lines().count(),
+            "falta cerrar un arreglo",
+        ));
+    }
+    Ok(table)
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(inner) = text
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return split_items(inner)
+            .into_iter()
+            .map(|item| parse_value(item.trim()))
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Array);
+    }
+    if let Some(inner) = text
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        return Some(Value::String(unescape(inner)));
+    }
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => text.replace('_', "").parse().ok().map(Value::Number),
+    }
+}
+
+// Splits on commas that are not inside a string; empty trailing items are dropped
+fn split_items(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut in_string = false;
+    let mut start = 0;
+    for (index, character) in text.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                items.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&text[start..]);
+    items.retain(|item| !item.trim().is_empty());
+    items
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+// How many more `[` than `]` there are outside strings
+fn open_brackets(text: &str) -> i32 {
+    let mut in_string = false;
+    let mut depth = 0;
+    for character in text.chars() {
+        match character {
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", "\n")
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\")
+}
+
+fn invalid_line(number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("línea {}: {}", number + 1, message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Value {
+        Value::Array(
+            items
+                .iter()
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn reads_sections_keys_and_values() {
+        let table = parse(
+            "seed = 42\n\
+             [camera]\n\
+             name = \"Aurelia # 1\"  # a comment\n\
+             zoom = 1_000.5\n\
+             kepler = true\n",
+        )
+        .unwrap();
+
+        assert_eq!(table["seed"], Value::Number(42.0));
+        assert_eq!(
+            table["camera.name"],
+            Value::String("Aurelia # 1".to_string())
+        );
+        assert_eq!(table["camera.zoom"], Value::Number(1000.5));
+        assert_eq!(table["camera.kepler"], Value::Bool(true));
+    }
+
+    #[test]
+    fn arrays_may_span_lines() {
+        let table = parse(
+            "skyboxes = [\n\
+             \"procedural:estrellas\",  # the default\n\
+             \"fondo.jpg\",\n\
+             ]\n\
+             after = 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            table["skyboxes"],
+            strings(&["procedural:estrellas", "fondo.jpg"])
+        );
+        assert_eq!(table["after"], Value::Number(1.0));
+    }
+
+    #[test]
+    fn brackets_inside_strings_do_not_open_arrays() {
+        let table = parse("title = \"[beta\"\nitems = [\"a]\", \"[b\"]\nnext = 2\n").unwrap();
+
+        assert_eq!(table["title"], Value::String("[beta".to_string()));
+        assert_eq!(table["items"], strings(&["a]", "[b"]));
+        assert_eq!(table["next"], Value::Number(2.0));
+    }
+
+    #[test]
+    fn unclosed_arrays_and_bad_lines_are_errors() {
+        assert!(parse("items = [1, 2\n").is_err());
+        assert!(parse("just a line\n").is_err());
+        assert!(parse("value = nope\n").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::f32::consts::TAU;
+
+use nalgebra_glm::Vec3;
+
+use crate::simulation::Simulation;
+
+// Seconds flying to the next body and then circling it
+const APPROACH_TIME: f32 = 3.0;
+const ORBIT_TIME: f32 = 8.0;
+// How far outside a body's collision radius the camera circles
+const ORBIT_MARGIN: f32 = 1.4;
+// Screen radius, in pixels, a body is framed at (within the zoom limits)
+const FRAMED_RADIUS: f32 = 100.0;
+
+// Automatic visit of every body in order: a spline flight to each one, a
+// full turn around it with its info on the HUD, and on to the next
+pub struct Tour {
+    pub stop: usize,
+    elapsed: f32,
+    origin: Vec3,
+    start_zoom: f32,
+}
+
+impl Tour {
+    pub fn new(simulation: &Simulation) -> Self {
+        Tour {
+            stop: 0,
+            elapsed: 0.0,
+            origin: simulation.camera.position,
+            start_zoom: simulation.camera.zoom,
+        }
+    }
+
+    // Moves the camera one step; false once the last body has been visited
+    pub fn advance(&mut self, simulation: &mut Simulation, delta: f32) -> bool {
+        self.elapsed += delta;
+        if self.elapsed >= APPROACH_TIME + ORBIT_TIME {
+            self.stop += 1;
+            self.elapsed -= APPROACH_TIME + ORBIT_TIME;
+            self.origin = simulation.camera.position;
+            self.start_zoom = simulation.camera.zoom;
+        }
+        if self.stop >= simulation.planets.len() {
+            return false;
+        }
+
+        let planet = &simulation.planets[self.stop];
+        let center = simulation.planet_position(self.stop);
+        let distance = planet.collision_radius * ORBIT_MARGIN;
+        let zoom = (FRAMED_RADIUS / planet.scale).clamp(0.35, 1.8);
+        let orbit = |seconds: f32| {
+            let angle = TAU * seconds / ORBIT_TIME;
+            center + Vec3::new(angle.sin(), 0.0, -angle.cos()) * distance
+        };
+
+        let camera = &mut simulation.camera;
+        camera.velocity = Vec3::zeros();
+        if self.elapsed < APPROACH_TIME {
+            // Arrives already moving along the circle so the turn starts smoothly
+            let t = self.elapsed / APPROACH_TIME;
+            let arrival = Vec3::new(1.0, 0.0, 0.0) * distance * TAU / ORBIT_TIME * APPROACH_TIME;
+            camera.position = hermite(self.origin, Vec3::zeros(), orbit(0.0), arrival, t);
+            camera.zoom = self.start_zoom + (zoom - self.start_zoom) * t * t * (3.0 - 2.0 * t);
+        } else {
+            camera.position = orbit(self.elapsed - APPROACH_TIME);
+            camera.zoom = zoom;
+        }
+        simulation.selected = Some(self.stop);
+        simulation.ship.velocity = Vec3::zeros();
+        true
+    }
+}
+
+// Cubic Hermite spline from `start` to `end` with the given tangents
+fn hermite(start: Vec3, start_tangent: Vec3, end: Vec3, end_tangent: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    start * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + start_tangent * (t3 - 2.0 * t2 + t)
+        + end * (3.0 * t2 - 2.0 * t3)
+        + end_tangent * (t3 - t2)
+}
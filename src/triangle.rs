@@ -1,9 +1,11 @@
 use nalgebra_glm::{Vec2, Vec3};
 
 use crate::color::Color;
-use crate::fragment::Fragment;
+use crate::fragment::{Fragment, Shaded};
 use crate::fragment_shaders::FragmentShader;
+use crate::framebuffer::Viewport;
 use crate::line::line;
+use crate::renderer::Uniforms;
 use crate::vertex::Vertex;
 
 pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
@@ -17,26 +19,67 @@ pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
     fragments
 }
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    triangle_with_shader(v1, v2, v3, |_, _, _, _, _, _| Color::new(100, 100, 100))
+pub fn triangle(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    uniforms: &Uniforms,
+    viewport: &Viewport,
+) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    triangle_with_shader(
+        v1,
+        v2,
+        v3,
+        uniforms,
+        |_, _, _, _, _, _, _| Some(Shaded::opaque(Color::new(100, 100, 100))),
+        viewport,
+        &mut fragments,
+    );
+    fragments
 }
 
+// Appends the triangle's fragments to `fragments`, so one list can gather a
+// whole batch of triangles and be reused from draw to draw
 pub fn triangle_with_shader(
     v1: &Vertex,
     v2: &Vertex,
     v3: &Vertex,
+    uniforms: &Uniforms,
     fragment_shader: FragmentShader,
-) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+    viewport: &Viewport,
+    fragments: &mut Vec<Fragment>,
+) {
     let (a, b, c) = (
         v1.transformed_position,
         v2.transformed_position,
         v3.transformed_position,
     );
 
-    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+    // A vertex the transform sent to NaN or infinity would make the bounding
+    // box span the whole viewport for nothing
+    let finite = |v: &Vec3| v.x.is_finite() && v.y.is_finite() && v.z.is_finite();
+    if !(finite(&a) && finite(&b) && finite(&c)) {
+        return;
+    }
 
+    // Seen edge-on or collapsed to a point it covers no pixels, and the
+    // barycentric coordinates would divide by zero
     let triangle_area = edge_function(&a, &b, &c);
+    if triangle_area == 0.0 || !triangle_area.is_finite() {
+        return;
+    }
+
+    // Only the part of the bounding box inside the viewport is visited, so
+    // bodies half off screen cost only what shows and ones off it nothing
+    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+    let min_x = min_x.max(viewport.x as i32);
+    let min_y = min_y.max(viewport.y as i32);
+    let max_x = max_x.min((viewport.x + viewport.width) as i32 - 1);
+    let max_y = max_y.min((viewport.y + viewport.height) as i32 - 1);
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
 
     // Iterate over each pixel in the bounding box
     for y in min_y..=max_y {
@@ -62,7 +105,8 @@ pub fn triangle_with_shader(
                 )
                 .normalize();
 
-                // Interpolate position (world space)
+                // Interpolate position in object space (before the model matrix), so
+                // surface features turn with the body instead of swimming over it
                 let position = Vec3::new(
                     v1.position.x * w1 + v2.position.x * w2 + v3.position.x * w3,
                     v1.position.y * w1 + v2.position.y * w2 + v3.position.y * w3,
@@ -75,18 +119,23 @@ pub fn triangle_with_shader(
                     v1.tex_coords.y * w1 + v2.tex_coords.y * w2 + v3.tex_coords.y * w3,
                 );
 
-                // Use fragment shader to calculate color
-                let color = fragment_shader(v1, v2, v3, position, normal, tex_coords);
+                // Use fragment shader to calculate color; it may discard the fragment
+                let Some(shaded) =
+                    fragment_shader(v1, v2, v3, position, normal, tex_coords, uniforms)
+                else {
+                    continue;
+                };
 
                 // Interpolate depth
                 let depth = a.z * w1 + b.z * w2 + c.z * w3;
 
-                fragments.push(Fragment::new(x as f32, y as f32, color, depth));
+                fragments.push(Fragment {
+                    emissive: shaded.emissive,
+                    ..Fragment::new(x as f32, y as f32, shaded.color, depth, shaded.alpha)
+                });
             }
         }
     }
-
-    fragments
 }
 
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
@@ -109,3 +158,227 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star::StarLight;
+    use nalgebra_glm::Mat4;
+
+    fn uniforms() -> Uniforms {
+        Uniforms {
+            model_matrix: Mat4::identity(),
+            time: 0.0,
+            expression: None,
+            star: StarLight::default(),
+            lights: Vec::new(),
+            stars: 0,
+            ambient: Vec3::zeros(),
+            atmosphere: 0.0,
+            seed: 0,
+            noise: None,
+            atlas: None,
+            ring: None,
+            environment: None,
+            occluders: Vec::new(),
+        }
+    }
+
+    fn vertex(x: f32, y: f32, depth: f32) -> Vertex {
+        Vertex::new(
+            Vec3::new(x, y, depth),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec2::zeros(),
+        )
+    }
+
+    fn rasterize(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+        triangle(v1, v2, v3, &uniforms(), &Viewport::full(64, 64))
+    }
+
+    #[test]
+    fn barycentric_coordinates_at_the_corners_and_center() {
+        let (a, b, c) = (
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 10.0, 0.0),
+        );
+        let area = edge_function(&a, &b, &c);
+        assert_eq!(
+            barycentric_coordinates(&a, &a, &b, &c, area),
+            (1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            barycentric_coordinates(&b, &a, &b, &c, area),
+            (0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            barycentric_coordinates(&c, &a, &b, &c, area),
+            (0.0, 0.0, 1.0)
+        );
+
+        let center = (a + b + c) / 3.0;
+        let (w1, w2, w3) = barycentric_coordinates(&center, &a, &b, &c, area);
+        for weight in [w1, w2, w3] {
+            assert!((weight - 1.0 / 3.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn barycentric_coordinates_sum_to_one_outside_too() {
+        let (a, b, c) = (
+            Vec3::new(2.0, 3.0, 0.0),
+            Vec3::new(17.0, 5.0, 0.0),
+            Vec3::new(6.0, 19.0, 0.0),
+        );
+        let area = edge_function(&a, &b, &c);
+        for point in [Vec3::new(8.0, 9.0, 0.0), Vec3::new(-4.0, 30.0, 0.0)] {
+            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, area);
+            assert!((w1 + w2 + w3 - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn bounding_box_covers_every_corner() {
+        let (a, b, c) = (
+            Vec3::new(1.5, 7.2, 0.0),
+            Vec3::new(-3.7, 2.0, 0.0),
+            Vec3::new(4.0, -0.5, 0.0),
+        );
+        assert_eq!(calculate_bounding_box(&a, &b, &c), (-4, -1, 4, 8));
+    }
+
+    #[test]
+    fn bounding_box_of_a_point_is_one_pixel() {
+        let point = Vec3::new(3.0, 4.0, 0.0);
+        assert_eq!(calculate_bounding_box(&point, &point, &point), (3, 4, 3, 4));
+    }
+
+    #[test]
+    fn degenerate_triangles_cover_nothing() {
+        let point = vertex(5.0, 5.0, 1.0);
+        assert!(rasterize(&point, &point, &point).is_empty());
+
+        let line = [
+            vertex(0.0, 0.0, 1.0),
+            vertex(5.0, 5.0, 1.0),
+            vertex(10.0, 10.0, 1.0),
+        ];
+        assert!(rasterize(&line[0], &line[1], &line[2]).is_empty());
+    }
+
+    #[test]
+    fn triangles_with_non_finite_vertices_cover_nothing() {
+        let (b, c) = (vertex(20.0, 3.0, 1.0), vertex(6.0, 15.0, 1.0));
+        for bad in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(rasterize(&vertex(bad, 1.0, 1.0), &b, &c).is_empty());
+            assert!(rasterize(&vertex(1.0, bad, 1.0), &b, &c).is_empty());
+            assert!(rasterize(&vertex(1.0, 1.0, bad), &b, &c).is_empty());
+        }
+    }
+
+    #[test]
+    fn huge_triangles_are_clamped_to_the_viewport() {
+        let (a, b, c) = (
+            vertex(-1e5, -1e5, 1.0),
+            vertex(1e5, -1e5, 1.0),
+            vertex(0.0, 1e5, 1.0),
+        );
+        assert_eq!(rasterize(&a, &b, &c).len(), 64 * 64);
+    }
+
+    #[test]
+    fn triangles_off_the_viewport_cover_nothing() {
+        let (a, b, c) = (
+            vertex(100.0, 100.0, 1.0),
+            vertex(140.0, 100.0, 1.0),
+            vertex(100.0, 140.0, 1.0),
+        );
+        assert!(rasterize(&a, &b, &c).is_empty());
+    }
+
+    #[test]
+    fn both_windings_cover_the_same_pixels() {
+        let (a, b, c) = (
+            vertex(1.0, 1.0, 1.0),
+            vertex(20.0, 3.0, 1.0),
+            vertex(6.0, 15.0, 1.0),
+        );
+        let pixels = |fragments: Vec<Fragment>| {
+            let mut pixels: Vec<(i32, i32)> = fragments
+                .iter()
+                .map(|fragment| (fragment.position.x as i32, fragment.position.y as i32))
+                .collect();
+            pixels.sort();
+            pixels
+        };
+        let clockwise = pixels(rasterize(&a, &b, &c));
+        assert!(!clockwise.is_empty());
+        assert_eq!(clockwise, pixels(rasterize(&a, &c, &b)));
+    }
+
+    #[test]
+    fn shaders_can_discard_or_tint_coverage() {
+        let (a, b, c) = (
+            vertex(0.0, 0.0, 1.0),
+            vertex(30.0, 0.0, 1.0),
+            vertex(0.0, 30.0, 1.0),
+        );
+        let viewport = Viewport::full(64, 64);
+        let discard: FragmentShader = |_, _, _, _, _, _, _| None;
+        let mut fragments = Vec::new();
+        triangle_with_shader(&a, &b, &c, &uniforms(), discard, &viewport, &mut fragments);
+        assert!(fragments.is_empty());
+
+        let translucent: FragmentShader = |_, _, _, _, _, _, _| {
+            Some(Shaded {
+                alpha: 0.25,
+                ..Shaded::opaque(Color::new(10, 20, 30))
+            })
+        };
+        triangle_with_shader(
+            &a,
+            &b,
+            &c,
+            &uniforms(),
+            translucent,
+            &viewport,
+            &mut fragments,
+        );
+        assert!(!fragments.is_empty());
+        assert!(fragments.iter().all(|fragment| fragment.alpha == 0.25));
+    }
+
+    #[test]
+    fn depth_is_interpolated_between_the_corners() {
+        let (a, b, c) = (
+            vertex(0.0, 0.0, 1.0),
+            vertex(30.0, 0.0, 4.0),
+            vertex(0.0, 30.0, 4.0),
+        );
+        for fragment in rasterize(&a, &b, &c) {
+            assert!((1.0..=4.0).contains(&fragment.depth));
+        }
+    }
+
+    #[test]
+    fn fragments_stay_inside_the_viewport() {
+        let viewport = Viewport {
+            x: 8,
+            y: 4,
+            width: 16,
+            height: 12,
+        };
+        let (a, b, c) = (
+            vertex(-20.0, -20.0, 1.0),
+            vertex(80.0, 0.0, 1.0),
+            vertex(0.0, 80.0, 1.0),
+        );
+        let fragments = triangle(&a, &b, &c, &uniforms(), &viewport);
+        assert_eq!(fragments.len(), 16 * 12);
+        for fragment in fragments {
+            let (x, y) = (fragment.position.x as usize, fragment.position.y as usize);
+            assert!(viewport.contains(x, y));
+        }
+    }
+}
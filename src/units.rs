@@ -0,0 +1,110 @@
+use crate::belt::BeltBody;
+use crate::simulation::Planet;
+
+// Semi-realistic proportions: bodies shrink to this fraction...
+const REALISTIC_SIZE: f32 = 0.35;
+// ...and orbits are spread out on a log curve, `FACTOR * ln(1 + r / KNEE)`,
+// so the gaps between them grow next to the bodies while the outer belt
+// stays within reach
+const REALISTIC_DISTANCE_KNEE: f32 = 400.0;
+const REALISTIC_DISTANCE_FACTOR: f32 = 1200.0;
+// Stylized orbit read as one pseudo-AU in the semi-realistic scale, the
+// rocky world with a moon
+const PSEUDO_AU_ORBIT: f32 = 340.0;
+
+// How scene coordinates map to what is drawn. Scenes are written in stylized
+// units and converted once when the simulation is set up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneScale {
+    // Big bodies on close orbits, as the scene is written
+    Stylized,
+    SemiRealistic,
+}
+
+impl SceneScale {
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "estilizada" => Some(SceneScale::Stylized),
+            "semirrealista" => Some(SceneScale::SemiRealistic),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SceneScale::Stylized => "estilizada",
+            SceneScale::SemiRealistic => "semirrealista",
+        }
+    }
+
+    pub fn size(&self, size: f32) -> f32 {
+        match self {
+            SceneScale::Stylized => size,
+            SceneScale::SemiRealistic => size * REALISTIC_SIZE,
+        }
+    }
+
+    pub fn distance(&self, radius: f32) -> f32 {
+        match self {
+            SceneScale::Stylized => radius,
+            SceneScale::SemiRealistic => {
+                REALISTIC_DISTANCE_FACTOR * (1.0 + radius / REALISTIC_DISTANCE_KNEE).ln()
+            }
+        }
+    }
+
+    // Scene units in one pseudo-AU, for readouts in the semi-realistic
+    // scale; the stylized scene is read in plain units
+    pub fn pseudo_au(&self) -> Option<f32> {
+        match self {
+            SceneScale::Stylized => None,
+            SceneScale::SemiRealistic => Some(self.distance(PSEUDO_AU_ORBIT)),
+        }
+    }
+
+    // Moons keep their orbits in proportion to their planet, and rings are
+    // already measured in planet radii
+    pub fn apply_to_planet(&self, planet: &mut Planet) {
+        planet.orbit_radius = self.distance(planet.orbit_radius);
+        planet.scale = self.size(planet.scale);
+        planet.collision_radius = self.size(planet.collision_radius);
+        for moon in &mut planet.moons {
+            moon.orbit_radius = self.size(moon.orbit_radius);
+            moon.scale = self.size(moon.scale);
+        }
+    }
+
+    pub fn apply_to_belt_body(&self, body: &mut BeltBody) {
+        let radius = self.distance(body.orbit_radius);
+        body.height *= radius / body.orbit_radius;
+        body.orbit_radius = radius;
+        body.scale = self.size(body.scale);
+    }
+}
+
+// The 1, 2 or 5 times a power of ten closest to `value` from below
+pub fn round_step(value: f32) -> f32 {
+    if value <= 0.0 || !value.is_finite() {
+        return 0.0;
+    }
+    let power = 10f32.powf(value.log10().floor());
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|factor| factor * power)
+        .find(|&step| step <= value)
+        .unwrap_or(power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_steps_are_one_two_or_five_times_a_power_of_ten() {
+        for (value, expected) in [(1.0, 1.0), (340.0, 200.0), (7.5, 5.0), (0.03, 0.02)] {
+            assert!((round_step(value) - expected).abs() < expected * 1e-4);
+        }
+        assert_eq!(round_step(0.0), 0.0);
+        assert_eq!(round_step(f32::INFINITY), 0.0);
+    }
+}
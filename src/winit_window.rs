@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+
+use softbuffer::{Context, Surface};
+use winit::application::ApplicationHandler;
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+#[cfg(target_arch = "wasm32")]
+use winit::platform::web::{EventLoopExtWebSys, WindowAttributesExtWebSys};
+use winit::window::{Window, WindowId};
+
+use computer_graphics_v3::app::{frame_size, App, WINDOW_HEIGHT, WINDOW_TITLE, WINDOW_WIDTH};
+use computer_graphics_v3::framebuffer::Framebuffer;
+use computer_graphics_v3::input::{InputSource, Key};
+use computer_graphics_v3::options::Options;
+
+// Desktop main loop with winit instead of minifb
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run(options: Options) {
+    let event_loop = EventLoop::new().expect("No se pudo crear el bucle de eventos");
+    event_loop
+        .run_app(&mut WinitApp {
+            options,
+            state: None,
+        })
+        .expect("No se pudo crear el bucle de eventos");
+}
+
+// Browser main loop: the canvas is added to the page and every animation
+// frame runs one `App::frame`. Returns right away; the browser drives it
+#[cfg(target_arch = "wasm32")]
+pub fn run(options: Options) {
+    console_error_panic_hook::set_once();
+    let event_loop = EventLoop::new().expect("No se pudo crear el bucle de eventos");
+    event_loop.spawn_app(WinitApp {
+        options,
+        state: None,
+    });
+}
+
+struct WinitApp {
+    options: Options,
+    // Created on the first `resumed`, when winit allows making windows
+    state: Option<State>,
+}
+
+struct State {
+    window: Rc<Window>,
+    surface: Surface<Rc<Window>, Rc<Window>>,
+    input: WindowInput,
+    // Where the frame goes inside the window, which may have been resized
+    letterbox: Letterbox,
+    app: App,
+}
+
+// Key and mouse state gathered from the events since the last frame. The
+// cursor is kept in logical pixels over the frame, so the app sees neither
+// the window's size nor its scale
+#[derive(Default)]
+struct WindowInput {
+    keys: HashSet<Key>,
+    cursor: Option<(f32, f32)>,
+}
+
+// The frame scaled to fit the window without stretching, centered, with
+// black bars on the sides that are left over
+#[derive(Clone, Copy)]
+struct Letterbox {
+    window_width: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Letterbox {
+    fn fit(window_width: usize, window_height: usize) -> Self {
+        let scale = (window_width as f32 / WINDOW_WIDTH as f32)
+            .min(window_height as f32 / WINDOW_HEIGHT as f32);
+        let width = ((WINDOW_WIDTH as f32 * scale) as usize).clamp(1, window_width);
+        let height = ((WINDOW_HEIGHT as f32 * scale) as usize).clamp(1, window_height);
+        Letterbox {
+            window_width,
+            x: (window_width - width) / 2,
+            y: (window_height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    // Window pixels to logical pixels over the frame; None over the bars
+    fn logical_position(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let fx = (x - self.x as f32) * WINDOW_WIDTH as f32 / self.width as f32;
+        let fy = (y - self.y as f32) * WINDOW_HEIGHT as f32 / self.height as f32;
+        let inside =
+            (0.0..WINDOW_WIDTH as f32).contains(&fx) && (0.0..WINDOW_HEIGHT as f32).contains(&fy);
+        inside.then_some((fx, fy))
+    }
+
+    // Nearest-neighbor copy of the frame into a window-sized buffer
+    fn present(&self, screen: &Framebuffer, target: &mut [u32]) {
+        for (y, row) in target.chunks_mut(self.window_width).enumerate() {
+            if y < self.y || y >= self.y + self.height {
+                row.fill(0);
+                continue;
+            }
+            let sy = (y - self.y) * screen.height / self.height;
+            let source = &screen.buffer[sy * screen.width..(sy + 1) * screen.width];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = if x < self.x || x >= self.x + self.width {
+                    0
+                } else {
+                    source[(x - self.x) * screen.width / self.width]
+                };
+            }
+        }
+    }
+}
+
+impl InputSource for WindowInput {
+    fn is_key_down(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    fn mouse_position(&self) -> Option<(f32, f32)> {
+        self.cursor
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (WINDOW_WIDTH, WINDOW_HEIGHT)
+    }
+}
+
+impl ApplicationHandler for WinitApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(LogicalSize::new(WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32));
+        #[cfg(target_arch = "wasm32")]
+        let attributes = attributes.with_append(true);
+        let window = Rc::new(
+            event_loop
+                .create_window(attributes)
+                .expect("No se pudo crear la ventana"),
+        );
+        let context = Context::new(Rc::clone(&window)).expect("No se pudo crear el lienzo");
+        let surface =
+            Surface::new(&context, Rc::clone(&window)).expect("No se pudo crear el lienzo");
+
+        // The monitor's scale unless --scale overrides it, and then the
+        // window is sized to fit the frame one to one
+        let scale = match self.options.scale {
+            Some(scale) => {
+                let (width, height) = frame_size(scale);
+                let _ = window.request_inner_size(PhysicalSize::new(width as u32, height as u32));
+                scale
+            }
+            None => window.scale_factor() as f32,
+        };
+
+        window.request_redraw();
+        let mut state = State {
+            window,
+            surface,
+            input: WindowInput::default(),
+            letterbox: Letterbox::fit(WINDOW_WIDTH, WINDOW_HEIGHT),
+            app: App::new(&self.options, scale),
+        };
+        let size = state.window.inner_size();
+        state.resize(size);
+        self.state = Some(state);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    if let Some(key) = key_from_code(code) {
+                        match event.state {
+                            ElementState::Pressed => state.input.keys.insert(key),
+                            ElementState::Released => state.input.keys.remove(&key),
+                        };
+                    }
+                }
+            }
+            // Keys released while the page had no focus never report it
+            WindowEvent::Focused(false) => state.input.keys.clear(),
+            WindowEvent::CursorMoved { position, .. } => {
+                state.input.cursor = state
+                    .letterbox
+                    .logical_position(position.x as f32, position.y as f32);
+            }
+            WindowEvent::CursorLeft { .. } => state.input.cursor = None,
+            WindowEvent::MouseInput {
+                state: button_state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                match button_state {
+                    ElementState::Pressed => state.input.keys.insert(Key::MouseLeft),
+                    ElementState::Released => state.input.keys.remove(&Key::MouseLeft),
+                };
+            }
+            WindowEvent::Resized(size) => state.resize(size),
+            WindowEvent::CloseRequested => {
+                state.app.shutdown();
+                event_loop.exit();
+            }
+            WindowEvent::RedrawRequested => {
+                if !state.app.frame(&state.input) {
+                    state.app.shutdown();
+                    event_loop.exit();
+                    return;
+                }
+                let mut buffer = state
+                    .surface
+                    .buffer_mut()
+                    .expect("No se pudo actualizar la ventana");
+                state.letterbox.present(state.app.screen(), &mut buffer);
+                buffer.present().expect("No se pudo actualizar la ventana");
+                state.window.request_redraw();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl State {
+    // A minimized window reports a zero size; the last surface is kept then
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        self.surface
+            .resize(width, height)
+            .expect("No se pudo redimensionar la ventana");
+        self.letterbox = Letterbox::fit(size.width as usize, size.height as usize);
+    }
+}
+
+fn key_from_code(code: KeyCode) -> Option<Key> {
+    let key = match code {
+        KeyCode::Digit1 => Key::Key1,
+        KeyCode::Digit2 => Key::Key2,
+        KeyCode::Digit3 => Key::Key3,
+        KeyCode::Digit4 => Key::Key4,
+        KeyCode::Digit5 => Key::Key5,
+        KeyCode::Digit6 => Key::Key6,
+        KeyCode::Digit7 => Key::Key7,
+        KeyCode::Digit8 => Key::Key8,
+        KeyCode::Digit9 => Key::Key9,
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyB => Key::B,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E,
+        KeyCode::KeyF => Key::F,
+        KeyCode::KeyG => Key::G,
+        KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I,
+        KeyCode::KeyJ => Key::J,
+        KeyCode::KeyK => Key::K,
+        KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M,
+        KeyCode::KeyN => Key::N,
+        KeyCode::KeyO => Key::O,
+        KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyR => Key::R,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::ShiftLeft => Key::LeftShift,
+        KeyCode::ShiftRight => Key::RightShift,
+        KeyCode::ControlLeft => Key::LeftCtrl,
+        KeyCode::ControlRight => Key::RightCtrl,
+        KeyCode::Equal => Key::Equal,
+        KeyCode::Minus => Key::Minus,
+        KeyCode::BracketLeft => Key::LeftBracket,
+        KeyCode::BracketRight => Key::RightBracket,
+        KeyCode::Comma => Key::Comma,
+        KeyCode::Period => Key::Period,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F12 => Key::F12,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Escape => Key::Escape,
+        _ => return None,
+    };
+    Some(key)
+}
@@ -0,0 +1,93 @@
+use computer_graphics_v3::{
+    triangle, vertex_shader, Color, Framebuffer, Uniforms, Vertex, Viewport,
+};
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+
+const SIZE: usize = 32;
+
+fn vertex(x: f32, y: f32, depth: f32) -> Vertex {
+    Vertex::new(
+        Vec3::new(x, y, depth),
+        Vec3::new(0.0, 0.0, -1.0),
+        Vec2::zeros(),
+    )
+}
+
+// No transform: the corners are already where they land on screen
+fn screen_space() -> Uniforms {
+    Uniforms {
+        model_matrix: Mat4::identity(),
+        ..Uniforms::default()
+    }
+}
+
+// Runs the corners through the vertex shader and rasterizes the triangle
+// into the framebuffer in one flat color
+fn draw(framebuffer: &mut Framebuffer, corners: [Vertex; 3], uniforms: &Uniforms, color: u32) {
+    let [a, b, c] = corners.map(|corner| vertex_shader(&corner, uniforms));
+    framebuffer.set_current_color(color);
+    for fragment in triangle(&a, &b, &c, uniforms, &Viewport::full(SIZE, SIZE)) {
+        framebuffer.point(
+            fragment.position.x as usize,
+            fragment.position.y as usize,
+            fragment.depth,
+        );
+    }
+}
+
+fn pixel(framebuffer: &Framebuffer, x: usize, y: usize) -> u32 {
+    framebuffer.buffer[y * framebuffer.width + x]
+}
+
+#[test]
+fn draws_a_triangle_into_a_framebuffer() {
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    let red = Color::new(255, 0, 0).to_hex();
+    let corners = [
+        vertex(2.0, 2.0, 1.0),
+        vertex(20.0, 2.0, 1.0),
+        vertex(2.0, 20.0, 1.0),
+    ];
+    draw(&mut framebuffer, corners, &screen_space(), red);
+
+    assert_eq!(pixel(&framebuffer, 5, 5), red);
+    assert_eq!(pixel(&framebuffer, 25, 25), 0);
+}
+
+#[test]
+fn nearer_triangles_hide_farther_ones() {
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    let (near, far) = (0x00FF00, 0x0000FF);
+    let covering = |depth| {
+        [
+            vertex(0.0, 0.0, depth),
+            vertex(30.0, 0.0, depth),
+            vertex(0.0, 30.0, depth),
+        ]
+    };
+    draw(&mut framebuffer, covering(1.0), &screen_space(), near);
+    draw(&mut framebuffer, covering(5.0), &screen_space(), far);
+
+    assert_eq!(pixel(&framebuffer, 8, 8), near);
+}
+
+#[test]
+fn the_vertex_shader_moves_the_triangle() {
+    let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+    framebuffer.clear();
+    let uniforms = Uniforms {
+        model_matrix: nalgebra_glm::translate(&Mat4::identity(), &Vec3::new(10.0, 10.0, 0.0)),
+        ..Uniforms::default()
+    };
+    let corners = [
+        vertex(0.0, 0.0, 1.0),
+        vertex(10.0, 0.0, 1.0),
+        vertex(0.0, 10.0, 1.0),
+    ];
+    draw(&mut framebuffer, corners, &uniforms, 0xFFFFFF);
+
+    assert_eq!(pixel(&framebuffer, 2, 2), 0);
+    assert_eq!(pixel(&framebuffer, 12, 12), 0xFFFFFF);
+}